@@ -0,0 +1,55 @@
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(&rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|v| v.trim().to_string())
+        .unwrap_or_else(|| "rustc (unknown version)".to_string());
+
+    let msrv = env::var("CARGO_PKG_RUST_VERSION").unwrap_or_default();
+    if let (Some(found), Some(required)) = (parse_version(&version), parse_version(&msrv))
+        && found < required
+    {
+        panic!(
+            "rswc requires Rust {} or newer, but is being built with {}. \
+             Please upgrade your toolchain, e.g. with `rustup update`.",
+            msrv, version
+        );
+    }
+
+    println!("cargo:rustc-env=RSWC_RUSTC_VERSION={}", version);
+    println!(
+        "cargo:rustc-env=RSWC_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    // musl targets link statically by default; glibc ones only when asked to.
+    let target_features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    let linkage = if target_features.split(',').any(|f| f == "crt-static") {
+        "static"
+    } else {
+        "dynamic"
+    };
+    println!("cargo:rustc-env=RSWC_LINKAGE={}", linkage);
+}
+
+/// Extracts `(major, minor, patch)` from `rustc 1.88.0 (...)` or `1.88`.
+fn parse_version(s: &str) -> Option<(u32, u32, u32)> {
+    let v = s
+        .split_whitespace()
+        .find(|w| w.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = v
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|p| p.parse().ok());
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
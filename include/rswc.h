@@ -0,0 +1,54 @@
+/* The C ABI of rswc, generated by cbindgen from src/ffi.rs: do not edit. */
+
+#ifndef RSWC_H
+#define RSWC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Counted.
+#define RSWC_OK 0
+
+// A pointer was null, or the path isn't valid UTF-8.
+#define RSWC_INVALID_ARGUMENT 1
+
+// The file could not be read.
+#define RSWC_IO_ERROR 2
+
+// The counts of an input, like those printed by `rswc -lwmc`.
+typedef struct RswcCounts {
+  uint64_t lines;
+  uint64_t words;
+  uint64_t chars;
+  uint64_t bytes;
+  // Bytes that are not valid UTF-8, counted along with `chars`.
+  uint64_t invalid_bytes;
+} RswcCounts;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Counts the `len` bytes at `data` into `out`. `data` may be null when
+// `len` is 0.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes, and `out` to a writable
+// `RswcCounts`.
+int rswc_count_buffer(const uint8_t *data, size_t len, struct RswcCounts *out);
+
+// Counts the file at `path`, a NUL-terminated UTF-8 string, into `out`.
+// `out` is left as it was unless the file is counted.
+//
+// # Safety
+//
+// `path` must point to a NUL-terminated string, and `out` to a writable
+// `RswcCounts`.
+int rswc_count_file(const char *path, struct RswcCounts *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RSWC_H */
@@ -0,0 +1,156 @@
+use crate::counter::FileResult;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Modified z-score cutoff suggested by Iglewicz and Hoaglin.
+const OUTLIER_THRESHOLD: f64 = 3.5;
+const MIN_SAMPLES: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub struct Anomaly {
+    pub path: PathBuf,
+    pub bytes_per_line: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AnomalyReport {
+    pub median: f64,
+    pub anomalies: Vec<Anomaly>,
+}
+
+fn bytes_per_line(bytes: u64, lines: u64) -> f64 {
+    bytes as f64 / lines.max(1) as f64
+}
+
+fn median(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn sorted(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values
+}
+
+pub fn find_anomalies(results: &[FileResult]) -> AnomalyReport {
+    let samples: Vec<(&PathBuf, f64)> = results
+        .iter()
+        .filter_map(|r| match r {
+            FileResult::Ok(path, c) if c.bytes > 0 => {
+                Some((path, bytes_per_line(c.bytes, c.lines)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    if samples.len() < MIN_SAMPLES {
+        return AnomalyReport {
+            median: 0.0,
+            anomalies: Vec::new(),
+        };
+    }
+
+    let ratios = sorted(samples.iter().map(|(_, r)| *r).collect());
+    let med = median(&ratios);
+    let deviations: Vec<f64> = ratios.iter().map(|r| (r - med).abs()).collect();
+    let mad = median(&sorted(deviations.clone()));
+
+    // Fall back to the mean absolute deviation when more than half of the
+    // files share the same ratio and the MAD collapses to zero.
+    let scale = if mad > 0.0 {
+        mad / 0.6745
+    } else {
+        1.253314 * deviations.iter().sum::<f64>() / deviations.len() as f64
+    };
+
+    let anomalies = if scale > 0.0 {
+        samples
+            .into_iter()
+            .filter(|(_, r)| (r - med).abs() / scale > OUTLIER_THRESHOLD)
+            .map(|(path, r)| Anomaly {
+                path: path.clone(),
+                bytes_per_line: r,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    AnomalyReport {
+        median: med,
+        anomalies,
+    }
+}
+
+pub fn print_anomalies<W: Write>(writer: &mut W, report: &AnomalyReport) -> io::Result<()> {
+    if report.anomalies.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "anomalies (bytes per line, median {:.1}):",
+        report.median
+    )?;
+    for a in &report.anomalies {
+        writeln!(writer, "{:>10.1} {}", a.bytes_per_line, a.path.display())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+    use std::io::Cursor;
+
+    fn ok(name: &str, lines: u64, bytes: u64) -> FileResult {
+        FileResult::Ok(
+            PathBuf::from(name),
+            Counts {
+                lines,
+                words: 0,
+                bytes,
+                chars: 0,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_find_anomalies_flags_minified_file() {
+        let results = vec![
+            ok("a.js", 100, 4000),
+            ok("b.js", 120, 5000),
+            ok("c.js", 80, 3100),
+            ok("d.js", 90, 3700),
+            ok("min.js", 1, 250000),
+            FileResult::Err(PathBuf::from("missing.js"), io::Error::other("gone").into()),
+        ];
+
+        let report = find_anomalies(&results);
+        assert_eq!(report.anomalies.len(), 1);
+        assert_eq!(report.anomalies[0].path, PathBuf::from("min.js"));
+
+        let mut output = Cursor::new(Vec::new());
+        print_anomalies(&mut output, &report).unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+        let expected = "
+anomalies (bytes per line, median 41.1):
+  250000.0 min.js
+";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_find_anomalies_needs_enough_samples() {
+        let results = vec![ok("a.txt", 10, 400), ok("min.js", 1, 250000)];
+        assert!(find_anomalies(&results).anomalies.is_empty());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::counter::{Annotations, CountOptions, FileOpener, FileResult, count_reader};
+use flate2::read::GzDecoder;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use zip::{CompressionMethod, ZipArchive};
+
+/// Whether `path` names an archive whose members can be counted.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".tar") || is_gzip(&name) || name.ends_with(".zip")
+}
+
+fn is_gzip(name: &str) -> bool {
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Counts every regular file inside the archive at `path`. Errors reading a
+/// single member are reported for that member; a corrupt archive is an error
+/// for the whole archive.
+pub fn count_archive(path: &Path, flags: &CountOptions) -> io::Result<Vec<FileResult>> {
+    if path.to_string_lossy().ends_with(".zip") {
+        return count_zip(path, flags);
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    if is_gzip(&path.to_string_lossy()) {
+        let mut members = count_tar(GzDecoder::new(reader), flags)?;
+        for m in &mut members {
+            if let FileResult::Ok(_, counts) = m {
+                counts.annotations.insert(Annotations::DECOMPRESSED);
+            }
+        }
+        Ok(members)
+    } else {
+        count_tar(reader, flags)
+    }
+}
+
+pub fn count_tar<R: Read>(reader: R, flags: &CountOptions) -> io::Result<Vec<FileResult>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut members = Vec::new();
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.into_owned();
+        members.push(match count_reader(entry, &flags.for_input(&path)) {
+            Ok(counts) => FileResult::Ok(path, counts),
+            Err(e) => FileResult::Err(path, e.into()),
+        });
+    }
+
+    Ok(members)
+}
+
+fn open_zip(path: &Path) -> io::Result<ZipArchive<BufReader<File>>> {
+    ZipArchive::new(BufReader::new(File::open(path)?)).map_err(io::Error::other)
+}
+
+/// Counts the files of the zip archive at `path`. Unlike tar, zip members can
+/// be read independently, so with the `parallel` feature every worker thread
+/// opens its own handle on the archive and counts a share of the members,
+/// unless they are counted one after another.
+pub fn count_zip(path: &Path, flags: &CountOptions) -> io::Result<Vec<FileResult>> {
+    let archive = open_zip(path)?;
+
+    #[cfg(feature = "parallel")]
+    if !flags.sequential() {
+        let members = (0..archive.len())
+            .into_par_iter()
+            .map_init(
+                || open_zip(path),
+                |handle, i| match handle {
+                    Ok(archive) => count_zip_member(archive, i, flags),
+                    Err(e) => Some(FileResult::Err(
+                        PathBuf::from(format!("#{}", i)),
+                        io::Error::new(e.kind(), e.to_string()).into(),
+                    )),
+                },
+            )
+            .collect::<Vec<_>>();
+        return Ok(members.into_iter().flatten().collect());
+    }
+
+    let mut archive = archive;
+    let members = (0..archive.len())
+        .map(|i| count_zip_member(&mut archive, i, flags))
+        .collect::<Vec<_>>();
+    Ok(members.into_iter().flatten().collect())
+}
+
+pub fn count_zip_reader<R: Read + Seek>(
+    reader: R,
+    flags: &CountOptions,
+) -> io::Result<Vec<FileResult>> {
+    let mut archive = ZipArchive::new(reader).map_err(io::Error::other)?;
+    Ok((0..archive.len())
+        .filter_map(|i| count_zip_member(&mut archive, i, flags))
+        .collect())
+}
+
+/// Counts member `i`, or returns `None` for directories.
+fn count_zip_member<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    i: usize,
+    flags: &CountOptions,
+) -> Option<FileResult> {
+    let name = PathBuf::from(archive.name_for_index(i)?);
+    let entry = match archive.by_index(i) {
+        Ok(entry) => entry,
+        Err(e) => return Some(FileResult::Err(name, io::Error::from(e).into())),
+    };
+    if entry.is_dir() {
+        return None;
+    }
+
+    let compressed = entry.compression() != CompressionMethod::Stored;
+    Some(match count_reader(entry, &flags.for_input(&name)) {
+        Ok(mut counts) => {
+            if compressed {
+                counts.annotations.insert(Annotations::DECOMPRESSED);
+            }
+            FileResult::Ok(name, counts)
+        }
+        Err(e) => FileResult::Err(name, e.into()),
+    })
+}
+
+/// The files of a zip archive read from `R`, e.g. one held in memory or a
+/// database blob, for `process_files_from`.
+pub struct ZipFiles<R>(Mutex<ZipArchive<R>>);
+
+impl<R: Read + Seek> ZipFiles<R> {
+    pub fn new(reader: R) -> io::Result<Self> {
+        let archive = ZipArchive::new(reader).map_err(io::Error::other)?;
+        Ok(ZipFiles(Mutex::new(archive)))
+    }
+
+    /// The paths of the files in the archive, directories left out.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let archive = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+impl<R: Read + Seek + Send> FileOpener for ZipFiles<R> {
+    /// Reads the whole member, since it can't outlive the lock on the
+    /// archive.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        let name = path
+            .to_str()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        let mut archive = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut member = archive.by_name(name).map_err(|e| match e {
+            zip::result::ZipError::FileNotFound => io::Error::from(io::ErrorKind::NotFound),
+            e => io::Error::other(e),
+        })?;
+        let mut data = Vec::new();
+        member.read_to_end(&mut data)?;
+        Ok(Box::new(io::Cursor::new(data)))
+    }
+}
+
+/// The files of an uncompressed tar archive read from `R`, for
+/// `process_files_from`. Tar has no index, so every file opened is looked
+/// for from the start of the archive.
+pub struct TarFiles<R>(Mutex<R>);
+
+impl<R: Read + Seek> TarFiles<R> {
+    pub fn new(reader: R) -> Self {
+        TarFiles(Mutex::new(reader))
+    }
+
+    /// The paths of the regular files in the archive.
+    pub fn paths(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        self.each_file(|path, _| {
+            paths.push(path.to_path_buf());
+            Ok(false)
+        })?;
+        Ok(paths)
+    }
+
+    /// Calls `f` with every regular file until it returns true.
+    fn each_file(
+        &self,
+        mut f: impl FnMut(&Path, &mut dyn Read) -> io::Result<bool>,
+    ) -> io::Result<()> {
+        let mut reader = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut archive = tar::Archive::new(&mut *reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?.into_owned();
+            if f(&path, &mut entry)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek + Send> FileOpener for TarFiles<R> {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        let mut found = None;
+        self.each_file(|member, entry| {
+            if member != path {
+                return Ok(false);
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            found = Some(data);
+            Ok(true)
+        })?;
+        match found {
+            Some(data) => Ok(Box::new(io::Cursor::new(data))),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Report;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    fn build_tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in [("docs/a.txt", "one two\nthree\n"), ("b.txt", "four\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_count_tar_members() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Default::default()
+        };
+        let tar = build_tar();
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        io::Write::write_all(&mut gz, &tar).unwrap();
+        let gz = gz.finish().unwrap();
+
+        for members in [
+            count_tar(tar.as_slice(), &flags).unwrap(),
+            count_tar(GzDecoder::new(gz.as_slice()), &flags).unwrap(),
+        ] {
+            let results = vec![FileResult::Archive(PathBuf::from("docs.tar"), members)];
+            let expected = "      2       3      14 docs.tar!docs/a.txt
+      1       1       5 docs.tar!b.txt
+      3       4      19 docs.tar
+";
+            assert_eq!(Report::new(&results, &flags).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_count_zip_members() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Default::default()
+        };
+
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("docs/", options).unwrap();
+        for (name, data) in [("docs/a.txt", "one two\nthree\n"), ("b.txt", "four\n")] {
+            writer.start_file(name, options).unwrap();
+            io::Write::write_all(&mut writer, data.as_bytes()).unwrap();
+        }
+        let zip = writer.finish().unwrap().into_inner();
+
+        let path = std::env::temp_dir().join(format!("rswc-test-{}.zip", std::process::id()));
+        std::fs::write(&path, &zip).unwrap();
+        let from_file = count_archive(&path, &flags);
+        std::fs::remove_file(&path).unwrap();
+
+        for members in [
+            from_file.unwrap(),
+            count_zip_reader(io::Cursor::new(zip), &flags).unwrap(),
+        ] {
+            let results = vec![FileResult::Archive(PathBuf::from("docs.zip"), members)];
+            let expected = "      2       3      14 docs.zip!docs/a.txt
+      1       1       5 docs.zip!b.txt
+      3       4      19 docs.zip
+";
+            assert_eq!(Report::new(&results, &flags).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_archive_files_from_readers() {
+        use crate::counter::process_files_from;
+
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("docs/", options).unwrap();
+        for (name, data) in [("docs/a.txt", "one two\nthree\n"), ("b.txt", "four\n")] {
+            writer.start_file(name, options).unwrap();
+            io::Write::write_all(&mut writer, data.as_bytes()).unwrap();
+        }
+        let zip = ZipFiles::new(writer.finish().unwrap()).unwrap();
+        let tar = TarFiles::new(io::Cursor::new(build_tar()));
+
+        let expected = |name: &str, lines, words, bytes| {
+            let counts = crate::counter::Counts {
+                lines,
+                words,
+                bytes,
+                ..Default::default()
+            };
+            FileResult::Ok(PathBuf::from(name), counts)
+        };
+        for (paths, opener) in [
+            (zip.paths(), &zip as &dyn FileOpener),
+            (tar.paths().unwrap(), &tar as &dyn FileOpener),
+        ] {
+            assert_eq!(paths, [PathBuf::from("docs/a.txt"), PathBuf::from("b.txt")]);
+            let mut paths = paths;
+            paths.push(PathBuf::from("gone.txt"));
+            assert_eq!(
+                process_files_from(&paths, &flags, opener),
+                [
+                    expected("docs/a.txt", 2, 3, 14),
+                    expected("b.txt", 1, 1, 5),
+                    FileResult::Err(
+                        PathBuf::from("gone.txt"),
+                        io::Error::from(io::ErrorKind::NotFound).into()
+                    ),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive(Path::new("backup.tar")));
+        assert!(is_archive(Path::new("backup.tar.gz")));
+        assert!(is_archive(Path::new("backup.tgz")));
+        assert!(is_archive(Path::new("backup.zip")));
+        assert!(!is_archive(Path::new("notes.txt")));
+    }
+}
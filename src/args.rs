@@ -1,8 +1,17 @@
-use std::{convert::TryFrom, path::PathBuf};
+use crate::error::RswcError;
+use std::{
+    convert::TryFrom,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 #[derive(Debug)]
 pub struct ArgSet {
     pub flags: Vec<String>,
+    pub values: Vec<(String, String)>,
+    /// The values of the options that take a path, which needn't be UTF-8.
+    pub paths: Vec<(String, PathBuf)>,
     pub file_paths: Vec<PathBuf>,
 }
 
@@ -10,44 +19,385 @@ impl ArgSet {
     pub fn has(&self, name: &str) -> bool {
         self.flags.iter().any(|f| f == name)
     }
+
+    /// The value given to option `name`, the last one if it was repeated.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The value of option `name` parsed as a `T`, like a number of threads,
+    /// failing with the option and the value it couldn't parse.
+    pub fn get_value<T: FromStr>(&self, name: &str) -> Result<Option<T>, RswcError> {
+        self.value(name)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    RswcError::Usage(format!("rswc: invalid argument '{}' for '{}'", value, name))
+                })
+            })
+            .transpose()
+    }
+
+    /// The path given to option `name`, the last one if it was repeated.
+    pub fn path(&self, name: &str) -> Option<&Path> {
+        self.paths
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, p)| p.as_path())
+    }
+
+    /// Every value given to option `name`, in order, for options that can
+    /// be repeated.
+    pub fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.values
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether option `name` was given, with or without a value.
+    pub fn given(&self, name: &str) -> bool {
+        self.has(name) || self.value(name).is_some() || self.path(name).is_some()
+    }
+
+    /// Checks the options against `rules`, failing on the first broken
+    /// `Conflicts` or `Requires` rule and returning a warning for every
+    /// option that has no effect.
+    pub fn validate(&self, rules: &[Rule]) -> Result<Vec<String>, RswcError> {
+        let mut warnings = Vec::new();
+        for rule in rules {
+            match *rule {
+                Rule::Conflicts(a, b) if self.given(a) && self.given(b) => {
+                    return Err(RswcError::Usage(format!(
+                        "rswc: {} cannot be used with {}",
+                        a, b
+                    )));
+                }
+                Rule::Requires(a, others) if self.given(a) && !self.given_any(others) => {
+                    return Err(RswcError::Usage(format!(
+                        "rswc: {} needs {}",
+                        a,
+                        either(others)
+                    )));
+                }
+                Rule::Affects(a, others) if self.given(a) && !self.given_any(others) => {
+                    warnings.push(format!(
+                        "rswc: warning: {} has no effect without {}",
+                        a,
+                        either(others)
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// The options, each as one argument and by its long name in
+    /// `synonyms`, like `--threads=4` for `-j 4`. Files are left out.
+    pub fn options(&self, synonyms: &[[&str; 2]]) -> Vec<String> {
+        let long = |name: &str| {
+            synonyms
+                .iter()
+                .find(|[short, _]| *short == name)
+                .map_or(name.to_string(), |[_, long]| long.to_string())
+        };
+        self.flags
+            .iter()
+            .map(|name| long(name))
+            .chain(
+                self.values
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", long(name), value)),
+            )
+            .chain(
+                self.paths
+                    .iter()
+                    .map(|(name, path)| format!("{}={}", long(name), path.display())),
+            )
+            .collect()
+    }
+
+    /// Whether these options override the default `option`, like
+    /// `--threads=4`: they give it themselves, under either of its names in
+    /// `synonyms`, or one that `rules` say it conflicts with.
+    pub fn overrides(&self, option: &str, rules: &[Rule], synonyms: &[[&str; 2]]) -> bool {
+        let name = option.split_once('=').map_or(option, |(name, _)| name);
+        let names = synonyms
+            .iter()
+            .find(|pair| pair.contains(&name))
+            .copied()
+            .unwrap_or([name, name]);
+        self.given_any(&names)
+            || rules.iter().any(|rule| match *rule {
+                Rule::Conflicts(a, b) => {
+                    (names.contains(&a) && self.given(b)) || (names.contains(&b) && self.given(a))
+                }
+                _ => false,
+            })
+    }
+
+    fn given_any(&self, names: &[&str]) -> bool {
+        names.iter().any(|n| self.given(n))
+    }
+}
+
+/// A constraint between options, checked by [`ArgSet::validate`].
+#[derive(Debug, Clone, Copy)]
+pub enum Rule<'a> {
+    /// The two options can't be combined.
+    Conflicts(&'a str, &'a str),
+    /// The option is an error without one of the others.
+    Requires(&'a str, &'a [&'a str]),
+    /// The option does nothing without one of the others.
+    Affects(&'a str, &'a [&'a str]),
+}
+
+/// Lists `names` as "a", "a or b", "a, b or c".
+fn either(names: &[&str]) -> String {
+    match names {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [rest @ .., last] => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+/// Whether a file operand is an HTTP or HTTPS URL rather than a path.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|p| p.starts_with("http://") || p.starts_with("https://"))
 }
 
 impl<I, S> TryFrom<(I, &[&str])> for ArgSet
 where
     I: IntoIterator<Item = S>,
-    S: Into<String> + AsRef<str>,
+    S: AsRef<OsStr>,
 {
-    type Error = String;
+    type Error = RswcError;
 
     fn try_from((args, custom_flags): (I, &[&str])) -> Result<Self, Self::Error> {
+        ArgSet::try_from((args, custom_flags, &[][..]))
+    }
+}
+
+impl<I, S> TryFrom<(I, &[&str], &[&str])> for ArgSet
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    type Error = RswcError;
+
+    fn try_from(
+        (args, custom_flags, value_flags): (I, &[&str], &[&str]),
+    ) -> Result<Self, Self::Error> {
+        ArgSet::try_from((args, custom_flags, value_flags, &[][..]))
+    }
+}
+
+/// Parses `args` against the boolean `custom_flags` and the `value_flags`
+/// that take an argument, either as `--opt value`, `--opt=value`, `-o value`
+/// or `-ovalue`. A long option in both lists takes an optional argument,
+/// which must then be given as `--opt=value`. Every argument after `--` is
+/// a file, even one that starts with a dash. Options and their values must
+/// be valid UTF-8, but for the values of the `path_flags`, which are among
+/// the `value_flags`, and the files.
+impl<I, S> TryFrom<(I, &[&str], &[&str], &[&str])> for ArgSet
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    type Error = RswcError;
+
+    fn try_from(
+        (args, custom_flags, value_flags, path_flags): (I, &[&str], &[&str], &[&str]),
+    ) -> Result<Self, Self::Error> {
         let mut flags = Vec::new();
+        let mut values = Vec::new();
+        let mut paths = Vec::new();
         let mut file_paths = Vec::new();
+        let mut args = args.into_iter();
+        let mut push_value = |name: &str, value: &OsStr| {
+            if path_flags.contains(&name) {
+                paths.push((name.to_string(), PathBuf::from(value)));
+            } else {
+                values.push((name.to_string(), utf8_value(value, name)?));
+            }
+            Ok::<_, RswcError>(())
+        };
 
-        for arg in args {
-            let arg = arg.as_ref();
+        while let Some(arg) = args.next() {
+            let Some(arg) = arg.as_ref().to_str() else {
+                let arg = arg.as_ref();
+                if arg.as_encoded_bytes().starts_with(b"-") {
+                    let (given, name, value) = split_option(arg, custom_flags, value_flags)?;
+                    flags.extend(given);
+                    push_value(&name, value)?;
+                } else {
+                    file_paths.push(PathBuf::from(arg));
+                }
+                continue;
+            };
 
-            if arg.starts_with('-') && arg != "-" {
-                if arg.len() > 2 && !arg.starts_with("--") {
-                    for ch in arg.chars().skip(1) {
-                        let flag = format!("-{}", ch);
-                        if custom_flags.contains(&flag.as_str()) {
-                            flags.push(flag);
-                        } else {
-                            return Err(format!("rswc: invalid option -{}", ch));
-                        }
+            if arg == "--" {
+                file_paths.extend(args.map(|arg| PathBuf::from(arg.as_ref())));
+                break;
+            } else if arg.starts_with("--") {
+                if let Some((name, value)) = arg.split_once('=') {
+                    if value_flags.contains(&name) {
+                        push_value(name, value.as_ref())?;
+                    } else if custom_flags.contains(&name) {
+                        return Err(RswcError::Usage(format!(
+                            "rswc: option '{}' doesn't allow an argument",
+                            name
+                        )));
+                    } else {
+                        return Err(unrecognized(name, custom_flags, value_flags));
                     }
                 } else if custom_flags.contains(&arg) {
                     flags.push(arg.to_string());
+                } else if value_flags.contains(&arg) {
+                    let value = args.next().ok_or_else(|| {
+                        RswcError::Usage(format!("rswc: option '{}' requires an argument", arg))
+                    })?;
+                    push_value(arg, value.as_ref())?;
                 } else {
-                    return Err(format!("rswc: unrecognized option {}", arg));
+                    return Err(unrecognized(arg, custom_flags, value_flags));
+                }
+            } else if arg.starts_with('-') && arg != "-" {
+                for (i, ch) in arg.char_indices().skip(1) {
+                    let flag = format!("-{}", ch);
+                    if value_flags.contains(&flag.as_str()) {
+                        let rest = &arg[i + ch.len_utf8()..];
+                        if rest.is_empty() {
+                            let value = args.next().ok_or_else(|| {
+                                RswcError::Usage(format!(
+                                    "rswc: option requires an argument -- '{}'",
+                                    ch
+                                ))
+                            })?;
+                            push_value(&flag, value.as_ref())?;
+                        } else {
+                            push_value(&flag, rest.as_ref())?;
+                        }
+                        break;
+                    } else if custom_flags.contains(&flag.as_str()) {
+                        flags.push(flag);
+                    } else if arg.len() > 2 {
+                        return Err(RswcError::Usage(format!("rswc: invalid option -{}", ch)));
+                    } else {
+                        return Err(RswcError::Usage(format!(
+                            "rswc: unrecognized option {}",
+                            arg
+                        )));
+                    }
                 }
             } else {
                 file_paths.push(PathBuf::from(arg));
             }
         }
 
-        Ok(ArgSet { flags, file_paths })
+        Ok(ArgSet {
+            flags,
+            values,
+            paths,
+            file_paths,
+        })
+    }
+}
+
+/// Splits an option `arg` that isn't valid UTF-8, like `--output=<path>` or
+/// `-lo<path>`, into the flags it starts with, the option given a value in
+/// it and the value, which is valid UTF-8 up to its first byte at least.
+fn split_option<'a>(
+    arg: &'a OsStr,
+    custom_flags: &[&str],
+    value_flags: &[&str],
+) -> Result<(Vec<String>, String, &'a OsStr), RswcError> {
+    let bytes = arg.as_encoded_bytes();
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(e) => e.valid_up_to(),
+    };
+    let head = std::str::from_utf8(&bytes[..valid]).unwrap_or_default();
+    let unrecognized = || RswcError::Usage(format!("rswc: unrecognized option {}", arg.display()));
+
+    let mut flags = Vec::new();
+    let (name, start) = if let Some(long) = head.strip_prefix("--") {
+        let (name, _) = long.split_once('=').ok_or_else(unrecognized)?;
+        (format!("--{}", name), name.len() + 3)
+    } else {
+        let mut found = None;
+        for (i, ch) in head.char_indices().skip(1) {
+            let flag = format!("-{}", ch);
+            if value_flags.contains(&flag.as_str()) {
+                found = Some((flag, i + ch.len_utf8()));
+                break;
+            } else if custom_flags.contains(&flag.as_str()) {
+                flags.push(flag);
+            } else {
+                return Err(unrecognized());
+            }
+        }
+        found.ok_or_else(unrecognized)?
+    };
+    if !value_flags.contains(&name.as_str()) {
+        return Err(unrecognized());
+    }
+    // SAFETY: `start` follows the ASCII `=` or the option's character, within
+    // the valid UTF-8 `head`, so the value starts on a character boundary.
+    let value = unsafe { OsStr::from_encoded_bytes_unchecked(&bytes[start..]) };
+    Ok((flags, name, value))
+}
+
+/// `value` given to option `name`, which must be valid UTF-8.
+fn utf8_value(value: &OsStr, name: &str) -> Result<String, RswcError> {
+    value.to_str().map(str::to_string).ok_or_else(|| {
+        RswcError::Usage(format!(
+            "rswc: invalid argument '{}' for '{}' (not valid UTF-8)",
+            value.display(),
+            name
+        ))
+    })
+}
+
+/// The error for an unknown long option, suggesting the closest known one
+/// when it is only a typo away.
+fn unrecognized(name: &str, custom_flags: &[&str], value_flags: &[&str]) -> RswcError {
+    let closest = custom_flags
+        .iter()
+        .chain(value_flags)
+        .filter(|known| known.starts_with("--"))
+        .map(|known| (edit_distance(name, known), known))
+        .min_by_key(|&(distance, _)| distance);
+    RswcError::Usage(match closest {
+        Some((distance, known)) if distance <= 2.max(name.len() / 4) => {
+            format!(
+                "rswc: unrecognized option {}; did you mean {}?",
+                name, known
+            )
+        }
+        _ => format!("rswc: unrecognized option {}", name),
+    })
+}
+
+/// The Levenshtein distance between `a` and `b`, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
     }
+    row[b.len()]
 }
 
 #[cfg(test)]
@@ -57,6 +407,7 @@ mod tests {
     const CUSTOM_FLAGS: &[&str] = &[
         "-l", "-c", "-w", "-m", "--lines", "--bytes", "--words", "--chars",
     ];
+    const VALUE_FLAGS: &[&str] = &["-j", "--threads"];
 
     #[test]
     fn test_single_valid_flag_and_file() {
@@ -78,14 +429,172 @@ mod tests {
     fn test_invalid_combined_valid_flags() {
         let args = vec!["-lz"];
         let err = ArgSet::try_from((args, CUSTOM_FLAGS)).unwrap_err();
-        assert_eq!(err, "rswc: invalid option -z");
+        assert_eq!(err.to_string(), "rswc: invalid option -z");
     }
 
     #[test]
     fn test_unknown_flag() {
         let args = vec!["-z"];
         let err = ArgSet::try_from((args, CUSTOM_FLAGS)).unwrap_err();
-        assert_eq!(err, "rswc: unrecognized option -z");
+        assert_eq!(err.to_string(), "rswc: unrecognized option -z");
+    }
+
+    #[test]
+    fn test_end_of_options() {
+        let args = vec!["-l", "--", "-weird-filename", "--lines", "-"];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS)).unwrap();
+        assert_eq!(result.flags, vec!["-l"]);
+        assert_eq!(
+            result.file_paths,
+            vec![
+                PathBuf::from("-weird-filename"),
+                PathBuf::from("--lines"),
+                PathBuf::from("-"),
+            ]
+        );
+
+        // Only the first `--` ends the options; a value can be `--`.
+        let args = vec!["-j", "--", "--", "--"];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(result.value("-j"), Some("--"));
+        assert_eq!(result.file_paths, vec![PathBuf::from("--")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_files() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let file = OsStr::from_bytes(b"caf\xe9.txt");
+        let dashed = OsStr::from_bytes(b"-\xff");
+        let args = [OsStr::new("-l"), file, OsStr::new("--"), dashed];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(result.flags, vec!["-l"]);
+        assert_eq!(
+            result.file_paths,
+            vec![PathBuf::from(file), PathBuf::from(dashed)]
+        );
+
+        // Options and their values are UTF-8.
+        let err = ArgSet::try_from(([dashed], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(err.to_string(), "rswc: unrecognized option -\u{fffd}");
+        let args = [OsStr::new("-j"), file];
+        let err = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: invalid argument 'caf\u{fffd}.txt' for '-j' (not valid UTF-8)"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+
+        const VALUE_FLAGS: &[&str] = &["-j", "--threads", "-o", "--output"];
+        const PATH_FLAGS: &[&str] = &["-o", "--output"];
+        let parse =
+            |args: &[&OsStr]| ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS));
+        let path = OsStr::from_bytes(b"caf\xe9.txt");
+        let long = OsStr::from_bytes(b"--output=caf\xe9.txt");
+        let short = OsStr::from_bytes(b"-locaf\xe9.txt");
+
+        for args in [
+            &[OsStr::new("--output"), path][..],
+            &[long],
+            &[OsStr::new("-l"), OsStr::new("-o"), path],
+            &[short],
+        ] {
+            let result = parse(args).unwrap();
+            assert_eq!(
+                result.path("--output").or(result.path("-o")),
+                Some(Path::new(path))
+            );
+            assert!(result.values.is_empty() && result.file_paths.is_empty());
+        }
+        assert_eq!(parse(&[short]).unwrap().flags, vec!["-l"]);
+        assert!(
+            parse(&[OsStr::new("-o"), OsStr::new("out.txt")])
+                .unwrap()
+                .given("-o")
+        );
+
+        // Other values are still UTF-8.
+        let err = parse(&[OsStr::from_bytes(b"--threads=\xff")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: invalid argument '\u{fffd}' for '--threads' (not valid UTF-8)"
+        );
+        let err = parse(&[OsStr::from_bytes(b"--outptu=\xff")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: unrecognized option --outptu=\u{fffd}"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_unpaired_surrogate_files() {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+
+        // `a\u{d800}.txt`: a name NTFS allows, that isn't valid UTF-16.
+        let file = OsString::from_wide(&[0x61, 0xd800, 0x2e, 0x74, 0x78, 0x74]);
+        let args = [OsString::from("-l"), file.clone()];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(result.flags, vec!["-l"]);
+        assert_eq!(result.file_paths, vec![PathBuf::from(file.clone())]);
+
+        let args = [OsString::from("--threads"), file];
+        let err = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: invalid argument 'a\u{fffd}.txt' for '--threads' (not valid UTF-8)"
+        );
+    }
+
+    #[test]
+    fn test_get_value() {
+        let args = vec!["--threads", "4", "-jx", "file.txt"];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(result.get_value::<usize>("--threads"), Ok(Some(4)));
+        assert_eq!(result.get_value::<usize>("--lines"), Ok(None));
+        assert_eq!(
+            result.get_value::<usize>("-j").unwrap_err().to_string(),
+            "rswc: invalid argument 'x' for '-j'"
+        );
+
+        let args = vec!["--threads=8"];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(result.get_value::<u8>("--threads"), Ok(Some(8)));
+    }
+
+    #[test]
+    fn test_overrides() {
+        const RULES: &[Rule] = &[Rule::Conflicts("--no-parallel", "--threads")];
+        const SYNONYMS: &[[&str; 2]] = &[["-j", "--threads"], ["-l", "--lines"]];
+        let flags = ["--no-parallel", "-l", "--lines", "-w", "--words"];
+        let args = vec!["-j", "2", "-w"];
+        let result = ArgSet::try_from((args, &flags[..], VALUE_FLAGS)).unwrap();
+        assert!(result.overrides("--threads=4", RULES, SYNONYMS));
+        assert!(result.overrides("-w", RULES, SYNONYMS));
+        assert!(!result.overrides("--lines", RULES, SYNONYMS));
+
+        let args = vec!["--no-parallel"];
+        let result = ArgSet::try_from((args, &flags[..], VALUE_FLAGS)).unwrap();
+        assert!(result.overrides("--threads=4", RULES, SYNONYMS));
+        assert!(!result.overrides("-l", RULES, SYNONYMS));
+    }
+
+    #[test]
+    fn test_options() {
+        const SYNONYMS: &[[&str; 2]] = &[["-j", "--threads"], ["-l", "--lines"]];
+        let args = vec!["-lw", "-j", "4", "--threads=2", "a.txt"];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(
+            result.options(SYNONYMS),
+            ["--lines", "-w", "--threads=4", "--threads=2"]
+        );
     }
 
     #[test]
@@ -120,10 +629,133 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_value_flags() {
+        let args = vec![
+            "-j",
+            "4",
+            "--threads=8",
+            "-lj2",
+            "--threads",
+            "3",
+            "file.txt",
+        ];
+        let result = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert_eq!(result.flags, vec!["-l"]);
+        assert_eq!(result.value("-j"), Some("2"));
+        assert_eq!(result.value("--threads"), Some("3"));
+        assert_eq!(result.values("--threads").collect::<Vec<_>>(), ["8", "3"]);
+        assert_eq!(result.file_paths, vec![PathBuf::from("file.txt")]);
+    }
+
+    #[test]
+    fn test_value_flag_missing_argument() {
+        let err = ArgSet::try_from((vec!["--threads"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: option '--threads' requires an argument"
+        );
+
+        let err = ArgSet::try_from((vec!["-lj"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(err.to_string(), "rswc: option requires an argument -- 'j'");
+    }
+
+    #[test]
+    fn test_boolean_flag_with_argument() {
+        let err = ArgSet::try_from((vec!["--lines=3"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: option '--lines' doesn't allow an argument"
+        );
+    }
+
     #[test]
     fn test_invalid_long_flag() {
         let args = vec!["--byte"];
         let err = ArgSet::try_from((args, CUSTOM_FLAGS)).unwrap_err();
-        assert_eq!(err, "rswc: unrecognized option --byte");
+        assert_eq!(
+            err.to_string(),
+            "rswc: unrecognized option --byte; did you mean --bytes?"
+        );
+    }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(Path::new("https://example.com/data.txt")));
+        assert!(is_url(Path::new("http://localhost:8080/")));
+        assert!(!is_url(Path::new("ftp://example.com/data.txt")));
+        assert!(!is_url(Path::new("http/data.txt")));
+    }
+
+    const RULES: &[Rule] = &[
+        Rule::Conflicts("--lines", "--threads"),
+        Rule::Requires("--chars", &["--words"]),
+        Rule::Affects("--bytes", &["-l", "--lines", "-j"]),
+    ];
+
+    #[test]
+    fn test_validate_conflicts_names_both_options() {
+        let args_set =
+            ArgSet::try_from((["--threads=2", "--lines"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        let err = args_set.validate(RULES).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: --lines cannot be used with --threads"
+        );
+    }
+
+    #[test]
+    fn test_validate_requires_and_affects() {
+        let args_set = ArgSet::try_from((["--chars"], CUSTOM_FLAGS)).unwrap();
+        let err = args_set.validate(RULES).unwrap_err();
+        assert_eq!(err.to_string(), "rswc: --chars needs --words");
+
+        let args_set = ArgSet::try_from((["--bytes", "--chars", "--words"], CUSTOM_FLAGS)).unwrap();
+        let warnings = args_set.validate(RULES).unwrap();
+        assert_eq!(
+            warnings,
+            vec!["rswc: warning: --bytes has no effect without -l, --lines or -j"]
+        );
+
+        let args_set =
+            ArgSet::try_from((["--bytes", "-j", "2"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap();
+        assert!(args_set.validate(RULES).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_long_flag_suggests_closest() {
+        let err = ArgSet::try_from((["--bytess"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: unrecognized option --bytess; did you mean --bytes?"
+        );
+
+        let err = ArgSet::try_from((["--thraeds=2"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rswc: unrecognized option --thraeds; did you mean --threads?"
+        );
+
+        let err = ArgSet::try_from((["--frobnicate"], CUSTOM_FLAGS, VALUE_FLAGS)).unwrap_err();
+        assert_eq!(err.to_string(), "rswc: unrecognized option --frobnicate");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("--lines", "--lines"), 0);
+        assert_eq!(edit_distance("--line", "--lines"), 1);
+        assert_eq!(edit_distance("--wrods", "--words"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_optional_value() {
+        let value_flags = &["--threads", "--tokens"][..];
+        let custom_flags = &["--tokens"][..];
+        let args = vec!["--tokens", "a.txt", "--tokens=p50k_base"];
+        let result = ArgSet::try_from((args, custom_flags, value_flags)).unwrap();
+        assert_eq!(result.flags, vec!["--tokens"]);
+        assert_eq!(result.value("--tokens"), Some("p50k_base"));
+        assert_eq!(result.file_paths, vec![PathBuf::from("a.txt")]);
     }
 }
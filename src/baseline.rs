@@ -0,0 +1,199 @@
+//! Changes since a report saved with `--json`, for `--baseline`, to follow
+//! how a set of documents grows over time:
+//!
+//! ```sh
+//! rswc --json docs/*.md > baseline.json
+//! # ... a week later
+//! rswc --baseline=baseline.json docs/*.md
+//! ```
+
+use crate::counter::{CountOptions, FileResult, counted_inputs};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The counts of a file by column name, like `lines`.
+type Columns = BTreeMap<String, u64>;
+
+/// The counts of every file of a saved report, archive members named like
+/// `a.tar!member`, and their total.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Baseline {
+    files: BTreeMap<String, Columns>,
+    total: Columns,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("rswc: cannot read baseline '{}': {}", path.display(), e))?;
+        Baseline::parse(&json)
+            .map_err(|e| format!("rswc: invalid baseline '{}': {}", path.display(), e))
+    }
+
+    /// Reads the output of `--json`. Files that could not be read are left
+    /// out.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let report: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let files = report
+            .get("files")
+            .and_then(Value::as_array)
+            .ok_or("expected the output of --json")?;
+        let mut baseline = Baseline {
+            total: report.get("total").map(columns).unwrap_or_default(),
+            ..Default::default()
+        };
+        for file in files {
+            baseline.add(file, None);
+        }
+        Ok(baseline)
+    }
+
+    fn add(&mut self, file: &Value, archive: Option<&str>) {
+        let Some(name) = file.get("file").and_then(Value::as_str) else {
+            return;
+        };
+        let name = match archive {
+            Some(archive) => format!("{}!{}", archive, name),
+            None => name.to_string(),
+        };
+        if file.get("error").is_some() {
+            return;
+        }
+        match file.get("members").and_then(Value::as_array) {
+            Some(members) => {
+                for member in members {
+                    self.add(member, Some(&name));
+                }
+            }
+            None => {
+                self.files.insert(name, columns(file));
+            }
+        }
+    }
+}
+
+fn columns(counts: &Value) -> Columns {
+    counts
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| Some((name.clone(), value.as_u64()?)))
+        .collect()
+}
+
+/// The differences of the counts of `now` from those of `then`, like
+/// `+123 lines, -4 words`, leaving out columns that didn't change or that
+/// only one of them has.
+fn deltas(then: &Columns, now: &[(&str, u64)]) -> Vec<String> {
+    now.iter()
+        .filter_map(|&(name, value)| {
+            let delta = value as i64 - *then.get(name)? as i64;
+            (delta != 0).then(|| format!("{:+} {}", delta, name))
+        })
+        .collect()
+}
+
+/// Prints how each input of `results` changed since `baseline`: its deltas,
+/// `new` with its counts, or `removed` for files only the baseline has.
+/// Unchanged files are left out. The total has the change in the number of
+/// files as well.
+pub fn print_deltas<W: Write>(
+    writer: &mut W,
+    baseline: &Baseline,
+    results: &[FileResult],
+    flags: &CountOptions,
+) -> io::Result<()> {
+    writeln!(writer)?;
+    writeln!(writer, "changes since the baseline:")?;
+    let inputs = counted_inputs(results);
+    for (name, counts) in &inputs {
+        let columns = counts.named_columns(flags);
+        match baseline.files.get(name) {
+            Some(then) => {
+                let deltas = deltas(then, &columns);
+                if !deltas.is_empty() {
+                    writeln!(writer, "{}: {}", name, deltas.join(", "))?;
+                }
+            }
+            None => {
+                let counts: Vec<String> = columns
+                    .iter()
+                    .map(|(column, value)| format!("{} {}", value, column))
+                    .collect();
+                writeln!(writer, "{}: new, {}", name, counts.join(", "))?;
+            }
+        }
+    }
+    for name in baseline.files.keys() {
+        if !inputs.iter().any(|(input, _)| input == name) {
+            writeln!(writer, "{}: removed", name)?;
+        }
+    }
+
+    let mut total = deltas(
+        &baseline.total,
+        &crate::counter::total(results).named_columns(flags),
+    );
+    let files = inputs.len() as i64 - baseline.files.len() as i64;
+    if files != 0 {
+        total.push(format!("{:+} files", files));
+    }
+    if total.is_empty() {
+        writeln!(writer, "total: unchanged")
+    } else {
+        writeln!(writer, "total: {}", total.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+
+    #[test]
+    fn test_parse() {
+        let json = r#"{"files":[{"file":"a.txt","lines":2,"words":5},{"file":"b.txt","error":"gone"},{"file":"c.tar","members":[{"file":"x","lines":1}]}],"total":{"lines":3,"words":5}}"#;
+        let baseline = Baseline::parse(json).unwrap();
+        assert_eq!(
+            baseline.files.keys().collect::<Vec<_>>(),
+            ["a.txt", "c.tar!x"]
+        );
+        assert_eq!(baseline.files["a.txt"]["words"], 5);
+        assert_eq!(baseline.total["lines"], 3);
+        assert!(Baseline::parse("[]").is_err());
+        assert!(Baseline::parse("{").is_err());
+    }
+
+    #[test]
+    fn test_print_deltas() {
+        let json = r#"{"files":[{"file":"a.txt","lines":2,"words":5},{"file":"b.txt","lines":1,"words":1},{"file":"gone.txt","lines":4,"words":4}],"total":{"lines":7,"words":10}}"#;
+        let baseline = Baseline::parse(json).unwrap();
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let counts = |lines, words| Counts {
+            lines,
+            words,
+            ..Default::default()
+        };
+        let results = [
+            FileResult::Ok("a.txt".into(), counts(125, 5)),
+            FileResult::Ok("new.txt".into(), counts(3, 9)),
+        ];
+        let mut out = Vec::new();
+        print_deltas(&mut out, &baseline, &results, &flags).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\nchanges since the baseline:\n\
+             a.txt: +123 lines\n\
+             new.txt: new, 3 lines, 9 words\n\
+             b.txt: removed\n\
+             gone.txt: removed\n\
+             total: +121 lines, +4 words, -1 files\n"
+        );
+    }
+}
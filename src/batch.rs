@@ -0,0 +1,276 @@
+//! `rswc batch`: many counting jobs from one process. Every input line is a
+//! job of options and files, answered by one line of JSON, so callers can
+//! avoid starting a process per job.
+
+#[cfg(feature = "core")]
+use crate::counter::process_files;
+use crate::counter::{CountOptions, Counts, FileResult, total};
+#[cfg(feature = "core")]
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+/// Turns the words of a job line into the flags and files to count.
+pub type ParseJob<'a> = dyn Fn(Vec<String>) -> Result<(CountOptions, Vec<PathBuf>), String> + 'a;
+
+/// Runs every job of `input`, writing one result per job to `output` as soon
+/// as it is done. Blank lines are skipped; jobs are numbered by line.
+#[cfg(feature = "core")]
+pub fn run<R: BufRead, W: Write>(input: R, output: &mut W, parse: &ParseJob<'_>) -> io::Result<()> {
+    for (n, line) in input.lines().enumerate() {
+        let line = line?;
+        let words: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        let job = n + 1;
+        let result = match parse(words) {
+            Ok((_, files)) if files.is_empty() => {
+                error_json(job, "rswc: a batch job needs at least one file")
+            }
+            Ok((flags, files)) => job_json(job, &process_files(&files, &flags), &flags),
+            Err(e) => error_json(job, &e),
+        };
+        writeln!(output, "{}", result)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "core")]
+fn job_json(job: usize, results: &[FileResult], flags: &CountOptions) -> String {
+    let files: Vec<String> = results.iter().map(|r| result_json(r, flags)).collect();
+    format!(
+        "{{\"job\":{},\"files\":[{}],\"total\":{}}}",
+        job,
+        files.join(","),
+        counts_json(&total(results), flags, None)
+    )
+}
+
+/// The results as one JSON object, like a job of `rswc batch` without the
+/// job number, for `--json`.
+pub fn report_json(results: &[FileResult], flags: &CountOptions) -> String {
+    let files: Vec<String> = results.iter().map(|r| result_json(r, flags)).collect();
+    format!(
+        "{{\"files\":[{}],\"total\":{}}}",
+        files.join(","),
+        counts_json(&total(results), flags, None)
+    )
+}
+
+/// The record of `--jsonl` for one file, written as soon as it is counted:
+/// its counts or its error, like the files of `--json`, typed `file`.
+pub fn file_record(result: &FileResult, flags: &CountOptions) -> String {
+    typed("file", &result_json(result, flags))
+}
+
+/// The last record of `--jsonl`, the total of the files, typed `total`.
+pub fn total_record(counts: &Counts, flags: &CountOptions) -> String {
+    typed("total", &counts_json(counts, flags, None))
+}
+
+fn typed(kind: &str, object: &str) -> String {
+    let fields = &object[1..object.len() - 1];
+    let separator = if fields.is_empty() { "" } else { "," };
+    format!("{{\"type\":\"{}\"{}{}}}", kind, separator, fields)
+}
+
+#[cfg(feature = "core")]
+fn error_json(job: usize, message: &str) -> String {
+    format!("{{\"job\":{},\"error\":{}}}", job, json_string(message))
+}
+
+pub(crate) fn result_json(result: &FileResult, flags: &CountOptions) -> String {
+    match result {
+        FileResult::Ok(path, c) => counts_json(c, flags, Some(&path.to_string_lossy())),
+        FileResult::Err(path, msg) => format!(
+            "{{\"file\":{},\"error\":{}}}",
+            json_string(&path.to_string_lossy()),
+            json_string(&msg.to_string())
+        ),
+        FileResult::Archive(path, members) => {
+            let members: Vec<String> = members.iter().map(|m| result_json(m, flags)).collect();
+            format!(
+                "{{\"file\":{},\"members\":[{}]}}",
+                json_string(&path.to_string_lossy()),
+                members.join(",")
+            )
+        }
+    }
+}
+
+pub(crate) fn counts_json(counts: &Counts, flags: &CountOptions, file: Option<&str>) -> String {
+    let mut fields: Vec<String> = file
+        .map(|f| format!("\"file\":{}", json_string(f)))
+        .into_iter()
+        .collect();
+    if let (Some(detected), Some(_)) = (counts.detected, file) {
+        fields.push(format!("\"type\":\"{}\"", detected));
+    }
+    let columns = counts.named_columns(flags);
+    fields.extend(
+        columns
+            .iter()
+            .map(|(name, value)| format!("\"{}\":{}", name, value)),
+    );
+    let approximate: Vec<String> = columns
+        .iter()
+        .filter(|&&(name, value)| counts.estimates.is_estimated(name, value))
+        .map(|(name, _)| json_string(name))
+        .collect();
+    if !approximate.is_empty() {
+        fields.push(format!("\"approximate\":[{}]", approximate.join(",")));
+    }
+    format!("{{{}}}", fields.join(","))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Estimates;
+    use std::io;
+
+    #[cfg(feature = "core")]
+    fn parse(words: Vec<String>) -> Result<(CountOptions, Vec<PathBuf>), String> {
+        let mut flags = CountOptions::default();
+        let mut files = Vec::new();
+        for w in words {
+            match w.as_str() {
+                "-l" => flags.lines = true,
+                "-c" => flags.bytes = true,
+                w if w.starts_with('-') => return Err(format!("rswc: invalid option {}", w)),
+                w => files.push(PathBuf::from(w)),
+            }
+        }
+        Ok((flags, files))
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_run_jobs() {
+        let input = "-l testdata/test.txt\n\n-c testdata/test.txt missing.txt\n-z x\n-l\n";
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, &parse).unwrap();
+
+        let expected = r#"{"job":1,"files":[{"file":"testdata/test.txt","lines":7145}],"total":{"lines":7145}}
+{"job":3,"files":[{"file":"testdata/test.txt","bytes":342190},{"file":"missing.txt","error":"No such file or directory (os error 2)"}],"total":{"bytes":342190}}
+{"job":4,"error":"rswc: invalid option -z"}
+{"job":5,"error":"rswc: a batch job needs at least one file"}
+"#;
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let results = [
+            FileResult::Ok(
+                "a.txt".into(),
+                Counts {
+                    lines: 2,
+                    ..Default::default()
+                },
+            ),
+            FileResult::Err("b.txt".into(), io::Error::other("gone").into()),
+        ];
+        assert_eq!(
+            report_json(&results, &flags),
+            r#"{"files":[{"file":"a.txt","lines":2},{"file":"b.txt","error":"gone"}],"total":{"lines":2}}"#
+        );
+    }
+
+    #[test]
+    fn test_jsonl_records() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let counts = Counts {
+            lines: 2,
+            ..Default::default()
+        };
+        let result = FileResult::Ok("a.txt".into(), counts.clone());
+        assert_eq!(
+            file_record(&result, &flags),
+            r#"{"type":"file","file":"a.txt","lines":2}"#
+        );
+        let result = FileResult::Err("b.txt".into(), io::Error::other("gone").into());
+        assert_eq!(
+            file_record(&result, &flags),
+            r#"{"type":"file","file":"b.txt","error":"gone"}"#
+        );
+        assert_eq!(
+            total_record(&counts, &flags),
+            r#"{"type":"total","lines":2}"#
+        );
+        assert_eq!(
+            total_record(&counts, &CountOptions::default()),
+            r#"{"type":"total"}"#
+        );
+    }
+
+    #[test]
+    fn test_counts_json_marks_estimates() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let mut counts = Counts {
+            lines: 2,
+            ..Default::default()
+        };
+        assert_eq!(counts_json(&counts, &flags, None), r#"{"lines":2}"#);
+        counts.estimates.insert(Estimates::TOKENS);
+        assert_eq!(counts_json(&counts, &flags, None), r#"{"lines":2}"#);
+
+        let mut saturated = Counts {
+            lines: u64::MAX,
+            ..Default::default()
+        };
+        saturated.estimates.insert(Estimates::SATURATED);
+        assert_eq!(
+            counts_json(&saturated, &flags, None),
+            format!(r#"{{"lines":{},"approximate":["lines"]}}"#, u64::MAX)
+        );
+
+        #[cfg(feature = "tokens")]
+        {
+            let flags = CountOptions {
+                lines: true,
+                tokens: Some(crate::tokens::Encoding::O200kBase),
+                ..Default::default()
+            };
+            assert_eq!(
+                counts_json(&counts, &flags, None),
+                r#"{"lines":2,"tokens":0,"approximate":["tokens"]}"#
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a \"b\"\\\n\u{1}"), r#""a \"b\"\\\n\u0001""#);
+    }
+}
@@ -0,0 +1,63 @@
+//! Blank lines for `--blank-lines`: empty lines and lines of whitespace
+//! only, counted like the blank lines of `--code` but without classifying
+//! the rest.
+
+/// Counts the blank lines of input fed in chunks of any size.
+#[derive(Debug)]
+pub struct BlankLines {
+    delimiter: u8,
+    count: u64,
+    /// Whether the line being read has anything but whitespace so far.
+    seen_text: bool,
+    /// Whether anything was read since the last delimiter.
+    seen_any: bool,
+}
+
+impl BlankLines {
+    /// Lines end at `delimiter`, like the line count.
+    pub fn new(delimiter: u8) -> Self {
+        BlankLines {
+            delimiter,
+            count: 0,
+            seen_text: false,
+            seen_any: false,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if b == self.delimiter {
+                if !self.seen_text {
+                    self.count += 1;
+                }
+                self.seen_text = false;
+                self.seen_any = false;
+            } else {
+                self.seen_text |= !b.is_ascii_whitespace();
+                self.seen_any = true;
+            }
+        }
+    }
+
+    /// The blank lines, the last one included if it had no delimiter.
+    pub fn finish(self) -> u64 {
+        self.count + u64::from(self.seen_any && !self.seen_text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blank_lines_in_any_chunking() {
+        let data = b"a\n\n  \t\nb c\n\r\n \n  ";
+        for size in 1..=data.len() {
+            let mut blank = BlankLines::new(b'\n');
+            for chunk in data.chunks(size) {
+                blank.feed(chunk);
+            }
+            assert_eq!(blank.finish(), 5, "chunk size {}", size);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Lines per hour or day for `--bucket`, by the timestamps of log lines, for
+//! a quick profile of traffic or volume over time.
+
+use crate::counter::{FileResult, total};
+use crate::lines::LineSplitter;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// How long the time buckets of `--bucket` are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Bucket {
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "hour" => Ok(Bucket::Hour),
+            "day" => Ok(Bucket::Day),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--bucket' (expected hour or day)",
+                name
+            )),
+        }
+    }
+}
+
+const MONTHS: [&[u8]; 12] = [
+    b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov", b"Dec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Item {
+    Year,
+    Month,
+    MonthName,
+    Day,
+    Hour,
+    /// Minutes and seconds are matched, but don't pick a bucket.
+    TwoDigits,
+    Literal(u8),
+}
+
+/// Where the timestamp of a line is and what it looks like, in a subset
+/// of `strftime`: `%Y`, `%m`, `%b`, `%d`, `%H`, `%M`, `%S` and `%%`. The
+/// first place in a line it matches is the timestamp of the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimestampFormat(Vec<Item>);
+
+impl Default for TimestampFormat {
+    /// ISO 8601 and most logging libraries: `2024-05-01 13:45:12` and
+    /// `2024-05-01T13:45:12Z`.
+    fn default() -> Self {
+        TimestampFormat::new("%Y-%m-%d %H").unwrap_or_else(|_| unreachable!())
+    }
+}
+
+impl TimestampFormat {
+    pub fn new(spec: &str) -> Result<Self, String> {
+        let mut items = Vec::new();
+        let mut bytes = spec.bytes();
+        while let Some(b) = bytes.next() {
+            if b != b'%' {
+                items.push(Item::Literal(b));
+                continue;
+            }
+            items.push(match bytes.next() {
+                Some(b'Y') => Item::Year,
+                Some(b'm') => Item::Month,
+                Some(b'b') => Item::MonthName,
+                Some(b'd') => Item::Day,
+                Some(b'H') => Item::Hour,
+                Some(b'M' | b'S') => Item::TwoDigits,
+                Some(b'%') => Item::Literal(b'%'),
+                _ => {
+                    return Err(format!(
+                        "rswc: invalid timestamp format '{}' (expected %Y, %m or %b, %d, and optionally %H, %M, %S)",
+                        spec
+                    ));
+                }
+            });
+        }
+
+        let has = |wanted: &[Item]| items.iter().any(|i| wanted.contains(i));
+        if !(has(&[Item::Year]) && has(&[Item::Month, Item::MonthName]) && has(&[Item::Day])) {
+            return Err(format!(
+                "rswc: timestamp format '{}' needs a year (%Y), a month (%m or %b) and a day (%d)",
+                spec
+            ));
+        }
+        Ok(TimestampFormat(items))
+    }
+
+    /// Whether the format has the hour, as hourly buckets need.
+    pub fn has_hour(&self) -> bool {
+        self.0.contains(&Item::Hour)
+    }
+
+    /// The year, month, day and hour of the first timestamp in `line`. A
+    /// space of the format also matches the `T` of ISO 8601.
+    fn find(&self, line: &[u8]) -> Option<[u32; 4]> {
+        (0..line.len()).find_map(|start| self.parse(&line[start..]))
+    }
+
+    fn parse(&self, mut text: &[u8]) -> Option<[u32; 4]> {
+        let mut time = [0; 4];
+        for item in &self.0 {
+            let len = match *item {
+                Item::Literal(b) => {
+                    let first = *text.first()?;
+                    if first != b && !(b == b' ' && first == b'T') {
+                        return None;
+                    }
+                    1
+                }
+                Item::Year => {
+                    time[0] = number(text, 4)?;
+                    4
+                }
+                Item::Month => {
+                    time[1] = number(text, 2)?;
+                    2
+                }
+                Item::MonthName => {
+                    time[1] = MONTHS.iter().position(|m| text.starts_with(m))? as u32 + 1;
+                    3
+                }
+                Item::Day => {
+                    time[2] = number(text, 2)?;
+                    2
+                }
+                Item::Hour => {
+                    time[3] = number(text, 2)?;
+                    2
+                }
+                Item::TwoDigits => {
+                    number(text, 2)?;
+                    2
+                }
+            };
+            text = &text[len..];
+        }
+
+        let [_, month, day, hour] = time;
+        ((1..=12).contains(&month) && (1..=31).contains(&day) && hour < 24).then_some(time)
+    }
+}
+
+/// The value of the `len` ASCII digits `text` starts with.
+fn number(text: &[u8], len: usize) -> Option<u32> {
+    let digits = text.get(..len)?;
+    digits.iter().try_fold(0, |n, &b| {
+        b.is_ascii_digit().then(|| n * 10 + u32::from(b - b'0'))
+    })
+}
+
+/// The number of lines of each time bucket, in order of time.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "Vec<([u32; 4], usize)>", into = "Vec<([u32; 4], usize)>")
+)]
+pub struct Buckets(BTreeMap<[u32; 4], usize>);
+
+impl Buckets {
+    pub fn merge(&mut self, other: &Buckets) {
+        for (bucket, lines) in &other.0 {
+            *self.0.entry(*bucket).or_default() += lines;
+        }
+    }
+
+    /// Each bucket as `2024-05-01`, or as `2024-05-01 13:00` for hours, with
+    /// its lines.
+    pub fn rows(&self, bucket: Bucket) -> Vec<(String, usize)> {
+        self.0
+            .iter()
+            .map(|(&[year, month, day, hour], &lines)| {
+                let date = format!("{:04}-{:02}-{:02}", year, month, day);
+                match bucket {
+                    Bucket::Hour => (format!("{} {:02}:00", date, hour), lines),
+                    Bucket::Day => (date, lines),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Serialized as pairs of a bucket, `[year, month, day, hour]`, and its
+/// lines, in order of time.
+#[cfg(feature = "serde")]
+impl From<Buckets> for Vec<([u32; 4], usize)> {
+    fn from(buckets: Buckets) -> Self {
+        buckets.0.into_iter().collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Vec<([u32; 4], usize)>> for Buckets {
+    fn from(pairs: Vec<([u32; 4], usize)>) -> Self {
+        Buckets(pairs.into_iter().collect())
+    }
+}
+
+/// Counts the lines of each time bucket of input fed in chunks of any size.
+/// Lines without a timestamp are in no bucket.
+pub struct BucketCounter<'a> {
+    format: &'a TimestampFormat,
+    bucket: Bucket,
+    buckets: Buckets,
+    lines: LineSplitter,
+}
+
+impl<'a> BucketCounter<'a> {
+    pub fn new(bucket: Bucket, format: &'a TimestampFormat) -> Self {
+        BucketCounter {
+            format,
+            bucket,
+            buckets: Buckets::default(),
+            lines: LineSplitter::default(),
+        }
+    }
+
+    /// Splits lines at `delimiter` instead of `\n`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.lines = LineSplitter::new(delimiter);
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut lines = std::mem::take(&mut self.lines);
+        lines.feed(chunk, |line| self.add(line));
+        self.lines = lines;
+    }
+
+    fn add(&mut self, line: &[u8]) {
+        if let Some(mut time) = self.format.find(line) {
+            if self.bucket == Bucket::Day {
+                time[3] = 0;
+            }
+            *self.buckets.0.entry(time).or_default() += 1;
+        }
+    }
+
+    /// Counts the last line if it had no newline.
+    pub fn finish(mut self) -> Buckets {
+        let lines = std::mem::take(&mut self.lines);
+        lines.finish(|line| self.add(line));
+        self.buckets
+    }
+}
+
+/// Prints the lines per bucket of all `results` together.
+pub fn print_buckets<W: Write>(
+    writer: &mut W,
+    results: &[FileResult],
+    bucket: Bucket,
+) -> io::Result<()> {
+    let Some(buckets) = total(results).buckets else {
+        return Ok(());
+    };
+    let rows = buckets.rows(bucket);
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    match bucket {
+        Bucket::Hour => writeln!(writer, "lines per hour:")?,
+        Bucket::Day => writeln!(writer, "lines per day:")?,
+    }
+    for (name, lines) in rows {
+        writeln!(writer, "{:>7} {}", lines, name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buckets(
+        bucket: Bucket,
+        format: &TimestampFormat,
+        log: &[u8],
+        size: usize,
+    ) -> Vec<(String, usize)> {
+        let mut counter = BucketCounter::new(bucket, format);
+        for chunk in log.chunks(size) {
+            counter.feed(chunk);
+        }
+        counter.finish().rows(bucket)
+    }
+
+    #[test]
+    fn test_iso_timestamps_in_any_chunking() {
+        let log = b"2024-05-01 13:05:00 INFO a\n\
+            [2024-05-01T13:59:59Z] b\n\
+            no timestamp\n\
+            2024-05-02 00:00:01 c\n\
+            2024-13-01 10:00:00 not a month";
+        let format = TimestampFormat::default();
+        for size in 1..=log.len() {
+            assert_eq!(
+                buckets(Bucket::Hour, &format, log, size),
+                [
+                    ("2024-05-01 13:00".to_string(), 2),
+                    ("2024-05-02 00:00".to_string(), 1)
+                ],
+                "chunk size {}",
+                size
+            );
+        }
+        assert_eq!(
+            buckets(Bucket::Day, &format, log, log.len()),
+            [("2024-05-01".to_string(), 2), ("2024-05-02".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_custom_timestamp_format() {
+        let format = TimestampFormat::new("%d/%b/%Y:%H:%M:%S").unwrap();
+        let log = b"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET / HTTP/1.0\" 200\n\
+            127.0.0.1 - - [10/Oct/2000:14:01:02 -0700] \"GET / HTTP/1.0\" 200\n\
+            127.0.0.1 - - [11/Oct/2000:09:00:00 -0700] \"GET / HTTP/1.0\" 200\n";
+        assert_eq!(
+            buckets(Bucket::Day, &format, log, log.len()),
+            [("2000-10-10".to_string(), 2), ("2000-10-11".to_string(), 1)]
+        );
+        assert!(format.has_hour());
+        assert!(!TimestampFormat::new("%Y-%m-%d").unwrap().has_hour());
+        assert!(TimestampFormat::new("%H:%M").is_err());
+        assert!(TimestampFormat::new("%Y-%m-%d %Q").is_err());
+    }
+}
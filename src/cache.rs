@@ -0,0 +1,278 @@
+//! The counts of files from earlier runs, for `--cache`, so counting a
+//! mostly unchanged tree again only reads the files that changed:
+//!
+//! ```sh
+//! rswc --cache -lw $(git ls-files)
+//! ```
+//!
+//! A file is recounted when its size or modification time changed, or when
+//! it was last counted with other options. The cache is a JSON file, read
+//! whole at the start and written back once the files are counted.
+
+use crate::counter::{CountOptions, FileResult, process_files};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// The counts of a file, and what they depend on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    /// The modification time, in nanoseconds since the Unix epoch.
+    mtime: u128,
+    /// The fingerprint of the options the file was counted with.
+    options: u64,
+    result: FileResult,
+}
+
+/// The cache file: its entries by canonical path, and the version of rswc
+/// that wrote it, since another one may count differently.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Contents {
+    version: String,
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    contents: Contents,
+    changed: bool,
+}
+
+impl Cache {
+    /// `$RSWC_CACHE`, or `rswc/counts.json` in `$XDG_CACHE_HOME` or
+    /// `~/.cache`.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("RSWC_CACHE") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(dir.join("rswc").join("counts.json"))
+    }
+
+    /// Reads the cache at `path`. A missing or unreadable cache, or one of
+    /// another version of rswc, is an empty one, replaced on `save`.
+    pub fn open(path: &Path) -> Self {
+        let contents = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Contents>(&json).ok())
+            .filter(|contents| contents.version == env!("CARGO_PKG_VERSION"))
+            .unwrap_or_default();
+        Cache {
+            path: path.to_path_buf(),
+            contents,
+            changed: false,
+        }
+    }
+
+    /// Removes the cache at `path`, if there is one.
+    pub fn clear(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether counts with `flags` can be cached. The distinct words of
+    /// `--unique-words` and the words of `--freq` are not kept, so the
+    /// total couldn't be told.
+    pub fn applies(flags: &CountOptions) -> bool {
+        !flags.unique_words && !flags.freq
+    }
+
+    /// Like `process_files`, taking the results of unchanged files from the
+    /// cache, and keeping those of the files counted for the next run.
+    /// Errors are never cached.
+    pub fn process_files(&mut self, files: &[PathBuf], flags: &CountOptions) -> Vec<FileResult> {
+        if !Cache::applies(flags) {
+            return process_files(files, flags);
+        }
+        let options = fingerprint(flags);
+        let keys: Vec<Option<Key>> = files.iter().map(|file| Key::of(file)).collect();
+
+        let mut results: Vec<Option<FileResult>> = files
+            .iter()
+            .zip(&keys)
+            .map(|(file, key)| {
+                let key = key.as_ref()?;
+                let entry = self.contents.entries.get(&key.path)?;
+                (entry.size == key.size && entry.mtime == key.mtime && entry.options == options)
+                    .then(|| renamed(entry.result.clone(), file))
+            })
+            .collect();
+
+        let missing: Vec<usize> = (0..files.len()).filter(|&i| results[i].is_none()).collect();
+        let paths: Vec<PathBuf> = missing.iter().map(|&i| files[i].clone()).collect();
+        let counted = process_files(&paths, flags);
+        // A file changed again within the resolution of its modification
+        // time would look unchanged, so files changed just before they
+        // were counted aren't kept.
+        let settled = SystemTime::now() - Duration::from_secs(2);
+        for (i, result) in missing.into_iter().zip(counted) {
+            if let Some(key) = &keys[i]
+                && !matches!(result, FileResult::Err(..))
+                && key.modified < settled
+            {
+                self.contents.entries.insert(
+                    key.path.clone(),
+                    Entry {
+                        size: key.size,
+                        mtime: key.mtime,
+                        options,
+                        result: result.clone(),
+                    },
+                );
+                self.changed = true;
+            }
+            results[i] = Some(result);
+        }
+        results.into_iter().flatten().collect()
+    }
+
+    /// Writes the cache back if anything was added, next to its path and
+    /// renamed over it, so a concurrent run never reads half of it.
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.contents.version = env!("CARGO_PKG_VERSION").to_string();
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.tmp", std::process::id()));
+        let tmp = self.path.with_file_name(name);
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut writer, &self.contents)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp, &self.path)?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+/// What a cached result of a regular file depends on, besides the options.
+struct Key {
+    path: PathBuf,
+    size: u64,
+    mtime: u128,
+    modified: SystemTime,
+}
+
+impl Key {
+    /// `None` for anything but a regular file, like stdin or a URL.
+    fn of(file: &Path) -> Option<Self> {
+        let metadata = fs::metadata(file).ok().filter(|m| m.is_file())?;
+        let modified = metadata.modified().ok()?;
+        Some(Key {
+            path: fs::canonicalize(file).ok()?,
+            size: metadata.len(),
+            mtime: modified
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .ok()?
+                .as_nanos(),
+            modified,
+        })
+    }
+}
+
+/// A hash of the options that can change the counts (FNV-1a, which stays
+/// the same from one build to the next).
+fn fingerprint(flags: &CountOptions) -> u64 {
+    let flags = CountOptions {
+        threads: 0,
+        header: false,
+        ..flags.clone()
+    };
+    let json = serde_json::to_string(&flags).unwrap_or_default();
+    json.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// `result` named `file`, the name it is counted under this time.
+fn renamed(result: FileResult, file: &Path) -> FileResult {
+    match result {
+        FileResult::Ok(_, counts) => FileResult::Ok(file.to_path_buf(), counts),
+        FileResult::Archive(_, members) => FileResult::Archive(file.to_path_buf(), members),
+        FileResult::Err(_, e) => FileResult::Err(file.to_path_buf(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+
+    #[test]
+    fn test_cache() {
+        let dir = env::temp_dir().join(format!("rswc-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.txt");
+        fs::write(&file, "one two\nthree\n").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let path = dir.join("counts.json");
+        let flags = CountOptions::new().lines(true).words(true);
+        let files = [file.clone(), dir.join("missing.txt")];
+
+        let mut cache = Cache::open(&path);
+        let results = cache.process_files(&files, &flags);
+        assert_eq!(results[0], FileResult::Ok(file.clone(), counted(2, 3, 14)));
+        assert!(matches!(results[1], FileResult::Err(..)));
+        cache.save().unwrap();
+
+        // The cached counts are taken as they are, so a planted entry
+        // shows whether the file was read again.
+        let mut cache = Cache::open(&path);
+        assert_eq!(cache.contents.entries.len(), 1);
+        for entry in cache.contents.entries.values_mut() {
+            entry.result = FileResult::Ok(PathBuf::new(), counted(7, 7, 7));
+        }
+        let results = cache.process_files(&files[..1], &flags);
+        assert_eq!(results[0], FileResult::Ok(file.clone(), counted(7, 7, 7)));
+
+        // Other options, or another size, and it is counted again.
+        let results = cache.process_files(&files[..1], &flags.clone().chars(true));
+        assert_eq!(
+            results[0],
+            FileResult::Ok(
+                file.clone(),
+                Counts {
+                    chars: 14,
+                    ..counted(2, 3, 14)
+                }
+            )
+        );
+        fs::write(&file, "one\n").unwrap();
+        let results = cache.process_files(&files[..1], &flags);
+        assert_eq!(results[0], FileResult::Ok(file.clone(), counted(1, 1, 4)));
+
+        Cache::clear(&path).unwrap();
+        assert!(!path.exists());
+        Cache::clear(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn counted(lines: u64, words: u64, bytes: u64) -> Counts {
+        Counts {
+            lines,
+            words,
+            bytes,
+            ..Default::default()
+        }
+    }
+}
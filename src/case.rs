@@ -0,0 +1,82 @@
+//! Case folding for comparing words in `--freq` and `--unique-words`.
+
+/// How words are lowercased before they are compared.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CaseFold {
+    /// Unicode lowercase mapping, without regard to language.
+    #[default]
+    Default,
+    /// Like `Default`, but with the dotted and dotless i of Turkish and
+    /// Azerbaijani: `I` folds to `ı` and `İ` to `i`.
+    Turkish,
+}
+
+impl CaseFold {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "default" => Ok(CaseFold::Default),
+            "turkish" => Ok(CaseFold::Turkish),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--fold-case' (expected default or turkish)",
+                name
+            )),
+        }
+    }
+
+    /// `word` lowercased. Invalid UTF-8 only has its ASCII letters folded.
+    pub fn fold(self, word: &[u8]) -> Vec<u8> {
+        match (self, std::str::from_utf8(word)) {
+            (CaseFold::Default, Ok(s)) => s.to_lowercase().into_bytes(),
+            (CaseFold::Default, Err(_)) => word.to_ascii_lowercase(),
+            (CaseFold::Turkish, Ok(s)) => {
+                let mut folded = String::with_capacity(s.len());
+                for c in s.chars() {
+                    match c {
+                        'I' => folded.push('ı'),
+                        'İ' => folded.push('i'),
+                        _ => folded.extend(c.to_lowercase()),
+                    }
+                }
+                folded.into_bytes()
+            }
+            (CaseFold::Turkish, Err(_)) => {
+                let mut folded = Vec::with_capacity(word.len());
+                for &b in word {
+                    match b {
+                        b'I' => folded.extend_from_slice("ı".as_bytes()),
+                        _ => folded.push(b.to_ascii_lowercase()),
+                    }
+                }
+                folded
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(CaseFold::Default.fold("ÜBER".as_bytes()), "über".as_bytes());
+        assert_eq!(CaseFold::Default.fold(b"ISTANBUL"), b"istanbul");
+        assert_eq!(
+            CaseFold::Turkish.fold("ISPARTA".as_bytes()),
+            "ısparta".as_bytes()
+        );
+        assert_eq!(
+            CaseFold::Turkish.fold("İSTANBUL".as_bytes()),
+            "istanbul".as_bytes()
+        );
+        assert_eq!(CaseFold::Turkish.fold(b"I\xff"), b"\xc4\xb1\xff");
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(CaseFold::new("turkish"), Ok(CaseFold::Turkish));
+        assert!(CaseFold::new("german").is_err());
+    }
+}
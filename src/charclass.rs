@@ -0,0 +1,250 @@
+//! Characters by class for `--char-classes`: letters, digits, punctuation,
+//! whitespace and everything else, to compare the make-up of texts in a
+//! corpus.
+
+use crate::counter::{FileResult, counted_inputs, total};
+use std::io::{self, Write};
+
+/// The characters of an input in five classes. Every byte that is not part
+/// of a valid UTF-8 character counts as an `other` character of its own.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharClasses {
+    /// Alphabetic characters, of any script.
+    pub letters: usize,
+    /// Numeric characters, like `7`, `٣` and `½`.
+    pub digits: usize,
+    /// ASCII punctuation and symbols, and the punctuation beyond ASCII of
+    /// Latin-1, General Punctuation, CJK and fullwidth forms.
+    pub punctuation: usize,
+    pub whitespace: usize,
+    /// Symbols, emoji, control characters and invalid bytes.
+    pub other: usize,
+}
+
+impl CharClasses {
+    pub fn merge(&mut self, other: &CharClasses) {
+        self.letters += other.letters;
+        self.digits += other.digits;
+        self.punctuation += other.punctuation;
+        self.whitespace += other.whitespace;
+        self.other += other.other;
+    }
+
+    fn add(&mut self, c: char) {
+        if c.is_whitespace() {
+            self.whitespace += 1;
+        } else if c.is_alphabetic() {
+            self.letters += 1;
+        } else if c.is_numeric() {
+            self.digits += 1;
+        } else if is_punctuation(c) {
+            self.punctuation += 1;
+        } else {
+            self.other += 1;
+        }
+    }
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(c,
+            '\u{a1}'..='\u{bf}'
+            | '\u{2010}'..='\u{2027}'
+            | '\u{2030}'..='\u{205e}'
+            | '\u{3001}'..='\u{303f}'
+            | '\u{ff01}'..='\u{ff0f}'
+            | '\u{ff1a}'..='\u{ff20}'
+            | '\u{ff3b}'..='\u{ff40}'
+            | '\u{ff5b}'..='\u{ff65}')
+}
+
+/// The first character of some bytes.
+enum Next {
+    Char(char),
+    /// The first byte doesn't start a valid character.
+    Invalid,
+    /// The bytes end in the middle of a character.
+    Incomplete,
+}
+
+fn next_char(bytes: &[u8]) -> Next {
+    let head = &bytes[..bytes.len().min(4)];
+    let valid = match std::str::from_utf8(head) {
+        Ok(s) => s,
+        Err(e) if e.valid_up_to() > 0 => {
+            std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(e) if e.error_len().is_none() => return Next::Incomplete,
+        Err(_) => return Next::Invalid,
+    };
+    valid.chars().next().map_or(Next::Invalid, Next::Char)
+}
+
+/// Classifies the characters of input fed in chunks of any size; a
+/// character may be split between two chunks.
+#[derive(Debug, Default)]
+pub struct CharClassCounter {
+    classes: CharClasses,
+    // Leading bytes of a character cut off at the end of the last chunk.
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl CharClassCounter {
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        if self.partial_len > 0 {
+            let held = self.partial_len;
+            let take = (4 - held).min(chunk.len());
+            let mut bytes = self.partial;
+            bytes[held..held + take].copy_from_slice(&chunk[..take]);
+            self.partial_len = 0;
+            match next_char(&bytes[..held + take]) {
+                Next::Char(c) => {
+                    self.classes.add(c);
+                    chunk = &chunk[c.len_utf8() - held..];
+                }
+                Next::Incomplete => {
+                    self.partial = bytes;
+                    self.partial_len = held + take;
+                    return;
+                }
+                // The held back bytes after the first are looked at afresh,
+                // and may start a character cut off again.
+                Next::Invalid => {
+                    self.classes.other += 1;
+                    self.feed(&bytes[1..held]);
+                    self.feed(chunk);
+                    return;
+                }
+            }
+        }
+
+        while let Some(&b) = chunk.first() {
+            if b.is_ascii() {
+                self.classes.add(b as char);
+                chunk = &chunk[1..];
+                continue;
+            }
+            match next_char(chunk) {
+                Next::Char(c) => {
+                    self.classes.add(c);
+                    chunk = &chunk[c.len_utf8()..];
+                }
+                Next::Invalid => {
+                    self.classes.other += 1;
+                    chunk = &chunk[1..];
+                }
+                Next::Incomplete => {
+                    self.partial[..chunk.len()].copy_from_slice(chunk);
+                    self.partial_len = chunk.len();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Ends the input: the bytes of a character still cut off are invalid.
+    pub fn finish(mut self) -> CharClasses {
+        self.classes.other += self.partial_len;
+        self.classes
+    }
+}
+
+/// Prints the character classes of every counted input, archive members
+/// included, followed by their total when there is more than one.
+pub fn print_char_classes<W: Write>(writer: &mut W, results: &[FileResult]) -> io::Result<()> {
+    let rows: Vec<(String, CharClasses)> = counted_inputs(results)
+        .into_iter()
+        .map(|(name, c)| {
+            let classes = c.char_classes.as_deref().copied().unwrap_or_default();
+            (name, classes)
+        })
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "character classes (letters, digits, punctuation, whitespace, other):"
+    )?;
+    for (name, classes) in &rows {
+        write_char_classes(writer, classes, name)?;
+    }
+    if rows.len() > 1 {
+        let total = total(results)
+            .char_classes
+            .as_deref()
+            .copied()
+            .unwrap_or_default();
+        write_char_classes(writer, &total, "total")?;
+    }
+    Ok(())
+}
+
+fn write_char_classes<W: Write>(
+    writer: &mut W,
+    classes: &CharClasses,
+    name: &str,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{:>7} {:>7} {:>7} {:>7} {:>7} {}",
+        classes.letters,
+        classes.digits,
+        classes.punctuation,
+        classes.whitespace,
+        classes.other,
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, count_slice};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_char_classes_in_any_chunking() {
+        // A letter split by an invalid byte, a truncated sequence before
+        // ASCII, and a character cut off at the end.
+        let data = "Naïve café, 42½ — “ok” 🦀\u{3000}".as_bytes();
+        let data = [data, b"\xe2\x82c\x80\xf0\x9f"].concat();
+        for size in 1..=data.len() {
+            let mut counter = CharClassCounter::default();
+            for chunk in data.chunks(size) {
+                counter.feed(chunk);
+            }
+            let expected = CharClasses {
+                letters: 12,
+                digits: 3,
+                punctuation: 4,
+                whitespace: 6,
+                other: 6,
+            };
+            assert_eq!(counter.finish(), expected, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_print_char_classes() {
+        let flags = CountOptions::new().char_classes(true);
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a"), count_slice(b"Hi, 2 you!\n", &flags)),
+            FileResult::Ok(PathBuf::from("b"), count_slice(b"x\t$\n", &flags)),
+        ];
+
+        let mut output = Vec::new();
+        print_char_classes(&mut output, &results).unwrap();
+        let expected = "
+character classes (letters, digits, punctuation, whitespace, other):
+      5       1       2       3       0 a
+      1       0       1       2       0 b
+      6       1       3       5       0 total
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
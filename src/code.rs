@@ -0,0 +1,262 @@
+//! Blank, comment and code line counting for `--code`, a lightweight take
+//! on cloc or tokei. Comment markers are found without parsing string
+//! literals, so a `//` inside a string is taken for a comment.
+
+use crate::lines::LineSplitter;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+}
+
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "C-like",
+        extensions: &[
+            "rs", "c", "h", "cc", "cpp", "cxx", "hpp", "hh", "go", "java", "js", "mjs", "cjs",
+            "jsx", "ts", "tsx", "cs", "swift", "kt", "kts", "scala", "dart", "zig",
+        ],
+        line_comments: &["//"],
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css"],
+        line_comments: &[],
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        line_comments: &["//", "#"],
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Hash",
+        extensions: &[
+            "py", "sh", "bash", "zsh", "fish", "rb", "pl", "pm", "r", "yml", "yaml", "toml",
+            "cmake", "nix", "ps1",
+        ],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        line_comments: &["--"],
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        line_comments: &["--"],
+        block_comments: &[("--[[", "]]")],
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs", "elm"],
+        line_comments: &["--"],
+        block_comments: &[("{-", "-}")],
+    },
+    Language {
+        name: "Markup",
+        extensions: &["html", "htm", "xml", "svg", "vue"],
+        line_comments: &[],
+        block_comments: &[("<!--", "-->")],
+    },
+    Language {
+        name: "Lisp",
+        extensions: &["lisp", "el", "clj", "scm"],
+        line_comments: &[";"],
+        block_comments: &[],
+    },
+];
+
+/// The language of `path` by its extension, if it is a known one.
+pub fn language_for(path: &Path) -> Option<&'static Language> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|l| l.extensions.contains(&extension.as_str()))
+}
+
+/// `CountOptions::language` as the name of the language, for serde.
+#[cfg(feature = "serde")]
+pub(crate) mod by_name {
+    use super::{LANGUAGES, Language};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        language: &Option<&'static Language>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        language.map(|l| l.name).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<&'static Language>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        LANGUAGES
+            .iter()
+            .find(|l| l.name == name)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("unknown language '{}'", name)))
+    }
+}
+
+/// Classifies lines fed in chunks of any size. Without a language there are
+/// no comments, only blank and code lines.
+#[derive(Default)]
+pub struct CodeLines {
+    language: Option<&'static Language>,
+    lines: LineSplitter,
+    // The end marker of the block comment the last line ended in.
+    in_block: Option<&'static str>,
+    pub blank: u64,
+    pub comment: u64,
+    pub code: u64,
+}
+
+impl CodeLines {
+    pub fn new(language: Option<&'static Language>) -> Self {
+        CodeLines {
+            language,
+            ..Default::default()
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut lines = std::mem::take(&mut self.lines);
+        lines.feed(chunk, |line| self.classify(line));
+        self.lines = lines;
+    }
+
+    /// Classifies the last line if it had no newline.
+    pub fn finish(mut self) -> Self {
+        let lines = std::mem::take(&mut self.lines);
+        lines.finish(|line| self.classify(line));
+        self
+    }
+
+    fn classify(&mut self, line: &[u8]) {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            self.blank += 1;
+            return;
+        }
+        let Some(language) = self.language else {
+            self.code += 1;
+            return;
+        };
+
+        let mut has_comment = self.in_block.is_some();
+        let mut has_code = false;
+        let mut rest = line;
+
+        while !rest.is_empty() {
+            if let Some(end) = self.in_block {
+                match find(rest, end.as_bytes()) {
+                    Some(i) => {
+                        rest = &rest[i + end.len()..];
+                        self.in_block = None;
+                    }
+                    None => break,
+                }
+                continue;
+            }
+
+            if rest[0].is_ascii_whitespace() {
+                rest = &rest[1..];
+            } else if let Some((start, end)) = language
+                .block_comments
+                .iter()
+                .find(|(start, _)| rest.starts_with(start.as_bytes()))
+            {
+                has_comment = true;
+                self.in_block = Some(end);
+                rest = &rest[start.len()..];
+            } else if language
+                .line_comments
+                .iter()
+                .any(|marker| rest.starts_with(marker.as_bytes()))
+            {
+                has_comment = true;
+                break;
+            } else {
+                has_code = true;
+                rest = &rest[1..];
+            }
+        }
+
+        if has_code {
+            self.code += 1;
+        } else if has_comment {
+            self.comment += 1;
+        } else {
+            self.blank += 1;
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(path: &str, source: &str, size: usize) -> (u64, u64, u64) {
+        let mut lines = CodeLines::new(language_for(Path::new(path)));
+        for chunk in source.as_bytes().chunks(size) {
+            lines.feed(chunk);
+        }
+        let lines = lines.finish();
+        (lines.blank, lines.comment, lines.code)
+    }
+
+    #[test]
+    fn test_code_lines_rust() {
+        let source = "// header
+use std::io;
+
+/* a block
+   comment */
+fn main() { /* inline */ }
+let x = 1; // trailing
+    /* open */ let y = 2;
+/** doc */
+    ";
+        for size in 1..=source.len() {
+            assert_eq!(
+                classify("main.rs", source, size),
+                (2, 4, 4),
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_code_lines_other_languages() {
+        assert_eq!(classify("run.py", "# setup\nimport os\n\n", 4), (1, 1, 1));
+        assert_eq!(
+            classify("q.SQL", "-- all\nSELECT 1; /* x\n*/\n", 4),
+            (0, 2, 1)
+        );
+        assert_eq!(
+            classify("a.lua", "--[[ long\ncomment ]]\nprint(1)", 3),
+            (0, 2, 1)
+        );
+        assert_eq!(classify("notes.txt", "# not a comment\n\n", 5), (1, 0, 1));
+    }
+}
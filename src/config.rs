@@ -0,0 +1,449 @@
+//! The user configuration file, a small subset of TOML: one `key = value`
+//! per line, with `#` comments. Keys without a table are default options,
+//! by their long name with `_` for `-`:
+//!
+//! ```toml
+//! chars = true
+//! threads = 4
+//! alias.docs = "--words --unique-words"
+//! preset.prose = "--words --chars"
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Presets shipped with rswc. The config file can redefine them and add
+/// its own.
+const BUILTIN_PRESETS: [(&str, &str); 3] = [
+    (
+        "prose",
+        "--words --chars --sentences --unique-words --ignore-case",
+    ),
+    ("code", "--lines --bytes --anomalies"),
+    ("data", "--lines --bytes --find-duplicates --archive"),
+];
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    aliases: BTreeMap<String, Vec<String>>,
+    presets: BTreeMap<String, Vec<String>>,
+    /// Default options, like `--threads=4` for `threads = 4`, with the key
+    /// that set them.
+    defaults: Vec<(String, String)>,
+}
+
+impl Config {
+    /// `$RSWC_CONFIG`, or `rswc/config.toml` in `$XDG_CONFIG_HOME` or
+    /// `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("RSWC_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("rswc").join("config.toml"))
+    }
+
+    /// Loads the configuration file; a missing file is an empty config.
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        match Config::load_from(&path) {
+            Err(_) if !path.exists() => Ok(Config::default()),
+            result => result,
+        }
+    }
+
+    /// Loads the configuration file at `path`, which must exist.
+    pub fn load_from(path: &Path) -> Result<Config, String> {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Config::parse(&text))
+            .map_err(|e| format!("rswc: {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = \"value\"`", n + 1))?;
+            let key = key.trim();
+            if !key.contains('.') {
+                let option = default_option(key, value.trim())
+                    .map_err(|e| format!("line {}: {}", n + 1, e))?;
+                config
+                    .defaults
+                    .extend(option.map(|option| (key.to_string(), option)));
+                continue;
+            }
+            let value = parse_value(value.trim()).map_err(|e| format!("line {}: {}", n + 1, e))?;
+
+            let args = value.split_whitespace().map(str::to_string).collect();
+            let (table, name) = if let Some(name) = key.strip_prefix("alias.") {
+                (&mut config.aliases, name)
+            } else if let Some(name) = key.strip_prefix("preset.") {
+                (&mut config.presets, name)
+            } else {
+                return Err(format!("line {}: unknown key '{}'", n + 1, key));
+            };
+            if name.is_empty() {
+                return Err(format!("line {}: missing name in '{}'", n + 1, key));
+            }
+            table.insert(name.to_string(), args);
+        }
+
+        Ok(config)
+    }
+
+    /// The default options, in the order they were set.
+    pub fn defaults(&self) -> impl Iterator<Item = &str> {
+        self.defaults.iter().map(|(_, option)| option.as_str())
+    }
+
+    /// Adds default options set elsewhere, like in the environment, after
+    /// those of the file.
+    pub fn extend_defaults<I>(&mut self, options: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.defaults
+            .extend(options.into_iter().map(|option| (option.clone(), option)));
+    }
+
+    /// `args` after the default options `overrides` is false of, so
+    /// options given explicitly take precedence.
+    pub fn with_defaults<F>(&self, args: Vec<OsString>, overrides: F) -> Vec<OsString>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut merged: Vec<OsString> = self
+            .defaults()
+            .filter(|option| !overrides(option))
+            .map(OsString::from)
+            .collect();
+        merged.extend(args);
+        merged
+    }
+
+    /// Fails on a default that isn't one of the boolean `custom_flags`, or
+    /// of the `value_flags` when it has a value.
+    pub fn check_defaults(
+        &self,
+        custom_flags: &[&str],
+        value_flags: &[&str],
+    ) -> Result<(), String> {
+        for (key, option) in &self.defaults {
+            let known = match option.split_once('=') {
+                Some((name, _)) => value_flags.contains(&name),
+                None => custom_flags.contains(&option.as_str()),
+            };
+            if !known {
+                return Err(format!("unknown option '{}'", key));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&[String]> {
+        self.aliases.get(name).map(Vec::as_slice)
+    }
+
+    /// The options of preset `name`, from the config file or built in.
+    pub fn preset(&self, name: &str) -> Option<Vec<String>> {
+        if let Some(args) = self.presets.get(name) {
+            return Some(args.clone());
+        }
+        BUILTIN_PRESETS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, options)| options.split_whitespace().map(str::to_string).collect())
+    }
+
+    /// Expands aliases, then presets. A preset's options go before all
+    /// other arguments, so options given explicitly take precedence.
+    /// Arguments after `--` are files, and left as they are, like those
+    /// that aren't valid UTF-8.
+    pub fn expand<I, S>(&self, args: I) -> Result<Vec<OsString>, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        let mut presets = Vec::new();
+        let mut rest = Vec::new();
+        let mut args = self.expand_aliases(args)?.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                rest.push(arg);
+                rest.extend(args);
+                break;
+            }
+            let name = match arg.to_str().and_then(|arg| arg.strip_prefix("--preset")) {
+                Some("") => args
+                    .next()
+                    .ok_or("rswc: option '--preset' requires an argument")?
+                    .to_string_lossy()
+                    .into_owned(),
+                Some(value) if value.starts_with('=') => value[1..].to_string(),
+                _ => {
+                    rest.push(arg);
+                    continue;
+                }
+            };
+            let options = self
+                .preset(&name)
+                .ok_or_else(|| format!("rswc: unknown preset '{}'", name))?;
+            presets.extend(options.into_iter().map(OsString::from));
+        }
+
+        presets.extend(rest);
+        Ok(presets)
+    }
+
+    /// Replaces every `@name` argument with the options of alias `name`.
+    /// Aliases are expanded once, so they can't refer to other aliases.
+    pub fn expand_aliases<I, S>(&self, args: I) -> Result<Vec<OsString>, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        let mut expanded = Vec::new();
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                expanded.push(arg);
+                expanded.extend(args);
+                break;
+            }
+            match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+                Some(name) => {
+                    let options = self
+                        .alias(name)
+                        .ok_or_else(|| format!("rswc: unknown alias '{}'", arg.display()))?;
+                    expanded.extend(options.iter().map(OsString::from));
+                }
+                None => expanded.push(arg),
+            }
+        }
+        Ok(expanded)
+    }
+}
+
+/// The option a default `key = raw` stands for: `--key` for `true`, and
+/// `--key=value` for a number or a string. `false` is no option.
+fn default_option(key: &str, raw: &str) -> Result<Option<String>, String> {
+    if key.is_empty()
+        || !key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+    {
+        return Err(format!("invalid key '{}'", key));
+    }
+    let option = format!("--{}", key.replace('_', "-"));
+    match raw {
+        "true" => Ok(Some(option)),
+        "false" => Ok(None),
+        _ if !raw.is_empty() && raw.bytes().all(|b| b.is_ascii_digit()) => {
+            Ok(Some(format!("{}={}", option, raw)))
+        }
+        _ if raw.starts_with('"') => {
+            parse_value(raw).map(|value| Some(format!("{}={}", option, value)))
+        }
+        _ => Err("values must be true, false, numbers or double-quoted strings".to_string()),
+    }
+}
+
+/// A double-quoted string with `\"` and `\\` escapes.
+fn parse_value(raw: &str) -> Result<String, String> {
+    let inner = raw
+        .strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .filter(|_| raw.len() >= 2)
+        .ok_or("values must be double-quoted strings")?;
+
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(e @ ('"' | '\\')) => value.push(e),
+                _ => return Err("unsupported escape sequence".to_string()),
+            },
+            '"' => return Err("unescaped quote in value".to_string()),
+            c => value.push(c),
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        let config = Config::parse(
+            r#"
+# house conventions
+alias.docs = "--words --unique-words"
+alias.logs = "-l  --unique-lines"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.alias("docs"),
+            Some(&["--words".to_string(), "--unique-words".to_string()][..])
+        );
+        assert_eq!(
+            config.alias("logs"),
+            Some(&["-l".to_string(), "--unique-lines".to_string()][..])
+        );
+        assert_eq!(config.alias("code"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Config::parse("alias.docs = --words"),
+            Err("line 1: values must be double-quoted strings".to_string())
+        );
+        assert_eq!(
+            Config::parse("\ntheme.colour = \"always\""),
+            Err("line 2: unknown key 'theme.colour'".to_string())
+        );
+        assert_eq!(
+            Config::parse("threads = four"),
+            Err("line 1: values must be true, false, numbers or double-quoted strings".to_string())
+        );
+        assert_eq!(
+            Config::parse("alias.docs"),
+            Err("line 1: expected `key = \"value\"`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let config = Config::parse(
+            r#"
+chars = true
+human_readable = true
+unique_words = false
+threads = 4
+group-digits = "."
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.defaults().collect::<Vec<_>>(),
+            [
+                "--chars",
+                "--human-readable",
+                "--threads=4",
+                "--group-digits=."
+            ]
+        );
+        assert_eq!(
+            config.check_defaults(
+                &["--chars", "--group-digits"],
+                &["--threads", "--group-digits"]
+            ),
+            Err("unknown option 'human_readable'".to_string())
+        );
+        assert_eq!(
+            config.check_defaults(
+                &["--chars", "--human-readable"],
+                &["--threads", "--group-digits"]
+            ),
+            Ok(())
+        );
+        assert!(
+            config
+                .check_defaults(&["--chars", "--human-readable", "--threads"], &[])
+                .is_err()
+        );
+
+        // Options given explicitly win over the defaults.
+        let given = args(&["-j", "2", "a.txt"]);
+        assert_eq!(
+            config.with_defaults(given, |option| option.starts_with("--threads")),
+            args(&[
+                "--chars",
+                "--human-readable",
+                "--group-digits=.",
+                "-j",
+                "2",
+                "a.txt"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_expand_presets() {
+        let config = Config::parse(r#"preset.code = "--lines --threads=2""#).unwrap();
+
+        assert_eq!(
+            config.expand(args(&["README.md", "--preset=prose"])),
+            Ok(args(&[
+                "--words",
+                "--chars",
+                "--sentences",
+                "--unique-words",
+                "--ignore-case",
+                "README.md"
+            ]))
+        );
+        assert_eq!(
+            config.expand(args(&["--threads=4", "--preset", "code", "main.rs"])),
+            Ok(args(&["--lines", "--threads=2", "--threads=4", "main.rs"]))
+        );
+        assert_eq!(
+            config.expand(args(&["--preset=poetry"])),
+            Err("rswc: unknown preset 'poetry'".to_string())
+        );
+        assert_eq!(
+            config.expand(args(&["--preset"])),
+            Err("rswc: option '--preset' requires an argument".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let config = Config::parse(r#"alias.docs = "--words --unique-words""#).unwrap();
+
+        assert_eq!(
+            config.expand_aliases(args(&["@docs", "-l", "README.md"])),
+            Ok(args(&["--words", "--unique-words", "-l", "README.md"]))
+        );
+        assert_eq!(
+            config.expand_aliases(args(&["@code", "main.rs"])),
+            Err("rswc: unknown alias '@code'".to_string())
+        );
+        assert_eq!(
+            config.expand(args(&["@docs", "--", "@docs", "--preset=code"])),
+            Ok(args(&[
+                "--words",
+                "--unique-words",
+                "--",
+                "@docs",
+                "--preset=code"
+            ]))
+        );
+    }
+}
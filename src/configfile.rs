@@ -0,0 +1,207 @@
+//! Configuration files for `--config-input`: how many keys and values YAML
+//! and TOML files hold and how deeply they nest, to measure config sprawl.
+
+use crate::counter::{CountOptions, Counts, count_slice};
+use std::io;
+use std::path::Path;
+
+/// The format `--config-input` reads files in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "yaml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--config-input' (expected yaml or toml)",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "YAML",
+            ConfigFormat::Toml => "TOML",
+        }
+    }
+}
+
+/// The keys and values of configuration files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigShape {
+    /// Keys of all tables or mappings, nested ones included.
+    pub keys: usize,
+    /// Levels of the deepest table, mapping or list; a file of top-level
+    /// keys only has one.
+    pub depth: usize,
+    /// Scalar values, like strings, numbers, dates and nulls, including
+    /// the items of lists.
+    pub values: usize,
+}
+
+impl ConfigShape {
+    pub fn merge(&mut self, other: &ConfigShape) {
+        self.keys += other.keys;
+        self.depth = self.depth.max(other.depth);
+        self.values += other.values;
+    }
+}
+
+/// Counts the configuration file at `path`.
+pub fn count_config(path: &Path, format: ConfigFormat, flags: &CountOptions) -> io::Result<Counts> {
+    count_config_slice(&std::fs::read(path)?, format, flags)
+}
+
+/// Counts `data` as usual, and the keys and values it holds in `format`.
+pub fn count_config_slice(
+    data: &[u8],
+    format: ConfigFormat,
+    flags: &CountOptions,
+) -> io::Result<Counts> {
+    let text =
+        std::str::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let shape = config_shape(text, format)?;
+    let mut counts = count_slice(data, flags);
+    counts.config = Some(Box::new(shape));
+    Ok(counts)
+}
+
+/// The keys and values of `text` in `format`. A YAML stream of several
+/// documents adds them up.
+pub fn config_shape(text: &str, format: ConfigFormat) -> io::Result<ConfigShape> {
+    #[cfg(feature = "config-input")]
+    {
+        let invalid = |e: &dyn std::fmt::Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid {}: {}", format.name(), e),
+            )
+        };
+        let mut shape = ConfigShape::default();
+        match format {
+            ConfigFormat::Yaml => {
+                let documents =
+                    yaml_rust2::YamlLoader::load_from_str(text).map_err(|e| invalid(&e))?;
+                for document in &documents {
+                    yaml_shape(document, 0, &mut shape);
+                }
+            }
+            ConfigFormat::Toml => {
+                let table: toml::Table = text.parse().map_err(|e| invalid(&e))?;
+                toml_table_shape(&table, 0, &mut shape);
+            }
+        }
+        Ok(shape)
+    }
+    #[cfg(not(feature = "config-input"))]
+    {
+        let _ = (text, format);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "YAML and TOML support is not enabled in this build (feature `config-input`)",
+        ))
+    }
+}
+
+#[cfg(feature = "config-input")]
+fn yaml_shape(node: &yaml_rust2::Yaml, level: usize, shape: &mut ConfigShape) {
+    use yaml_rust2::Yaml;
+
+    match node {
+        Yaml::Hash(map) => {
+            shape.depth = shape.depth.max(level + 1);
+            shape.keys += map.len();
+            for value in map.values() {
+                yaml_shape(value, level + 1, shape);
+            }
+        }
+        Yaml::Array(items) => {
+            shape.depth = shape.depth.max(level + 1);
+            for item in items {
+                yaml_shape(item, level + 1, shape);
+            }
+        }
+        // Aliases repeat a value counted where it was anchored.
+        Yaml::Alias(_) | Yaml::BadValue => {}
+        _ => shape.values += 1,
+    }
+}
+
+#[cfg(feature = "config-input")]
+fn toml_table_shape(table: &toml::Table, level: usize, shape: &mut ConfigShape) {
+    shape.depth = shape.depth.max(level + 1);
+    shape.keys += table.len();
+    for value in table.values() {
+        toml_shape(value, level + 1, shape);
+    }
+}
+
+#[cfg(feature = "config-input")]
+fn toml_shape(value: &toml::Value, level: usize, shape: &mut ConfigShape) {
+    match value {
+        toml::Value::Table(table) => toml_table_shape(table, level, shape),
+        toml::Value::Array(items) => {
+            shape.depth = shape.depth.max(level + 1);
+            for item in items {
+                toml_shape(item, level + 1, shape);
+            }
+        }
+        _ => shape.values += 1,
+    }
+}
+
+#[cfg(all(test, feature = "config-input"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_shape() {
+        let yaml = "name: web
+ports: [80, 443]
+env:
+  debug: false
+  db: {host: localhost, port: ~}
+---
+replicas: 3
+";
+        assert_eq!(
+            config_shape(yaml, ConfigFormat::Yaml).unwrap(),
+            ConfigShape {
+                keys: 8,
+                depth: 3,
+                values: 7
+            }
+        );
+        assert!(config_shape("a: [1", ConfigFormat::Yaml).is_err());
+    }
+
+    #[test]
+    fn test_toml_shape() {
+        let toml = r#"title = "site"
+
+[server]
+ports = [80, 443]
+
+[[server.routes]]
+path = "/"
+"#;
+        assert_eq!(
+            config_shape(toml, ConfigFormat::Toml).unwrap(),
+            ConfigShape {
+                keys: 5,
+                depth: 4,
+                values: 4
+            }
+        );
+        assert!(config_shape("a = ", ConfigFormat::Toml).is_err());
+    }
+}
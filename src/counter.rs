@@ -1,43 +1,1451 @@
+use crate::blank::BlankLines;
+use crate::buckets::{Bucket, BucketCounter, Buckets, TimestampFormat};
+use crate::case::CaseFold;
+use crate::charclass::{CharClassCounter, CharClasses};
+use crate::code::{CodeLines, Language, language_for};
+#[cfg(feature = "core")]
+use crate::configfile::count_config;
+use crate::configfile::{ConfigFormat, ConfigShape};
+use crate::crash::processing;
+use crate::detect::{Detected, SAMPLE_LEN, type_column};
+#[cfg(feature = "core")]
+use crate::document::{Document, is_epub, is_html};
+use crate::eol::{EolCounter, LineEnds};
+use crate::error::RswcError;
+use crate::fields::{FieldCounter, FieldCounts};
+use crate::freq::{FreqCounter, Frequencies};
+use crate::groups::Groups;
+use crate::indent::{IndentCounter, Indents};
+use crate::lang::{ENGLISH, LangPack, LangWords};
+#[cfg(feature = "core")]
+use crate::lang::{detect, detect_file};
+use crate::layout::{Layout, LayoutOptions};
+use crate::links::LinkWords;
+use crate::loglevels::{LevelCounter, LevelWords, LogLevels};
+use crate::nonascii::NonAscii;
+use crate::notebook::Cells;
+#[cfg(feature = "core")]
+use crate::notebook::{count_notebook, is_notebook};
+use crate::numbers::{NumberWords, Numbers};
+#[cfg(feature = "pattern")]
+use crate::pattern::{GroupCounter, LineFilter, MatchCounter, Pattern};
+use crate::range::{LineRange, end_of_lines};
+#[cfg(feature = "core")]
+use crate::readahead::ReadAhead;
+use crate::scanner::{InvalidUtf8, Scanner, WordRules};
+use crate::sentences::Sentences;
+use crate::stats::{LineMeter, LineStats};
+use crate::status;
+use crate::subtitles::Timeline;
+#[cfg(feature = "core")]
+use crate::subtitles::{count_subtitles, is_subtitles};
+#[cfg(feature = "tokens")]
+use crate::tokens::{Encoding, TokenCounter};
+#[cfg(feature = "encoding")]
+use crate::transcode::Transcoder;
+use crate::unique::{DupLines, RepeatedLines, UniqueLines, UniqueWords, WordSet};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::{self, BufReader, Read, Write};
+#[cfg(feature = "digest")]
+use sha2::{Digest, Sha256};
+use std::fmt;
+#[cfg(feature = "core")]
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+#[cfg(feature = "core")]
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::iter::Sum;
+use std::num::NonZeroUsize;
+use std::ops::{Add, AddAssign};
 use std::path::{Path, PathBuf};
+#[cfg(feature = "core")]
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-macro_rules! print_field {
-    ($writer:expr, $value:expr, $enabled:expr, $width:expr) => {
-        if $enabled {
-            write!($writer, "{:>width$} ", $value, width = $width)?;
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Counts {
+    pub lines: u64,
+    pub words: u64,
+    /// Numeric words, counted when `CountOptions::numbers` leaves them out of `words`.
+    pub numbers: u64,
+    /// URLs and email addresses, each counted as one word.
+    pub links: u64,
+    pub bytes: u64,
+    pub chars: u64,
+    /// Bytes that are not valid UTF-8, counted along with `chars`.
+    pub invalid_bytes: u64,
+    /// Offset of the first of the `invalid_bytes`.
+    pub first_invalid: Option<u64>,
+    /// Characters outside ASCII, when `CountOptions::non_ascii` is set.
+    pub non_ascii: u64,
+    /// Characters by class, when `CountOptions::char_classes` is set. Boxed
+    /// like `line_stats`.
+    pub char_classes: Option<Box<CharClasses>>,
+    /// Characters of the longest word, when `CountOptions::max_word_length`
+    /// is set; in a total, the longest of its inputs.
+    pub max_word_length: u64,
+    pub sentences: u64,
+    /// Tokens of `CountOptions::tokens`.
+    pub tokens: u64,
+    pub blank_lines: u64,
+    pub comment_lines: u64,
+    pub code_lines: u64,
+    /// Matches of `CountOptions::pattern`.
+    pub matches: u64,
+    /// Line ending styles, when `CountOptions::eol` is set. Boxed like
+    /// `line_stats`.
+    pub line_ends: Option<Box<LineEnds>>,
+    /// Lines indented with tabs and with spaces, when
+    /// `CountOptions::indent_stats` is set. Boxed like `line_stats`.
+    pub indents: Option<Box<Indents>>,
+    /// Fields per record, when `CountOptions::fields` is set. Boxed like
+    /// `line_stats`.
+    pub field_counts: Option<Box<FieldCounts>>,
+    /// Cues and timeline of subtitles, when `CountOptions::subtitles` is set.
+    /// Boxed like `line_stats`.
+    pub timeline: Option<Box<Timeline>>,
+    /// What the cells of notebooks hold, when `CountOptions::ipynb` is set.
+    /// Boxed like `line_stats`.
+    pub cells: Option<Box<Cells>>,
+    /// Keys and values of configuration files, when `CountOptions::config_input`
+    /// is set. Boxed like `line_stats`.
+    pub config: Option<Box<ConfigShape>>,
+    /// Lines per severity, when `CountOptions::log_levels` is set. Boxed like
+    /// `line_stats`.
+    pub log_levels: Option<Box<LogLevels>>,
+    /// Lines per value of the capture group of `CountOptions::extract`.
+    pub groups: Option<Box<Groups>>,
+    /// Lines per time bucket, when `CountOptions::bucket` is set.
+    pub buckets: Option<Box<Buckets>>,
+    /// SHA-256 of the input, when `CountOptions::digest` is set. Boxed like
+    /// `line_stats`.
+    pub digest: Option<Box<[u8; 32]>>,
+    /// Distinct lines; in a total, the sum over its inputs.
+    pub unique_lines: u64,
+    /// The most repeated lines, when `CountOptions::dup_lines` is set. Boxed
+    /// like `line_stats`, and like the digest left out of totals.
+    pub repeated_lines: Option<Box<RepeatedLines>>,
+    /// How often each word occurs, when `CountOptions::freq` is set. Left out
+    /// of totals and by serde, as the words need not be UTF-8.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub frequencies: Option<Box<Frequencies>>,
+    /// Distinct words, merged across the inputs of a total. Boxed like
+    /// `line_stats`. Left out by serde, as it only holds hashes of words.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub vocabulary: Option<Box<WordSet>>,
+    /// Line lengths, when `CountOptions::stats` is set. Boxed as they are rarely
+    /// asked for and would double the size of every `Counts`.
+    pub line_stats: Option<Box<LineStats>>,
+    /// What the input looks like from its start, when
+    /// `CountOptions::detect` is set.
+    pub detected: Option<Detected>,
+    pub annotations: Annotations,
+    /// Counts that may be off, so reports can mark them as approximate.
+    pub estimates: Estimates,
+}
+
+/// Policies that were applied while counting an input, so that surprising
+/// numbers can be explained from the report itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "Vec<String>", into = "Vec<&'static str>")
+)]
+pub struct Annotations(u8);
+
+impl Annotations {
+    /// The byte count was taken from file metadata without reading.
+    pub const METADATA: Annotations = Annotations(1 << 0);
+    /// The file was memory-mapped.
+    pub const MMAP: Annotations = Annotations(1 << 1);
+    /// The input was counted as parallel ranges.
+    pub const PARALLEL: Annotations = Annotations(1 << 2);
+    /// The counts are of the decompressed data.
+    pub const DECOMPRESSED: Annotations = Annotations(1 << 3);
+    /// The counts are of the text extracted from a document.
+    pub const EXTRACTED: Annotations = Annotations(1 << 4);
+    /// The input looks binary: its start has a NUL byte.
+    pub const BINARY: Annotations = Annotations(1 << 5);
+    /// Counting stopped at `head_bytes` or `head_lines`.
+    pub const HEAD: Annotations = Annotations(1 << 6);
+
+    const NAMES: [(Annotations, &'static str); 7] = [
+        (Annotations::METADATA, "metadata"),
+        (Annotations::MMAP, "mmap"),
+        (Annotations::PARALLEL, "parallel"),
+        (Annotations::DECOMPRESSED, "decompressed"),
+        (Annotations::EXTRACTED, "extracted"),
+        (Annotations::BINARY, "binary"),
+        (Annotations::HEAD, "head"),
+    ];
+
+    pub fn contains(self, other: Annotations) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Annotations) {
+        self.0 |= other.0;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Lists the applied policies as `[mmap, parallel]`, or nothing.
+impl fmt::Display for Annotations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let names: Vec<&str> = Annotations::NAMES
+            .iter()
+            .filter(|(a, _)| self.contains(*a))
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "[{}]", names.join(", "))
+    }
+}
+
+/// Columns whose value is an estimate rather than an exact count, named as
+/// in `Counts::named_columns`. Everything not listed is exact; distinct
+/// lines and words are compared by 128-bit hashes and count as exact too.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "Vec<String>", into = "Vec<&'static str>")
+)]
+pub struct Estimates(u8);
+
+impl Estimates {
+    /// Tokens of input that isn't valid UTF-8, or of a very long line that
+    /// had to be split between two words.
+    pub const TOKENS: Estimates = Estimates(1 << 0);
+    /// A count of a total that grew past `u64::MAX` and is held there. It
+    /// isn't of one column, so its name is of none: the columns it applies
+    /// to are those at `u64::MAX`.
+    pub const SATURATED: Estimates = Estimates(1 << 1);
+
+    const NAMES: [(Estimates, &'static str); 2] = [
+        (Estimates::TOKENS, "tokens"),
+        (Estimates::SATURATED, "saturated"),
+    ];
+
+    pub fn contains(self, other: Estimates) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Estimates) {
+        self.0 |= other.0;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the column called `name`, of `value`, is an estimate.
+    pub fn is_estimated(self, name: &str, value: u64) -> bool {
+        (value == u64::MAX && self.contains(Estimates::SATURATED))
+            || Estimates::NAMES
+                .iter()
+                .any(|(e, n)| *n == name && self.contains(*e))
+    }
+}
+
+/// Serialization of a set of flags as the names of those set, in the order
+/// of its `NAMES`, like `["mmap", "parallel"]`, rather than as a bitmask.
+#[cfg(feature = "serde")]
+macro_rules! serde_names {
+    ($set:ident, $what:literal) => {
+        impl From<$set> for Vec<&'static str> {
+            fn from(set: $set) -> Self {
+                $set::NAMES
+                    .iter()
+                    .filter(|(flag, _)| set.contains(*flag))
+                    .map(|(_, name)| *name)
+                    .collect()
+            }
+        }
+
+        impl TryFrom<Vec<String>> for $set {
+            type Error = String;
+
+            fn try_from(names: Vec<String>) -> Result<Self, String> {
+                let mut set = $set::default();
+                for name in names {
+                    let (flag, _) = $set::NAMES
+                        .iter()
+                        .find(|(_, n)| *n == name)
+                        .ok_or_else(|| format!("unknown {} '{}'", $what, name))?;
+                    set.insert(*flag);
+                }
+                Ok(set)
+            }
         }
     };
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct Counts {
-    pub lines: usize,
-    pub words: usize,
-    pub bytes: usize,
-    pub chars: usize,
+#[cfg(feature = "serde")]
+serde_names!(Annotations, "annotation");
+#[cfg(feature = "serde")]
+serde_names!(Estimates, "estimate");
+
+impl Counts {
+    /// No counts, the start of a total.
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Adds `other` to these counts, as in a total. The offset of the first
+    /// invalid byte, the digest, the detected type and the annotations
+    /// describe one input and are left as they are; estimates carry over. A
+    /// count too large to hold saturates at `u64::MAX` and is marked
+    /// `Estimates::SATURATED`.
+    pub fn merge(&mut self, other: &Counts) {
+        if self.add_counts(other) {
+            self.estimates.insert(Estimates::SATURATED);
+        }
+        if let Some(classes) = &other.char_classes {
+            self.char_classes.get_or_insert_default().merge(classes);
+        }
+        self.estimates.insert(other.estimates);
+        if let Some(buckets) = &other.buckets {
+            self.buckets.get_or_insert_default().merge(buckets);
+        }
+        if let Some(groups) = &other.groups {
+            self.groups.get_or_insert_default().merge(groups);
+        }
+        if let Some(levels) = &other.log_levels {
+            self.log_levels.get_or_insert_default().merge(levels);
+        }
+        if let Some(config) = &other.config {
+            self.config.get_or_insert_default().merge(config);
+        }
+        if let Some(cells) = &other.cells {
+            self.cells.get_or_insert_default().merge(cells);
+        }
+        if let Some(timeline) = &other.timeline {
+            self.timeline.get_or_insert_default().merge(timeline);
+        }
+        if let Some(ends) = &other.line_ends {
+            self.line_ends.get_or_insert_default().merge(ends);
+        }
+        if let Some(indents) = &other.indents {
+            self.indents.get_or_insert_default().merge(indents);
+        }
+        if let Some(fields) = &other.field_counts {
+            self.field_counts.get_or_insert_default().merge(fields);
+        }
+        if let Some(stats) = &other.line_stats {
+            self.line_stats.get_or_insert_default().merge(stats);
+        }
+        if let Some(words) = &other.vocabulary {
+            self.vocabulary.get_or_insert_default().merge(words);
+        }
+    }
+
+    /// Adds the plain counts of `other` to these, saturating, and tells
+    /// whether any of them saturated.
+    fn add_counts(&mut self, other: &Counts) -> bool {
+        let mut saturated = false;
+        let mut add = |sum: &mut u64, n: u64| {
+            *sum = sum.checked_add(n).unwrap_or_else(|| {
+                saturated = true;
+                u64::MAX
+            });
+        };
+        add(&mut self.lines, other.lines);
+        add(&mut self.words, other.words);
+        add(&mut self.numbers, other.numbers);
+        add(&mut self.links, other.links);
+        add(&mut self.bytes, other.bytes);
+        add(&mut self.chars, other.chars);
+        add(&mut self.invalid_bytes, other.invalid_bytes);
+        add(&mut self.non_ascii, other.non_ascii);
+        add(&mut self.sentences, other.sentences);
+        add(&mut self.tokens, other.tokens);
+        add(&mut self.blank_lines, other.blank_lines);
+        add(&mut self.comment_lines, other.comment_lines);
+        add(&mut self.code_lines, other.code_lines);
+        add(&mut self.matches, other.matches);
+        add(&mut self.unique_lines, other.unique_lines);
+        self.max_word_length = self.max_word_length.max(other.max_word_length);
+        saturated
+    }
+
+    /// The enabled counts, in output column order.
+    pub fn columns(&self, flags: &CountOptions) -> Vec<u64> {
+        self.named_columns(flags)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// The enabled counts with their names, in output column order.
+    pub fn named_columns(&self, flags: &CountOptions) -> Vec<(&'static str, u64)> {
+        let fields = self.field_counts.as_deref().copied().unwrap_or_default();
+        let timeline = self.timeline.as_deref().copied().unwrap_or_default();
+        let cells = self.cells.as_deref().copied().unwrap_or_default();
+        let config = self.config.as_deref().copied().unwrap_or_default();
+        let levels = self.log_levels.as_deref().copied().unwrap_or_default();
+        [
+            ("lines", flags.lines, self.lines),
+            ("blank", flags.code || flags.blank_lines, self.blank_lines),
+            ("comment", flags.code, self.comment_lines),
+            ("code", flags.code, self.code_lines),
+            ("words", flags.words, self.words),
+            (
+                "numbers",
+                flags.words && flags.numbers == Numbers::Separate,
+                self.numbers,
+            ),
+            ("links", flags.links, self.links),
+            ("bytes", flags.bytes, self.bytes),
+            ("chars", flags.chars, self.chars),
+            (
+                "invalid",
+                flags.chars && flags.invalid_utf8 == InvalidUtf8::Count,
+                self.invalid_bytes,
+            ),
+            ("non_ascii", flags.non_ascii, self.non_ascii),
+            (
+                "max_word_length",
+                flags.max_word_length,
+                self.max_word_length,
+            ),
+            ("sentences", flags.sentences, self.sentences),
+            ("tokens", flags.has_tokens(), self.tokens),
+            ("matches", flags.has_pattern(), self.matches),
+            ("error", flags.log_levels.is_some(), levels.0[0] as u64),
+            ("warn", flags.log_levels.is_some(), levels.0[1] as u64),
+            ("info", flags.log_levels.is_some(), levels.0[2] as u64),
+            ("debug", flags.log_levels.is_some(), levels.0[3] as u64),
+            ("cues", flags.subtitles, timeline.cues as u64),
+            (
+                "wpm",
+                flags.subtitles,
+                timeline.words_per_minute(self.words),
+            ),
+            ("code_lines", flags.ipynb, cells.code_lines as u64),
+            ("markdown_words", flags.ipynb, cells.markdown_words as u64),
+            ("outputs", flags.ipynb, cells.outputs as u64),
+            ("keys", flags.config_input.is_some(), config.keys as u64),
+            ("depth", flags.config_input.is_some(), config.depth as u64),
+            ("values", flags.config_input.is_some(), config.values as u64),
+            ("fields", flags.fields.is_some(), fields.total as u64),
+            ("min_fields", flags.fields.is_some(), fields.min as u64),
+            ("max_fields", flags.fields.is_some(), fields.max as u64),
+            ("unique_lines", flags.unique_lines, self.unique_lines),
+            (
+                "unique_words",
+                flags.unique_words,
+                self.vocabulary.as_deref().map_or(0, |w| w.len() as u64),
+            ),
+            (
+                "reading_minutes",
+                flags.reading_wpm.is_some(),
+                flags
+                    .reading_wpm
+                    .map_or(0, |wpm| self.words.div_ceil(wpm.get() as u64)),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled, value)| enabled.then_some((name, value)))
+        .collect()
+    }
+
+    /// The names of the enabled counts, in output column order.
+    pub fn column_names(flags: &CountOptions) -> Vec<&'static str> {
+        Counts::default()
+            .named_columns(flags)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Renders the enabled counts as right-aligned columns, each followed by
+    /// a space, ready to be suffixed with the input name.
+    pub fn to_table(&self, flags: &CountOptions) -> String {
+        self.to_table_with(flags, &LayoutOptions::default())
+    }
+
+    pub fn to_table_with(&self, flags: &CountOptions, options: &LayoutOptions) -> String {
+        let row = self.columns(flags);
+        let mut layout = Layout::new(options.clone());
+        layout.measure(&row);
+
+        let mut out = String::new();
+        // Writing into a String cannot fail.
+        let _ = layout.write_fields(&mut out, &row);
+        out
+    }
+}
+
+impl AddAssign<&Counts> for Counts {
+    fn add_assign(&mut self, other: &Counts) {
+        self.merge(other);
+    }
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Counts) {
+        self.merge(&other);
+    }
+}
+
+impl Add<&Counts> for Counts {
+    type Output = Counts;
+
+    fn add(mut self, other: &Counts) -> Counts {
+        self.merge(other);
+        self
+    }
+}
+
+impl Add for Counts {
+    type Output = Counts;
+
+    fn add(self, other: Counts) -> Counts {
+        self + &other
+    }
+}
+
+impl<'a> Sum<&'a Counts> for Counts {
+    fn sum<I: Iterator<Item = &'a Counts>>(iter: I) -> Counts {
+        iter.fold(Counts::zero(), |total, c| total + c)
+    }
+}
+
+impl Sum for Counts {
+    fn sum<I: Iterator<Item = Counts>>(iter: I) -> Counts {
+        iter.fold(Counts::zero(), |total, c| total + c)
+    }
+}
+
+/// Formats the lines, words and bytes columns, wc's default selection.
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Default::default()
+        };
+        f.write_str(self.to_table(&flags).trim_end())
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct Flags {
+/// What to count and how. Library users should build it with
+/// [`CountOptions::new`] and the setters, like
+/// `CountOptions::new().lines(true).max_line_length(true)`, which keep
+/// compiling as options are added.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CountOptions {
     pub lines: bool,
     pub words: bool,
     pub bytes: bool,
     pub chars: bool,
+    /// What counting `chars` does with malformed UTF-8.
+    pub invalid_utf8: InvalidUtf8,
+    pub digest: bool,
+    pub force_read: bool,
+    /// Count every input from this many bytes in, e.g. the part of a log
+    /// written since a saved size. A word cut at that point is counted with
+    /// the bytes before it. Regular files are seeked, other inputs read past.
+    pub seek: Option<u64>,
+    /// Count only the first this many bytes of every input.
+    pub head_bytes: Option<usize>,
+    /// Count only the first this many lines of every input.
+    pub head_lines: Option<usize>,
+    /// Count only these lines of every input, of those within `head_bytes`
+    /// and `head_lines`.
+    pub range: Option<LineRange>,
+    pub backend: Backend,
+    /// Bytes read from a file at a time, 512 KiB when unset. Larger reads
+    /// suit fast drives, smaller ones may suit network filesystems.
+    pub buffer_size: Option<usize>,
+    /// Stop reading stdin once nothing has arrived for this long, and count
+    /// what was read as all of it.
+    pub timeout: Option<Duration>,
+    /// Size of the thread pool used for counting, 0 for rayon's default.
+    /// With 1 files and their ranges are counted one after another on the
+    /// calling thread, without rayon, and streams are read on it too.
+    pub threads: usize,
+    /// Count the members of archives instead of the archive files.
+    pub archives: bool,
+    /// Count the text of PDF, DOCX and ODT files instead of their bytes.
+    pub documents: bool,
+    /// Count the captions of SRT and WebVTT files rather than their
+    /// timestamps, with their cues and words per minute.
+    pub subtitles: bool,
+    /// Count the cell sources of Jupyter notebooks rather than their JSON,
+    /// with code lines, markdown words and outputs apart.
+    pub ipynb: bool,
+    /// Count the lines per hour or day, by timestamps of `timestamp_format`.
+    pub bucket: Option<Bucket>,
+    pub timestamp_format: TimestampFormat,
+    /// Count the lines of each log level, by the words marking each.
+    pub log_levels: Option<LevelWords>,
+    /// Read every file as YAML or TOML, and count its keys, depth and
+    /// values besides the usual counts.
+    pub config_input: Option<ConfigFormat>,
+    /// Count the chapters of EPUB books, and the text of HTML files rather
+    /// than their markup.
+    pub epub: bool,
+    /// Print a row naming the columns above the counts.
+    pub header: bool,
+    /// Show the `Annotations` of every row after its name.
+    pub annotate: bool,
+    /// Mark binary inputs after their name even without `annotate`.
+    pub mark_binary: bool,
+    /// Report binary files as skipped instead of counting them.
+    pub skip_binary: bool,
+    /// Tell the type of every input from its start, text in an encoding or
+    /// binary, shown in a column before its name.
+    pub detect: bool,
+    pub sentences: bool,
+    /// Measure line lengths, in characters too if `chars` is set.
+    pub stats: bool,
+    /// Count the LF, CRLF and lone CR line ends.
+    pub eol: bool,
+    /// Count the lines indented with tabs and with spaces.
+    pub indent_stats: bool,
+    /// Count the characters outside ASCII.
+    pub non_ascii: bool,
+    /// Measure the longest word, in characters.
+    pub max_word_length: bool,
+    /// Count letters, digits, punctuation, whitespace and other characters.
+    pub char_classes: bool,
+    /// Count the tokens of this encoding.
+    #[cfg(feature = "tokens")]
+    pub tokens: Option<Encoding>,
+    /// Split lines into blank, comment and code lines.
+    pub code: bool,
+    /// Count the empty and whitespace-only lines, as `code` does.
+    pub blank_lines: bool,
+    /// Comment syntax of the input being counted, set per file for `code`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::code::by_name"))]
+    pub language: Option<&'static Language>,
+    /// Count the matches of this pattern.
+    #[cfg(feature = "pattern")]
+    pub pattern: Option<Pattern>,
+    /// Count only the lines matching this pattern, or with `invert_match`
+    /// only those that don't.
+    #[cfg(feature = "pattern")]
+    pub line_filter: Option<Pattern>,
+    #[cfg(feature = "pattern")]
+    pub invert_match: bool,
+    /// Leave out the lines matching this pattern before counting anything,
+    /// and before `line_filter` picks lines.
+    #[cfg(feature = "pattern")]
+    pub ignore_lines: Option<Pattern>,
+    /// Count the lines per value of capture group `capture_group` of this
+    /// pattern, or per match for group 0.
+    #[cfg(feature = "pattern")]
+    pub extract: Option<Pattern>,
+    #[cfg(feature = "pattern")]
+    pub capture_group: usize,
+    pub unique_lines: bool,
+    pub unique_words: bool,
+    /// Count how often each word occurs, for `--freq`.
+    pub freq: bool,
+    /// List this many of the most repeated lines of every input, or all
+    /// the repeated lines for 0.
+    pub dup_lines: Option<usize>,
+    /// Count the fields of records separated by this byte, with double
+    /// quotes as in CSV unless it is a tab.
+    pub fields: Option<u8>,
+    /// End lines at this byte instead of newlines, e.g. NUL for `-z`, for
+    /// every line-based count but the comment and code lines of `code`.
+    /// Words are split at it too if `word_rules` has it as their delimiter.
+    pub record_delimiter: Option<u8>,
+    /// Which punctuation splits words, for every count and list of words.
+    pub word_rules: WordRules,
+    /// Whether numeric words are part of the word count.
+    pub numbers: Numbers,
+    /// Count URLs and email addresses, and keep them one word whatever
+    /// `word_rules` say.
+    pub links: bool,
+    /// Compare words case-folded when collecting distinct words.
+    pub fold_case: Option<CaseFold>,
+    /// Show the minutes needed to read the words at this many words per
+    /// minute, rounded up.
+    pub reading_wpm: Option<NonZeroUsize>,
+    /// Language of the sentences and words, English if not set.
+    #[cfg_attr(feature = "serde", serde(with = "crate::lang::by_code"))]
+    pub lang: Option<&'static LangPack>,
+    /// Set `lang` per file from the start of its contents.
+    pub detect_lang: bool,
+    /// Decode the input from this encoding, counting everything but bytes
+    /// on the text.
+    #[cfg(feature = "encoding")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::transcode::by_name"))]
+    pub encoding: Option<&'static encoding_rs::Encoding>,
+}
+
+/// How file contents are brought into memory for counting.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Backend {
+    /// Buffered reads, switching to mmap for large regular files.
+    #[default]
+    Auto,
+    /// Always buffered reads.
+    Read,
+    /// Memory-map regular files, falling back to reads when that fails.
+    Mmap,
+    /// Read regular files through io_uring, several reads in flight at
+    /// once, with the `uring` feature on Linux. Falls back to buffered reads
+    /// where io_uring is not available.
+    Uring,
 }
 
-const MAX_WIDTH: usize = 7;
+impl Backend {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(Backend::Auto),
+            "read" => Ok(Backend::Read),
+            "mmap" => Ok(Backend::Mmap),
+            "uring" => Ok(Backend::Uring),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--io-backend' (expected auto, read, mmap or uring)",
+                name
+            )),
+        }
+    }
+}
+
+/// The old name of [`CountOptions`].
+#[deprecated(note = "use `CountOptions`, built with `CountOptions::new()`")]
+pub type Flags = CountOptions;
 
-fn count_reader<R: Read>(mut reader: R, flags: &Flags) -> io::Result<Counts> {
-    let mut buf = [0u8; 512 * 1024];
-    let mut counts = Counts {
-        lines: 0,
-        words: 0,
-        bytes: 0,
-        chars: 0,
+/// A setter for each field, taking the value, or what an optional field
+/// holds.
+macro_rules! setters {
+    (Some: $($(#[$attr:meta])* $field:ident: $ty:ty),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.$field = Some($field);
+                self
+            }
+        )*
+    };
+    ($($(#[$attr:meta])* $field:ident: $ty:ty),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.$field = $field;
+                self
+            }
+        )*
     };
-    let mut in_word = false;
+}
+
+impl CountOptions {
+    /// Counts nothing until a count is set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    setters! {
+        lines: bool,
+        words: bool,
+        bytes: bool,
+        chars: bool,
+        invalid_utf8: InvalidUtf8,
+        digest: bool,
+        force_read: bool,
+        backend: Backend,
+        threads: usize,
+        archives: bool,
+        documents: bool,
+        subtitles: bool,
+        ipynb: bool,
+        timestamp_format: TimestampFormat,
+        epub: bool,
+        header: bool,
+        annotate: bool,
+        mark_binary: bool,
+        skip_binary: bool,
+        detect: bool,
+        sentences: bool,
+        stats: bool,
+        eol: bool,
+        indent_stats: bool,
+        non_ascii: bool,
+        max_word_length: bool,
+        char_classes: bool,
+        code: bool,
+        blank_lines: bool,
+        #[cfg(feature = "pattern")]
+        invert_match: bool,
+        #[cfg(feature = "pattern")]
+        capture_group: usize,
+        unique_lines: bool,
+        unique_words: bool,
+        freq: bool,
+        word_rules: WordRules,
+        numbers: Numbers,
+        links: bool,
+        detect_lang: bool,
+    }
+
+    setters! {
+        Some:
+        bucket: Bucket,
+        log_levels: LevelWords,
+        config_input: ConfigFormat,
+        #[cfg(feature = "tokens")]
+        tokens: Encoding,
+        #[cfg(feature = "pattern")]
+        pattern: Pattern,
+        #[cfg(feature = "pattern")]
+        line_filter: Pattern,
+        #[cfg(feature = "pattern")]
+        ignore_lines: Pattern,
+        #[cfg(feature = "pattern")]
+        extract: Pattern,
+        dup_lines: usize,
+        seek: u64,
+        head_bytes: usize,
+        head_lines: usize,
+        range: LineRange,
+        buffer_size: usize,
+        timeout: Duration,
+        fields: u8,
+        record_delimiter: u8,
+        fold_case: CaseFold,
+        reading_wpm: NonZeroUsize,
+        lang: &'static LangPack,
+        #[cfg(feature = "encoding")]
+        encoding: &'static encoding_rs::Encoding,
+    }
+
+    /// Measures the longest line, with the other line lengths of `stats`.
+    pub fn max_line_length(self, max_line_length: bool) -> Self {
+        self.stats(max_line_length)
+    }
+
+    /// Bytes read from a file at a time.
+    pub(crate) fn read_size(&self) -> usize {
+        self.buffer_size.unwrap_or(BUF_SIZE)
+    }
+
+    pub fn has_pattern(&self) -> bool {
+        #[cfg(feature = "pattern")]
+        return self.pattern.is_some();
+        #[cfg(not(feature = "pattern"))]
+        false
+    }
+
+    pub fn has_tokens(&self) -> bool {
+        #[cfg(feature = "tokens")]
+        return self.tokens.is_some();
+        #[cfg(not(feature = "tokens"))]
+        false
+    }
+
+    fn has_encoding(&self) -> bool {
+        #[cfg(feature = "encoding")]
+        return self.encoding.is_some();
+        #[cfg(not(feature = "encoding"))]
+        false
+    }
+
+    /// Whether to count files, and the ranges of large ones, one after
+    /// another rather than on a pool.
+    pub(crate) fn sequential(&self) -> bool {
+        !cfg!(feature = "parallel") || self.threads == 1
+    }
+
+    fn has_extract(&self) -> bool {
+        #[cfg(feature = "pattern")]
+        return self.extract.is_some();
+        #[cfg(not(feature = "pattern"))]
+        false
+    }
+
+    fn has_line_filter(&self) -> bool {
+        #[cfg(feature = "pattern")]
+        return self.line_filter.is_some() || self.ignore_lines.is_some();
+        #[cfg(not(feature = "pattern"))]
+        false
+    }
+
+    /// The language pack sentences and words are counted with.
+    pub fn lang_pack(&self) -> &'static LangPack {
+        self.lang.unwrap_or(&ENGLISH)
+    }
+
+    /// True when the language pack splits words beyond `word_rules`.
+    fn has_lang_words(&self) -> bool {
+        let pack = self.lang_pack();
+        self.words && (pack.ideographic || !pack.elisions.is_empty())
+    }
+
+    /// Whether the language of every input is to be detected, for counts
+    /// that depend on it.
+    #[cfg(feature = "core")]
+    fn wants_detected_lang(&self) -> bool {
+        self.detect_lang && (self.words || self.sentences)
+    }
+
+    /// The byte that ends a line.
+    pub fn delimiter(&self) -> u8 {
+        self.record_delimiter.unwrap_or(b'\n')
+    }
+
+    /// These flags for counting the input at `path`.
+    pub fn for_input(&self, path: &Path) -> CountOptions {
+        CountOptions {
+            language: language_for(path),
+            ..self.clone()
+        }
+    }
+
+    /// True when the input has to be counted front to back in one pass:
+    /// hashes can't be split, and sentences, tokens, matches, filtered lines,
+    /// block comments and encoded characters may span the edges of parallel
+    /// ranges, and `seek`, `head_bytes`, `head_lines` and `range` only count
+    /// some of the input.
+    fn needs_single_pass(&self) -> bool {
+        self.digest
+            || self.has_encoding()
+            || self.numbers != Numbers::Count
+            || self.links
+            || self.has_lang_words()
+            || self.sentences
+            || self.stats
+            || self.eol
+            || self.indent_stats
+            || self.non_ascii
+            || self.max_word_length
+            || self.char_classes
+            || self.has_tokens()
+            || self.code
+            || self.blank_lines
+            || self.has_pattern()
+            || self.has_line_filter()
+            || self.has_extract()
+            || self.unique_lines
+            || self.unique_words
+            || self.freq
+            || self.dup_lines.is_some()
+            || self.log_levels.is_some()
+            || self.bucket.is_some()
+            || self.fields.is_some()
+            || self.has_head()
+            || self.detect
+    }
+
+    /// Whether counting leaves out some of the inputs.
+    fn has_head(&self) -> bool {
+        self.seek.is_some()
+            || self.head_bytes.is_some()
+            || self.head_lines.is_some()
+            || self.range.is_some()
+    }
+
+    /// True when the byte count can be taken from file metadata alone.
+    #[cfg(feature = "core")]
+    fn bytes_only(&self) -> bool {
+        self.bytes
+            && !self.lines
+            && !self.words
+            && !self.chars
+            && !self.needs_single_pass()
+            && !self.force_read
+    }
+}
+
+pub(crate) const BUF_SIZE: usize = 512 * 1024;
+/// Inputs with a NUL byte this close to their start are taken for binary.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+/// Regular files at least this large are memory-mapped under `Backend::Auto`.
+#[cfg(feature = "core")]
+const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+/// Mapped inputs at least this large are counted in parallel ranges.
+const PARALLEL_THRESHOLD: u64 = 64 * 1024 * 1024;
+const MIN_RANGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Incremental counting state, fed one chunk of input at a time by whichever
+/// input backend is reading the data.
+struct Counter<'a> {
+    progress: &'a Progress<'a>,
+    /// Bytes of `seek` still to skip.
+    skip_left: u64,
+    /// Bytes and lines of `head_bytes` and `head_lines` still to count, and
+    /// whether either was reached.
+    head_bytes_left: Option<usize>,
+    head_lines_left: Option<usize>,
+    head_reached: bool,
+    /// Lines before `range` still to skip, and lines of it still to count.
+    range_skip: usize,
+    range_left: Option<usize>,
+    delimiter: u8,
+    /// Whether the rest of the input is left out.
+    full: bool,
+    #[cfg(feature = "encoding")]
+    decoder: Option<Transcoder>,
+    /// Bytes of input before decoding.
+    input_bytes: u64,
+    /// Bytes of text still to be looked at for NULs, and whether one was.
+    sniff_left: usize,
+    binary: bool,
+    /// The start of the input, to tell its type from.
+    sample: Option<Vec<u8>>,
+    /// Leaves out the lines of `ignore_lines`, before `filter`.
+    #[cfg(feature = "pattern")]
+    ignore: Option<LineFilter<'a>>,
+    #[cfg(feature = "pattern")]
+    filter: Option<LineFilter<'a>>,
+    scanner: Scanner,
+    #[cfg(feature = "digest")]
+    hasher: Option<Sha256>,
+    numbers: Option<NumberWords>,
+    links: Option<LinkWords>,
+    lang_words: Option<LangWords>,
+    line_meter: Option<LineMeter>,
+    eol: Option<EolCounter>,
+    indents: Option<IndentCounter>,
+    non_ascii: Option<NonAscii>,
+    char_classes: Option<CharClassCounter>,
+    sentences: Option<Sentences>,
+    #[cfg(feature = "tokens")]
+    tokens: Option<TokenCounter>,
+    code: Option<CodeLines>,
+    /// Blank lines when `code`, which counts them too, is not set.
+    blank: Option<BlankLines>,
+    #[cfg(feature = "pattern")]
+    matches: Option<MatchCounter<'a>>,
+    #[cfg(feature = "pattern")]
+    groups: Option<GroupCounter<'a>>,
+    unique_lines: Option<UniqueLines>,
+    unique_words: Option<UniqueWords>,
+    freq: Option<FreqCounter>,
+    dup_lines: Option<DupLines>,
+    fields: Option<FieldCounter>,
+    log_levels: Option<LevelCounter<'a>>,
+    buckets: Option<BucketCounter<'a>>,
+}
+
+impl<'a> Counter<'a> {
+    fn new(flags: &'a CountOptions, progress: &'a Progress<'a>) -> Self {
+        let delimiter = flags.delimiter();
+        Counter {
+            progress,
+            skip_left: flags.seek.unwrap_or(0),
+            head_bytes_left: flags.head_bytes,
+            head_lines_left: flags.head_lines,
+            head_reached: false,
+            range_skip: flags.range.map_or(0, |r| r.skipped()),
+            range_left: flags.range.and_then(|r| r.lines()),
+            delimiter,
+            full: false,
+            #[cfg(feature = "encoding")]
+            decoder: flags.encoding.map(Transcoder::new),
+            input_bytes: 0,
+            // NULs are expected when they end records.
+            sniff_left: if delimiter == 0 { 0 } else { BINARY_SNIFF_LEN },
+            binary: false,
+            sample: flags.detect.then(Vec::new),
+            #[cfg(feature = "pattern")]
+            ignore: flags
+                .ignore_lines
+                .as_ref()
+                .map(|p| LineFilter::new(p, true).with_delimiter(delimiter)),
+            #[cfg(feature = "pattern")]
+            filter: flags
+                .line_filter
+                .as_ref()
+                .map(|p| LineFilter::new(p, flags.invert_match).with_delimiter(delimiter)),
+            scanner: Scanner::new(flags.chars)
+                .with_invalid_utf8(flags.invalid_utf8)
+                .with_word_rules(flags.word_rules)
+                .with_longest_word(flags.max_word_length)
+                .with_delimiter(delimiter),
+            #[cfg(feature = "digest")]
+            hasher: flags.digest.then(Sha256::new),
+            numbers: (flags.numbers != Numbers::Count).then(|| NumberWords::new(flags.word_rules)),
+            links: flags.links.then(|| LinkWords::new(flags.word_rules)),
+            lang_words: flags
+                .has_lang_words()
+                .then(|| LangWords::new(flags.lang_pack(), flags.word_rules)),
+            line_meter: flags
+                .stats
+                .then(|| LineMeter::new(flags.chars).with_delimiter(delimiter)),
+            eol: flags.eol.then(EolCounter::default),
+            indents: flags.indent_stats.then(IndentCounter::default),
+            non_ascii: flags.non_ascii.then(NonAscii::default),
+            char_classes: flags.char_classes.then(CharClassCounter::default),
+            sentences: flags.sentences.then(|| Sentences::new(flags.lang_pack())),
+            #[cfg(feature = "tokens")]
+            tokens: flags.tokens.map(TokenCounter::new),
+            code: flags.code.then(|| CodeLines::new(flags.language)),
+            blank: (flags.blank_lines && !flags.code).then(|| BlankLines::new(delimiter)),
+            #[cfg(feature = "pattern")]
+            matches: flags
+                .pattern
+                .as_ref()
+                .map(|p| MatchCounter::new(p).with_delimiter(delimiter)),
+            #[cfg(feature = "pattern")]
+            groups: flags
+                .extract
+                .as_ref()
+                .map(|p| GroupCounter::new(p, flags.capture_group).with_delimiter(delimiter)),
+            unique_lines: flags
+                .unique_lines
+                .then(|| UniqueLines::default().with_delimiter(delimiter)),
+            unique_words: flags
+                .unique_words
+                .then(|| UniqueWords::new(flags.word_rules, flags.fold_case)),
+            freq: flags
+                .freq
+                .then(|| FreqCounter::new(flags.word_rules, flags.fold_case)),
+            dup_lines: flags.dup_lines.map(|n| DupLines::new(n, delimiter)),
+            fields: flags
+                .fields
+                .map(|d| FieldCounter::new(d, d != b'\t').with_record_delimiter(delimiter)),
+            log_levels: flags
+                .log_levels
+                .as_ref()
+                .map(|w| LevelCounter::new(w).with_delimiter(delimiter)),
+            buckets: flags
+                .bucket
+                .map(|b| BucketCounter::new(b, &flags.timestamp_format).with_delimiter(delimiter)),
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        let chunk = self.skip(chunk);
+        if let Some(sample) = self.sample.as_mut()
+            && sample.len() < SAMPLE_LEN
+        {
+            let take = chunk.len().min(SAMPLE_LEN - sample.len());
+            sample.extend_from_slice(&chunk[..take]);
+        }
+        let chunk = self.head(chunk);
+        let chunk = self.in_range(chunk);
+        self.input_bytes += chunk.len() as u64;
+        status::read(chunk.len());
+        #[cfg(feature = "encoding")]
+        if let Some(mut decoder) = self.decoder.take() {
+            decoder.feed(chunk, |text| self.filter(text));
+            self.decoder = Some(decoder);
+            (self.progress)(chunk.len());
+            return;
+        }
+
+        self.filter(chunk);
+        (self.progress)(chunk.len());
+    }
+
+    /// The rest of `chunk` after the bytes of `seek` still to skip. Words
+    /// carry on after the last byte skipped, so one cut there isn't counted
+    /// twice.
+    fn skip<'c>(&mut self, chunk: &'c [u8]) -> &'c [u8] {
+        if self.skip_left == 0 {
+            return chunk;
+        }
+        let n = usize::try_from(self.skip_left).map_or(chunk.len(), |left| left.min(chunk.len()));
+        self.skip_left -= n as u64;
+        if self.skip_left == 0
+            && let Some(&last) = chunk[..n].last()
+        {
+            self.scanner.continue_after(last);
+        }
+        &chunk[n..]
+    }
+
+    /// The start of `chunk` up to `head_bytes` and `head_lines`, noting
+    /// when one of them is reached.
+    fn head<'c>(&mut self, chunk: &'c [u8]) -> &'c [u8] {
+        let mut end = chunk.len();
+        if let Some(left) = self.head_bytes_left
+            && left <= end
+        {
+            end = left;
+            self.head_reached = true;
+        }
+        if let Some(left) = self.head_lines_left.as_mut()
+            && let Some(lines_end) = end_of_lines(&chunk[..end], self.delimiter, left)
+        {
+            end = lines_end;
+            self.head_reached = true;
+        }
+        if let Some(left) = self.head_bytes_left.as_mut() {
+            *left -= end;
+        }
+        self.full |= self.head_reached;
+        &chunk[..end]
+    }
+
+    /// The lines of `chunk` within `range`, noting when its end is reached.
+    fn in_range<'c>(&mut self, chunk: &'c [u8]) -> &'c [u8] {
+        let mut start = 0;
+        if self.range_skip > 0 {
+            match end_of_lines(chunk, self.delimiter, &mut self.range_skip) {
+                Some(skipped) => start = skipped,
+                None => return &[],
+            }
+        }
+        let chunk = &chunk[start..];
+        if let Some(left) = self.range_left.as_mut()
+            && let Some(end) = end_of_lines(chunk, self.delimiter, left)
+        {
+            self.full = true;
+            return &chunk[..end];
+        }
+        chunk
+    }
+
+    /// Whether `head_bytes`, `head_lines` or the end of `range` were
+    /// reached, so the rest of the input needn't be read.
+    fn is_full(&self) -> bool {
+        self.full
+    }
+
+    fn filter(&mut self, chunk: &[u8]) {
+        if self.sniff_left > 0 {
+            let head = &chunk[..chunk.len().min(self.sniff_left)];
+            self.binary |= head.contains(&0);
+            self.sniff_left -= head.len();
+        }
+
+        #[cfg(feature = "pattern")]
+        if let Some(mut ignore) = self.ignore.take() {
+            ignore.feed(chunk, |line| self.select(line));
+            self.ignore = Some(ignore);
+            return;
+        }
+
+        self.select(chunk);
+    }
+
+    /// Counts `chunk`, or the lines of it `filter` picks.
+    fn select(&mut self, chunk: &[u8]) {
+        #[cfg(feature = "pattern")]
+        if let Some(mut filter) = self.filter.take() {
+            filter.feed(chunk, |line| self.count(line));
+            self.filter = Some(filter);
+            return;
+        }
+
+        self.count(chunk);
+    }
+
+    fn has_decoder(&self) -> bool {
+        #[cfg(feature = "encoding")]
+        return self.decoder.is_some();
+        #[cfg(not(feature = "encoding"))]
+        false
+    }
+
+    fn has_filter(&self) -> bool {
+        #[cfg(feature = "pattern")]
+        return self.ignore.is_some() || self.filter.is_some();
+        #[cfg(not(feature = "pattern"))]
+        false
+    }
+
+    fn count(&mut self, chunk: &[u8]) {
+        self.scanner.feed(chunk);
+
+        #[cfg(feature = "digest")]
+        if let Some(hasher) = self.hasher.as_mut() {
+            hasher.update(chunk);
+        }
+        if let Some(numbers) = self.numbers.as_mut() {
+            numbers.feed(chunk);
+        }
+        if let Some(links) = self.links.as_mut() {
+            links.feed(chunk);
+        }
+        if let Some(words) = self.lang_words.as_mut() {
+            words.feed(chunk);
+        }
+        if let Some(meter) = self.line_meter.as_mut() {
+            meter.feed(chunk);
+        }
+        if let Some(eol) = self.eol.as_mut() {
+            eol.feed(chunk);
+        }
+        if let Some(indents) = self.indents.as_mut() {
+            indents.feed(chunk);
+        }
+        if let Some(non_ascii) = self.non_ascii.as_mut() {
+            non_ascii.feed(chunk);
+        }
+        if let Some(classes) = self.char_classes.as_mut() {
+            classes.feed(chunk);
+        }
+        if let Some(sentences) = self.sentences.as_mut() {
+            sentences.feed(chunk);
+        }
+        #[cfg(feature = "tokens")]
+        if let Some(tokens) = self.tokens.as_mut() {
+            tokens.feed(chunk);
+        }
+        if let Some(code) = self.code.as_mut() {
+            code.feed(chunk);
+        }
+        if let Some(blank) = self.blank.as_mut() {
+            blank.feed(chunk);
+        }
+        #[cfg(feature = "pattern")]
+        if let Some(matches) = self.matches.as_mut() {
+            matches.feed(chunk);
+        }
+        #[cfg(feature = "pattern")]
+        if let Some(groups) = self.groups.as_mut() {
+            groups.feed(chunk);
+        }
+        if let Some(unique) = self.unique_lines.as_mut() {
+            unique.feed(chunk);
+        }
+        if let Some(unique) = self.unique_words.as_mut() {
+            unique.feed(chunk);
+        }
+        if let Some(freq) = self.freq.as_mut() {
+            freq.feed(chunk);
+        }
+        if let Some(dup) = self.dup_lines.as_mut() {
+            dup.feed(chunk);
+        }
+        if let Some(fields) = self.fields.as_mut() {
+            fields.feed(chunk);
+        }
+        if let Some(levels) = self.log_levels.as_mut() {
+            levels.feed(chunk);
+        }
+        if let Some(buckets) = self.buckets.as_mut() {
+            buckets.feed(chunk);
+        }
+    }
+
+    // Only flushing the decoder and the line filter needs `mut`.
+    #[cfg_attr(not(any(feature = "encoding", feature = "pattern")), allow(unused_mut))]
+    fn finish(mut self) -> Counts {
+        // The size of decoded input is that of the input, unless only some
+        // lines of it are counted.
+        let decoded = self.has_decoder() && !self.has_filter();
+        #[cfg(feature = "encoding")]
+        if let Some(decoder) = self.decoder.take() {
+            decoder.finish(|text| self.filter(text));
+        }
+        #[cfg(feature = "pattern")]
+        if let Some(ignore) = self.ignore.take() {
+            ignore.finish(|line| self.select(line));
+        }
+        #[cfg(feature = "pattern")]
+        if let Some(filter) = self.filter.take() {
+            filter.finish(|line| self.count(line));
+        }
+
+        #[cfg(feature = "digest")]
+        let digest = self.hasher.map(|h| Box::new(h.finalize().into()));
+        #[cfg(not(feature = "digest"))]
+        let digest = None;
+        #[cfg(feature = "tokens")]
+        let (tokens, tokens_exact) = self.tokens.map_or((0, true), TokenCounter::finish);
+        #[cfg(not(feature = "tokens"))]
+        let (tokens, tokens_exact) = (0, true);
+        let mut estimates = Estimates::default();
+        if !tokens_exact {
+            estimates.insert(Estimates::TOKENS);
+        }
+        let code = self.code.map(CodeLines::finish).unwrap_or_default();
+        #[cfg(feature = "pattern")]
+        let matches = self.matches.map_or(0, MatchCounter::finish);
+        #[cfg(not(feature = "pattern"))]
+        let matches = 0;
+        #[cfg(feature = "pattern")]
+        let groups = self.groups.map(|g| Box::new(g.finish()));
+        #[cfg(not(feature = "pattern"))]
+        let groups = None;
+
+        let numbers = self.numbers.map_or(0, NumberWords::finish);
+        let (links, joined) = self.links.map_or((0, 0), LinkWords::finish);
+        let split = self.lang_words.map_or(0, LangWords::finish);
+
+        let mut annotations = Annotations::default();
+        if self.binary {
+            annotations.insert(Annotations::BINARY);
+        }
+        if self.head_reached {
+            annotations.insert(Annotations::HEAD);
+        }
+        let mut s = self.scanner;
+        s.flush();
+        Counts {
+            lines: s.lines,
+            words: s.words + split - numbers - joined,
+            numbers,
+            links,
+            bytes: if decoded { self.input_bytes } else { s.bytes },
+            chars: s.chars,
+            invalid_bytes: s.invalid,
+            first_invalid: s.first_invalid,
+            non_ascii: self.non_ascii.map_or(0, NonAscii::finish),
+            char_classes: self.char_classes.map(|c| Box::new(c.finish())),
+            max_word_length: s.longest_word,
+            sentences: self.sentences.map_or(0, Sentences::finish),
+            tokens,
+            blank_lines: code.blank + self.blank.map_or(0, BlankLines::finish),
+            comment_lines: code.comment,
+            code_lines: code.code,
+            matches,
+            line_ends: self.eol.map(|e| Box::new(e.finish())),
+            indents: self.indents.map(|i| Box::new(i.finish())),
+            field_counts: self.fields.map(|f| Box::new(f.finish())),
+            timeline: None,
+            cells: None,
+            config: None,
+            log_levels: self.log_levels.map(|l| Box::new(l.finish())),
+            groups,
+            buckets: self.buckets.map(|b| Box::new(b.finish())),
+            digest,
+            unique_lines: self.unique_lines.map_or(0, UniqueLines::finish),
+            repeated_lines: self.dup_lines.map(|d| Box::new(d.finish())),
+            frequencies: self.freq.map(|f| Box::new(f.finish())),
+            vocabulary: self.unique_words.map(|u| Box::new(u.finish())),
+            line_stats: self.line_meter.map(|m| Box::new(m.finish())),
+            detected: self.sample.map(|s| Detected::of(&s)),
+            annotations,
+            estimates,
+        }
+    }
+}
+
+/// Called with the number of bytes just counted, after every chunk of input.
+pub type Progress<'a> = dyn Fn(usize) + Sync + 'a;
+
+fn no_progress(_: usize) {}
+
+/// Counts everything `reader` yields until EOF.
+pub fn count_reader<R: Read>(reader: R, flags: &CountOptions) -> io::Result<Counts> {
+    count_reader_with_progress(reader, flags, &no_progress)
+}
+
+pub fn count_reader_with_progress<R: Read>(
+    mut reader: R,
+    flags: &CountOptions,
+    progress: &Progress<'_>,
+) -> io::Result<Counts> {
+    let mut buf = vec![0u8; flags.read_size()];
+    let mut counter = Counter::new(flags, progress);
 
     loop {
         let n = reader.read(&mut buf)?;
@@ -45,274 +1453,2143 @@ fn count_reader<R: Read>(mut reader: R, flags: &Flags) -> io::Result<Counts> {
             break;
         }
 
-        counts.bytes += n;
+        counter.update(&buf[..n]);
+        if counter.is_full() {
+            break;
+        }
+    }
+
+    strict_utf8(counter.finish(), flags)
+}
+
+/// Fails `counts` with malformed UTF-8 under `InvalidUtf8::Strict`.
+fn strict_utf8(counts: Counts, flags: &CountOptions) -> io::Result<Counts> {
+    match counts.first_invalid {
+        Some(at) if flags.chars && flags.invalid_utf8 == InvalidUtf8::Strict => {
+            Err(RswcError::Encoding(format!("invalid UTF-8 at byte {}", at)).into_io())
+        }
+        _ => Ok(counts),
+    }
+}
+
+/// Counts an in-memory buffer, splitting large ones into ranges counted in
+/// parallel.
+pub fn count_slice(data: &[u8], flags: &CountOptions) -> Counts {
+    count_slice_with_progress(data, flags, &no_progress)
+}
+
+fn count_slice_with_progress(data: &[u8], flags: &CountOptions, progress: &Progress<'_>) -> Counts {
+    if flags.sequential() || flags.needs_single_pass() || (data.len() as u64) < PARALLEL_THRESHOLD {
+        return count_sequential(data, flags, progress);
+    }
+
+    #[cfg(feature = "parallel")]
+    let ranges = rayon::current_num_threads().max(1);
+    #[cfg(not(feature = "parallel"))]
+    let ranges = 1;
+
+    let range_size = data.len().div_ceil(ranges).max(MIN_RANGE_SIZE);
+    let mut counts = count_chunked(data, flags, range_size, progress);
+    counts.annotations.insert(Annotations::PARALLEL);
+    counts
+}
+
+fn count_sequential(data: &[u8], flags: &CountOptions, progress: &Progress<'_>) -> Counts {
+    let mut counter = Counter::new(flags, progress);
+    // Feed the same chunk sizes as buffered reads so both backends agree.
+    for chunk in data.chunks(flags.read_size()) {
+        counter.update(chunk);
+        if counter.is_full() {
+            break;
+        }
+    }
+    counter.finish()
+}
+
+/// Counts `data` as independent ranges of roughly `range_size` bytes on the
+/// rayon pool. Range edges are moved forward onto character boundaries, and
+/// a word running across an edge is counted only once.
+fn count_chunked(
+    data: &[u8],
+    flags: &CountOptions,
+    range_size: usize,
+    progress: &Progress<'_>,
+) -> Counts {
+    let mut bounds = vec![0];
+    let mut start = 0;
+    while start < data.len() {
+        let mut end = (start + range_size).min(data.len());
+        while end < data.len() && data[end] & 0xC0 == 0x80 {
+            end += 1;
+        }
+        bounds.push(end);
+        start = end;
+    }
+
+    #[cfg(feature = "parallel")]
+    let windows = bounds.par_windows(2);
+    #[cfg(not(feature = "parallel"))]
+    let windows = bounds.windows(2);
+
+    let parts: Vec<Counts> = windows
+        .map(|w| count_sequential(&data[w[0]..w[1]], flags, progress))
+        .collect();
+
+    let mut total = Counts {
+        annotations: parts.first().map(|c| c.annotations).unwrap_or_default(),
+        ..Default::default()
+    };
+    for (i, c) in parts.iter().enumerate() {
+        total.lines += c.lines;
+        total.words += c.words;
+        total.bytes += c.bytes;
+        total.chars += c.chars;
+        total.invalid_bytes += c.invalid_bytes;
+        if total.first_invalid.is_none() {
+            total.first_invalid = c.first_invalid.map(|at| bounds[i] as u64 + at);
+        }
+
+        let edge = bounds[i];
+        let rules = flags.word_rules;
+        if edge > 0
+            && rules.separator_len_before(&data[..edge]) == 0
+            && rules.separator_len(&data[edge..]) == 0
+        {
+            total.words -= 1;
+        }
+    }
+    total
+}
+
+#[cfg(feature = "mmap")]
+fn count_mmap(file: &File, flags: &CountOptions, progress: &Progress<'_>) -> io::Result<Counts> {
+    // SAFETY: the map is only read for the duration of this call. If another
+    // process truncates the file meanwhile the read faults, which is the
+    // documented risk of opting into memory-mapped I/O.
+    let map = unsafe { Mmap::map(file)? };
+    let start =
+        usize::try_from((&*file).stream_position()?).map_or(map.len(), |s| s.min(map.len()));
+    let mut counts = count_slice_with_progress(&map[start..], flags, progress);
+    counts.annotations.insert(Annotations::MMAP);
+    Ok(counts)
+}
+
+#[cfg(feature = "core")]
+fn count_file(path: &Path, flags: &CountOptions, progress: &Progress<'_>) -> io::Result<Counts> {
+    if path == Path::new("-") {
+        return count_stream_detected(io::stdin(), flags, progress);
+    }
+
+    // Said up front rather than failing the first read, and on Windows,
+    // where a directory can't even be opened.
+    let file = match File::open(path) {
+        Err(_) if path.is_dir() => return Err(is_a_directory()),
+        file => file?,
+    };
+    let metadata = file.metadata()?;
+    if metadata.is_dir() {
+        return Err(is_a_directory());
+    }
+    // Pipes, sockets and devices: streams, maybe of a fast producer.
+    if !metadata.is_file() {
+        return count_stream_detected(file, flags, progress);
+    }
+
+    // Read from the byte before the offset, for words to carry on after.
+    let resumed;
+    let flags = match flags.seek {
+        Some(offset) if offset > 0 => {
+            (&file).seek(SeekFrom::Start(offset - 1))?;
+            resumed = CountOptions {
+                seek: Some(1),
+                ..flags.clone()
+            };
+            &resumed
+        }
+        _ => flags,
+    };
+
+    if flags.bytes_only() {
+        // Files in pseudo filesystems like /proc report a size of zero, so
+        // only trust the metadata when it actually has something to say.
+        if metadata.is_file() && metadata.len() > 0 {
+            return Ok(Counts {
+                bytes: metadata.len(),
+                annotations: Annotations::METADATA,
+                ..Default::default()
+            });
+        }
+    }
+
+    let use_mmap = metadata.is_file()
+        && match flags.backend {
+            Backend::Auto => metadata.len() >= MMAP_THRESHOLD,
+            Backend::Mmap => true,
+            Backend::Read | Backend::Uring => false,
+        };
+
+    // Fall back to buffered reads if the file cannot be mapped.
+    #[cfg(feature = "mmap")]
+    if use_mmap && let Ok(counts) = count_mmap(&file, flags, progress) {
+        return strict_utf8(counts, flags);
+    }
+    #[cfg(not(feature = "mmap"))]
+    let _ = use_mmap;
+
+    let backend: &dyn ReadBackend = match flags.backend {
+        #[cfg(all(feature = "uring", target_os = "linux"))]
+        Backend::Uring if metadata.is_file() => &crate::uring::Uring,
+        _ => &Buffered,
+    };
+    count_with(backend, &file, flags, progress)
+}
+
+/// The error of a directory given as a file, worded like wc's.
+#[cfg(feature = "core")]
+fn is_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::IsADirectory, "Is a directory")
+}
+
+/// Brings the contents of an open file into memory for counting.
+#[cfg(feature = "core")]
+pub(crate) trait ReadBackend {
+    /// Hands `file` to `chunk` from its current position, in order and `size` bytes at
+    /// a time at most, until its end or until `chunk` returns false.
+    fn read(
+        &self,
+        file: &File,
+        size: usize,
+        chunk: &mut dyn FnMut(&[u8]) -> bool,
+    ) -> io::Result<()>;
+}
+
+/// Reads through a buffer: the portable backend.
+#[cfg(feature = "core")]
+pub(crate) struct Buffered;
+
+#[cfg(feature = "core")]
+impl ReadBackend for Buffered {
+    fn read(
+        &self,
+        file: &File,
+        size: usize,
+        chunk: &mut dyn FnMut(&[u8]) -> bool,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::with_capacity(size, file);
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() || !chunk(buf) {
+                return Ok(());
+            }
+            let n = buf.len();
+            reader.consume(n);
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+fn count_with(
+    backend: &dyn ReadBackend,
+    file: &File,
+    flags: &CountOptions,
+    progress: &Progress<'_>,
+) -> io::Result<Counts> {
+    let mut counter = Counter::new(flags, progress);
+    backend.read(file, flags.read_size(), &mut |chunk| {
+        counter.update(chunk);
+        !counter.is_full()
+    })?;
+    strict_utf8(counter.finish(), flags)
+}
+
+#[cfg(feature = "core")]
+pub fn process_stdin(flags: &CountOptions) -> io::Result<Counts> {
+    count_stream(io::stdin(), flags, &no_progress)
+}
+
+/// The counts of stdin as the result of an input named `-`, so stdin that
+/// can't be counted fails like a file does instead of ending the run.
+#[cfg(feature = "core")]
+pub fn stdin_result(counts: io::Result<Counts>) -> FileResult {
+    match counts {
+        Ok(counts) => FileResult::Ok("-".into(), counts),
+        Err(e) => FileResult::Err("-".into(), e.into()),
+    }
+}
+
+/// Stdin, ending early when nothing arrives for the `timeout` of `flags`.
+#[cfg(feature = "core")]
+pub fn stdin_reader(flags: &CountOptions) -> Box<dyn Read> {
+    match flags.timeout {
+        Some(_) => {
+            Box::new(ReadAhead::spawn(io::stdin(), flags.read_size()).with_timeout(flags.timeout))
+        }
+        None => Box::new(io::stdin().lock()),
+    }
+}
+
+/// Counts a stream, in the language of its start with `detect_lang`. The
+/// start is read once, to tell the language from and then to count.
+#[cfg(feature = "core")]
+fn count_stream_detected<R: Read + Send + 'static>(
+    mut reader: R,
+    flags: &CountOptions,
+    progress: &Progress<'_>,
+) -> io::Result<Counts> {
+    if !flags.wants_detected_lang() {
+        return count_stream(reader, flags, progress);
+    }
+    let mut sample = Vec::new();
+    (&mut reader)
+        .take(crate::lang::SAMPLE_SIZE)
+        .read_to_end(&mut sample)?;
+    let detected = CountOptions {
+        lang: Some(detect(&sample)),
+        ..flags.clone()
+    };
+    count_stream(io::Cursor::new(sample).chain(reader), &detected, progress)
+}
+
+/// Counts a stream like stdin or a pipe, read ahead on a thread of its own
+/// when there is a core to spare for it, or for the `timeout` of `flags`.
+#[cfg(feature = "core")]
+fn count_stream<R: Read + Send + 'static>(
+    reader: R,
+    flags: &CountOptions,
+    progress: &Progress<'_>,
+) -> io::Result<Counts> {
+    static SPARE_CORE: OnceLock<bool> = OnceLock::new();
+    let spare_core =
+        *SPARE_CORE.get_or_init(|| std::thread::available_parallelism().is_ok_and(|n| n.get() > 1));
+    if flags.timeout.is_some() || (spare_core && flags.threads != 1) {
+        count_read_ahead(reader, flags, progress)
+    } else {
+        count_reader_with_progress(reader, flags, progress)
+    }
+}
+
+/// Counts a stream read ahead on a thread of its own, which ends early
+/// when nothing arrives for the `timeout` of `flags`.
+#[cfg(feature = "core")]
+fn count_read_ahead<R: Read + Send + 'static>(
+    reader: R,
+    flags: &CountOptions,
+    progress: &Progress<'_>,
+) -> io::Result<Counts> {
+    let mut chunks = ReadAhead::spawn(reader, flags.read_size()).with_timeout(flags.timeout);
+    let mut counter = Counter::new(flags, progress);
+    while let Some(chunk) = chunks.next_chunk()? {
+        counter.update(&chunk);
+        chunks.recycle(chunk);
+        if counter.is_full() {
+            break;
+        }
+    }
+    strict_utf8(counter.finish(), flags)
+}
+
+/// Like `process_stdin`, copying stdin unchanged to `copy` as it is read,
+/// so rswc can sit in the middle of a pipeline.
+#[cfg(feature = "core")]
+pub fn process_stdin_tee(flags: &CountOptions, copy: &mut dyn Write) -> io::Result<Counts> {
+    let counts = count_reader(
+        Tee {
+            reader: stdin_reader(flags),
+            copy: &mut *copy,
+        },
+        flags,
+    )?;
+    copy.flush()?;
+    Ok(counts)
+}
+
+/// A reader writing everything read from `reader` to `copy`.
+#[cfg(feature = "core")]
+struct Tee<'a, R> {
+    reader: R,
+    copy: &'a mut dyn Write,
+}
+
+#[cfg(feature = "core")]
+impl<R: Read> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.copy.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+// Most results are `Ok`, and the rarely used counts are boxed already, so
+// boxing `Counts` as well would only add an allocation per file.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FileResult {
+    Ok(PathBuf, Counts),
+    Err(PathBuf, RswcError),
+    /// An archive and the results of its members, named relative to it.
+    Archive(PathBuf, Vec<FileResult>),
+}
+
+#[cfg(feature = "core")]
+fn file_result(path: &Path, flags: &CountOptions, progress: &Progress<'_>) -> FileResult {
+    let named;
+    let flags = if flags.code {
+        named = flags.for_input(path);
+        &named
+    } else {
+        flags
+    };
+
+    #[cfg(feature = "remote")]
+    if crate::args::is_url(path) {
+        let url = path.to_string_lossy();
+        return match crate::remote::count_url(&url, flags, progress) {
+            Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+    #[cfg(not(feature = "remote"))]
+    if crate::args::is_url(path) {
+        return FileResult::Err(
+            path.to_path_buf(),
+            RswcError::Usage(
+                "URL support is not enabled in this build (feature `remote`)".to_string(),
+            ),
+        );
+    }
+
+    #[cfg(feature = "archive")]
+    if flags.archives && crate::archive::is_archive(path) {
+        return match crate::archive::count_archive(path, flags) {
+            Ok(members) => FileResult::Archive(path.to_path_buf(), members),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+
+    if let Some(format) = flags.config_input {
+        return match count_config(path, format, flags) {
+            Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+    if flags.ipynb && is_notebook(path) {
+        return match count_notebook(path, flags) {
+            Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+    if flags.subtitles && is_subtitles(path) {
+        return match count_subtitles(path, flags) {
+            Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+
+    #[cfg(feature = "office")]
+    if flags.epub && is_epub(path) {
+        return match crate::epub::count_epub(path, flags) {
+            Ok(chapters) => FileResult::Archive(path.to_path_buf(), chapters),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+    #[cfg(feature = "office")]
+    if flags.epub && is_html(path) {
+        return match crate::epub::count_html(path, flags) {
+            Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+    #[cfg(not(feature = "office"))]
+    if flags.epub && (is_epub(path) || is_html(path)) {
+        return FileResult::Err(
+            path.to_path_buf(),
+            RswcError::Usage(
+                "EPUB support is not enabled in this build (feature `office`)".to_string(),
+            ),
+        );
+    }
+
+    if flags.documents
+        && let Some(document) = Document::of(path)
+    {
+        return match crate::document::count_document(path, document, flags) {
+            Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+            Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+        };
+    }
+
+    // Text decoded from another encoding may well have NULs in its bytes.
+    let skip_binary = flags.skip_binary && !flags.has_encoding();
+    if skip_binary && is_binary_file(path).unwrap_or(false) {
+        return skipped_binary(path);
+    }
+
+    // Streams are detected from the start of their counting, in `count_file`.
+    let detected;
+    let flags = if flags.wants_detected_lang()
+        && fs::metadata(path).is_ok_and(|m| m.is_file())
+        && let Ok(pack) = detect_file(path)
+    {
+        detected = CountOptions {
+            lang: Some(pack),
+            ..flags.clone()
+        };
+        &detected
+    } else {
+        flags
+    };
+
+    match count_file(path, flags, progress) {
+        // Streams are only told binary once counted, from what was read.
+        Ok(counts) if skip_binary && counts.annotations.contains(Annotations::BINARY) => {
+            skipped_binary(path)
+        }
+        Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+        Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+    }
+}
+
+#[cfg(feature = "core")]
+fn skipped_binary(path: &Path) -> FileResult {
+    FileResult::Err(
+        path.to_path_buf(),
+        RswcError::Skipped("binary file".to_string()),
+    )
+}
+
+/// Whether the regular file at `path` has a NUL byte near its start, so it
+/// can be skipped without reading the rest. Pipes and other streams are
+/// never looked at here, as what is read of them is gone for counting.
+#[cfg(feature = "core")]
+fn is_binary_file(path: &Path) -> io::Result<bool> {
+    if path == Path::new("-") || !fs::metadata(path)?.is_file() {
+        return Ok(false);
+    }
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    File::open(path)?
+        .take(BINARY_SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    Ok(head.contains(&0))
+}
+
+/// Where `process_files_from` reads the files it counts, so tests can count
+/// in-memory sources and embedders files kept in a database or an overlay
+/// filesystem, without touching the disk.
+pub trait FileOpener: Sync {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>>;
+}
+
+/// Opens files on disk, like `process_files` does.
+#[cfg(feature = "core")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Disk;
+
+#[cfg(feature = "core")]
+impl FileOpener for Disk {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(BufReader::with_capacity(
+            BUF_SIZE,
+            File::open(path)?,
+        )))
+    }
+}
+
+/// Files held in memory by path; others are not found.
+impl FileOpener for std::collections::HashMap<PathBuf, Vec<u8>> {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        match self.get(path) {
+            Some(data) => Ok(Box::new(data.as_slice())),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+}
+
+fn opened_result(path: &Path, flags: &CountOptions, opener: &dyn FileOpener) -> FileResult {
+    let named;
+    let flags = if flags.code {
+        named = flags.for_input(path);
+        &named
+    } else {
+        flags
+    };
+
+    match opener
+        .open(path)
+        .and_then(|reader| count_reader(reader, flags))
+    {
+        Ok(counts) => FileResult::Ok(path.to_path_buf(), counts),
+        Err(e) => FileResult::Err(path.to_path_buf(), e.into()),
+    }
+}
+
+/// Set by `interrupt`.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Stops counting, e.g. on Ctrl-C: files already being counted are finished,
+/// and those not started yet get an `Interrupted` error instead, so the
+/// results so far can still be reported.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether `interrupt` was called.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// The result of `count`, with `path` noted for crash reports, unless
+/// counting was interrupted.
+fn counted(path: &Path, count: impl FnOnce() -> FileResult) -> FileResult {
+    if interrupted() {
+        FileResult::Err(path.to_path_buf(), RswcError::Interrupted)
+    } else {
+        status::started(path);
+        let result = processing(path, count);
+        status::finished(path);
+        result
+    }
+}
+
+/// Whether `result` is of a file left out by `interrupt`.
+pub fn is_interrupted(result: &FileResult) -> bool {
+    matches!(result, FileResult::Err(_, RswcError::Interrupted))
+}
+
+#[cfg(feature = "core")]
+pub fn process_files(files: &[PathBuf], flags: &CountOptions) -> Vec<FileResult> {
+    process_files_with_progress(files, flags, &|_, _| {})
+}
+
+/// Like `process_files`, reporting progress as `(file index, bytes counted)`.
+#[cfg(feature = "core")]
+pub fn process_files_with_progress(
+    files: &[PathBuf],
+    flags: &CountOptions,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Vec<FileResult> {
+    each_file(files, flags, &|i, path| {
+        file_result(path, flags, &|n| progress(i, n))
+    })
+}
+
+/// Like `process_files`, reading every file from `opener` instead of the
+/// disk. The files are counted as plain text: archives, documents,
+/// notebooks, URLs and the like are only understood on disk.
+pub fn process_files_from(
+    files: &[PathBuf],
+    flags: &CountOptions,
+    opener: &dyn FileOpener,
+) -> Vec<FileResult> {
+    each_file(files, flags, &|_, path| opened_result(path, flags, opener))
+}
+
+/// The result of `count` for each of `files` by index, in order, with the
+/// file noted for crash reports, counted one after another.
+fn each_file_in_turn(
+    files: &[PathBuf],
+    count: &(dyn Fn(usize, &Path) -> FileResult + Sync),
+) -> Vec<FileResult> {
+    files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| counted(path, || count(i, path)))
+        .collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn each_file(
+    files: &[PathBuf],
+    _flags: &CountOptions,
+    count: &(dyn Fn(usize, &Path) -> FileResult + Sync),
+) -> Vec<FileResult> {
+    each_file_in_turn(files, count)
+}
+
+/// The result of `count` for each of `files` by index, in order, with the
+/// file noted for crash reports, counted in parallel on a pool of
+/// `flags.threads` unless that is 1.
+#[cfg(feature = "parallel")]
+fn each_file(
+    files: &[PathBuf],
+    flags: &CountOptions,
+    count: &(dyn Fn(usize, &Path) -> FileResult + Sync),
+) -> Vec<FileResult> {
+    if flags.sequential() {
+        return each_file_in_turn(files, count);
+    }
+    on_pool(flags, || {
+        files
+            .par_iter()
+            .enumerate()
+            .map(|(i, path)| counted(path, || count(i, path)))
+            .collect()
+    })
+}
+
+/// Runs `f` on a pool of `flags.threads`, or on rayon's global pool for 0.
+#[cfg(feature = "parallel")]
+fn on_pool<T: Send>(flags: &CountOptions, f: impl FnOnce() -> T + Send) -> T {
+    if flags.threads == 0 {
+        return f();
+    }
+
+    // Everything spawned inside `install`, including the parallel ranges of
+    // large files, stays on this pool.
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(flags.threads)
+        .build()
+    {
+        Ok(pool) => pool.install(f),
+        Err(_) => f(),
+    }
+}
+
+/// Like `process_files`, but hands every result to `sink` as soon as its
+/// file is counted, with the index of the file in `files`, so that a report
+/// can show it while slower files are still being counted. Results come in
+/// the order the files are done.
+#[cfg(feature = "parallel")]
+pub fn process_files_unordered(
+    files: &[PathBuf],
+    flags: &CountOptions,
+    sink: &mut dyn FnMut(usize, FileResult),
+) {
+    if flags.sequential() {
+        return each_result_in_turn(files, flags, sink);
+    }
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            on_pool(flags, || {
+                files
+                    .par_iter()
+                    .enumerate()
+                    .for_each_with(sender, |sender, (i, path)| {
+                        let result = counted(path, || file_result(path, flags, &no_progress));
+                        // The receiver outlives the senders, so this succeeds.
+                        let _ = sender.send((i, result));
+                    })
+            })
+        });
+        for (i, result) in receiver {
+            sink(i, result);
+        }
+    });
+}
+
+/// Like `process_files`, but hands every result to `sink` as soon as its
+/// file is counted, with the index of the file in `files`. Without the
+/// `parallel` feature that is in order.
+#[cfg(all(feature = "core", not(feature = "parallel")))]
+pub fn process_files_unordered(
+    files: &[PathBuf],
+    flags: &CountOptions,
+    sink: &mut dyn FnMut(usize, FileResult),
+) {
+    each_result_in_turn(files, flags, sink)
+}
+
+/// Hands the result of each of `files` to `sink`, counted one after another.
+#[cfg(feature = "core")]
+fn each_result_in_turn(
+    files: &[PathBuf],
+    flags: &CountOptions,
+    sink: &mut dyn FnMut(usize, FileResult),
+) {
+    for (i, path) in files.iter().enumerate() {
+        let result = counted(path, || file_result(path, flags, &no_progress));
+        sink(i, result);
+    }
+}
+
+/// Per-file results rendered the way the CLI prints them: one aligned row
+/// per file, error rows in place, and a `total` row for multiple inputs.
+#[derive(Debug)]
+pub struct Report<'a> {
+    results: &'a [FileResult],
+    flags: &'a CountOptions,
+    layout: LayoutOptions,
+    show_total: bool,
+    total: Option<Counts>,
+    partial: bool,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(results: &'a [FileResult], flags: &'a CountOptions) -> Self {
+        Report {
+            results,
+            flags,
+            layout: LayoutOptions::default(),
+            show_total: true,
+            total: None,
+            partial: false,
+        }
+    }
+
+    pub fn with_layout(mut self, layout: LayoutOptions) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Leaves out the `total` row, e.g. for a batch of a longer run whose
+    /// total is printed at its end.
+    pub fn without_total(mut self) -> Self {
+        self.show_total = false;
+        self
+    }
+
+    /// Prints `total` as the `total` row instead of the total of the rows,
+    /// e.g. when only some of the inputs are shown.
+    pub fn with_total(mut self, total: Counts) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    /// Marks the total as that of only some of the inputs, because counting
+    /// was interrupted: it is named `partial total`, and printed even for a
+    /// single input.
+    pub fn partial(mut self) -> Self {
+        self.partial = true;
+        self
+    }
+
+    /// `name` after a type column of `kind` with `--detect`.
+    fn typed<N: fmt::Display>(&self, kind: &str, name: N) -> String {
+        if self.flags.detect {
+            type_column(&self.layout, kind, name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn annotated<N: fmt::Display>(&self, name: N, counts: &Counts) -> String {
+        let name = self.typed(counts.detected.map_or("", Detected::name), name);
+        let annotations = if self.flags.annotate {
+            counts.annotations
+        } else if self.flags.mark_binary && counts.annotations.contains(Annotations::BINARY) {
+            Annotations::BINARY
+        } else {
+            Annotations::default()
+        };
+        if annotations.is_empty() {
+            name.to_string()
+        } else {
+            format!("{} {}", name, annotations)
+        }
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = self.flags;
+        let mut layout = Layout::new(self.layout.clone());
+        let total = match &self.total {
+            Some(total) => total.clone(),
+            None => total(self.results),
+        };
+        let names = Counts::column_names(flags);
+        if flags.header {
+            layout.measure_names(&names);
+        }
+
+        for r in self.results {
+            match r {
+                FileResult::Ok(_, c) => layout.measure(&c.columns(flags)),
+                FileResult::Archive(_, members) => {
+                    for m in members {
+                        if let FileResult::Ok(_, c) = m {
+                            layout.measure(&c.columns(flags));
+                        }
+                    }
+                    layout.measure_total(&archive_subtotal(members).columns(flags));
+                }
+                FileResult::Err(..) => {}
+            }
+        }
+
+        let show_total =
+            self.show_total && (self.total.is_some() || self.partial || self.results.len() > 1);
+        if show_total {
+            layout.measure_total(&total.columns(flags));
+        }
+
+        if flags.header {
+            layout.write_names(f, &names, &self.typed("type", "file"))?;
+        }
+
+        for r in self.results {
+            match r {
+                FileResult::Err(path, msg) => {
+                    layout.write_error(f, format_args!("rswc: {}: {} ", path.display(), msg))?;
+                }
+                FileResult::Ok(path, c) => {
+                    let name = self.annotated(path.display(), c);
+                    layout.write_row(f, &c.columns(flags), name)?;
+                }
+                FileResult::Archive(path, members) => {
+                    for m in members {
+                        match m {
+                            FileResult::Ok(member, c) => {
+                                let name = format!("{}!{}", path.display(), member.display());
+                                let name = self.annotated(name, c);
+                                layout.write_row(f, &c.columns(flags), name)?;
+                            }
+                            FileResult::Err(member, msg) => {
+                                layout.write_error(
+                                    f,
+                                    format_args!(
+                                        "rswc: {}!{}: {} ",
+                                        path.display(),
+                                        member.display(),
+                                        msg
+                                    ),
+                                )?;
+                            }
+                            FileResult::Archive(..) => {}
+                        }
+                    }
+                    let subtotal = archive_subtotal(members);
+                    let name = self.typed("", path.display());
+                    layout.write_total(f, &subtotal.columns(flags), name)?;
+                }
+            }
+        }
+
+        if show_total {
+            let name = if self.partial {
+                "partial total"
+            } else {
+                "total"
+            };
+            layout.write_total(f, &total.columns(flags), self.typed("", name))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The counts of every successful input, archive members included, with
+/// the names they are reported under, like `a.tar!member`.
+pub(crate) fn counted_inputs(results: &[FileResult]) -> Vec<(String, &Counts)> {
+    let mut inputs = Vec::new();
+    for r in results {
+        match r {
+            FileResult::Ok(path, c) => inputs.push((path.display().to_string(), c)),
+            FileResult::Archive(path, members) => {
+                for m in members {
+                    if let FileResult::Ok(member, c) = m {
+                        inputs.push((format!("{}!{}", path.display(), member.display()), c));
+                    }
+                }
+            }
+            FileResult::Err(..) => {}
+        }
+    }
+    inputs
+}
+
+/// The counts of all successful results added up, archive members included.
+pub fn total<'a>(results: impl IntoIterator<Item = &'a FileResult>) -> Counts {
+    let mut total = Counts::zero();
+    for r in results {
+        match r {
+            FileResult::Ok(_, c) => total += c,
+            FileResult::Archive(_, members) => total += archive_subtotal(members),
+            FileResult::Err(..) => {}
+        }
+    }
+    total
+}
+
+fn archive_subtotal(members: &[FileResult]) -> Counts {
+    total(members)
+}
+
+/// Prints nothing but the total of the one selected count, for scripts.
+/// Errors go to `errors` instead of the report.
+pub fn print_raw_total<W: Write, E: Write>(
+    writer: &mut W,
+    errors: &mut E,
+    results: &[FileResult],
+    flags: &CountOptions,
+) -> io::Result<()> {
+    print_errors(errors, results)?;
+    for value in total(results).columns(flags) {
+        writeln!(writer, "{}", value)?;
+    }
+    Ok(())
+}
+
+/// Whether a count of the total of `results` is too large to hold in 64
+/// bits, like the bytes of the same huge sparse file given a million times,
+/// and saturates.
+/// Only the plain counts are added up, so this is cheaper than the total.
+pub fn total_saturates(results: &[FileResult]) -> bool {
+    fn add(sum: &mut Counts, results: &[FileResult]) -> bool {
+        let mut saturated = false;
+        for r in results {
+            saturated |= match r {
+                FileResult::Ok(_, c) => {
+                    sum.add_counts(c) || c.estimates.contains(Estimates::SATURATED)
+                }
+                FileResult::Archive(_, members) => add(sum, members),
+                FileResult::Err(..) => false,
+            };
+        }
+        saturated
+    }
+    add(&mut Counts::zero(), results)
+}
+
+/// The number of inputs of `results` and of those that failed, archive
+/// members included.
+pub fn count_failures(results: &[FileResult]) -> (usize, usize) {
+    results.iter().fold((0, 0), |(inputs, failed), r| match r {
+        FileResult::Ok(..) => (inputs + 1, failed),
+        FileResult::Err(..) => (inputs + 1, failed + 1),
+        FileResult::Archive(_, members) => {
+            let (m_inputs, m_failed) = count_failures(members);
+            (inputs + m_inputs, failed + m_failed)
+        }
+    })
+}
+
+/// The number of inputs of `results` that are directories.
+pub fn count_directories(results: &[FileResult]) -> usize {
+    results
+        .iter()
+        .filter(|r| {
+            matches!(r, FileResult::Err(_, e) if e.io_kind() == Some(io::ErrorKind::IsADirectory))
+        })
+        .count()
+}
+
+/// Prints a line for every input of `results` that failed, archive members
+/// included.
+pub fn print_errors<E: Write>(errors: &mut E, results: &[FileResult]) -> io::Result<()> {
+    for r in results {
+        match r {
+            FileResult::Err(path, msg) => writeln!(errors, "rswc: {}: {}", path.display(), msg)?,
+            FileResult::Archive(path, members) => {
+                for m in members {
+                    if let FileResult::Err(member, msg) = m {
+                        writeln!(
+                            errors,
+                            "rswc: {}!{}: {}",
+                            path.display(),
+                            member.display(),
+                            msg
+                        )?;
+                    }
+                }
+            }
+            FileResult::Ok(..) => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn print_files_results<W: Write>(
+    writer: &mut W,
+    results: &[FileResult],
+    flags: &CountOptions,
+    layout: &LayoutOptions,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "{}",
+        Report::new(results, flags).with_layout(layout.clone())
+    )
+}
+
+/// Prints the counts of an implicit stdin input under `label`; an empty
+/// label prints the numbers alone, like GNU wc does.
+pub fn print_stdin_results<W: Write>(
+    writer: &mut W,
+    counts: &Counts,
+    flags: &CountOptions,
+    layout: &LayoutOptions,
+    label: &str,
+) -> io::Result<()> {
+    let typed = |kind: &str, name: &str| {
+        if flags.detect {
+            type_column(layout, kind, name)
+        } else {
+            name.to_string()
+        }
+    };
+    let table = if flags.header {
+        let row = counts.columns(flags);
+        let names = Counts::column_names(flags);
+        let mut layout = Layout::new(layout.clone());
+        layout.measure_names(&names);
+        layout.measure(&row);
+        let mut table = String::new();
+        // Writing into a String cannot fail.
+        let _ = layout.write_names(&mut table, &names, &typed("type", "file"));
+        let _ = layout.write_fields(&mut table, &row);
+        table
+    } else {
+        counts.to_table_with(flags, layout)
+    };
+    if label.is_empty() {
+        writeln!(writer, "{}", table.trim_end())
+    } else {
+        let label = typed(counts.detected.map_or("", Detected::name), label);
+        write!(writer, "{}{}{}", table, label, layout.terminator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(all(unix, feature = "core"))]
+    use crate::freq::frequencies_of;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_count_failures() {
+        let results = [
+            FileResult::Ok("a".into(), Counts::default()),
+            FileResult::Err("b".into(), io::Error::other("gone").into()),
+            FileResult::Archive(
+                "c.tar".into(),
+                vec![
+                    FileResult::Ok("x".into(), Counts::default()),
+                    FileResult::Err("y".into(), io::Error::other("corrupt").into()),
+                ],
+            ),
+        ];
+        assert_eq!(count_failures(&results), (4, 2));
+    }
+
+    #[test]
+    fn test_print_results_from_mutli_files_with_large_numbers() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: false,
+            ..Default::default()
+        };
+
+        let results = vec![
+            FileResult::Ok(
+                PathBuf::from("file1.txt"),
+                Counts {
+                    lines: 2143500000,
+                    words: 17449200000,
+                    bytes: 102657000000,
+                    chars: 0,
+                    ..Default::default()
+                },
+            ),
+            FileResult::Ok(
+                PathBuf::from("file2.txt"),
+                Counts {
+                    lines: 2143500000,
+                    words: 17449200000,
+                    bytes: 102657000000,
+                    chars: 0,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        let mut output = Cursor::new(Vec::new());
+        print_files_results(&mut output, &results, &flags, &LayoutOptions::default()).unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+
+        let expected = "\
+            2143500000 17449200000 102657000000 file1.txt
+2143500000 17449200000 102657000000 file2.txt
+4287000000 34898400000 205314000000 total
+";
+
+        assert_eq!(actual, expected, "Output does not match");
+    }
+
+    #[test]
+    fn test_print_stdin_results_with_large_numbers() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: false,
+            ..Default::default()
+        };
+
+        let counts = Counts {
+            lines: 2143500000,
+            words: 17449200000,
+            bytes: 102657000000,
+            chars: 0,
+            ..Default::default()
+        };
+
+        let mut output = Cursor::new(Vec::new());
+        print_stdin_results(&mut output, &counts, &flags, &LayoutOptions::default(), "-").unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+
+        let expected = "\
+            2143500000 17449200000 102657000000 -
+";
+
+        assert_eq!(actual, expected, "Output does not match");
+    }
+
+    #[test]
+    fn test_counts_display_and_to_table() {
+        let counts = Counts {
+            lines: 7145,
+            words: 58164,
+            bytes: 342190,
+            chars: 339292,
+            ..Default::default()
+        };
+        let flags = CountOptions {
+            lines: true,
+            chars: true,
+            ..Default::default()
+        };
+
+        assert_eq!(counts.to_string(), "   7145   58164  342190");
+        assert_eq!(counts.to_table(&flags), "   7145  339292 ");
+    }
+
+    #[test]
+    fn test_report_display_matches_print_files_results() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(
+                PathBuf::from("file1.txt"),
+                Counts {
+                    lines: 1,
+                    words: 2,
+                    bytes: 3,
+                    ..Default::default()
+                },
+            ),
+            FileResult::Err(
+                PathBuf::from("file2.txt"),
+                io::Error::other("Is a directory").into(),
+            ),
+        ];
+
+        let mut output = Cursor::new(Vec::new());
+        print_files_results(&mut output, &results, &flags, &LayoutOptions::default()).unwrap();
+        let printed = String::from_utf8(output.into_inner()).unwrap();
+
+        let expected = "      1       2       3 file1.txt
+rswc: file2.txt: Is a directory 
+      1       2       3 total
+";
+        assert_eq!(Report::new(&results, &flags).to_string(), expected);
+        assert_eq!(printed, expected);
+    }
+
+    #[test]
+    fn test_report_total_widens_columns() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let counts = Counts {
+            lines: 6000000,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a"), counts.clone()),
+            FileResult::Ok(PathBuf::from("b"), counts),
+        ];
+
+        let expected = " 6000000 a
+ 6000000 b
+12000000 total
+";
+        assert_eq!(Report::new(&results, &flags).to_string(), expected);
+    }
+
+    #[test]
+    fn test_report_partial_total() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let counts = Counts {
+            lines: 12,
+            ..Default::default()
+        };
+        let results = vec![FileResult::Ok(PathBuf::from("a"), counts)];
+        let expected = "     12 a
+     12 partial total
+";
+        assert_eq!(
+            Report::new(&results, &flags).partial().to_string(),
+            expected
+        );
+        assert!(is_interrupted(&FileResult::Err(
+            PathBuf::from("b"),
+            RswcError::Interrupted
+        )));
+    }
+
+    #[test]
+    fn test_report_header_widens_columns() {
+        let flags = CountOptions {
+            lines: true,
+            non_ascii: true,
+            header: true,
+            ..Default::default()
+        };
+        let results = vec![FileResult::Ok(
+            PathBuf::from("a"),
+            Counts {
+                lines: 12,
+                non_ascii: 3,
+                ..Default::default()
+            },
+        )];
+
+        let expected = "  lines non_ascii file
+     12         3 a
+";
+        assert_eq!(Report::new(&results, &flags).to_string(), expected);
+
+        let mut output = Cursor::new(Vec::new());
+        print_stdin_results(
+            &mut output,
+            &Counts::default(),
+            &flags,
+            &LayoutOptions::default(),
+            "-",
+        )
+        .unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(actual, "  lines non_ascii file\n      0         0 -\n");
+    }
+
+    #[test]
+    fn test_print_stdin_results_without_label() {
+        let flags = CountOptions {
+            lines: true,
+            bytes: true,
+            ..Default::default()
+        };
+        let counts = Counts {
+            lines: 1000,
+            bytes: 3893,
+            ..Default::default()
+        };
+
+        let mut output = Cursor::new(Vec::new());
+        print_stdin_results(&mut output, &counts, &flags, &LayoutOptions::default(), "").unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+
+        assert_eq!(actual, "   1000    3893\n");
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_count_file() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: true,
+            ..Default::default()
+        };
+        let path = Path::new("testdata/test.txt");
+        assert!(path.exists(), "Test file does not exist: {:?}", path);
+
+        let actual = count_file(path, &flags, &no_progress).unwrap();
+        let expected = Counts {
+            lines: 7145,
+            words: 58164,
+            bytes: 342190,
+            chars: 339292,
+            ..Default::default()
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_count_file_mmap_matches_read() {
+        let path = Path::new("testdata/test.txt");
+        let mut flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: true,
+            digest: true,
+            backend: Backend::Read,
+            ..Default::default()
+        };
+        let read = count_file(path, &flags, &no_progress).unwrap();
+
+        flags.backend = Backend::Mmap;
+        let mut mapped = count_file(path, &flags, &no_progress).unwrap();
+        assert!(mapped.annotations.contains(Annotations::MMAP));
+        mapped.annotations = read.annotations;
+        assert_eq!(read, mapped);
+
+        // Character devices can't be mapped and must fall back to reads.
+        let devnull = count_file(Path::new("/dev/null"), &flags, &no_progress).unwrap();
+        assert_eq!(devnull.bytes, 0);
+    }
+
+    #[test]
+    fn test_count_chunked_stitches_words_and_chars() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: true,
+            ..Default::default()
+        };
+        let data = "héllo wörld ✓✓ 𝄞x\n  tail\n".repeat(5);
+        let expected = count_sequential(data.as_bytes(), &flags, &no_progress);
+        assert_eq!(expected.chars, data.chars().count() as u64);
+        assert_eq!(expected.words, data.split_ascii_whitespace().count() as u64);
+
+        for range_size in 1..12 {
+            let actual = count_chunked(data.as_bytes(), &flags, range_size, &no_progress);
+            assert_eq!(actual, expected, "range size {}", range_size);
+        }
+    }
+
+    #[test]
+    fn test_count_chunked_with_word_rules() {
+        let flags = CountOptions {
+            words: true,
+            word_rules: WordRules {
+                split_hyphens: true,
+                split_apostrophes: true,
+                ..WordRules::DEFAULT
+            },
+            ..Default::default()
+        };
+        let data = b"state-of-the-art don't --x- y";
+        let expected = count_sequential(data, &flags, &no_progress);
+        assert_eq!(expected.words, 8);
+
+        for range_size in 1..8 {
+            let actual = count_chunked(data, &flags, range_size, &no_progress);
+            assert_eq!(actual.words, expected.words, "range size {}", range_size);
+        }
+
+        let flags = CountOptions {
+            words: true,
+            word_rules: WordRules {
+                unicode_whitespace: true,
+                ..WordRules::DEFAULT
+            },
+            ..Default::default()
+        };
+        let data = "a\u{a0}b\u{3000}\u{3000}cd\u{2009}é".as_bytes();
+        for range_size in 1..8 {
+            let actual = count_chunked(data, &flags, range_size, &no_progress);
+            assert_eq!(actual.words, 4, "range size {}", range_size);
+        }
+    }
+
+    #[test]
+    fn test_count_reader_chars_across_buffer_edges() {
+        let flags = CountOptions {
+            chars: true,
+            ..Default::default()
+        };
+        let data = "äöü€𝄞".repeat(3).into_bytes();
+        let mut counter = Counter::new(&flags, &no_progress);
+        for chunk in data.chunks(3) {
+            counter.update(chunk);
+        }
+        assert_eq!(counter.finish().chars, 15);
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_tee_copies_what_is_counted() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let data = "one two\nthree\n".repeat(10_000).into_bytes();
+        let mut copy = Vec::new();
+        let tee = Tee {
+            reader: data.as_slice(),
+            copy: &mut copy,
+        };
+        let counts = count_reader(tee, &flags).unwrap();
+        assert_eq!((counts.lines, counts.words), (20_000, 30_000));
+        assert_eq!(copy, data);
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_tee_lists_word_frequencies() {
+        let flags = CountOptions::new().words(true).freq(true);
+        let data = b"to be or not to be\n";
+        let mut copy = Vec::new();
+        let tee = Tee {
+            reader: &data[..],
+            copy: &mut copy,
+        };
+        let freqs = count_reader(tee, &flags).unwrap().frequencies.unwrap();
+        assert_eq!(
+            (freqs[&b"to"[..]], freqs[&b"or"[..]], freqs.len()),
+            (2, 1, 4)
+        );
+        assert_eq!(copy, data);
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_count_file_bytes_only_uses_metadata() {
+        let flags = CountOptions {
+            bytes: true,
+            ..Default::default()
+        };
+        let path = Path::new("testdata/test.txt");
+
+        let actual = count_file(path, &flags, &no_progress).unwrap();
+        let expected = Counts {
+            bytes: 342190,
+            annotations: Annotations::METADATA,
+            ..Default::default()
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_count_file_unique_lines() {
+        let flags = CountOptions {
+            bytes: true,
+            unique_lines: true,
+            ..Default::default()
+        };
+        let path = Path::new("testdata/test.txt");
 
-        let mut i = 0;
-        while i < n {
-            let b = buf[i];
-            if b == b'\n' {
-                counts.lines += 1;
-            }
+        let actual = count_file(path, &flags, &no_progress).unwrap();
+        assert_eq!(actual.unique_lines, 5409);
+        assert!(actual.annotations.is_empty());
+    }
 
-            if b.is_ascii_whitespace() {
-                in_word = false;
-            } else if !in_word {
-                counts.words += 1;
-                in_word = true;
-            }
+    #[test]
+    fn test_numbers_left_out_of_words() {
+        let data = b"In 2024, 3 of 10 tests failed.";
+        let flags = |numbers| CountOptions {
+            words: true,
+            numbers,
+            ..Default::default()
+        };
 
-            i += 1;
-        }
+        let counts = count_slice(data, &flags(Numbers::Count));
+        assert_eq!((counts.words, counts.numbers), (7, 0));
+        let counts = count_slice(data, &flags(Numbers::Ignore));
+        assert_eq!(counts.columns(&flags(Numbers::Ignore)), vec![4]);
+        let counts = count_slice(data, &flags(Numbers::Separate));
+        assert_eq!(
+            counts.named_columns(&flags(Numbers::Separate)),
+            vec![("words", 4), ("numbers", 3)]
+        );
+    }
 
-        if flags.chars {
-            counts.chars += std::str::from_utf8(&buf[..n])
-                .unwrap_or_default()
-                .chars()
-                .count();
-        }
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_count_encoded() {
+        let data: Vec<u8> = "\u{feff}où est\nl'été\n"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            chars: true,
+            bytes: true,
+            encoding: Some(encoding_rs::UTF_16BE),
+            ..Default::default()
+        };
+        let counts = count_reader(data.as_slice(), &flags).unwrap();
+        assert_eq!(
+            (counts.lines, counts.words, counts.chars, counts.bytes),
+            (2, 3, 13, 28)
+        );
     }
 
-    Ok(counts)
-}
+    #[test]
+    fn test_invalid_utf8_policies() {
+        let data = b"caf\xe9 ok\n\xff\xfe";
+        let flags = |invalid_utf8| CountOptions {
+            chars: true,
+            invalid_utf8,
+            ..Default::default()
+        };
 
-fn count_file(path: &Path, flags: &Flags) -> io::Result<Counts> {
-    let file = File::open(path)?;
-    let reader = BufReader::with_capacity(512 * 1024, file);
-    count_reader(reader, flags)
-}
+        let lossy = flags(InvalidUtf8::Lossy);
+        assert_eq!(count_reader(&data[..], &lossy).unwrap().chars, 10);
+        let count = flags(InvalidUtf8::Count);
+        assert_eq!(
+            count_slice(data, &count).named_columns(&count),
+            vec![("chars", 7), ("invalid", 3)]
+        );
+        let err = count_reader(&data[..], &flags(InvalidUtf8::Strict)).unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-8 at byte 3");
+    }
 
-pub fn process_stdin(flags: &Flags) -> io::Result<Counts> {
-    let stdin = io::stdin();
-    let handle = stdin.lock();
-    count_reader(handle, flags)
-}
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_stdin_fails_like_a_file() {
+        let flags = CountOptions {
+            chars: true,
+            invalid_utf8: InvalidUtf8::Strict,
+            ..Default::default()
+        };
+        let counted = count_stream(&b"h\xffi\n"[..], &flags, &no_progress);
+        let results = [stdin_result(counted)];
+        let mut errors = Vec::new();
+        print_errors(&mut errors, &results).unwrap();
+        assert_eq!(errors, b"rswc: -: invalid UTF-8 at byte 1\n");
+        assert_eq!(count_failures(&results), (1, 1));
+    }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum FileResult {
-    Ok(PathBuf, Counts),
-    Err(PathBuf, String),
-}
+    #[test]
+    fn test_non_ascii_column() {
+        let flags = CountOptions {
+            chars: true,
+            non_ascii: true,
+            ..Default::default()
+        };
+        let counts = count_slice("naïve “text”\n".as_bytes(), &flags);
+        assert_eq!(
+            counts.named_columns(&flags),
+            vec![("chars", 13), ("non_ascii", 3)]
+        );
+    }
 
-pub fn process_files(files: &[PathBuf], flags: &Flags) -> Vec<FileResult> {
-    files
-        .par_iter()
-        .map(|path| match count_file(path, flags) {
-            Ok(counts) => FileResult::Ok(path.clone(), counts),
-            Err(e) => FileResult::Err(path.clone(), e.to_string()),
-        })
-        .collect()
-}
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_binary_inputs() {
+        let flags = CountOptions {
+            lines: true,
+            mark_binary: true,
+            ..Default::default()
+        };
+        let binary = count_slice(b"\x7fELF\0\0\n", &flags);
+        assert!(binary.annotations.contains(Annotations::BINARY));
+        let zero_terminated = CountOptions {
+            record_delimiter: Some(0),
+            ..flags.clone()
+        };
+        assert!(
+            count_slice(b"a\0b\0", &zero_terminated)
+                .annotations
+                .is_empty()
+        );
 
-pub fn print_files_results<W: Write>(
-    writer: &mut W,
-    results: &[FileResult],
-    flags: &Flags,
-) -> io::Result<()> {
-    let mut max_lines = 0;
-    let mut max_words = 0;
-    let mut max_bytes = 0;
-    let mut max_chars = 0;
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a.out"), binary),
+            FileResult::Ok(PathBuf::from("a.txt"), count_slice(b"text\n", &flags)),
+        ];
+        let expected = "      1 a.out [binary]\n      1 a.txt\n      2 total\n";
+        assert_eq!(Report::new(&results, &flags).to_string(), expected);
 
-    let mut total = Counts {
-        lines: 0,
-        words: 0,
-        bytes: 0,
-        chars: 0,
-    };
+        let path = std::env::temp_dir().join(format!("rswc-test-{}.bin", std::process::id()));
+        std::fs::write(&path, b"GIF89a\0\x01").unwrap();
+        let skipping = CountOptions {
+            skip_binary: true,
+            ..flags
+        };
+        let result = process_files(std::slice::from_ref(&path), &skipping);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            result,
+            vec![FileResult::Err(
+                path,
+                RswcError::Skipped("binary file".to_string())
+            )]
+        );
+    }
 
-    for r in results {
-        if let FileResult::Ok(_, c) = r {
-            if flags.lines {
-                max_lines = max_lines.max(c.lines);
-            }
-            if flags.words {
-                max_words = max_words.max(c.words);
-            }
-            if flags.bytes {
-                max_bytes = max_bytes.max(c.bytes);
-            }
-            if flags.chars {
-                max_chars = max_chars.max(c.chars);
-            }
+    /// A FIFO that `data` is written to once opened, like the input of a
+    /// process substitution.
+    #[cfg(all(unix, feature = "core"))]
+    fn fifo(name: &str, data: &'static [u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rswc-test-{}-{}", std::process::id(), name));
+        let made = std::process::Command::new("mkfifo").arg(&path).status();
+        assert!(made.unwrap().success());
+        let writer = path.clone();
+        std::thread::spawn(move || std::fs::write(writer, data));
+        path
+    }
 
-            total.lines += c.lines;
-            total.words += c.words;
-            total.bytes += c.bytes;
-            total.chars += c.chars;
+    #[cfg(feature = "core")]
+    #[cfg(unix)]
+    #[test]
+    fn test_skip_binary_streams() {
+        let flags = CountOptions::new().lines(true).skip_binary(true);
+        let text = fifo("text", b"one\ntwo\n");
+        let binary = fifo("binary", b"\x7fELF\0\0\n");
+        let results = process_files(&[text.clone(), binary.clone()], &flags);
+        std::fs::remove_file(&text).unwrap();
+        std::fs::remove_file(&binary).unwrap();
+        match &results[..] {
+            [FileResult::Ok(_, counts), FileResult::Err(_, e)] => {
+                assert_eq!(counts.lines, 2);
+                assert_eq!(e, &RswcError::Skipped("binary file".to_string()));
+            }
+            other => panic!("unexpected {:?}", other),
         }
     }
 
-    let width_lines = max_lines.to_string().len().max(MAX_WIDTH);
-    let width_words = max_words.to_string().len().max(MAX_WIDTH);
-    let width_bytes = max_bytes.to_string().len().max(MAX_WIDTH);
-    let width_chars = max_chars.to_string().len().max(MAX_WIDTH);
+    #[cfg(feature = "core")]
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_lang_of_streams() {
+        let data = "これは日本語の文章です。今日はいい天気ですね。\n".as_bytes();
+        let flags = CountOptions::new().words(true).detect_lang(true);
+        let path = fifo("ja", data);
+        let results = process_files(std::slice::from_ref(&path), &flags);
+        std::fs::remove_file(&path).unwrap();
 
-    for r in results {
-        match r {
-            FileResult::Err(path, msg) => {
-                writeln!(writer, "rswc: {}: {} ", path.display(), msg)?;
-            }
-            FileResult::Ok(path, c) => {
-                print_field!(writer, c.lines, flags.lines, width_lines);
-                print_field!(writer, c.words, flags.words, width_words);
-                print_field!(writer, c.bytes, flags.bytes, width_bytes);
-                print_field!(writer, c.chars, flags.chars, width_chars);
-                writeln!(writer, "{}", path.display())?;
+        let japanese = count_slice(data, &flags.clone().lang(detect(data)));
+        assert_eq!(detect(data).code, "ja");
+        assert_ne!(japanese.words, count_slice(data, &flags).words);
+        match &results[..] {
+            [FileResult::Ok(_, counts)] => {
+                assert_eq!(
+                    (counts.words, counts.bytes),
+                    (japanese.words, data.len() as u64)
+                )
             }
+            other => panic!("unexpected {:?}", other),
         }
     }
 
-    if results.len() > 1 {
-        print_field!(writer, total.lines, flags.lines, width_lines);
-        print_field!(writer, total.words, flags.words, width_words);
-        print_field!(writer, total.bytes, flags.bytes, width_bytes);
-        print_field!(writer, total.chars, flags.chars, width_chars);
-        writeln!(writer, "total")?;
+    #[cfg(feature = "core")]
+    #[cfg(unix)]
+    #[test]
+    fn test_word_frequencies_of_streams() {
+        let flags = CountOptions::new().words(true).freq(true).seek(4);
+        let path = fifo("freq", b"one two two\nthree\n");
+        let results = process_files(std::slice::from_ref(&path), &flags);
+        std::fs::remove_file(&path).unwrap();
+
+        let freqs = frequencies_of(&results);
+        let expected = Frequencies::from([(b"two".to_vec(), 2), (b"three".to_vec(), 1)]);
+        assert_eq!(freqs, expected);
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_max_word_length_column() {
+        let flags = CountOptions::new().words(true).max_word_length(true);
+        let results = vec![
+            FileResult::Ok("a".into(), count_slice("a naïve word\n".as_bytes(), &flags)),
+            FileResult::Ok("b".into(), count_slice(b"ab abc\n", &flags)),
+        ];
+        assert_eq!(
+            total(&results).named_columns(&flags),
+            vec![("words", 5), ("max_word_length", 5)]
+        );
+    }
 
-pub fn print_stdin_results<W: Write>(
-    writer: &mut W,
-    counts: &Counts,
-    flags: &Flags,
-) -> io::Result<()> {
-    print_field!(
-        writer,
-        counts.lines,
-        flags.lines,
-        counts.lines.to_string().len().max(MAX_WIDTH)
-    );
-    print_field!(
-        writer,
-        counts.words,
-        flags.words,
-        counts.words.to_string().len().max(MAX_WIDTH)
-    );
-    print_field!(
-        writer,
-        counts.bytes,
-        flags.bytes,
-        counts.bytes.to_string().len().max(MAX_WIDTH)
-    );
-    print_field!(
-        writer,
-        counts.chars,
-        flags.chars,
-        counts.chars.to_string().len().max(MAX_WIDTH)
-    );
-    writeln!(writer, "-")?;
+    #[test]
+    fn test_blank_lines_column() {
+        let flags = CountOptions::new().lines(true).blank_lines(true);
+        let counts = count_slice(b"fn main() {\n\n    \n}\n", &flags);
+        assert_eq!(
+            counts.named_columns(&flags),
+            vec![("lines", 4), ("blank", 2)]
+        );
+        let code = flags.clone().code(true);
+        let counts = count_slice(b"fn main() {\n\n    \n}\n", &code);
+        assert_eq!(
+            counts.named_columns(&code),
+            vec![("lines", 4), ("blank", 2), ("comment", 0), ("code", 2)]
+        );
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_record_delimiter_lines() {
+        let flags = CountOptions {
+            lines: true,
+            unique_lines: true,
+            record_delimiter: Some(0),
+            ..Default::default()
+        };
+        let counts = count_slice(b"a b\n\0c\0a b\n\0d", &flags);
+        assert_eq!((counts.lines, counts.unique_lines), (3, 3));
+        assert_eq!(flags.delimiter(), 0);
+        assert_eq!(CountOptions::default().delimiter(), b'\n');
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+    #[test]
+    fn test_total_fields() {
+        let flags = CountOptions {
+            fields: Some(b','),
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a"), count_slice(b"a,b\nc,d\n", &flags)),
+            FileResult::Ok(PathBuf::from("b"), count_slice(b"", &flags)),
+            FileResult::Ok(PathBuf::from("c"), count_slice(b"e,f,g\n", &flags)),
+        ];
+        assert_eq!(total(&results).columns(&flags), vec![7, 2, 3]);
+    }
 
     #[test]
-    fn test_print_results_from_mutli_files_with_large_numbers() {
-        let flags = Flags {
+    fn test_total_saturates() {
+        let big = Counts {
+            bytes: u64::MAX - 1,
+            lines: 1,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a"), big.clone()),
+            FileResult::Archive(
+                PathBuf::from("b.zip"),
+                vec![FileResult::Ok(PathBuf::from("c"), big.clone())],
+            ),
+        ];
+        let sum = total(&results);
+        assert_eq!((sum.bytes, sum.lines), (u64::MAX, 2));
+        assert!(sum.estimates.contains(Estimates::SATURATED));
+        assert!(total_saturates(&results));
+        assert!(!total_saturates(&results[..1]));
+        assert!(
+            !total(&results[..1])
+                .estimates
+                .contains(Estimates::SATURATED)
+        );
+    }
+
+    #[test]
+    fn test_counts_arithmetic() {
+        let flags = CountOptions::new()
+            .lines(true)
+            .words(true)
+            .eol(true)
+            .unique_words(true);
+        let a = count_slice(b"one two\r\n", &flags);
+        let b = count_slice(b"two three\n", &flags);
+
+        let sum = a.clone() + &b;
+        assert_eq!((sum.lines, sum.words), (2, 4));
+        assert_eq!(
+            sum.line_ends.as_deref().map(|e| (e.lf, e.crlf)),
+            Some((1, 1))
+        );
+        assert_eq!(sum.vocabulary.as_deref().map(WordSet::len), Some(3));
+
+        let mut merged = Counts::zero();
+        merged += &a;
+        merged += b.clone();
+        assert_eq!(merged, sum);
+        assert_eq!([a.clone(), b.clone()].iter().sum::<Counts>(), sum);
+        assert_eq!([a, b].into_iter().sum::<Counts>(), sum);
+    }
+
+    #[test]
+    fn test_record_delimiter_splits_words() {
+        let flags = CountOptions {
             lines: true,
             words: true,
-            bytes: true,
-            chars: false,
+            record_delimiter: Some(b';'),
+            word_rules: WordRules::DEFAULT.with_delimiter(b';'),
+            ..Default::default()
         };
+        let counts = count_slice(b"a b;c;;d e\nf", &flags);
+        assert_eq!((counts.lines, counts.words), (3, 6));
+    }
 
+    #[test]
+    fn test_report_total_merges_unique_words() {
+        let flags = CountOptions {
+            unique_words: true,
+            ..Default::default()
+        };
         let results = vec![
             FileResult::Ok(
-                PathBuf::from("file1.txt"),
-                Counts {
-                    lines: 2143500000,
-                    words: 17449200000,
-                    bytes: 102657000000,
-                    chars: 0,
-                },
-            ),
-            FileResult::Ok(
-                PathBuf::from("file2.txt"),
-                Counts {
-                    lines: 2143500000,
-                    words: 17449200000,
-                    bytes: 102657000000,
-                    chars: 0,
-                },
+                PathBuf::from("a"),
+                count_slice(b"one two three two", &flags),
             ),
+            FileResult::Ok(PathBuf::from("b"), count_slice(b"three four", &flags)),
         ];
 
-        let mut output = Cursor::new(Vec::new());
-        print_files_results(&mut output, &results, &flags).unwrap();
-        let actual = String::from_utf8(output.into_inner()).unwrap();
-
-        let expected = "\
-            2143500000 17449200000 102657000000 file1.txt
-2143500000 17449200000 102657000000 file2.txt
-4287000000 34898400000 205314000000 total
+        let expected = "      3 a
+      2 b
+      4 total
 ";
+        assert_eq!(Report::new(&results, &flags).to_string(), expected);
+    }
 
-        assert_eq!(actual, expected, "Output does not match");
+    #[test]
+    fn test_reading_minutes_round_up_from_words() {
+        let flags = CountOptions {
+            words: true,
+            reading_wpm: NonZeroUsize::new(2),
+            ..Default::default()
+        };
+        let counts = count_slice(b"one two three", &flags);
+        assert_eq!(
+            counts.named_columns(&flags),
+            vec![("words", 3), ("reading_minutes", 2)]
+        );
+        assert_eq!(
+            Counts::default().named_columns(&flags)[1],
+            ("reading_minutes", 0)
+        );
     }
 
     #[test]
-    fn test_print_stdin_results_with_large_numbers() {
-        let flags = Flags {
+    fn test_print_raw_total() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a.csv"), count_slice(b"1\n2\n", &flags)),
+            FileResult::Err(PathBuf::from("b.csv"), io::Error::other("gone").into()),
+            FileResult::Ok(PathBuf::from("c.csv"), count_slice(b"3\n", &flags)),
+        ];
+
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        print_raw_total(&mut output, &mut errors, &results, &flags).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+        assert_eq!(String::from_utf8(errors).unwrap(), "rswc: b.csv: gone\n");
+    }
+
+    #[cfg(feature = "pattern")]
+    #[test]
+    fn test_count_slice_with_line_filter() {
+        let mut flags = CountOptions {
             lines: true,
             words: true,
             bytes: true,
-            chars: false,
+            line_filter: Some(Pattern::new("^ERROR").unwrap()),
+            ..Default::default()
         };
+        let data = b"ERROR disk full\ninfo ok\nERROR retry";
 
-        let counts = Counts {
-            lines: 2143500000,
-            words: 17449200000,
-            bytes: 102657000000,
-            chars: 0,
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (1, 5, 27));
+
+        flags.invert_match = true;
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (1, 2, 8));
+    }
+
+    #[cfg(feature = "pattern")]
+    #[test]
+    fn test_count_slice_ignoring_lines() {
+        let mut flags = CountOptions::new()
+            .lines(true)
+            .words(true)
+            .bytes(true)
+            .code(true)
+            .freq(true)
+            .ignore_lines(Pattern::any([r"^\s*$", r"^\s*//"]).unwrap());
+        flags.language = language_for(Path::new("a.rs"));
+        let data = b"// header\nfn main() {\n\n    // say hi\n    hi(); /* hi */\n}";
+
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (2, 8, 32));
+        assert_eq!(
+            (counts.code_lines, counts.comment_lines, counts.blank_lines),
+            (3, 0, 0)
+        );
+        let freqs = counts.frequencies.unwrap();
+        assert_eq!(freqs.values().sum::<u64>(), 8);
+        assert!(!freqs.contains_key(&b"header"[..]));
+
+        flags.line_filter = Some(Pattern::new("hi").unwrap());
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (1, 4, 19));
+    }
+
+    #[test]
+    fn test_count_head() {
+        let mut flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            head_lines: Some(2),
+            ..Default::default()
         };
+        let data = b"one two\nthree\nfour five six\n";
 
-        let mut output = Cursor::new(Vec::new());
-        print_stdin_results(&mut output, &counts, &flags).unwrap();
-        let actual = String::from_utf8(output.into_inner()).unwrap();
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (2, 3, 14));
+        assert!(counts.annotations.contains(Annotations::HEAD));
 
-        let expected = "\
-            2143500000 17449200000 102657000000 -
-";
+        flags.head_bytes = Some(5);
+        let counts = count_reader(&data[..], &flags).unwrap();
+        assert_eq!((counts.lines, counts.words, counts.bytes), (0, 2, 5));
 
-        assert_eq!(actual, expected, "Output does not match");
+        flags.head_lines = None;
+        flags.head_bytes = Some(100);
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (3, 6, 28));
+        assert!(!counts.annotations.contains(Annotations::HEAD));
     }
 
     #[test]
-    fn test_count_file() {
-        let flags = Flags {
+    fn test_count_range() {
+        let mut flags = CountOptions {
             lines: true,
             words: true,
             bytes: true,
-            chars: true,
+            range: Some(LineRange::new("2:3").unwrap()),
+            ..Default::default()
         };
-        let path = Path::new("testdata/test.txt");
-        assert!(path.exists(), "Test file does not exist: {:?}", path);
+        let data = b"one two\nthree\nfour five six\nseven";
 
-        let actual = count_file(path, &flags).unwrap();
-        let expected = Counts {
-            lines: 7145,
-            words: 58164,
-            bytes: 342190,
-            chars: 339292,
+        let counts = count_slice(data, &flags);
+        assert_eq!((counts.lines, counts.words, counts.bytes), (2, 4, 20));
+        assert!(!counts.annotations.contains(Annotations::HEAD));
+
+        flags.range = Some(LineRange::new("3:").unwrap());
+        let counts = count_reader(&data[..], &flags).unwrap();
+        assert_eq!((counts.lines, counts.words, counts.bytes), (1, 4, 19));
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_count_seek() {
+        let data = std::fs::read("testdata/test.txt").unwrap();
+        let whole = CountOptions::new().lines(true).words(true).bytes(true);
+        let expected = count_slice(&data, &whole);
+        // Offsets in a word, between words, past the end.
+        for offset in [1, 17, 5000, 342189, 342190, 400000] {
+            let head = count_slice(&data, &whole.clone().head_bytes(offset));
+            let flags = whole.clone().seek(offset as u64);
+            let path = Path::new("testdata/test.txt");
+            let file = count_file(path, &flags, &no_progress).unwrap();
+            let read = count_reader(&data[..], &flags).unwrap();
+            assert_eq!(file, read, "offset {}", offset);
+            assert_eq!(
+                (head.lines + read.lines, head.words + read.words),
+                (expected.lines, expected.words),
+                "offset {}",
+                offset
+            );
+            assert_eq!(read.bytes, data.len().saturating_sub(offset) as u64);
+        }
+
+        let flags = CountOptions::new().words(true).seek(4).head_bytes(6);
+        assert_eq!(count_slice(b"one two three", &flags).words, 2);
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_directory_reported() {
+        let flags = CountOptions::new().lines(true).bytes(true);
+        let files = [
+            PathBuf::from("testdata"),
+            PathBuf::from("testdata/test.txt"),
+        ];
+        let results = process_files(&files, &flags);
+        match &results[0] {
+            FileResult::Err(path, e) => {
+                assert_eq!(path, Path::new("testdata"));
+                assert_eq!(e.io_kind(), Some(io::ErrorKind::IsADirectory));
+                assert_eq!(e.to_string(), "Is a directory");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(results[1], FileResult::Ok(..)));
+        assert_eq!(count_directories(&results), 1);
+        assert_eq!(count_failures(&results), (2, 1));
+    }
+
+    #[test]
+    fn test_report_annotations() {
+        let mut flags = CountOptions {
+            bytes: true,
+            ..Default::default()
         };
-        assert_eq!(actual, expected);
+        let mut annotations = Annotations::MMAP;
+        annotations.insert(Annotations::PARALLEL);
+        let results = vec![FileResult::Ok(
+            PathBuf::from("big.log"),
+            Counts {
+                bytes: 5,
+                annotations,
+                ..Default::default()
+            },
+        )];
+
+        assert_eq!(
+            Report::new(&results, &flags).to_string(),
+            "      5 big.log\n"
+        );
+        flags.annotate = true;
+        assert_eq!(
+            Report::new(&results, &flags).to_string(),
+            "      5 big.log [mmap, parallel]\n"
+        );
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_process_files_from_memory() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let files: std::collections::HashMap<PathBuf, Vec<u8>> =
+            [(PathBuf::from("db/a.txt"), b"one two\nthree\n".to_vec())].into();
+
+        let results = process_files_from(
+            &[PathBuf::from("db/a.txt"), PathBuf::from("db/gone.txt")],
+            &flags,
+            &files,
+        );
+        assert_eq!(
+            results,
+            [
+                FileResult::Ok(
+                    PathBuf::from("db/a.txt"),
+                    count_slice(b"one two\nthree\n", &flags)
+                ),
+                FileResult::Err(
+                    PathBuf::from("db/gone.txt"),
+                    io::Error::from(io::ErrorKind::NotFound).into()
+                ),
+            ]
+        );
+
+        let disk = process_files_from(&[PathBuf::from("testdata/test.txt")], &flags, &Disk);
+        assert_eq!(
+            disk,
+            process_files(&[PathBuf::from("testdata/test.txt")], &flags)
+        );
     }
 
     //test results for seq 300000
     //2143500000 17449200000 102657000000
 
+    #[cfg(feature = "core")]
     #[test]
     fn test_process_files_mixed_ok_and_err() {
-        let flags = Flags {
+        let flags = CountOptions {
             lines: true,
             words: true,
             bytes: true,
             chars: false,
+            ..Default::default()
         };
         let valid_path = PathBuf::from("testdata/test.txt");
         let invalid_path = PathBuf::from("testdata/test.t");
@@ -328,6 +3605,7 @@ mod tests {
             words: 58164,
             bytes: 342190,
             chars: 0,
+            ..Default::default()
         };
 
         for a in actual {
@@ -340,12 +3618,188 @@ mod tests {
                 FileResult::Err(path, msg) => {
                     err_found = true;
                     assert_eq!(path, invalid_path);
-                    assert_eq!(msg, "No such file or directory (os error 2)");
+                    assert_eq!(msg.io_kind(), Some(io::ErrorKind::NotFound));
+                    assert_eq!(msg.to_string(), "No such file or directory (os error 2)");
                 }
+                FileResult::Archive(path, _) => panic!("unexpected archive: {:?}", path),
             }
         }
 
         assert!(ok_found, "Expected one successful FileResult::Ok");
         assert!(err_found, "Expected one unsuccessful FileResult::Err");
     }
+
+    #[cfg(feature = "core")]
+    #[cfg(not(feature = "remote"))]
+    #[test]
+    fn test_process_files_url_without_remote_feature() {
+        let url = PathBuf::from("https://example.com/data.txt");
+        let actual = process_files(std::slice::from_ref(&url), &CountOptions::default());
+        assert_eq!(
+            actual,
+            vec![FileResult::Err(
+                url,
+                RswcError::Usage(
+                    "URL support is not enabled in this build (feature `remote`)".to_string()
+                )
+            )]
+        );
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_process_files_with_thread_pool() {
+        let flags = CountOptions {
+            lines: true,
+            threads: 2,
+            ..Default::default()
+        };
+        let path = PathBuf::from("testdata/test.txt");
+
+        let actual = process_files(&[path.clone(), path.clone()], &flags);
+        for r in actual {
+            match r {
+                FileResult::Ok(_, c) => assert_eq!(c.lines, 7145),
+                FileResult::Err(_, msg) => panic!("unexpected error: {}", msg),
+                FileResult::Archive(path, _) => panic!("unexpected archive: {:?}", path),
+            }
+        }
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_process_files_unordered() {
+        let flags = CountOptions::new().lines(true).threads(2);
+        let files = [
+            PathBuf::from("testdata/test.txt"),
+            PathBuf::from("testdata/missing.txt"),
+            PathBuf::from("testdata/test.txt"),
+        ];
+        let mut results = Vec::new();
+        process_files_unordered(&files, &flags, &mut |i, r| results.push((i, r)));
+        results.sort_by_key(|&(i, _)| i);
+        let results: Vec<FileResult> = results.into_iter().map(|(_, r)| r).collect();
+        assert_eq!(results, process_files(&files, &flags));
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn test_one_thread_counts_in_turn() {
+        let flags = CountOptions::new().lines(true).threads(1);
+        assert!(flags.sequential());
+        let files = [
+            PathBuf::from("testdata/test.txt"),
+            PathBuf::from("gone"),
+            PathBuf::from("testdata/test.txt"),
+        ];
+        let mut order = Vec::new();
+        process_files_unordered(&files, &flags, &mut |i, _| order.push(i));
+        assert_eq!(order, [0, 1, 2]);
+        assert_eq!(
+            process_files(&files, &flags),
+            process_files(&files, &CountOptions::new().lines(true))
+        );
+    }
+
+    #[test]
+    fn test_count_options_builder() {
+        let options = CountOptions::new()
+            .lines(true)
+            .max_line_length(true)
+            .fields(b'\t')
+            .lang(&ENGLISH);
+        assert_eq!(
+            options,
+            CountOptions {
+                lines: true,
+                stats: true,
+                fields: Some(b'\t'),
+                lang: Some(&ENGLISH),
+                ..Default::default()
+            }
+        );
+        #[allow(deprecated)]
+        let flags: Flags = options.clone();
+        assert_eq!(flags, options);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_wire_format() {
+        let counts = Counts {
+            lines: 2,
+            words: 5,
+            bytes: 20,
+            line_ends: Some(Box::new(LineEnds {
+                lf: 1,
+                crlf: 1,
+                cr: 0,
+            })),
+            annotations: Annotations::MMAP,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok("a.txt".into(), counts),
+            FileResult::Err(
+                "gone.txt".into(),
+                io::Error::from(io::ErrorKind::NotFound).into(),
+            ),
+            FileResult::Archive(
+                "b.tar".into(),
+                vec![FileResult::Err(
+                    "x.bin".into(),
+                    RswcError::Skipped("binary file".to_string()),
+                )],
+            ),
+        ];
+        let json = serde_json::to_string(&results).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"[{"ok":["a.txt",{"lines":2,"words":5,"numbers":0,"links":0,"bytes":20,"#,
+                r#""chars":0,"invalid_bytes":0,"first_invalid":null,"non_ascii":0,"char_classes":null,"#,
+                r#""max_word_length":0,"sentences":0,"tokens":0,"blank_lines":0,"comment_lines":0,"code_lines":0,"matches":0,"#,
+                r#""line_ends":{"lf":1,"crlf":1,"cr":0},"indents":null,"field_counts":null,"#,
+                r#""timeline":null,"cells":null,"config":null,"log_levels":null,"groups":null,"buckets":null,"#,
+                r#""digest":null,"unique_lines":0,"repeated_lines":null,"line_stats":null,"detected":null,"annotations":["mmap"],"estimates":[]}]},"#,
+                r#"{"err":["gone.txt",{"io":{"kind":"NotFound","message":"entity not found"}}]},"#,
+                r#"{"archive":["b.tar",[{"err":["x.bin",{"skipped":"binary file"}]}]]}]"#
+            )
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<FileResult>>(&json).unwrap(),
+            results
+        );
+
+        let mut estimates = Estimates::TOKENS;
+        estimates.insert(Estimates::SATURATED);
+        assert_eq!(
+            serde_json::to_string(&estimates).unwrap(),
+            r#"["tokens","saturated"]"#
+        );
+        assert!(serde_json::from_str::<Annotations>(r#"["fast"]"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_flags() {
+        let json = r#"{"lines":true,"words":true,"invalid_utf8":"strict","numbers":"separate","language":"Hash","lang":"en","fold_case":"turkish"}"#;
+        let flags: CountOptions = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            flags,
+            CountOptions {
+                lines: true,
+                words: true,
+                invalid_utf8: InvalidUtf8::Strict,
+                numbers: Numbers::Separate,
+                language: language_for(Path::new("x.py")),
+                lang: Some(&ENGLISH),
+                fold_case: Some(CaseFold::Turkish),
+                ..Default::default()
+            }
+        );
+        let json = serde_json::to_string(&flags).unwrap();
+        assert_eq!(serde_json::from_str::<CountOptions>(&json).unwrap(), flags);
+        assert!(serde_json::from_str::<CountOptions>(r#"{"language":"Cobol"}"#).is_err());
+    }
 }
@@ -0,0 +1,128 @@
+//! Crash reports: when counting panics, a report with the command line, the
+//! file being counted, a backtrace and the build goes to a temporary file,
+//! so the failure can be reported and reproduced.
+
+use crate::features::capabilities;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+
+const ISSUES_URL: &str = "https://github.com/nobletk/rswc/issues";
+
+thread_local! {
+    static CURRENT_FILE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with `path` noted as the file counted on this thread, for the
+/// crash report of a panic inside it.
+pub fn processing<T>(path: &Path, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT_FILE.with(|c| c.replace(Some(path.to_path_buf())));
+    let result = f();
+    CURRENT_FILE.with(|c| *c.borrow_mut() = previous);
+    result
+}
+
+/// Replaces the panic message with a crash report written to the temporary
+/// directory, and a pointer to it.
+pub fn install(args: Vec<String>) {
+    panic::set_hook(Box::new(move |info| {
+        let file = CURRENT_FILE.with(|c| c.borrow().clone());
+        let report = crash_report(
+            &panic_message(info),
+            &args,
+            file.as_deref(),
+            &Backtrace::force_capture().to_string(),
+        );
+        let path = std::env::temp_dir().join(format!(
+            "rswc-crash-{}-{}.txt",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        ));
+
+        eprintln!("rswc: internal error: {}", panic_message(info));
+        match std::fs::write(&path, &report) {
+            Ok(()) => eprintln!(
+                "rswc: a crash report was written to {}\nrswc: please attach it to an issue at {}",
+                path.display(),
+                ISSUES_URL
+            ),
+            Err(_) => eprintln!(
+                "rswc: please report this at {} with the following:\n{}",
+                ISSUES_URL, report
+            ),
+        }
+    }));
+}
+
+/// The message of a panic and where it happened.
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    match info.location() {
+        Some(at) => format!("{} at {}:{}", message, at.file(), at.line()),
+        None => message,
+    }
+}
+
+fn crash_report(message: &str, args: &[String], file: Option<&Path>, backtrace: &str) -> String {
+    let mut report = String::new();
+    // Writing into a String cannot fail.
+    let _ = writeln!(report, "rswc {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "built with {}", env!("RSWC_RUSTC_VERSION"));
+    let _ = writeln!(
+        report,
+        "target: {} ({} linking)",
+        env!("RSWC_TARGET"),
+        env!("RSWC_LINKAGE")
+    );
+    let features: Vec<&str> = capabilities()
+        .into_iter()
+        .filter(|c| c.available)
+        .map(|c| c.name)
+        .collect();
+    let _ = writeln!(report, "features: {}", features.join(", "));
+    let _ = writeln!(report, "arguments: {:?}", args);
+    match file {
+        Some(file) => {
+            let _ = writeln!(report, "file: {}", file.display());
+        }
+        None => {
+            let _ = writeln!(report, "file: none");
+        }
+    }
+    let _ = writeln!(report, "panic: {}", message);
+    let _ = write!(report, "\nbacktrace:\n{}", backtrace);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_report() {
+        let args = vec!["-l".to_string(), "odd.bin".to_string()];
+        let report = processing(Path::new("odd.bin"), || {
+            let file = CURRENT_FILE.with(|c| c.borrow().clone());
+            crash_report(
+                "boom at src/scanner.rs:1",
+                &args,
+                file.as_deref(),
+                "0: main\n",
+            )
+        });
+        assert!(report.starts_with(&format!("rswc {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(report.contains("\narguments: [\"-l\", \"odd.bin\"]\nfile: odd.bin\n"));
+        assert!(report.contains("\npanic: boom at src/scanner.rs:1\n"));
+        assert!(report.ends_with("\nbacktrace:\n0: main\n"));
+        assert_eq!(CURRENT_FILE.with(|c| c.borrow().clone()), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! `--dedup`: every file counted once, however many times and by however
+//! many names it is given, so that a glob matching a file twice or a path
+//! given both relative and absolute doesn't inflate the total.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The files given so far, by canonical path.
+#[derive(Debug, Default)]
+pub struct Seen {
+    first: HashMap<PathBuf, PathBuf>,
+}
+
+impl Seen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The name the file at `path` was first given by, if it was given
+    /// before; otherwise `path` is noted as its name. Paths that can't be
+    /// resolved, like those of missing files and URLs, are compared as
+    /// they are.
+    pub fn earlier(&mut self, path: &Path) -> Option<&Path> {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        match self.first.entry(key) {
+            Entry::Occupied(first) => Some(first.into_mut().as_path()),
+            Entry::Vacant(entry) => {
+                entry.insert(path.to_path_buf());
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seen_by_canonical_path() {
+        let mut seen = Seen::new();
+        assert_eq!(seen.earlier(Path::new("Cargo.toml")), None);
+        assert_eq!(
+            seen.earlier(Path::new("src/../Cargo.toml")),
+            Some(Path::new("Cargo.toml"))
+        );
+        assert_eq!(
+            seen.earlier(Path::new("Cargo.toml")),
+            Some(Path::new("Cargo.toml"))
+        );
+        assert_eq!(seen.earlier(Path::new("missing.txt")), None);
+        assert_eq!(
+            seen.earlier(Path::new("missing.txt")),
+            Some(Path::new("missing.txt"))
+        );
+        assert_eq!(seen.earlier(Path::new("README.md")), None);
+    }
+}
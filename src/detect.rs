@@ -0,0 +1,149 @@
+//! `--detect`: what an input looks like from its first bytes, text in a
+//! probable encoding or binary, to explain character counts that seem off.
+
+use crate::layout::LayoutOptions;
+use std::fmt;
+
+/// Bytes at the start of an input that its type is told from.
+pub const SAMPLE_LEN: usize = 8 * 1024;
+
+/// The widest type name, that the column is padded to.
+const WIDTH: usize = 9;
+
+/// The type of an input, from a sample of its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Detected {
+    Empty,
+    Ascii,
+    Utf8,
+    /// UTF-8 starting with a byte order mark.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Text that isn't UTF-8, probably in Latin-1 or another 8-bit encoding
+    /// like Windows-1252, whose bytes beyond ASCII count as invalid.
+    Latin1,
+    Binary,
+}
+
+impl Detected {
+    /// Tells the type of an input from `sample`, its first bytes, which may
+    /// end in the middle of a character.
+    pub fn of(sample: &[u8]) -> Self {
+        if sample.is_empty() {
+            return Detected::Empty;
+        }
+        if sample.starts_with(b"\xef\xbb\xbf") {
+            return Detected::Utf8Bom;
+        }
+        if sample.starts_with(b"\xff\xfe") {
+            return Detected::Utf16Le;
+        }
+        if sample.starts_with(b"\xfe\xff") {
+            return Detected::Utf16Be;
+        }
+        if sample.contains(&0) {
+            return utf16_without_bom(sample).unwrap_or(Detected::Binary);
+        }
+        match std::str::from_utf8(sample) {
+            Ok(text) if text.is_ascii() => Detected::Ascii,
+            Ok(_) => Detected::Utf8,
+            // Only the last character is cut off.
+            Err(e) if e.error_len().is_none() => Detected::Utf8,
+            Err(_) if is_mostly_text(sample) => Detected::Latin1,
+            Err(_) => Detected::Binary,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Detected::Empty => "empty",
+            Detected::Ascii => "ascii",
+            Detected::Utf8 => "utf-8",
+            Detected::Utf8Bom => "utf-8-bom",
+            Detected::Utf16Le => "utf-16le",
+            Detected::Utf16Be => "utf-16be",
+            Detected::Latin1 => "latin-1",
+            Detected::Binary => "binary",
+        }
+    }
+}
+
+impl fmt::Display for Detected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// UTF-16 text of mostly ASCII characters has a NUL byte in every pair, the
+/// high one: the second in little endian, the first in big endian.
+fn utf16_without_bom(sample: &[u8]) -> Option<Detected> {
+    let pairs = sample.len() / 2;
+    if pairs == 0 {
+        return None;
+    }
+    let high_nul = |i: usize| {
+        sample
+            .chunks_exact(2)
+            .filter(|pair| pair[i] == 0 && pair[1 - i] != 0)
+            .count()
+    };
+    if high_nul(1) * 10 >= pairs * 9 {
+        Some(Detected::Utf16Le)
+    } else if high_nul(0) * 10 >= pairs * 9 {
+        Some(Detected::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Whether hardly any bytes of `sample` are control characters other than
+/// the whitespace ones and escape, as in text of any 8-bit encoding.
+fn is_mostly_text(sample: &[u8]) -> bool {
+    let control = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || b == 0x7f)
+        .count();
+    control * 100 <= sample.len()
+}
+
+/// The type column of a row and its `name`: `kind` padded to the widest
+/// type, unless the layout leaves values unpadded.
+pub fn type_column(layout: &LayoutOptions, kind: &str, name: impl fmt::Display) -> String {
+    let width = if layout.align { WIDTH } else { 0 };
+    format!("{:<width$}{}{}", kind, layout.separator, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detected() {
+        assert_eq!(Detected::of(b""), Detected::Empty);
+        assert_eq!(Detected::of(b"plain text\n"), Detected::Ascii);
+        assert_eq!(Detected::of("naïve\n".as_bytes()), Detected::Utf8);
+        assert_eq!(Detected::of(b"caf\xc3"), Detected::Utf8);
+        assert_eq!(Detected::of(b"\xef\xbb\xbfhi"), Detected::Utf8Bom);
+        assert_eq!(Detected::of(b"\xff\xfeh\0i\0"), Detected::Utf16Le);
+        assert_eq!(Detected::of(b"h\0i\0\n\0"), Detected::Utf16Le);
+        assert_eq!(Detected::of(b"\0h\0i\0\n"), Detected::Utf16Be);
+        assert_eq!(Detected::of(b"na\xefve caf\xe9\n"), Detected::Latin1);
+        assert_eq!(Detected::of(b"\x7fELF\x02\x01\x01\0\0\0"), Detected::Binary);
+        assert_eq!(
+            Detected::of(b"\x89PNG\r\n\x1a\n\x01\x02\x03"),
+            Detected::Binary
+        );
+    }
+
+    #[test]
+    fn test_type_column() {
+        let layout = LayoutOptions::default();
+        assert_eq!(type_column(&layout, "utf-8", "a.txt"), "utf-8     a.txt");
+        assert_eq!(
+            type_column(&LayoutOptions::print0(), "utf-8", "a.txt"),
+            "utf-8\ta.txt"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! `rswc diff A B`: the counts of two inputs side by side with their
+//! differences, to check what a transformation kept, like the lines of a
+//! file before and after a script rewrote it.
+
+use crate::counter::{CountOptions, Counts};
+use std::io::{self, Write};
+
+/// Prints a row per count with its value in `a` and `b` and the change from
+/// `a` to `b`, under a row of the names. Returns whether any count differs.
+pub fn print_diff<W: Write>(
+    writer: &mut W,
+    (name_a, a): (&str, &Counts),
+    (name_b, b): (&str, &Counts),
+    flags: &CountOptions,
+) -> io::Result<bool> {
+    let rows: Vec<[String; 4]> = a
+        .named_columns(flags)
+        .into_iter()
+        .zip(b.named_columns(flags))
+        .map(|((name, a), (_, b))| {
+            let change = b as i64 - a as i64;
+            let change = if change == 0 {
+                "0".to_string()
+            } else {
+                format!("{:+}", change)
+            };
+            [name.to_string(), a.to_string(), b.to_string(), change]
+        })
+        .collect();
+    let header = [
+        String::new(),
+        name_a.to_string(),
+        name_b.to_string(),
+        "change".to_string(),
+    ];
+
+    let mut widths = [0; 4];
+    for row in rows.iter().chain([&header]) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in [&header].into_iter().chain(&rows) {
+        writeln!(
+            writer,
+            "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        )?;
+    }
+    Ok(rows.iter().any(|row| row[3] != "0"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_diff() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let before = Counts {
+            lines: 12,
+            words: 100,
+            ..Default::default()
+        };
+        let after = Counts {
+            lines: 12,
+            words: 98,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let differ =
+            print_diff(&mut out, ("in.txt", &before), ("out.txt", &after), &flags).unwrap();
+        assert!(differ);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "       in.txt  out.txt  change\n\
+             lines      12       12       0\n\
+             words     100       98      -2\n"
+        );
+
+        let mut out = Vec::new();
+        assert!(!print_diff(&mut out, ("a", &before), ("b", &before), &flags).unwrap());
+    }
+}
@@ -0,0 +1,115 @@
+//! Subtotals per directory for `--group-by-dir`, for a tree of files named
+//! by a glob or by `find ... -print0 | rswc --files0-from=-`.
+
+use crate::counter::{FileResult, total};
+use std::collections::BTreeMap;
+use std::path::{Component, MAIN_SEPARATOR, Path, PathBuf};
+
+/// The directories `path` is in, outermost first, down to `depth` levels
+/// below where its name starts. A leading `/` or `./` is kept in the
+/// directory names but isn't a level.
+fn directories(path: &Path, depth: Option<usize>) -> Vec<PathBuf> {
+    let Some(parent) = path.parent() else {
+        return Vec::new();
+    };
+    let mut dirs = Vec::new();
+    let mut dir = PathBuf::new();
+    for component in parent.components() {
+        dir.push(component);
+        if matches!(component, Component::Normal(_) | Component::ParentDir) {
+            if depth.is_some_and(|depth| dirs.len() == depth) {
+                break;
+            }
+            dirs.push(dir.clone());
+        }
+    }
+    dirs
+}
+
+/// A subtotal row for each directory of `results`, named like `src/` and
+/// ordered by name, with everything counted below it up to `depth` levels.
+/// Archives count in the directory they are in; failed inputs count nowhere.
+pub fn dir_subtotals(results: &[FileResult], depth: Option<usize>) -> Vec<FileResult> {
+    let mut groups: BTreeMap<PathBuf, Vec<&FileResult>> = BTreeMap::new();
+    for r in results {
+        let path = match r {
+            FileResult::Ok(path, _) | FileResult::Archive(path, _) => path,
+            FileResult::Err(..) => continue,
+        };
+        for dir in directories(path, depth) {
+            groups.entry(dir).or_default().push(r);
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(dir, members)| {
+            let name = format!("{}{}", dir.display(), MAIN_SEPARATOR);
+            FileResult::Ok(name.into(), total(members))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+    use std::io;
+
+    #[test]
+    fn test_directories() {
+        let dirs = |path: &str, depth| -> Vec<String> {
+            directories(Path::new(path), depth)
+                .iter()
+                .map(|d| d.display().to_string())
+                .collect()
+        };
+        assert_eq!(dirs("src/a/b/x.rs", None), ["src", "src/a", "src/a/b"]);
+        assert_eq!(dirs("src/a/b/x.rs", Some(2)), ["src", "src/a"]);
+        assert_eq!(dirs("./src/x.rs", None), ["./src"]);
+        assert_eq!(dirs("/var/log/x.log", Some(1)), ["/var"]);
+        assert!(dirs("x.rs", None).is_empty());
+    }
+
+    #[test]
+    fn test_dir_subtotals() {
+        let lines = |lines| Counts {
+            lines,
+            ..Default::default()
+        };
+        let results = [
+            FileResult::Ok("src/main.rs".into(), lines(10)),
+            FileResult::Ok("src/io/read.rs".into(), lines(3)),
+            FileResult::Err(
+                "src/gone.rs".into(),
+                io::Error::other("No such file or directory").into(),
+            ),
+            FileResult::Archive(
+                "docs/old.zip".into(),
+                vec![FileResult::Ok("a.txt".into(), lines(4))],
+            ),
+            FileResult::Ok("README".into(), lines(1)),
+        ];
+        let rows = |depth| -> Vec<(String, u64)> {
+            dir_subtotals(&results, depth)
+                .iter()
+                .map(|r| match r {
+                    FileResult::Ok(name, c) => (name.display().to_string(), c.lines),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        let sep = MAIN_SEPARATOR;
+        assert_eq!(
+            rows(None),
+            [
+                (format!("docs{}", sep), 4),
+                (format!("src{}", sep), 13),
+                (format!("src/io{}", sep), 3)
+            ]
+        );
+        assert_eq!(
+            rows(Some(1)),
+            [(format!("docs{}", sep), 4), (format!("src{}", sep), 13)]
+        );
+    }
+}
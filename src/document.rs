@@ -0,0 +1,324 @@
+//! The text of office documents, so that PDF, DOCX and ODT files are counted
+//! by what they read rather than by their compressed bytes.
+
+use crate::counter::{Annotations, CountOptions, Counts, count_slice};
+use std::io;
+use std::path::Path;
+
+/// A document format whose text can be extracted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Document {
+    Pdf,
+    Docx,
+    Odt,
+}
+
+impl Document {
+    /// The format of `path`, by its extension.
+    pub fn of(path: &Path) -> Option<Document> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "pdf" => Some(Document::Pdf),
+            "docx" => Some(Document::Docx),
+            "odt" => Some(Document::Odt),
+            _ => None,
+        }
+    }
+
+    /// The feature that extracts the text of this format.
+    fn feature(self) -> &'static str {
+        match self {
+            Document::Pdf => "pdf",
+            Document::Docx | Document::Odt => "office",
+        }
+    }
+}
+
+/// Whether `path` names an EPUB book.
+pub fn is_epub(path: &Path) -> bool {
+    has_extension(path, &["epub"])
+}
+
+/// Whether `path` names an HTML or XHTML file.
+pub fn is_html(path: &Path) -> bool {
+    has_extension(path, &["html", "htm", "xhtml"])
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+}
+
+/// Counts the text of the document at `path`.
+pub fn count_document(path: &Path, document: Document, flags: &CountOptions) -> io::Result<Counts> {
+    let text = extract_text(path, document)?;
+    let mut counts = count_slice(text.as_bytes(), flags);
+    counts.annotations.insert(Annotations::EXTRACTED);
+    Ok(counts)
+}
+
+/// The text of the document at `path`, with a line per paragraph.
+pub fn extract_text(path: &Path, document: Document) -> io::Result<String> {
+    match document {
+        #[cfg(feature = "pdf")]
+        Document::Pdf => pdf_text(path),
+        #[cfg(feature = "office")]
+        Document::Docx => office_text(path, "word/document.xml", &DOCX),
+        #[cfg(feature = "office")]
+        Document::Odt => office_text(path, "content.xml", &ODT),
+        #[allow(unreachable_patterns)]
+        _ => {
+            let _ = path;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "document text extraction is not enabled in this build (feature `{}`)",
+                    document.feature()
+                ),
+            ))
+        }
+    }
+}
+
+/// The most a page of a PDF may decompress to, against compression bombs.
+#[cfg(feature = "pdf")]
+const PDF_PAGE_LIMIT: usize = 256 << 20;
+
+#[cfg(feature = "pdf")]
+fn pdf_text(path: &Path) -> io::Result<String> {
+    let doc = lopdf::Document::load(path).map_err(io::Error::other)?;
+    let pages: Vec<u32> = doc.get_pages().into_keys().collect();
+    doc.extract_text_with_limit(&pages, PDF_PAGE_LIMIT)
+        .map_err(io::Error::other)
+}
+
+/// The element names of an XML document format.
+#[cfg(feature = "office")]
+struct Markup {
+    /// Paragraphs and headings, ended by a newline.
+    paragraphs: &'static [&'static [u8]],
+    /// Elements whose text is content; `None` if all text in a paragraph is.
+    text: Option<&'static [u8]>,
+    tab: &'static [u8],
+    line_break: &'static [u8],
+    /// Runs of spaces, with their length in a `c` attribute.
+    spaces: Option<&'static [u8]>,
+}
+
+#[cfg(feature = "office")]
+const DOCX: Markup = Markup {
+    paragraphs: &[b"w:p"],
+    text: Some(b"w:t"),
+    tab: b"w:tab",
+    line_break: b"w:br",
+    spaces: None,
+};
+
+#[cfg(feature = "office")]
+const ODT: Markup = Markup {
+    paragraphs: &[b"text:p", b"text:h"],
+    text: None,
+    tab: b"text:tab",
+    line_break: b"text:line-break",
+    spaces: Some(b"text:s"),
+};
+
+#[cfg(feature = "office")]
+fn office_text(path: &Path, member: &str, markup: &Markup) -> io::Result<String> {
+    let file = std::fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(file)).map_err(io::Error::other)?;
+    let entry = archive.by_name(member).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("not a document: {}: {}", member, e),
+        )
+    })?;
+    xml_text(io::BufReader::new(entry), markup)
+}
+
+#[cfg(feature = "office")]
+fn xml_text<R: io::BufRead>(reader: R, markup: &Markup) -> io::Result<String> {
+    use quick_xml::escape::resolve_predefined_entity;
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut paragraphs = 0usize;
+    let mut in_text = 0usize;
+    let is_content = |paragraphs: usize, in_text: usize| {
+        paragraphs > 0 && (markup.text.is_none() || in_text > 0)
+    };
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(io::Error::other)? {
+            Event::Start(e) => {
+                let name = e.name();
+                if markup.paragraphs.contains(&name.as_ref()) {
+                    paragraphs += 1;
+                } else if markup.text == Some(name.as_ref()) {
+                    in_text += 1;
+                }
+            }
+            Event::End(e) => {
+                let name = e.name();
+                if markup.paragraphs.contains(&name.as_ref()) {
+                    paragraphs = paragraphs.saturating_sub(1);
+                    text.push('\n');
+                } else if markup.text == Some(name.as_ref()) {
+                    in_text = in_text.saturating_sub(1);
+                }
+            }
+            Event::Empty(e) => {
+                let name = e.name();
+                if markup.paragraphs.contains(&name.as_ref()) {
+                    text.push('\n');
+                } else if name.as_ref() == markup.tab {
+                    text.push('\t');
+                } else if name.as_ref() == markup.line_break {
+                    text.push('\n');
+                } else if markup.spaces == Some(name.as_ref()) {
+                    let count = e
+                        .try_get_attribute("text:c")
+                        .ok()
+                        .flatten()
+                        .and_then(|a| std::str::from_utf8(&a.value).ok()?.parse().ok())
+                        .unwrap_or(1);
+                    text.extend(std::iter::repeat_n(' ', count));
+                }
+            }
+            Event::Text(t) if is_content(paragraphs, in_text) => {
+                text.push_str(&t.decode().map_err(io::Error::other)?);
+            }
+            Event::CData(t) if is_content(paragraphs, in_text) => {
+                text.push_str(&t.decode().map_err(io::Error::other)?);
+            }
+            Event::GeneralRef(r) if is_content(paragraphs, in_text) => {
+                if let Some(c) = r.resolve_char_ref().map_err(io::Error::other)? {
+                    text.push(c);
+                } else if let Some(s) =
+                    resolve_predefined_entity(&r.decode().map_err(io::Error::other)?)
+                {
+                    text.push_str(s);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_of() {
+        assert_eq!(Document::of(Path::new("report.PDF")), Some(Document::Pdf));
+        assert_eq!(Document::of(Path::new("a/b.docx")), Some(Document::Docx));
+        assert_eq!(Document::of(Path::new("notes.odt")), Some(Document::Odt));
+        assert_eq!(Document::of(Path::new("notes.txt")), None);
+        assert_eq!(Document::of(Path::new("pdf")), None);
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn test_docx_text() {
+        let xml = br#"<?xml version="1.0"?>
+<w:document><w:body>
+  <w:p><w:r><w:t>Fish &amp; </w:t></w:r><w:r><w:t xml:space="preserve">chips</w:t></w:r></w:p>
+  <w:p><w:r><w:instrText>PAGE</w:instrText><w:t>a</w:t><w:tab/><w:t>b</w:t><w:br/><w:t>&#233;t&#xE9;</w:t></w:r></w:p>
+  <w:p/>
+</w:body></w:document>"#;
+        assert_eq!(
+            xml_text(&xml[..], &DOCX).unwrap(),
+            "Fish & chips\na\tb\nété\n\n"
+        );
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn test_odt_text() {
+        let xml = br#"<office:document-content><office:body><office:text>
+  <text:h>Title</text:h>
+  <text:p>one<text:s text:c="3"/>two <text:span>three</text:span><text:line-break/>four</text:p>
+</office:text></office:body></office:document-content>"#;
+        assert_eq!(
+            xml_text(&xml[..], &ODT).unwrap(),
+            "Title\none   two three\nfour\n"
+        );
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn test_pdf_words() {
+        use lopdf::content::{Content, Operation};
+        use lopdf::{Object, Stream, dictionary};
+
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![100.into(), 600.into()]),
+                Operation::new("Tj", vec![Object::string_literal("Three little words")]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let path = std::env::temp_dir().join(format!("rswc-test-{}.pdf", std::process::id()));
+        doc.save(&path).unwrap();
+        let flags = CountOptions {
+            words: true,
+            ..Default::default()
+        };
+        let counts = count_document(&path, Document::Pdf, &flags);
+        std::fs::remove_file(&path).unwrap();
+
+        let counts = counts.unwrap();
+        assert_eq!(counts.words, 3);
+        assert!(counts.annotations.contains(Annotations::EXTRACTED));
+    }
+
+    #[cfg(not(feature = "pdf"))]
+    #[test]
+    fn test_missing_feature() {
+        let err = extract_text(Path::new("report.pdf"), Document::Pdf).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "document text extraction is not enabled in this build (feature `pdf`)"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::counter::FileResult;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub bytes: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    pub fn wasted_bytes(&self) -> u64 {
+        self.bytes * (self.paths.len() - 1) as u64
+    }
+}
+
+pub fn find_duplicates(results: &[FileResult]) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut index: HashMap<[u8; 32], usize> = HashMap::new();
+
+    for r in results {
+        if let FileResult::Ok(path, c) = r {
+            let Some(&digest) = c.digest.as_deref() else {
+                continue;
+            };
+            if c.bytes == 0 {
+                continue;
+            }
+
+            match index.get(&digest) {
+                Some(&i) => groups[i].paths.push(path.clone()),
+                None => {
+                    index.insert(digest, groups.len());
+                    groups.push(DuplicateGroup {
+                        bytes: c.bytes,
+                        paths: vec![path.clone()],
+                    });
+                }
+            }
+        }
+    }
+
+    groups.retain(|g| g.paths.len() > 1);
+    groups
+}
+
+pub fn print_duplicates<W: Write>(writer: &mut W, groups: &[DuplicateGroup]) -> io::Result<()> {
+    if groups.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    for g in groups {
+        writeln!(writer, "duplicates ({} bytes each):", g.bytes)?;
+        for path in &g.paths {
+            writeln!(writer, "  {}", path.display())?;
+        }
+    }
+
+    let wasted: u64 = groups.iter().map(DuplicateGroup::wasted_bytes).sum();
+    writeln!(writer, "wasted: {} bytes", wasted)?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "digest"))]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, process_files};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_find_duplicates_groups_identical_content() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: false,
+            digest: true,
+            ..Default::default()
+        };
+        let path = PathBuf::from("testdata/test.txt");
+        let results = process_files(
+            &[path.clone(), path.clone(), PathBuf::from("Cargo.toml")],
+            &flags,
+        );
+
+        let groups = find_duplicates(&results);
+        assert_eq!(
+            groups,
+            vec![DuplicateGroup {
+                bytes: 342190,
+                paths: vec![path.clone(), path],
+            }]
+        );
+
+        let mut output = Cursor::new(Vec::new());
+        print_duplicates(&mut output, &groups).unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+        let expected = "
+duplicates (342190 bytes each):
+  testdata/test.txt
+  testdata/test.txt
+wasted: 342190 bytes
+";
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,56 @@
+//! Files embedded in a binary with `include_dir!`, counted by
+//! `process_files_from` like files on disk.
+
+use crate::counter::FileOpener;
+use include_dir::Dir;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Files by their path relative to the embedded directory.
+impl FileOpener for Dir<'_> {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        match self.get_file(path) {
+            Some(file) => Ok(Box::new(file.contents())),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+}
+
+/// The paths of every file in `dir` and its subdirectories, to count all
+/// of them.
+pub fn embedded_files(dir: &Dir<'_>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = dir.files().map(|f| f.path().to_path_buf()).collect();
+    for sub in dir.dirs() {
+        files.extend(embedded_files(sub));
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, FileResult, process_files, process_files_from};
+    use include_dir::include_dir;
+
+    static TESTDATA: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/testdata");
+
+    #[test]
+    fn test_count_embedded_files() {
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let files = embedded_files(&TESTDATA);
+        assert_eq!(files, [PathBuf::from("test.txt")]);
+
+        let results = process_files_from(&files, &flags, &TESTDATA);
+        let on_disk = process_files(&[PathBuf::from("testdata/test.txt")], &flags);
+        match (&results[..], &on_disk[..]) {
+            ([FileResult::Ok(_, embedded)], [FileResult::Ok(_, disk)]) => {
+                assert_eq!(embedded, disk)
+            }
+            other => panic!("unexpected results: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Line ending styles for `--eol` and `rswc explain`.
+
+use crate::counter::{FileResult, counted_inputs, total};
+use std::fmt;
+use std::io::{self, Write};
+
+/// How many lines end in LF, CRLF and a lone CR.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineEnds {
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+}
+
+impl LineEnds {
+    pub fn merge(&mut self, other: &LineEnds) {
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+        self.cr += other.cr;
+    }
+
+    /// Whether more than one style is used.
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&n| n > 0)
+            .count()
+            > 1
+    }
+}
+
+impl fmt::Display for LineEnds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let styles: Vec<(&str, usize)> = [("LF", self.lf), ("CRLF", self.crlf), ("CR", self.cr)]
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .collect();
+
+        match styles.as_slice() {
+            [] => write!(f, "none"),
+            [(style, n)] => write!(f, "{} ({})", style, n),
+            _ => {
+                let parts: Vec<String> =
+                    styles.iter().map(|(s, n)| format!("{} {}", s, n)).collect();
+                write!(f, "mixed ({})", parts.join(", "))
+            }
+        }
+    }
+}
+
+/// Classifies the line ends of input fed in chunks of any size; a CRLF
+/// may be split between two chunks.
+#[derive(Debug, Default)]
+pub struct EolCounter {
+    ends: LineEnds,
+    after_cr: bool,
+}
+
+impl EolCounter {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            match b {
+                b'\n' if self.after_cr => self.ends.crlf += 1,
+                b'\n' => self.ends.lf += 1,
+                _ if self.after_cr => self.ends.cr += 1,
+                _ => {}
+            }
+            self.after_cr = b == b'\r';
+        }
+    }
+
+    pub fn finish(mut self) -> LineEnds {
+        if self.after_cr {
+            self.ends.cr += 1;
+        }
+        self.ends
+    }
+}
+
+/// Prints the line ends of every counted input, archive members included,
+/// marking those with mixed styles, followed by their total when there is
+/// more than one.
+pub fn print_line_ends<W: Write>(writer: &mut W, results: &[FileResult]) -> io::Result<()> {
+    let rows: Vec<(String, LineEnds)> = counted_inputs(results)
+        .into_iter()
+        .map(|(name, c)| (name, c.line_ends.as_deref().copied().unwrap_or_default()))
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "line endings (lf, crlf, cr):")?;
+    for (name, ends) in &rows {
+        write_line_ends(writer, ends, name)?;
+    }
+    if rows.len() > 1 {
+        let total = total(results)
+            .line_ends
+            .as_deref()
+            .copied()
+            .unwrap_or_default();
+        write_line_ends(writer, &total, "total")?;
+    }
+    Ok(())
+}
+
+fn write_line_ends<W: Write>(writer: &mut W, ends: &LineEnds, name: &str) -> io::Result<()> {
+    let mixed = if ends.is_mixed() { " (mixed)" } else { "" };
+    writeln!(
+        writer,
+        "{:>7} {:>7} {:>7} {}{}",
+        ends.lf, ends.crlf, ends.cr, name, mixed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, count_slice};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_line_ends_in_any_chunking() {
+        let data = b"a\r\nb\nc\rd\r\n\r\re\r";
+        for size in 1..=data.len() {
+            let mut counter = EolCounter::default();
+            for chunk in data.chunks(size) {
+                counter.feed(chunk);
+            }
+            let expected = LineEnds {
+                lf: 1,
+                crlf: 2,
+                cr: 4,
+            };
+            assert_eq!(counter.finish(), expected, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_print_line_ends() {
+        let flags = CountOptions {
+            eol: true,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(PathBuf::from("unix"), count_slice(b"a\nb\n", &flags)),
+            FileResult::Ok(PathBuf::from("both"), count_slice(b"a\r\nb\n", &flags)),
+        ];
+
+        let mut output = Vec::new();
+        print_line_ends(&mut output, &results).unwrap();
+        let expected = "
+line endings (lf, crlf, cr):
+      2       0       0 unix
+      1       1       0 both (mixed)
+      3       1       0 total (mixed)
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -0,0 +1,325 @@
+//! Books for `--epub`: the chapters of EPUB files in reading order, and the
+//! text of HTML files, without their markup.
+
+use crate::counter::{Annotations, CountOptions, Counts, FileResult, count_slice};
+use quick_xml::escape::resolve_html5_entity;
+use quick_xml::events::{BytesStart, Event};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Elements that end a line of text.
+const BLOCKS: &[&[u8]] = &[
+    b"p",
+    b"div",
+    b"br",
+    b"li",
+    b"tr",
+    b"h1",
+    b"h2",
+    b"h3",
+    b"h4",
+    b"h5",
+    b"h6",
+    b"blockquote",
+    b"pre",
+    b"section",
+    b"article",
+    b"aside",
+    b"header",
+    b"footer",
+    b"dt",
+    b"dd",
+    b"figcaption",
+    b"hr",
+];
+
+/// Elements whose text is not read.
+const HIDDEN: &[&[u8]] = &[b"head", b"script", b"style", b"template"];
+
+/// Counts every chapter of the book at `path`, named by its path inside the
+/// book. Errors reading a single chapter are reported for that chapter; a
+/// book without a readable table of contents is an error for the book.
+pub fn count_epub(path: &Path, flags: &CountOptions) -> io::Result<Vec<FileResult>> {
+    count_epub_reader(BufReader::new(File::open(path)?), flags)
+}
+
+pub fn count_epub_reader<R: Read + Seek>(
+    reader: R,
+    flags: &CountOptions,
+) -> io::Result<Vec<FileResult>> {
+    let mut book = ZipArchive::new(reader).map_err(io::Error::other)?;
+
+    let container = read_member(&mut book, "META-INF/container.xml")?;
+    let package = attribute_values(&container, b"rootfile", b"full-path")?
+        .into_iter()
+        .next()
+        .ok_or_else(|| invalid("no package document in META-INF/container.xml"))?;
+    let chapters = spine(&read_member(&mut book, &package)?)?;
+
+    let base = match package.rfind('/') {
+        Some(i) => &package[..=i],
+        None => "",
+    };
+    Ok(chapters
+        .into_iter()
+        .map(|href| {
+            let name = PathBuf::from(&href);
+            let text = read_member(&mut book, &format!("{}{}", base, href))
+                .and_then(|xhtml| html_text(&xhtml));
+            match text {
+                Ok(text) => FileResult::Ok(name, count_text(&text, flags)),
+                Err(e) => FileResult::Err(name, e.into()),
+            }
+        })
+        .collect())
+}
+
+/// Counts the text of the HTML file at `path`.
+pub fn count_html(path: &Path, flags: &CountOptions) -> io::Result<Counts> {
+    let text = html_text(&std::fs::read(path)?)?;
+    Ok(count_text(&text, flags))
+}
+
+fn count_text(text: &str, flags: &CountOptions) -> Counts {
+    let mut counts = count_slice(text.as_bytes(), flags);
+    counts.annotations.insert(Annotations::EXTRACTED);
+    counts
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("not an EPUB book: {}", message),
+    )
+}
+
+fn read_member<R: Read + Seek>(book: &mut ZipArchive<R>, name: &str) -> io::Result<Vec<u8>> {
+    let mut entry = book
+        .by_name(name)
+        .map_err(|e| invalid(&format!("{}: {}", name, e)))?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// The values of attribute `attr` of every `element`, in document order.
+fn attribute_values(xml: &[u8], element: &[u8], attr: &[u8]) -> io::Result<Vec<String>> {
+    let mut values = Vec::new();
+    for_each_element(xml, |e| {
+        if e.local_name().as_ref() == element
+            && let Some(value) = attribute(e, attr)
+        {
+            values.push(value);
+        }
+    })?;
+    Ok(values)
+}
+
+/// The content documents of a package document, in reading order.
+fn spine(opf: &[u8]) -> io::Result<Vec<String>> {
+    let mut items = Vec::new();
+    let mut order = Vec::new();
+    for_each_element(opf, |e| match e.local_name().as_ref() {
+        b"item" => {
+            if let (Some(id), Some(href)) = (attribute(e, b"id"), attribute(e, b"href")) {
+                items.push((id, href));
+            }
+        }
+        b"itemref" => order.extend(attribute(e, b"idref")),
+        _ => {}
+    })?;
+
+    Ok(order
+        .into_iter()
+        .filter_map(|idref| {
+            let (_, href) = items.iter().find(|(id, _)| *id == idref)?;
+            Some(href.clone())
+        })
+        .collect())
+}
+
+fn for_each_element(xml: &[u8], mut f: impl FnMut(&BytesStart<'_>)) -> io::Result<()> {
+    let mut reader = quick_xml::Reader::from_reader(xml);
+    loop {
+        match reader.read_event().map_err(io::Error::other)? {
+            Event::Start(e) | Event::Empty(e) => f(&e),
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn attribute(e: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    let attr = e.try_get_attribute(name).ok()??;
+    Some(attr.unescape_value().ok()?.into_owned())
+}
+
+/// The text of an HTML or XHTML document, with a line per paragraph,
+/// heading and list item. The head, scripts and styles are left out, and
+/// unclosed tags as in HTML are fine.
+pub fn html_text(html: &[u8]) -> io::Result<String> {
+    let html = without_raw_text(html);
+    let mut reader = quick_xml::Reader::from_reader(html.as_slice());
+    let config = reader.config_mut();
+    config.check_end_names = false;
+    config.allow_unmatched_ends = true;
+
+    let mut text = String::new();
+    let mut hidden = 0usize;
+    loop {
+        match reader.read_event().map_err(io::Error::other)? {
+            Event::Start(e) => {
+                let name = e.local_name().as_ref().to_ascii_lowercase();
+                if HIDDEN.contains(&name.as_slice()) {
+                    hidden += 1;
+                } else if name == b"br" && hidden == 0 {
+                    text.push('\n');
+                }
+            }
+            Event::End(e) => {
+                let name = e.local_name().as_ref().to_ascii_lowercase();
+                if HIDDEN.contains(&name.as_slice()) {
+                    hidden = hidden.saturating_sub(1);
+                } else if BLOCKS.contains(&name.as_slice()) && hidden == 0 {
+                    text.push('\n');
+                }
+            }
+            Event::Empty(e) => {
+                let name = e.local_name().as_ref().to_ascii_lowercase();
+                if BLOCKS.contains(&name.as_slice()) && hidden == 0 {
+                    text.push('\n');
+                }
+            }
+            Event::Text(t) if hidden == 0 => {
+                text.push_str(&t.decode().map_err(io::Error::other)?);
+            }
+            Event::CData(t) if hidden == 0 => {
+                text.push_str(&t.decode().map_err(io::Error::other)?);
+            }
+            Event::GeneralRef(r) if hidden == 0 => {
+                if let Some(c) = r.resolve_char_ref().map_err(io::Error::other)? {
+                    text.push(c);
+                } else if let Some(s) = resolve_html5_entity(&r.decode().map_err(io::Error::other)?)
+                {
+                    text.push_str(s);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(text)
+}
+
+/// `html` without its scripts and styles, whose `<` needn't start a tag.
+fn without_raw_text(html: &[u8]) -> Vec<u8> {
+    let lower = html.to_ascii_lowercase();
+    let mut out = Vec::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some((start, name)) = [&b"script"[..], b"style"]
+        .iter()
+        .filter_map(|name| Some((find_tag(&lower[pos..], name)? + pos, *name)))
+        .min()
+    {
+        out.extend_from_slice(&html[pos..start]);
+        let close = [b"</", name].concat();
+        pos = match find(&lower[start..], &close) {
+            Some(i) => {
+                let end = start + i;
+                find(&lower[end..], b">").map_or(html.len(), |j| end + j + 1)
+            }
+            None => html.len(),
+        };
+    }
+    out.extend_from_slice(&html[pos..]);
+    out
+}
+
+/// Where the first start tag of `name` in lowercase `html` begins.
+fn find_tag(html: &[u8], name: &[u8]) -> Option<usize> {
+    let open = [b"<", name].concat();
+    let mut from = 0;
+    while let Some(i) = find(&html[from..], &open) {
+        let at = from + i;
+        match html.get(at + open.len()) {
+            Some(b'>' | b'/') => return Some(at),
+            Some(b) if b.is_ascii_whitespace() => return Some(at),
+            _ => from = at + 1,
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Report;
+    use std::io::Write;
+
+    #[test]
+    fn test_html_text() {
+        let html = b"<!DOCTYPE html><html><head><title>Skipped</title>
+<style>p { color: red }</style></head>
+<body><h1>Chapter&nbsp;One</h1><p>It was a <em>dark</em> night.<br>Very dark.
+<script>if (a<b) { x(); }</script><p>Caf&eacute; &#8212; &amp; more</p></body></html>";
+        assert_eq!(
+            html_text(html).unwrap(),
+            "\nChapter\u{a0}One\nIt was a dark night.\nVery dark.\nCafé — & more\n"
+        );
+    }
+
+    #[test]
+    fn test_count_epub_chapters() {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        let files = [
+            ("mimetype", "application/epub+zip"),
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package><manifest>
+<item id="c2" href="text/two.xhtml"/><item id="c1" href="text/one.xhtml"/><item id="gone" href="gone.xhtml"/>
+</manifest><spine><itemref idref="c1"/><itemref idref="c2"/><itemref idref="gone"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/text/one.xhtml",
+                "<html><body><p>Call me Ishmael.</p></body></html>",
+            ),
+            (
+                "OEBPS/text/two.xhtml",
+                "<html><body><p>Some years ago</p><p>never mind</p></body></html>",
+            ),
+        ];
+        for (name, data) in files {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        let epub = writer.finish().unwrap();
+
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let mut chapters = count_epub_reader(epub, &flags).unwrap();
+        let gone = chapters.pop().unwrap();
+        assert!(matches!(gone, FileResult::Err(name, _) if name == Path::new("gone.xhtml")));
+
+        let results = vec![FileResult::Archive(PathBuf::from("moby.epub"), chapters)];
+        let expected = "      1       3 moby.epub!text/one.xhtml
+      2       5 moby.epub!text/two.xhtml
+      3       8 moby.epub
+";
+        assert_eq!(Report::new(&results, &flags).to_string(), expected);
+    }
+}
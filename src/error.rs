@@ -0,0 +1,128 @@
+//! The errors of rswc, told apart so embedders can handle a missing file
+//! differently from a bad option or a file that isn't valid UTF-8.
+
+use std::io;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RswcError {
+    /// The options or operands are wrong, like an unknown option or two
+    /// that conflict, or ask for something this build can't do.
+    #[error("{0}")]
+    Usage(String),
+    /// An input could not be read.
+    #[error("{message}")]
+    Io {
+        #[cfg_attr(feature = "serde", serde(with = "kind"))]
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// An input isn't valid in its encoding, with `--invalid-utf8=strict`.
+    #[error("{0}")]
+    Encoding(String),
+    /// An input was left out on purpose, like a binary file with
+    /// `--skip-binary`.
+    #[error("skipped {0}")]
+    Skipped(String),
+    /// An input wasn't counted because counting was interrupted, like by
+    /// Ctrl-C.
+    #[error("interrupted")]
+    Interrupted,
+}
+
+impl RswcError {
+    /// An `io::Error` carrying the error, for readers and counting functions
+    /// that return `io::Result`; converting it back gives the error again.
+    pub fn into_io(self) -> io::Error {
+        let kind = match &self {
+            RswcError::Io { kind, .. } => *kind,
+            RswcError::Encoding(_) => io::ErrorKind::InvalidData,
+            RswcError::Usage(_) | RswcError::Skipped(_) => io::ErrorKind::Other,
+            RswcError::Interrupted => io::ErrorKind::Interrupted,
+        };
+        io::Error::new(kind, self)
+    }
+
+    /// The kind of IO error, for errors reading an input.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            RswcError::Io { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RswcError {
+    fn from(e: io::Error) -> Self {
+        match e
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<RswcError>())
+        {
+            Some(inner) => inner.clone(),
+            None => RswcError::Io {
+                kind: e.kind(),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+/// `io::ErrorKind` by its name, like `NotFound`. Kinds this doesn't know
+/// come back as `Other`.
+#[cfg(feature = "serde")]
+mod kind {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::io::ErrorKind;
+
+    const KINDS: [ErrorKind; 15] = [
+        ErrorKind::NotFound,
+        ErrorKind::PermissionDenied,
+        ErrorKind::ConnectionRefused,
+        ErrorKind::ConnectionReset,
+        ErrorKind::AlreadyExists,
+        ErrorKind::WouldBlock,
+        ErrorKind::InvalidInput,
+        ErrorKind::InvalidData,
+        ErrorKind::TimedOut,
+        ErrorKind::WriteZero,
+        ErrorKind::Interrupted,
+        ErrorKind::Unsupported,
+        ErrorKind::UnexpectedEof,
+        ErrorKind::OutOfMemory,
+        ErrorKind::IsADirectory,
+    ];
+
+    pub fn serialize<S: Serializer>(kind: &ErrorKind, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", kind))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ErrorKind, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(KINDS
+            .into_iter()
+            .find(|kind| format!("{:?}", kind) == name)
+            .unwrap_or(ErrorKind::Other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_io_error() {
+        let e = RswcError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(e.io_kind(), Some(io::ErrorKind::NotFound));
+
+        let encoding = RswcError::Encoding("invalid UTF-8 at byte 3".to_string());
+        let io = encoding.clone().into_io();
+        assert_eq!(io.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(io.to_string(), "invalid UTF-8 at byte 3");
+        assert_eq!(RswcError::from(io), encoding);
+
+        let io = RswcError::Interrupted.into_io();
+        assert_eq!(io.kind(), io::ErrorKind::Interrupted);
+        assert_eq!(RswcError::from(io), RswcError::Interrupted);
+    }
+}
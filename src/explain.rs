@@ -0,0 +1,191 @@
+//! `rswc explain FILE`: what rswc detected about a file and how it counted
+//! it, for when its numbers differ from another tool's.
+
+use crate::counter::{Annotations, CountOptions, Counts, FileResult, process_files};
+use crate::eol::{EolCounter, LineEnds};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Encoding, line ends and the binary heuristic look at this much of the
+/// start of the file.
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// What the start of a file looks like.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Inspection {
+    pub bom: Option<&'static str>,
+    pub encoding: &'static str,
+    pub line_ends: LineEnds,
+    /// A NUL byte was found, the heuristic git and grep use.
+    pub binary: bool,
+}
+
+pub fn inspect(sample: &[u8]) -> Inspection {
+    let bom = if sample.starts_with(b"\xEF\xBB\xBF") {
+        Some("UTF-8")
+    } else if sample.starts_with(b"\xFF\xFE") {
+        Some("UTF-16LE")
+    } else if sample.starts_with(b"\xFE\xFF") {
+        Some("UTF-16BE")
+    } else {
+        None
+    };
+
+    let encoding = match bom {
+        Some(encoding) => encoding,
+        None if sample.is_ascii() => "ASCII",
+        None => match std::str::from_utf8(sample) {
+            Ok(_) => "UTF-8",
+            // The sample may end in the middle of a character.
+            Err(e) if e.error_len().is_none() => "UTF-8",
+            Err(_) => "unknown (not UTF-8)",
+        },
+    };
+
+    let mut line_ends = EolCounter::default();
+    line_ends.feed(sample);
+
+    Inspection {
+        bom,
+        encoding,
+        line_ends: line_ends.finish(),
+        binary: sample.contains(&0),
+    }
+}
+
+#[derive(Debug)]
+pub struct Explanation {
+    pub path: PathBuf,
+    pub size: u64,
+    pub inspection: Inspection,
+    pub counts: Counts,
+}
+
+pub fn explain(path: &Path) -> io::Result<Explanation> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut sample = Vec::new();
+    file.take(SAMPLE_SIZE).read_to_end(&mut sample)?;
+
+    let flags = CountOptions {
+        lines: true,
+        words: true,
+        bytes: true,
+        chars: true,
+        ..Default::default()
+    };
+    let counts = match process_files(&[path.to_path_buf()], &flags).pop() {
+        Some(FileResult::Ok(_, counts)) => counts,
+        Some(FileResult::Err(_, e)) => return Err(e.into_io()),
+        _ => unreachable!("one input gives one plain result"),
+    };
+
+    Ok(Explanation {
+        path: path.to_path_buf(),
+        size,
+        inspection: inspect(&sample),
+        counts,
+    })
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let i = &self.inspection;
+        let c = &self.counts;
+        let sampled = if self.size > SAMPLE_SIZE {
+            format!(" (first {} KiB)", SAMPLE_SIZE / 1024)
+        } else {
+            String::new()
+        };
+
+        let mut path = vec![if c.annotations.contains(Annotations::MMAP) {
+            "mmap"
+        } else {
+            "buffered reads"
+        }];
+        if c.annotations.contains(Annotations::PARALLEL) {
+            path.push("parallel ranges");
+        }
+        path.push(if cfg!(feature = "simd") {
+            "simd"
+        } else {
+            "scalar"
+        });
+
+        writeln!(f, "file:       {}", self.path.display())?;
+        writeln!(f, "size:       {} bytes", self.size)?;
+        writeln!(f, "encoding:   {}{}", i.encoding, sampled)?;
+        writeln!(f, "bom:        {}", i.bom.unwrap_or("none"))?;
+        writeln!(f, "line ends:  {}{}", i.line_ends, sampled)?;
+        writeln!(
+            f,
+            "binary:     {}{}",
+            if i.binary { "yes" } else { "no" },
+            sampled
+        )?;
+        writeln!(f, "counted by: {}", path.join(", "))?;
+        writeln!(f, "lines:      {}", c.lines)?;
+        writeln!(f, "words:      {}", c.words)?;
+        writeln!(f, "bytes:      {}", c.bytes)?;
+        writeln!(f, "chars:      {}", c.chars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect() {
+        let text = inspect("héllo\r\nwörld\r\n".as_bytes());
+        assert_eq!(
+            text,
+            Inspection {
+                bom: None,
+                encoding: "UTF-8",
+                line_ends: LineEnds {
+                    crlf: 2,
+                    ..Default::default()
+                },
+                binary: false,
+            }
+        );
+        assert_eq!(text.line_ends.to_string(), "CRLF (2)");
+
+        let utf16 = inspect(b"\xFF\xFEa\x00\n\x00");
+        assert_eq!(utf16.bom, Some("UTF-16LE"));
+        assert!(utf16.binary);
+
+        let mixed = inspect(b"a\nb\r\nc\rd\n\xFF");
+        assert_eq!(mixed.encoding, "unknown (not UTF-8)");
+        assert_eq!(mixed.line_ends.to_string(), "mixed (LF 2, CRLF 1, CR 1)");
+    }
+
+    #[test]
+    fn test_explain_file() {
+        let explanation = explain(Path::new("testdata/test.txt")).unwrap();
+        let expected = format!(
+            "\
+file:       testdata/test.txt
+size:       342190 bytes
+encoding:   UTF-8 (first 64 KiB)
+bom:        UTF-8
+line ends:  CRLF (1170) (first 64 KiB)
+binary:     no (first 64 KiB)
+counted by: buffered reads, {}
+lines:      7145
+words:      58164
+bytes:      342190
+chars:      339292
+",
+            if cfg!(feature = "simd") {
+                "simd"
+            } else {
+                "scalar"
+            }
+        );
+        assert_eq!(explanation.to_string(), expected);
+    }
+}
@@ -0,0 +1,83 @@
+//! Totals per file extension for `--by-extension`, to see how much of a
+//! tree is Markdown and how much code.
+
+use crate::counter::{FileResult, total};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The row name of files without an extension.
+pub const NO_EXTENSION: &str = "(none)";
+
+/// The extension of `path` as a row name, like `.rs`, in lower case so that
+/// `.MD` and `.md` are counted together.
+fn extension(path: &Path) -> String {
+    match path.extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy().to_ascii_lowercase()),
+        None => NO_EXTENSION.to_string(),
+    }
+}
+
+/// A total row for each extension of `results`, ordered by name. Archive
+/// members count by their own extension; failed inputs count nowhere.
+pub fn extension_totals(results: &[FileResult]) -> Vec<FileResult> {
+    let mut groups: BTreeMap<String, Vec<&FileResult>> = BTreeMap::new();
+    add(&mut groups, results);
+    groups
+        .into_iter()
+        .map(|(ext, members)| FileResult::Ok(ext.into(), total(members)))
+        .collect()
+}
+
+fn add<'a>(groups: &mut BTreeMap<String, Vec<&'a FileResult>>, results: &'a [FileResult]) {
+    for r in results {
+        match r {
+            FileResult::Ok(path, _) => groups.entry(extension(path)).or_default().push(r),
+            FileResult::Archive(_, members) => add(groups, members),
+            FileResult::Err(..) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+    use std::io;
+
+    #[test]
+    fn test_extension_totals() {
+        let lines = |lines| Counts {
+            lines,
+            ..Default::default()
+        };
+        let results = [
+            FileResult::Ok("src/main.rs".into(), lines(10)),
+            FileResult::Ok("README.md".into(), lines(5)),
+            FileResult::Ok("src/lib.rs".into(), lines(3)),
+            FileResult::Err("gone.rs".into(), io::Error::other("gone").into()),
+            FileResult::Archive(
+                "docs.zip".into(),
+                vec![
+                    FileResult::Ok("guide.MD".into(), lines(4)),
+                    FileResult::Ok("LICENSE".into(), lines(2)),
+                ],
+            ),
+            FileResult::Ok(".gitignore".into(), lines(1)),
+        ];
+        let rows: Vec<(String, u64)> = extension_totals(&results)
+            .iter()
+            .map(|r| match r {
+                FileResult::Ok(name, c) => (name.display().to_string(), c.lines),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("(none)".to_string(), 3),
+                (".md".to_string(), 9),
+                (".rs".to_string(), 13)
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::lang::PACKS;
+use std::io::{self, Write};
+use std::thread;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Capability {
+    pub name: &'static str,
+    pub available: bool,
+    pub detail: String,
+}
+
+fn capability(name: &'static str, available: bool, detail: impl Into<String>) -> Capability {
+    Capability {
+        name,
+        available,
+        detail: detail.into(),
+    }
+}
+
+/// The optional capabilities of this build, combined with what the machine
+/// it runs on supports.
+pub fn capabilities() -> Vec<Capability> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    vec![
+        capability(
+            "parallel",
+            cfg!(feature = "parallel"),
+            format!("up to {} threads", threads),
+        ),
+        capability("mmap", cfg!(feature = "mmap"), ""),
+        capability("digest", cfg!(feature = "digest"), "sha256"),
+        capability("watch", cfg!(feature = "watch"), ""),
+        capability(
+            "archive",
+            cfg!(feature = "archive"),
+            "tar, gzip (.tar.gz, .tgz), zip (deflate)",
+        ),
+        capability("pattern", cfg!(feature = "pattern"), "regex"),
+        capability(
+            "tokens",
+            cfg!(feature = "tokens"),
+            "o200k_base, cl100k_base, p50k_base, r50k_base",
+        ),
+        capability(
+            "lang",
+            PACKS.len() > 1,
+            PACKS.iter().map(|p| p.code).collect::<Vec<_>>().join(", "),
+        ),
+        documents(),
+        capability("encoding", cfg!(feature = "encoding"), ""),
+        capability("notebook", cfg!(feature = "notebook"), "ipynb"),
+        capability("config-input", cfg!(feature = "config-input"), "yaml, toml"),
+        capability("remote", cfg!(feature = "remote"), "http, https (rustls)"),
+        capability("self-update", cfg!(feature = "self-update"), ""),
+        capability("tui", cfg!(feature = "tui"), ""),
+        capability(
+            "io_uring",
+            cfg!(all(feature = "uring", target_os = "linux")),
+            "--io-backend=uring",
+        ),
+        simd(),
+    ]
+}
+
+fn documents() -> Capability {
+    let formats: Vec<&str> = [
+        (cfg!(feature = "pdf"), "pdf"),
+        (cfg!(feature = "office"), "docx, odt"),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, formats)| enabled.then_some(formats))
+    .collect();
+    capability("documents", !formats.is_empty(), formats.join(", "))
+}
+
+/// The widest vector extension the CPU offers, which character counts use
+/// with the `simd` feature.
+// Elsewhere there is none to detect, and clippy sees through the `None`.
+#[cfg_attr(
+    not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")),
+    allow(clippy::unnecessary_literal_unwrap)
+)]
+fn simd() -> Capability {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    let level = if is_x86_feature_detected!("avx512bw") {
+        Some("avx512bw")
+    } else if is_x86_feature_detected!("avx2") {
+        Some("avx2")
+    } else if is_x86_feature_detected!("sse4.2") {
+        Some("sse4.2")
+    } else if is_x86_feature_detected!("sse2") {
+        Some("sse2")
+    } else {
+        None
+    };
+    #[cfg(target_arch = "aarch64")]
+    let level = std::arch::is_aarch64_feature_detected!("neon").then_some("neon");
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    let level: Option<&str> = None;
+
+    capability(
+        "simd",
+        cfg!(feature = "simd") && level.is_some(),
+        level.unwrap_or("none detected"),
+    )
+}
+
+pub fn print_capabilities<W: Write>(writer: &mut W, capabilities: &[Capability]) -> io::Result<()> {
+    for c in capabilities {
+        let state = if c.available { "yes" } else { "no" };
+        let line = format!("{:<12} {:<3} {}", c.name, state, c.detail);
+        writeln!(writer, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_capabilities_match_build() {
+        let caps = capabilities();
+        let find = |name| caps.iter().find(|c| c.name == name).unwrap();
+        assert_eq!(find("mmap").available, cfg!(feature = "mmap"));
+        assert_eq!(find("remote").available, cfg!(feature = "remote"));
+        assert_eq!(
+            find("io_uring").available,
+            cfg!(all(feature = "uring", target_os = "linux"))
+        );
+    }
+
+    #[test]
+    fn test_print_capabilities() {
+        let caps = vec![
+            capability("mmap", true, ""),
+            capability("remote", false, "http, https (rustls)"),
+        ];
+        let mut output = Cursor::new(Vec::new());
+        print_capabilities(&mut output, &caps).unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+        let expected = "\
+mmap         yes
+remote       no  http, https (rustls)
+";
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,135 @@
+//! Fields per record of delimiter-separated files for `--fields`, to spot
+//! ragged rows in CSV and TSV data.
+
+/// How many fields the records of an input have.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldCounts {
+    pub records: usize,
+    pub min: usize,
+    pub max: usize,
+    pub total: usize,
+}
+
+impl FieldCounts {
+    pub fn add(&mut self, fields: usize) {
+        if self.records == 0 {
+            self.min = fields;
+        }
+        self.records += 1;
+        self.min = self.min.min(fields);
+        self.max = self.max.max(fields);
+        self.total += fields;
+    }
+
+    pub fn merge(&mut self, other: &FieldCounts) {
+        if other.records == 0 {
+            return;
+        }
+        if self.records == 0 {
+            *self = *other;
+            return;
+        }
+        self.records += other.records;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.total += other.total;
+    }
+}
+
+/// Counts the fields of the records of input fed in chunks of any size.
+/// With `quotes`, as in CSV, delimiters and record ends between double
+/// quotes are part of a field; `""` inside quotes is an escaped quote and
+/// needs no special handling. Blank records are skipped.
+#[derive(Debug)]
+pub struct FieldCounter {
+    delimiter: u8,
+    quotes: bool,
+    record_delimiter: u8,
+    counts: FieldCounts,
+    fields: usize,
+    in_record: bool,
+    in_quotes: bool,
+}
+
+impl FieldCounter {
+    pub fn new(delimiter: u8, quotes: bool) -> Self {
+        FieldCounter {
+            delimiter,
+            quotes,
+            record_delimiter: b'\n',
+            counts: FieldCounts::default(),
+            fields: 1,
+            in_record: false,
+            in_quotes: false,
+        }
+    }
+
+    /// Ends records at `delimiter` instead of `\n`.
+    pub fn with_record_delimiter(mut self, delimiter: u8) -> Self {
+        self.record_delimiter = delimiter;
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.quotes && b == b'"' {
+                self.in_quotes = !self.in_quotes;
+                self.in_record = true;
+            } else if self.in_quotes {
+                continue;
+            } else if b == self.record_delimiter {
+                self.end_record();
+            } else if b == self.delimiter {
+                self.fields += 1;
+                self.in_record = true;
+            } else if b != b'\r' {
+                self.in_record = true;
+            }
+        }
+    }
+
+    fn end_record(&mut self) {
+        if self.in_record {
+            self.counts.add(self.fields);
+        }
+        self.fields = 1;
+        self.in_record = false;
+    }
+
+    pub fn finish(mut self) -> FieldCounts {
+        self.end_record();
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_in_any_chunking() {
+        let data = b"a,b,c\r\n\"x,y\",\"multi\nline\",\"\"\"q\"\"\"\n\n1,2\nlast,";
+        for size in 1..=data.len() {
+            let mut counter = FieldCounter::new(b',', true);
+            for chunk in data.chunks(size) {
+                counter.feed(chunk);
+            }
+            let expected = FieldCounts {
+                records: 4,
+                min: 2,
+                max: 3,
+                total: 10,
+            };
+            assert_eq!(counter.finish(), expected, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_fields_without_quotes() {
+        let mut counter = FieldCounter::new(b'\t', false);
+        counter.feed(b"a\t\"b\tc\nd\n");
+        let counts = counter.finish();
+        assert_eq!((counts.records, counts.min, counts.max), (2, 1, 3));
+    }
+}
@@ -0,0 +1,144 @@
+//! File names read from a list for `--files-from` and `--files0-from`,
+//! one at a time so that huge lists needn't be held in memory.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// The names of a list separated by newlines or NUL bytes. Empty names are
+/// skipped, and with newlines a `\r` before them is dropped.
+pub struct FileList<R> {
+    reader: R,
+    separator: u8,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> FileList<R> {
+    pub fn new(reader: R, separator: u8) -> Self {
+        FileList {
+            reader,
+            separator,
+            buf: Vec::new(),
+        }
+    }
+
+    /// The next `n` names at most, empty at the end of the list.
+    pub fn next_batch(&mut self, n: usize) -> io::Result<Vec<PathBuf>> {
+        self.by_ref().take(n).collect()
+    }
+}
+
+/// The list in the file at `path`, or on standard input if it is `-`.
+pub fn open(path: &Path, separator: u8) -> io::Result<FileList<Box<dyn BufRead + Send>>> {
+    let reader: Box<dyn BufRead + Send> = if path == Path::new("-") {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+    Ok(FileList::new(reader, separator))
+}
+
+impl<R: BufRead> Iterator for FileList<R> {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(self.separator, &mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+
+            let mut name = self.buf.as_slice();
+            if let Some(rest) = name.strip_suffix(&[self.separator]) {
+                name = rest;
+            }
+            if self.separator == b'\n'
+                && let Some(rest) = name.strip_suffix(b"\r")
+            {
+                name = rest;
+            }
+            if !name.is_empty() {
+                return Some(Ok(path_of(name)));
+            }
+        }
+    }
+}
+
+/// The names of a list read ahead on a thread of their own, while the names
+/// before them are counted. At most `size` names wait to be counted, so a
+/// list of millions of files never sits in memory at once.
+pub struct Queue {
+    names: Receiver<io::Result<PathBuf>>,
+}
+
+impl Queue {
+    pub fn new<R: BufRead + Send + 'static>(list: FileList<R>, size: usize) -> Self {
+        let (sender, names) = mpsc::sync_channel(size);
+        thread::spawn(move || {
+            for name in list {
+                // The receiver is gone once the names aren't wanted anymore.
+                if sender.send(name).is_err() {
+                    break;
+                }
+            }
+        });
+        Queue { names }
+    }
+
+    /// The next `n` names at most, empty at the end of the list.
+    pub fn next_batch(&mut self, n: usize) -> io::Result<Vec<PathBuf>> {
+        self.by_ref().take(n).collect()
+    }
+}
+
+impl Iterator for Queue {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.names.recv().ok()
+    }
+}
+
+#[cfg(unix)]
+fn path_of(name: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(name))
+}
+
+#[cfg(not(unix))]
+fn path_of(name: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_list() {
+        let list = FileList::new(&b"a.txt\r\n\nb c.txt\nlast"[..], b'\n');
+        let names: Vec<PathBuf> = list.map(Result::unwrap).collect();
+        assert_eq!(names, ["a.txt", "b c.txt", "last"].map(PathBuf::from));
+
+        let mut list = FileList::new(&b"x\ny\0\0z\0"[..], 0);
+        assert_eq!(list.next_batch(1).unwrap(), [PathBuf::from("x\ny")]);
+        assert_eq!(list.next_batch(5).unwrap(), [PathBuf::from("z")]);
+        assert!(list.next_batch(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_queue() {
+        let list = FileList::new(&b"a\nb\nc\nd\ne\n"[..], b'\n');
+        let mut queue = Queue::new(list, 2);
+        assert_eq!(
+            queue.next_batch(3).unwrap(),
+            ["a", "b", "c"].map(PathBuf::from)
+        );
+        assert_eq!(queue.next_batch(3).unwrap(), ["d", "e"].map(PathBuf::from));
+        assert!(queue.next_batch(3).unwrap().is_empty());
+    }
+}
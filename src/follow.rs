@@ -0,0 +1,249 @@
+//! `--follow`: the counts of files that keep being appended to, like logs,
+//! read the way `tail -f` reads them. Every poll counts the complete lines
+//! appended since the last one, so a line being written is counted once it
+//! ends, and starts over when the file is truncated or replaced.
+
+use crate::counter::{CountOptions, Counts, count_slice};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often followed files are read again, like the default of `tail -f`.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What `--follow` prints for a file that grew.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FollowMode {
+    /// The counts of the whole file so far.
+    #[default]
+    Totals,
+    /// The counts of what was appended since the last row.
+    Deltas,
+}
+
+impl FollowMode {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "total" => Ok(FollowMode::Totals),
+            "delta" => Ok(FollowMode::Deltas),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--follow' (expected total or delta)",
+                name
+            )),
+        }
+    }
+}
+
+/// Why a followed file is counted from its start again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// It is shorter than what was read of it.
+    Truncated,
+    /// Its path names another file, as after a log rotation.
+    Replaced,
+}
+
+impl fmt::Display for Restart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Restart::Truncated => write!(f, "file truncated"),
+            Restart::Replaced => write!(f, "file replaced, following the new file"),
+        }
+    }
+}
+
+/// What a poll found.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// The counts of the lines completed since the last poll, if any were.
+    pub appended: Option<Counts>,
+    /// Set when the counts started over.
+    pub restart: Option<Restart>,
+}
+
+/// A file followed by `--follow`, kept open between polls.
+#[derive(Debug)]
+pub struct Follower {
+    path: PathBuf,
+    file: Option<File>,
+    /// Bytes of the open file read so far.
+    offset: u64,
+    /// The bytes read after the last complete line, counted once it is.
+    partial: Vec<u8>,
+    counts: Counts,
+}
+
+impl Follower {
+    /// Follows `path` from its start; it is opened by the first `poll`.
+    pub fn new(path: &Path) -> Self {
+        Follower {
+            path: path.to_path_buf(),
+            file: None,
+            offset: 0,
+            partial: Vec::new(),
+            counts: Counts::zero(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The counts of the complete lines of the file read so far.
+    pub fn counts(&self) -> &Counts {
+        &self.counts
+    }
+
+    /// Counts the complete lines appended since the last poll, starting
+    /// over when the file was truncated or replaced. What was appended to a
+    /// replaced file before it was noticed is among the lines appended, but
+    /// not in the counts of the new file.
+    pub fn poll(&mut self, flags: &CountOptions) -> io::Result<Update> {
+        let mut update = Update::default();
+        let Some(file) = &self.file else {
+            self.file = Some(File::open(&self.path)?);
+            update.appended = self.read(flags)?;
+            return Ok(update);
+        };
+
+        let mut appended = None;
+        if self.replaced(file) {
+            // The last line of the old file won't be ended anymore.
+            appended = self.read(flags)?;
+            if !self.partial.is_empty() {
+                let last = count_slice(&std::mem::take(&mut self.partial), flags);
+                appended.get_or_insert_default().merge(&last);
+            }
+            update.restart = Some(Restart::Replaced);
+            self.file = Some(File::open(&self.path)?);
+            self.restart();
+        } else if file.metadata()?.len() < self.offset {
+            update.restart = Some(Restart::Truncated);
+            self.restart();
+        }
+        if let Some(new) = self.read(flags)? {
+            appended.get_or_insert_default().merge(&new);
+        }
+        update.appended = appended;
+        Ok(update)
+    }
+
+    /// Whether the path of the open `file` now names another file.
+    fn replaced(&self, file: &File) -> bool {
+        match (fs::metadata(&self.path), file.metadata()) {
+            (Ok(now), Ok(open)) => !same_file(&now, &open),
+            // Gone, as between the rename and the creation of a rotation:
+            // keep reading the old file until the new one is there.
+            _ => false,
+        }
+    }
+
+    fn restart(&mut self) {
+        self.offset = 0;
+        self.partial.clear();
+        self.counts = Counts::zero();
+    }
+
+    /// Reads the open file from `offset` to its end, and counts the complete
+    /// lines of what it read, if any.
+    fn read(&mut self, flags: &CountOptions) -> io::Result<Option<Counts>> {
+        let Some(file) = &mut self.file else {
+            return Ok(None);
+        };
+        file.seek(SeekFrom::Start(self.offset))?;
+        let read = file.read_to_end(&mut self.partial)?;
+        self.offset += read as u64;
+
+        let Some(end) = self.partial.iter().rposition(|&b| b == flags.delimiter()) else {
+            return Ok(None);
+        };
+        let rest = self.partial.split_off(end + 1);
+        let lines = std::mem::replace(&mut self.partial, rest);
+        let appended = count_slice(&lines, flags);
+        self.counts.merge(&appended);
+        Ok(Some(appended))
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+// std has no portable identity of files elsewhere, so only truncation is
+// noticed.
+#[cfg(not(unix))]
+fn same_file(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn lines_and_words() -> CountOptions {
+        CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_follow_counts_complete_lines() {
+        let dir = std::env::temp_dir().join(format!("rswc-follow-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        fs::write(&path, "one two\nthree").unwrap();
+        let flags = lines_and_words();
+
+        let mut follower = Follower::new(&path);
+        let update = follower.poll(&flags).unwrap();
+        let appended = update.appended.unwrap();
+        assert_eq!((appended.lines, appended.words), (1, 2));
+        assert_eq!(follower.poll(&flags).unwrap(), Update::default());
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b" four\nfive\n").unwrap();
+        let appended = follower.poll(&flags).unwrap().appended.unwrap();
+        assert_eq!((appended.lines, appended.words), (2, 3));
+        let counts = follower.counts();
+        assert_eq!((counts.lines, counts.words), (3, 5));
+
+        fs::write(&path, "six\n").unwrap();
+        let update = follower.poll(&flags).unwrap();
+        assert_eq!(update.restart, Some(Restart::Truncated));
+        assert_eq!(follower.counts().lines, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_rotation() {
+        let dir = std::env::temp_dir().join(format!("rswc-rotate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        fs::write(&path, "one\n").unwrap();
+        let flags = lines_and_words();
+
+        let mut follower = Follower::new(&path);
+        follower.poll(&flags).unwrap();
+        let mut old = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        old.write_all(b"two\nthree").unwrap();
+        fs::rename(&path, dir.join("app.log.1")).unwrap();
+        fs::write(&path, "four five\n").unwrap();
+
+        let update = follower.poll(&flags).unwrap();
+        assert_eq!(update.restart, Some(Restart::Replaced));
+        let appended = update.appended.unwrap();
+        assert_eq!((appended.lines, appended.words), (2, 4));
+        assert_eq!(follower.counts().lines, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+use crate::case::CaseFold;
+use crate::counter::{BUF_SIZE, FileResult, counted_inputs};
+use crate::scanner::WordRules;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+pub type Frequencies = HashMap<Vec<u8>, u64>;
+
+/// Counts how often each word occurs in input fed in chunks of any size,
+/// tokenized like the word counter with `rules`. With `fold_case` words are
+/// compared case-folded.
+#[derive(Debug)]
+pub struct FreqCounter {
+    freqs: Frequencies,
+    // The input after the last separator so far, a word maybe cut off.
+    pending: Vec<u8>,
+    rules: WordRules,
+    fold_case: Option<CaseFold>,
+}
+
+impl FreqCounter {
+    pub fn new(rules: WordRules, fold_case: Option<CaseFold>) -> Self {
+        FreqCounter {
+            freqs: Frequencies::new(),
+            pending: Vec::new(),
+            rules,
+            fold_case,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let rules = self.rules;
+        let Some(end) = chunk.iter().rposition(|&b| rules.is_separator(b)) else {
+            self.pending.extend_from_slice(chunk);
+            return;
+        };
+        if self.pending.is_empty() {
+            add_words(&mut self.freqs, rules.words(&chunk[..end]), self.fold_case);
+        } else {
+            self.pending.extend_from_slice(&chunk[..end]);
+            add_words(&mut self.freqs, rules.words(&self.pending), self.fold_case);
+            self.pending.clear();
+        }
+        self.pending.extend_from_slice(&chunk[end..]);
+    }
+
+    pub fn finish(mut self) -> Frequencies {
+        add_words(
+            &mut self.freqs,
+            self.rules.words(&self.pending),
+            self.fold_case,
+        );
+        self.freqs
+    }
+}
+
+/// Counts how often each word occurs in `reader`, tokenized like the word
+/// counter with `rules`. With `fold_case` words are compared case-folded.
+pub fn word_frequencies<R: Read>(
+    mut reader: R,
+    rules: WordRules,
+    fold_case: Option<CaseFold>,
+) -> io::Result<Frequencies> {
+    let mut counter = FreqCounter::new(rules, fold_case);
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(counter.finish()),
+            Ok(n) => counter.feed(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn add_words<'a>(
+    freqs: &mut Frequencies,
+    words: impl Iterator<Item = &'a [u8]>,
+    fold_case: Option<CaseFold>,
+) {
+    for word in words {
+        if let Some(fold) = fold_case {
+            *freqs.entry(fold.fold(word)).or_default() += 1;
+        } else if let Some(count) = freqs.get_mut(word) {
+            *count += 1;
+        } else {
+            freqs.insert(word.to_vec(), 1);
+        }
+    }
+}
+
+/// Adds the counts of `b` to `a`, copying only the words `a` doesn't have.
+pub fn merge(a: &mut Frequencies, b: &Frequencies) {
+    for (word, &count) in b {
+        if let Some(total) = a.get_mut(word) {
+            *total += count;
+        } else {
+            a.insert(word.clone(), count);
+        }
+    }
+}
+
+/// The combined word frequencies of every input counted with
+/// `CountOptions::freq`, archive members included, of the lines and bytes
+/// that were counted. Unreadable files are left out; their rows in the
+/// report already say what happened to them.
+pub fn frequencies_of(results: &[FileResult]) -> Frequencies {
+    counted_inputs(results)
+        .into_iter()
+        .filter_map(|(_, c)| c.frequencies.as_deref())
+        .fold(Frequencies::new(), |mut all, freqs| {
+            merge(&mut all, freqs);
+            all
+        })
+}
+
+/// The `n` most common words, most common first and ties in byte order.
+/// `n == 0` returns all of them.
+pub fn top(freqs: &Frequencies, n: usize) -> Vec<(&[u8], u64)> {
+    let mut words: Vec<(&[u8], u64)> = freqs.iter().map(|(w, &c)| (w.as_slice(), c)).collect();
+    words.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if n > 0 {
+        words.truncate(n);
+    }
+    words
+}
+
+pub fn print_frequencies<W: Write>(writer: &mut W, words: &[(&[u8], u64)]) -> io::Result<()> {
+    if words.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "word frequencies:")?;
+    for (word, count) in words {
+        writeln!(writer, "{:>7} {}", count, String::from_utf8_lossy(word))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Returns at most `size` bytes per read to exercise words split
+    /// across reads.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_word_frequencies_in_any_chunking() {
+        let data = b"the cat and the hat\nThe end";
+        for size in 1..=data.len() {
+            let freqs = word_frequencies(Trickle(data, size), WordRules::DEFAULT, None).unwrap();
+            assert_eq!(freqs[&b"the"[..]], 2, "chunk size {}", size);
+            assert_eq!(freqs[&b"The"[..]], 1, "chunk size {}", size);
+            assert_eq!(freqs.values().sum::<u64>(), 7, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_word_frequencies_fold_case() {
+        let freqs = word_frequencies(
+            "Über über ÜBER the".as_bytes(),
+            WordRules::DEFAULT,
+            Some(CaseFold::Default),
+        );
+        let freqs = freqs.unwrap();
+        assert_eq!(freqs["über".as_bytes()], 3);
+        assert_eq!(freqs.len(), 2);
+
+        let text = "Iğdır IĞDIR ılık ILIK İzmir izmir";
+        let freqs =
+            word_frequencies(text.as_bytes(), WordRules::DEFAULT, Some(CaseFold::Turkish)).unwrap();
+        assert_eq!(freqs["ığdır".as_bytes()], 2);
+        assert_eq!(freqs["ılık".as_bytes()], 2);
+        assert_eq!(freqs["izmir".as_bytes()], 2);
+    }
+
+    #[test]
+    fn test_merge_and_top() {
+        let a = word_frequencies(&b"b a c a"[..], WordRules::DEFAULT, None).unwrap();
+        let b = word_frequencies(&b"b b d"[..], WordRules::DEFAULT, None).unwrap();
+        let mut merged = a;
+        merge(&mut merged, &b);
+
+        let expected: Vec<(&[u8], u64)> = vec![(b"b", 3), (b"a", 2), (b"c", 1)];
+        assert_eq!(top(&merged, 3), expected);
+        assert_eq!(top(&merged, 0).len(), 4);
+    }
+
+    #[test]
+    fn test_print_frequencies() {
+        let words: Vec<(&[u8], u64)> = vec![(b"the", 120), (b"of", 8)];
+        let mut output = Cursor::new(Vec::new());
+        print_frequencies(&mut output, &words).unwrap();
+        let actual = String::from_utf8(output.into_inner()).unwrap();
+        let expected = "
+word frequencies:
+    120 the
+      8 of
+";
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,109 @@
+//! Lines per value of a capture group for `--extract`, a small group-by
+//! report like `awk '{print $9}' | sort | uniq -c` of an access log.
+
+use crate::counter::{FileResult, total};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// The number of lines of each value of a capture group.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "Vec<(Vec<u8>, usize)>", into = "Vec<(Vec<u8>, usize)>")
+)]
+pub struct Groups(HashMap<Vec<u8>, usize>);
+
+impl Groups {
+    pub fn add(&mut self, value: &[u8]) {
+        *self.0.entry(value.to_vec()).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &Groups) {
+        for (value, lines) in &other.0 {
+            *self.0.entry(value.clone()).or_default() += lines;
+        }
+    }
+
+    /// The values with their lines, most lines first, ties by value.
+    pub fn sorted(&self) -> Vec<(&[u8], usize)> {
+        let mut groups: Vec<(&[u8], usize)> =
+            self.0.iter().map(|(v, &n)| (v.as_slice(), n)).collect();
+        groups.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        groups
+    }
+}
+
+/// Serialized as pairs of a value and its lines, ordered by value.
+#[cfg(feature = "serde")]
+impl From<Groups> for Vec<(Vec<u8>, usize)> {
+    fn from(groups: Groups) -> Self {
+        let mut pairs: Vec<_> = groups.0.into_iter().collect();
+        pairs.sort();
+        pairs
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Vec<(Vec<u8>, usize)>> for Groups {
+    fn from(pairs: Vec<(Vec<u8>, usize)>) -> Self {
+        Groups(pairs.into_iter().collect())
+    }
+}
+
+/// Prints the lines per value of capture group `group` of all `results`
+/// together; group 0 is the whole match.
+pub fn print_groups<W: Write>(
+    writer: &mut W,
+    results: &[FileResult],
+    group: usize,
+) -> io::Result<()> {
+    let Some(groups) = total(results).groups else {
+        return Ok(());
+    };
+    let groups = groups.sorted();
+    if groups.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    match group {
+        0 => writeln!(writer, "lines by match:")?,
+        n => writeln!(writer, "lines by capture group {}:", n)?,
+    }
+    for (value, lines) in groups {
+        writeln!(writer, "{:>7} {}", lines, String::from_utf8_lossy(value))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+
+    #[test]
+    fn test_print_groups() {
+        let file = |values: &[&str]| {
+            let mut groups = Groups::default();
+            for v in values {
+                groups.add(v.as_bytes());
+            }
+            FileResult::Ok(
+                "access.log".into(),
+                Counts {
+                    groups: Some(Box::new(groups)),
+                    ..Default::default()
+                },
+            )
+        };
+        let results = [file(&["200", "404", "200"]), file(&["500", "404", "200"])];
+
+        let mut out = Vec::new();
+        print_groups(&mut out, &results, 1).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\nlines by capture group 1:\n      3 200\n      2 404\n      1 500\n"
+        );
+    }
+}
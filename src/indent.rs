@@ -0,0 +1,209 @@
+//! Indentation for `--indent-stats`: how many lines are indented with tabs
+//! and how many with spaces, and the usual indent width, for formatting
+//! audits of a tree.
+
+use crate::counter::{FileResult, counted_inputs, total};
+use std::io::{self, Write};
+
+/// Indent steps counted for the usual width, in spaces; wider ones are
+/// left out.
+const MAX_STEP: usize = 16;
+
+/// How the lines of an input are indented. Blank lines are left out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Indents {
+    /// Lines indented with tabs only.
+    pub tabs: usize,
+    /// Lines indented with spaces only.
+    pub spaces: usize,
+    /// Lines indented with both.
+    pub mixed: usize,
+    /// How often the indentation of a line indented with spaces grows by
+    /// each number of spaces over the line before, by that number.
+    pub steps: [usize; MAX_STEP + 1],
+}
+
+impl Indents {
+    pub fn merge(&mut self, other: &Indents) {
+        self.tabs += other.tabs;
+        self.spaces += other.spaces;
+        self.mixed += other.mixed;
+        for (step, n) in self.steps.iter_mut().zip(other.steps) {
+            *step += n;
+        }
+    }
+
+    /// The number of spaces the indentation most often grows by, the
+    /// smallest of those tied, if any line is indented with spaces.
+    pub fn width(&self) -> Option<usize> {
+        let (width, &n) = self
+            .steps
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|&(_, n)| n)?;
+        (n > 0).then_some(width)
+    }
+}
+
+/// Classifies the indentation of input fed in chunks of any size.
+#[derive(Debug)]
+pub struct IndentCounter {
+    indents: Indents,
+    /// Whether the bytes read since the last line end are all indentation.
+    in_indent: bool,
+    tabs: usize,
+    spaces: usize,
+    /// The spaces indenting the last line that wasn't blank, `None` if it
+    /// was indented with tabs.
+    last_width: Option<usize>,
+}
+
+impl Default for IndentCounter {
+    fn default() -> Self {
+        IndentCounter {
+            indents: Indents::default(),
+            in_indent: true,
+            tabs: 0,
+            spaces: 0,
+            last_width: Some(0),
+        }
+    }
+}
+
+impl IndentCounter {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if b == b'\n' {
+                self.in_indent = true;
+                self.tabs = 0;
+                self.spaces = 0;
+                continue;
+            }
+            if !self.in_indent {
+                continue;
+            }
+            match b {
+                b' ' => self.spaces += 1,
+                b'\t' => self.tabs += 1,
+                // The CR of a CRLF ending a blank line.
+                b'\r' => {}
+                _ => self.line_start(),
+            }
+        }
+    }
+
+    /// The first byte of a line that isn't indentation.
+    fn line_start(&mut self) {
+        self.in_indent = false;
+        match (self.tabs, self.spaces) {
+            (0, 0) => self.last_width = Some(0),
+            (0, width) => {
+                self.indents.spaces += 1;
+                if let Some(last) = self.last_width
+                    && width > last
+                    && let Some(n) = self.indents.steps.get_mut(width - last)
+                {
+                    *n += 1;
+                }
+                self.last_width = Some(width);
+            }
+            (_, 0) => {
+                self.indents.tabs += 1;
+                self.last_width = None;
+            }
+            _ => {
+                self.indents.mixed += 1;
+                self.last_width = None;
+            }
+        }
+    }
+
+    pub fn finish(self) -> Indents {
+        self.indents
+    }
+}
+
+/// Prints the indentation of every counted input, archive members
+/// included, followed by their total when there is more than one.
+pub fn print_indents<W: Write>(writer: &mut W, results: &[FileResult]) -> io::Result<()> {
+    let rows: Vec<(String, Indents)> = counted_inputs(results)
+        .into_iter()
+        .map(|(name, c)| (name, c.indents.as_deref().copied().unwrap_or_default()))
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "indentation (tabs, spaces, mixed, width):")?;
+    for (name, indents) in &rows {
+        write_indents(writer, indents, name)?;
+    }
+    if rows.len() > 1 {
+        let total = total(results)
+            .indents
+            .as_deref()
+            .copied()
+            .unwrap_or_default();
+        write_indents(writer, &total, "total")?;
+    }
+    Ok(())
+}
+
+fn write_indents<W: Write>(writer: &mut W, indents: &Indents, name: &str) -> io::Result<()> {
+    let width = indents
+        .width()
+        .map_or_else(|| "-".to_string(), |w| w.to_string());
+    writeln!(
+        writer,
+        "{:>7} {:>7} {:>7} {:>7} {}",
+        indents.tabs, indents.spaces, indents.mixed, width, name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, count_slice};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_indents_in_any_chunking() {
+        let data = b"fn a() {\n    if x {\n        y\n\n    }\n}\n\tz\n \tw\r\n    \r\nv\n";
+        for size in 1..=data.len() {
+            let mut counter = IndentCounter::default();
+            for chunk in data.chunks(size) {
+                counter.feed(chunk);
+            }
+            let indents = counter.finish();
+            assert_eq!(
+                (indents.tabs, indents.spaces, indents.mixed),
+                (1, 3, 1),
+                "chunk size {}",
+                size
+            );
+            assert_eq!(indents.width(), Some(4), "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_print_indents() {
+        let flags = CountOptions::new().indent_stats(true);
+        let results = vec![
+            FileResult::Ok(PathBuf::from("go"), count_slice(b"a\n\tb\n\t\tc\n", &flags)),
+            FileResult::Ok(PathBuf::from("js"), count_slice(b"a\n  b\n    c\n", &flags)),
+        ];
+
+        let mut output = Vec::new();
+        print_indents(&mut output, &results).unwrap();
+        let expected = "
+indentation (tabs, spaces, mixed, width):
+      2       0       0       - go
+      0       2       0       2 js
+      2       2       0       2 total
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -0,0 +1,395 @@
+//! Language packs for `--lang`: per-language abbreviations for sentence
+//! detection and word segmentation rules. English is always built in;
+//! the other packs are compiled in with their `lang-*` features.
+
+use crate::scanner::WordRules;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// How sentences and words of one language are told apart.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LangPack {
+    /// ISO 639-1 code, as given to `--lang`.
+    pub code: &'static str,
+    /// Lowercased words that don't end a sentence when followed by a period.
+    pub abbreviations: &'static [&'static [u8]],
+    /// Lowercased elided words that count as a word of their own before an
+    /// apostrophe, like `l'` in `l'homme`.
+    pub elisions: &'static [&'static [u8]],
+    /// Every CJK ideograph and kana is a word, and `。`, `！` and `？` end
+    /// sentences without whitespace after them.
+    pub ideographic: bool,
+    /// Frequent short words, used to detect the language of an input.
+    stopwords: &'static [&'static [u8]],
+}
+
+pub static ENGLISH: LangPack = LangPack {
+    code: "en",
+    abbreviations: &[
+        b"mr", b"mrs", b"ms", b"dr", b"prof", b"sr", b"jr", b"st", b"vs", b"etc", b"e.g", b"i.e",
+        b"cf", b"fig", b"no", b"approx",
+    ],
+    elisions: &[],
+    ideographic: false,
+    stopwords: &[
+        b"the", b"and", b"of", b"to", b"is", b"in", b"that", b"it", b"was", b"for",
+    ],
+};
+
+#[cfg(feature = "lang-de")]
+pub static GERMAN: LangPack = LangPack {
+    code: "de",
+    abbreviations: &[
+        b"z.b", b"bzw", b"usw", b"vgl", b"evtl", b"ggf", b"inkl", b"ca", b"nr", b"str", b"u.a",
+        b"d.h", b"s.o", b"s.u", b"dr", b"prof", b"hr", b"fr", b"abs", b"bd",
+    ],
+    elisions: &[],
+    ideographic: false,
+    stopwords: &[
+        b"der", b"die", b"und", b"das", b"ist", b"nicht", b"den", b"mit", b"ein", b"zu",
+    ],
+};
+
+#[cfg(feature = "lang-fr")]
+pub static FRENCH: LangPack = LangPack {
+    code: "fr",
+    abbreviations: &[
+        b"mme", b"mlle", b"mm", b"dr", b"pr", b"etc", b"cf", b"p.ex", b"env", b"av", b"bd", b"st",
+        b"ste", b"vol", b"chap", b"n",
+    ],
+    elisions: &[
+        b"l", b"d", b"j", b"m", b"n", b"s", b"t", b"c", b"qu", b"jusqu", b"lorsqu", b"puisqu",
+    ],
+    ideographic: false,
+    stopwords: &[
+        b"le", b"la", b"les", b"et", b"est", b"des", b"une", b"un", b"du", b"que",
+    ],
+};
+
+#[cfg(feature = "lang-ja")]
+pub static JAPANESE: LangPack = LangPack {
+    code: "ja",
+    abbreviations: &[],
+    elisions: &[],
+    ideographic: true,
+    stopwords: &[],
+};
+
+/// The packs compiled into this build.
+pub static PACKS: &[&LangPack] = &[
+    &ENGLISH,
+    #[cfg(feature = "lang-de")]
+    &GERMAN,
+    #[cfg(feature = "lang-fr")]
+    &FRENCH,
+    #[cfg(feature = "lang-ja")]
+    &JAPANESE,
+];
+
+/// The pack for language `code`.
+pub fn lang_pack(code: &str) -> Result<&'static LangPack, String> {
+    PACKS
+        .iter()
+        .copied()
+        .find(|p| p.code == code)
+        .ok_or_else(|| {
+            let codes: Vec<_> = PACKS.iter().map(|p| p.code).collect();
+            format!(
+                "rswc: no language pack for '{}' in this build (available: {}, auto)",
+                code,
+                codes.join(", ")
+            )
+        })
+}
+
+/// `CountOptions::lang` as the code of the pack, for serde.
+#[cfg(feature = "serde")]
+pub(crate) mod by_code {
+    use super::{LangPack, lang_pack};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        pack: &Option<&'static LangPack>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        pack.map(|p| p.code).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<&'static LangPack>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|code| lang_pack(&code).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Bytes looked at to detect the language of a file.
+pub(crate) const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// The language of `sample`: Japanese if a tenth of its letters are kana
+/// or ideographs, otherwise the pack whose stopwords occur most often,
+/// and English if none do.
+pub fn detect(sample: &[u8]) -> &'static LangPack {
+    let text = String::from_utf8_lossy(sample);
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    let ideographs = text.chars().filter(|&c| is_ideographic(c)).count();
+    if let Some(pack) = PACKS.iter().find(|p| p.ideographic)
+        && ideographs > 0
+        && ideographs * 10 >= letters
+    {
+        return pack;
+    }
+
+    let mut scores = vec![0; PACKS.len()];
+    for word in WordRules::DEFAULT.words(sample) {
+        let word = word.to_ascii_lowercase();
+        for (score, pack) in scores.iter_mut().zip(PACKS) {
+            if pack.stopwords.contains(&word.as_slice()) {
+                *score += 1;
+            }
+        }
+    }
+    // The first pack wins ties, so English is the fallback.
+    let best = scores
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|&(_, score)| score)
+        .map_or(0, |(i, _)| i);
+    PACKS[best]
+}
+
+/// The language of the start of the file at `path`. The file is read on
+/// its own, so it should be a regular file: the start of a pipe read here
+/// is gone for whatever reads it next.
+pub fn detect_file(path: &Path) -> io::Result<&'static LangPack> {
+    let mut sample = Vec::new();
+    File::open(path)?
+        .take(SAMPLE_SIZE)
+        .read_to_end(&mut sample)?;
+    Ok(detect(&sample))
+}
+
+/// CJK ideographs, hiragana and katakana, which are written without spaces
+/// between words.
+pub fn is_ideographic(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // hiragana, katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{FF66}'..='\u{FF9D}' // halfwidth katakana
+    )
+}
+
+/// Counts the words a language pack adds to those split by `WordRules`, for
+/// input fed in chunks of any size: elided words before an apostrophe, and
+/// every ideograph of a token that mixes them with other text.
+pub struct LangWords {
+    pack: &'static LangPack,
+    rules: WordRules,
+    // The lowercased start of the current token, enough for any elision.
+    head: [u8; LangWords::HEAD_LEN],
+    head_len: usize,
+    in_token: bool,
+    // A UTF-8 sequence cut off at the end of the last chunk.
+    partial: [u8; 4],
+    partial_len: usize,
+    // Words of the current token under `rules`, and under the pack.
+    words: usize,
+    in_word: bool,
+    ideographs: usize,
+    runs: usize,
+    in_run: bool,
+    extra: u64,
+}
+
+impl LangWords {
+    const HEAD_LEN: usize = 10;
+
+    pub fn new(pack: &'static LangPack, rules: WordRules) -> Self {
+        LangWords {
+            pack,
+            rules,
+            head: [0; LangWords::HEAD_LEN],
+            head_len: 0,
+            in_token: false,
+            partial: [0; 4],
+            partial_len: 0,
+            words: 0,
+            in_word: false,
+            ideographs: 0,
+            runs: 0,
+            in_run: false,
+            extra: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if b.is_ascii_whitespace() {
+                self.end_token();
+                continue;
+            }
+
+            self.in_token = true;
+            if self.head_len < LangWords::HEAD_LEN {
+                self.head[self.head_len] = b.to_ascii_lowercase();
+                self.head_len += 1;
+            }
+            if self.rules.is_separator(b) {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.words += 1;
+                self.in_word = true;
+            }
+            if self.pack.ideographic {
+                self.feed_char_byte(b);
+            }
+        }
+    }
+
+    fn feed_char_byte(&mut self, b: u8) {
+        if b < 0x80 {
+            self.partial_len = 0;
+            self.char(Some(char::from(b)));
+            return;
+        }
+        if b >= 0xC0 {
+            self.partial_len = 0;
+        }
+        if self.partial_len < 4 {
+            self.partial[self.partial_len] = b;
+            self.partial_len += 1;
+        }
+        let width = match self.partial[0] {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        if self.partial_len == width {
+            let c = std::str::from_utf8(&self.partial[..width])
+                .ok()
+                .and_then(|s| s.chars().next());
+            self.partial_len = 0;
+            self.char(c);
+        }
+    }
+
+    /// Ideographs are words of their own, runs of other letters and digits
+    /// are one word, and punctuation only separates them.
+    fn char(&mut self, c: Option<char>) {
+        match c {
+            Some(c) if is_ideographic(c) => {
+                self.ideographs += 1;
+                self.in_run = false;
+            }
+            Some(c) if c.is_alphanumeric() => {
+                if !self.in_run {
+                    self.runs += 1;
+                    self.in_run = true;
+                }
+            }
+            _ => self.in_run = false,
+        }
+    }
+
+    fn end_token(&mut self) {
+        if !self.in_token {
+            return;
+        }
+
+        if self.ideographs > 0 {
+            self.extra += (self.ideographs + self.runs).saturating_sub(self.words) as u64;
+        }
+        if self.elided() {
+            self.extra += 1;
+        }
+
+        self.head_len = 0;
+        self.in_token = false;
+        self.partial_len = 0;
+        self.words = 0;
+        self.in_word = false;
+        self.ideographs = 0;
+        self.runs = 0;
+        self.in_run = false;
+    }
+
+    /// Whether the token starts with an elision, an apostrophe and more.
+    fn elided(&self) -> bool {
+        let head = &self.head[..self.head_len];
+        self.pack.elisions.iter().any(|e| {
+            let Some(rest) = head.strip_prefix(*e) else {
+                return false;
+            };
+            match rest {
+                // An ASCII apostrophe splits the word already if the rules
+                // say so.
+                [b'\'', _, ..] => !self.rules.split_apostrophes,
+                [0xE2, 0x80, 0x99, _, ..] => true,
+                _ => false,
+            }
+        })
+    }
+
+    /// The number of words to add to the word count.
+    pub fn finish(mut self) -> u64 {
+        self.end_token();
+        self.extra
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lang_pack() {
+        assert_eq!(lang_pack("en"), Ok(&ENGLISH));
+        assert!(lang_pack("xx").unwrap_err().contains("available: en"));
+    }
+
+    #[test]
+    fn test_detect_defaults_to_english() {
+        assert_eq!(detect(b"The cat and the hat"), &ENGLISH);
+        assert_eq!(detect(b"12 34 56"), &ENGLISH);
+        assert_eq!(detect(b""), &ENGLISH);
+    }
+
+    #[cfg(all(feature = "lang-de", feature = "lang-fr", feature = "lang-ja"))]
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect("Der Hund und die Katze sind nicht da".as_bytes()),
+            &GERMAN
+        );
+        assert_eq!(detect("Le chat et la souris est là".as_bytes()), &FRENCH);
+        assert_eq!(detect("これはペンです。".as_bytes()), &JAPANESE);
+    }
+
+    #[cfg(all(feature = "lang-fr", feature = "lang-ja"))]
+    #[test]
+    fn test_lang_words_in_any_chunking() {
+        let count = |pack, data: &[u8]| {
+            (1..=data.len())
+                .map(|size| {
+                    let mut words = LangWords::new(pack, WordRules::DEFAULT);
+                    for chunk in data.chunks(size) {
+                        words.feed(chunk);
+                    }
+                    words.finish()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let french = "L'homme qu’il voit aujourd'hui".as_bytes();
+        assert!(count(&FRENCH, french).iter().all(|&n| n == 2));
+        let japanese = "これはペン です。Rust版".as_bytes();
+        assert!(count(&JAPANESE, japanese).iter().all(|&n| n == 7));
+    }
+}
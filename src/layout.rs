@@ -0,0 +1,535 @@
+use std::fmt;
+
+pub const DEFAULT_MIN_WIDTH: usize = 7;
+
+/// Formatting knobs shared by the textual column printers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutOptions {
+    /// Minimum width of every numeric column.
+    pub min_width: usize,
+    /// Character used to right-align numbers within their column.
+    pub pad: char,
+    /// Written after every column, including the last one before the name.
+    pub separator: String,
+    /// Written between groups of three digits, like `,` in `1,234,567`.
+    pub group_separator: Option<char>,
+    /// Colors totals and errors with ANSI escapes, for `--color`.
+    pub color: bool,
+    /// Colors the largest value of every column as well.
+    pub color_largest: bool,
+    /// Pads every value to the width of its column.
+    pub align: bool,
+    /// Written at the end of every row.
+    pub terminator: char,
+}
+
+/// What a row or a value is, for `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Total,
+    Error,
+    Largest,
+}
+
+impl Style {
+    /// The escape that starts the style: bold totals, red errors and bold
+    /// yellow largest values.
+    fn escape(self) -> &'static str {
+        match self {
+            Style::Total => "\x1b[1m",
+            Style::Error => "\x1b[31m",
+            Style::Largest => "\x1b[1;33m",
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+
+/// When to color the table, the argument of `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--color' (expected auto, always or never)",
+                name
+            )),
+        }
+    }
+
+    /// Whether to color output to a `terminal` or not: `Auto` colors
+    /// terminals unless `$NO_COLOR` is set to anything.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            min_width: DEFAULT_MIN_WIDTH,
+            pad: ' ',
+            separator: " ".to_string(),
+            group_separator: None,
+            color: false,
+            color_largest: false,
+            align: true,
+            terminator: '\n',
+        }
+    }
+}
+
+/// Column widths measured over every row that is going to be printed, so
+/// per-file rows and the total row line up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    options: LayoutOptions,
+    widths: Vec<usize>,
+    /// The largest value of every column, totals left out.
+    largest: Vec<u64>,
+}
+
+impl Layout {
+    pub fn new(options: LayoutOptions) -> Self {
+        Layout {
+            options,
+            widths: Vec::new(),
+            largest: Vec::new(),
+        }
+    }
+
+    /// Widens the columns as needed to fit `row`.
+    pub fn measure(&mut self, row: &[u64]) {
+        self.measure_total(row);
+        if self.largest.len() < row.len() {
+            self.largest.resize(row.len(), 0);
+        }
+        for (largest, value) in self.largest.iter_mut().zip(row) {
+            *largest = (*largest).max(*value);
+        }
+    }
+
+    /// Widens the columns as needed to fit the total or subtotal `row`,
+    /// whose values aren't the largest of an input.
+    pub fn measure_total(&mut self, row: &[u64]) {
+        if self.widths.len() < row.len() {
+            self.widths.resize(row.len(), self.options.min_width);
+        }
+        for (width, value) in self.widths.iter_mut().zip(row) {
+            *width = (*width).max(self.options.width(*value));
+        }
+    }
+
+    /// Widens the columns as needed to fit the header `names`.
+    pub fn measure_names(&mut self, names: &[&str]) {
+        if self.widths.len() < names.len() {
+            self.widths.resize(names.len(), self.options.min_width);
+        }
+        for (width, name) in self.widths.iter_mut().zip(names) {
+            *width = (*width).max(name.chars().count());
+        }
+    }
+
+    pub fn widths(&self) -> &[usize] {
+        &self.widths
+    }
+
+    pub fn write_fields<W: fmt::Write>(&self, writer: &mut W, row: &[u64]) -> fmt::Result {
+        self.write_values(writer, row, self.options.color_largest)
+    }
+
+    fn write_values<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        row: &[u64],
+        color_largest: bool,
+    ) -> fmt::Result {
+        for (i, value) in row.iter().enumerate() {
+            let width = self
+                .widths
+                .get(i)
+                .copied()
+                .unwrap_or(self.options.min_width);
+            if self.options.align {
+                for _ in self.options.width(*value)..width {
+                    writer.write_char(self.options.pad)?;
+                }
+            }
+            let largest = color_largest
+                && self.options.color
+                && *value > 0
+                && self.largest.get(i) == Some(value);
+            if largest {
+                writer.write_str(Style::Largest.escape())?;
+            }
+            match self.options.group_separator {
+                Some(separator) => write!(writer, "{}", group_digits(*value, separator))?,
+                None => write!(writer, "{}", value)?,
+            }
+            if largest {
+                writer.write_str(RESET)?;
+            }
+            writer.write_str(&self.options.separator)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the header `names` right-aligned over their columns, and
+    /// `last` over the input names.
+    pub fn write_names<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        names: &[&str],
+        last: &str,
+    ) -> fmt::Result {
+        for (i, name) in names.iter().enumerate() {
+            let width = self
+                .widths
+                .get(i)
+                .copied()
+                .unwrap_or(self.options.min_width);
+            if self.options.align {
+                for _ in name.chars().count()..width {
+                    writer.write_char(' ')?;
+                }
+            }
+            writer.write_str(name)?;
+            writer.write_str(&self.options.separator)?;
+        }
+        write!(writer, "{}{}", last, self.options.terminator)
+    }
+
+    pub fn write_row<W: fmt::Write, N: fmt::Display>(
+        &self,
+        writer: &mut W,
+        row: &[u64],
+        name: N,
+    ) -> fmt::Result {
+        self.write_fields(writer, row)?;
+        write!(writer, "{}{}", name, self.options.terminator)
+    }
+
+    /// Writes a total or subtotal row, in the `Total` style.
+    pub fn write_total<W: fmt::Write, N: fmt::Display>(
+        &self,
+        writer: &mut W,
+        row: &[u64],
+        name: N,
+    ) -> fmt::Result {
+        self.write_styled(writer, Style::Total, |writer| {
+            self.write_values(writer, row, false)?;
+            write!(writer, "{}", name)
+        })
+    }
+
+    /// Writes the line of a failed input, in the `Error` style.
+    pub fn write_error<W: fmt::Write, L: fmt::Display>(
+        &self,
+        writer: &mut W,
+        line: L,
+    ) -> fmt::Result {
+        self.write_styled(writer, Style::Error, |writer| write!(writer, "{}", line))
+    }
+
+    /// Writes a line by `write`, in `style` if colors are on. The newline
+    /// is left out of the style, so a pager showing part of it doesn't
+    /// carry the color over.
+    fn write_styled<W, F>(&self, writer: &mut W, style: Style, write: F) -> fmt::Result
+    where
+        W: fmt::Write,
+        F: FnOnce(&mut W) -> fmt::Result,
+    {
+        if self.options.color {
+            writer.write_str(style.escape())?;
+            write(writer)?;
+            writer.write_str(RESET)?;
+        } else {
+            write(writer)?;
+        }
+        writer.write_char(self.options.terminator)
+    }
+}
+
+impl LayoutOptions {
+    /// The characters `value` takes up, group separators included.
+    fn width(&self, value: u64) -> usize {
+        let n = digits(value);
+        match self.group_separator {
+            Some(_) => n + (n - 1) / 3,
+            None => n,
+        }
+    }
+
+    /// Ends every column with a tab, for `--format=aligned-tsv`: each field
+    /// is `cut -f` away, and since a column pads its values to one width, the
+    /// tab after it stops at the same place in every row of a terminal.
+    pub fn aligned_tsv() -> Self {
+        LayoutOptions {
+            min_width: 1,
+            separator: "\t".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Unpadded fields ending with a tab and rows ending with a NUL byte, for
+    /// `--print0`: a name may hold newlines, leading spaces or anything but
+    /// a NUL and still be read back as it is.
+    pub fn print0() -> Self {
+        LayoutOptions {
+            min_width: 1,
+            separator: "\t".to_string(),
+            align: false,
+            terminator: '\0',
+            ..Default::default()
+        }
+    }
+
+    /// Reproduces GNU wc's column width: a single count of a single input is
+    /// printed unpadded, otherwise every column is as wide as the combined
+    /// size of the inputs, and at least 7 when one of them isn't a regular
+    /// file whose size is known up front.
+    pub fn gnu_compatible(total_bytes: u64, all_regular: bool, single_value: bool) -> Self {
+        let min_width = if single_value {
+            1
+        } else if all_regular {
+            digits(total_bytes)
+        } else {
+            digits(total_bytes).max(DEFAULT_MIN_WIDTH)
+        };
+
+        LayoutOptions {
+            min_width,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn digits(mut value: u64) -> usize {
+    let mut n = 1;
+    while value >= 10 {
+        value /= 10;
+        n += 1;
+    }
+    n
+}
+
+/// `value` with `separator` between groups of three digits.
+pub fn group_digits(value: u64, separator: char) -> String {
+    let digits = value.to_string();
+    let mut out = String::with_capacity(digits.len() * 2);
+    for (i, d) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(separator);
+        }
+        out.push(d);
+    }
+    out
+}
+
+/// The digit group separator of `locale`, a name like `de_CH.UTF-8`: `'`
+/// in Switzerland, `.` or a narrow no-break space in much of Europe, and
+/// `,` elsewhere.
+pub fn locale_group_separator(locale: &str) -> char {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, territory) = name.split_once('_').unwrap_or((name, ""));
+    match (language, territory) {
+        (_, "CH" | "LI") => '\'',
+        (
+            "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "sl" | "hr"
+            | "sr" | "vi",
+            _,
+        ) => '.',
+        (
+            "fr" | "ru" | "sv" | "fi" | "nb" | "nn" | "no" | "pl" | "cs" | "sk" | "uk" | "hu"
+            | "bg" | "et" | "lt" | "lv",
+            _,
+        ) => '\u{202f}',
+        _ => ',',
+    }
+}
+
+/// The digit group separator of the locale that `LC_ALL`, `LC_NUMERIC` or
+/// `LANG` name, the first one set.
+pub fn system_group_separator() -> char {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    locale_group_separator(&locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_uses_widest_value_and_min_width() {
+        let mut layout = Layout::new(LayoutOptions::default());
+        layout.measure(&[1, 123456789]);
+        layout.measure(&[22, 3]);
+        assert_eq!(layout.widths(), &[7, 9]);
+    }
+
+    #[test]
+    fn test_write_row_with_custom_options() {
+        let mut layout = Layout::new(LayoutOptions {
+            min_width: 3,
+            pad: '0',
+            separator: "\t".to_string(),
+            ..Default::default()
+        });
+        layout.measure(&[5, 1234]);
+
+        let mut out = String::new();
+        layout.write_row(&mut out, &[5, 12], "file.txt").unwrap();
+        assert_eq!(out, "005\t0012\tfile.txt\n");
+    }
+
+    // Expected rows are coreutils 9.1 output for `seq 1000`.
+    #[test]
+    fn test_gnu_compatible_matches_coreutils() {
+        let row = |opts: LayoutOptions, values: &[u64], name: &str| {
+            let mut layout = Layout::new(opts);
+            layout.measure(values);
+            let mut out = String::new();
+            layout.write_fields(&mut out, values).unwrap();
+            out + name
+        };
+
+        // wc -l seq.txt
+        let opts = LayoutOptions::gnu_compatible(3893, true, true);
+        assert_eq!(row(opts, &[1000], "seq.txt"), "1000 seq.txt");
+
+        // wc seq.txt
+        let opts = LayoutOptions::gnu_compatible(3893, true, false);
+        assert_eq!(
+            row(opts, &[1000, 1000, 3893], "seq.txt"),
+            "1000 1000 3893 seq.txt"
+        );
+
+        // wc -l seq.txt seq.txt
+        let opts = LayoutOptions::gnu_compatible(7786, true, false);
+        assert_eq!(row(opts, &[2000], "total"), "2000 total");
+
+        // seq 1000 | wc -lm
+        let opts = LayoutOptions::gnu_compatible(3893, false, false);
+        assert_eq!(row(opts, &[1000, 3893], ""), "   1000    3893 ");
+    }
+
+    #[test]
+    fn test_print0() {
+        let mut layout = Layout::new(LayoutOptions::print0());
+        layout.measure(&[3, 12]);
+        layout.measure(&[120, 5]);
+        let mut out = String::new();
+        layout
+            .write_names(&mut out, &["lines", "words"], "file")
+            .unwrap();
+        layout.write_row(&mut out, &[3, 12], " a\nb").unwrap();
+        layout.write_total(&mut out, &[123, 17], "total").unwrap();
+        assert_eq!(
+            out,
+            "lines\twords\tfile\x003\t12\t a\nb\x00123\t17\ttotal\0"
+        );
+    }
+
+    #[test]
+    fn test_aligned_tsv() {
+        let mut layout = Layout::new(LayoutOptions::aligned_tsv());
+        layout.measure(&[3, 12]);
+        layout.measure(&[120, 5]);
+        let mut out = String::new();
+        layout.write_row(&mut out, &[3, 12], "a").unwrap();
+        layout.write_row(&mut out, &[120, 5], "total").unwrap();
+        assert_eq!(out, "  3\t12\ta\n120\t 5\ttotal\n");
+    }
+
+    #[test]
+    fn test_color() {
+        let mut layout = Layout::new(LayoutOptions {
+            min_width: 1,
+            color: true,
+            color_largest: true,
+            ..Default::default()
+        });
+        layout.measure(&[3, 12]);
+        layout.measure(&[120, 5]);
+        layout.measure_total(&[123, 17]);
+        let mut out = String::new();
+        layout.write_row(&mut out, &[3, 12], "a").unwrap();
+        layout.write_error(&mut out, "rswc: b: gone").unwrap();
+        layout.write_total(&mut out, &[123, 17], "total").unwrap();
+        assert_eq!(
+            out,
+            "  3 \x1b[1;33m12\x1b[0m a\n\
+             \x1b[31mrswc: b: gone\x1b[0m\n\
+             \x1b[1m123 17 total\x1b[0m\n"
+        );
+
+        let mut plain = Layout::new(LayoutOptions::default());
+        plain.measure(&[3]);
+        let mut out = String::new();
+        plain.write_error(&mut out, "rswc: b: gone").unwrap();
+        plain.write_total(&mut out, &[3], "total").unwrap();
+        assert_eq!(out, "rswc: b: gone\n      3 total\n");
+
+        assert_eq!(ColorChoice::new("always"), Ok(ColorChoice::Always));
+        assert!(ColorChoice::new("yes").is_err());
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(9), 1);
+        assert_eq!(digits(10), 2);
+        assert_eq!(digits(u64::MAX), u64::MAX.to_string().len());
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0, ','), "0");
+        assert_eq!(group_digits(999, ','), "999");
+        assert_eq!(group_digits(1000, ','), "1,000");
+        assert_eq!(group_digits(1234567, '.'), "1.234.567");
+
+        let mut layout = Layout::new(LayoutOptions {
+            group_separator: Some(','),
+            ..Default::default()
+        });
+        layout.measure(&[1234567, 12]);
+        layout.measure(&[89, 12345]);
+        let mut out = String::new();
+        layout.write_row(&mut out, &[1234567, 12], "a").unwrap();
+        layout.write_row(&mut out, &[89, 12345], "b").unwrap();
+        assert_eq!(out, "1,234,567      12 a\n       89  12,345 b\n");
+    }
+
+    #[test]
+    fn test_locale_group_separator() {
+        assert_eq!(locale_group_separator("en_US.UTF-8"), ',');
+        assert_eq!(locale_group_separator("de_DE.UTF-8"), '.');
+        assert_eq!(locale_group_separator("de_CH.UTF-8"), '\'');
+        assert_eq!(locale_group_separator("fr_FR@euro"), '\u{202f}');
+        assert_eq!(locale_group_separator("C"), ',');
+        assert_eq!(locale_group_separator(""), ',');
+    }
+}
@@ -0,0 +1,144 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod scanner;
+
+#[cfg(feature = "std")]
+pub mod anomalies;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "std")]
+pub mod args;
+#[cfg(feature = "baseline")]
+pub mod baseline;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod blank;
+#[cfg(feature = "std")]
+pub mod buckets;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod case;
+#[cfg(feature = "std")]
+pub mod charclass;
+#[cfg(feature = "std")]
+pub mod code;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod configfile;
+#[cfg(feature = "std")]
+pub mod counter;
+#[cfg(feature = "std")]
+pub mod crash;
+#[cfg(feature = "std")]
+pub mod dedup;
+#[cfg(feature = "std")]
+pub mod detect;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dirs;
+#[cfg(feature = "std")]
+pub mod document;
+#[cfg(feature = "std")]
+pub mod duplicates;
+#[cfg(feature = "embed")]
+pub mod embed;
+#[cfg(feature = "std")]
+pub mod eol;
+#[cfg(feature = "office")]
+pub mod epub;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "core")]
+pub mod explain;
+#[cfg(feature = "std")]
+pub mod extensions;
+#[cfg(feature = "std")]
+pub mod features;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fields;
+#[cfg(feature = "std")]
+pub mod filelist;
+#[cfg(feature = "std")]
+pub mod follow;
+#[cfg(feature = "std")]
+pub mod freq;
+#[cfg(feature = "std")]
+pub mod groups;
+#[cfg(feature = "std")]
+pub mod indent;
+#[cfg(feature = "std")]
+pub mod lang;
+#[cfg(feature = "std")]
+pub mod layout;
+#[cfg(feature = "std")]
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod lines;
+#[cfg(feature = "std")]
+pub mod links;
+#[cfg(feature = "std")]
+pub mod loglevels;
+#[cfg(feature = "std")]
+pub mod nonascii;
+#[cfg(feature = "std")]
+pub mod notebook;
+#[cfg(feature = "std")]
+pub mod numbers;
+#[cfg(feature = "std")]
+pub mod outfile;
+#[cfg(feature = "std")]
+pub mod output;
+#[cfg(feature = "pattern")]
+pub mod pattern;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+pub mod range;
+#[cfg(feature = "std")]
+pub mod readahead;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "archive")]
+pub mod repro;
+#[cfg(feature = "std")]
+pub mod runlog;
+#[cfg(feature = "std")]
+pub mod segments;
+#[cfg(feature = "std")]
+pub mod sentences;
+#[cfg(feature = "watch")]
+pub mod serve;
+#[cfg(feature = "std")]
+pub mod size;
+#[cfg(feature = "std")]
+pub mod sort;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod status;
+#[cfg(feature = "std")]
+pub mod subtitles;
+#[cfg(feature = "std")]
+pub mod summary;
+#[cfg(feature = "tokens")]
+pub mod tokens;
+#[cfg(feature = "encoding")]
+pub mod transcode;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "std")]
+pub mod unique;
+#[cfg(feature = "self-update")]
+pub mod update;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+mod uring;
+#[cfg(feature = "watch")]
+pub mod watch;
@@ -0,0 +1,156 @@
+//! Limits on counts for `--max-lines` and the like, checked once counting
+//! is done, so a CI job can fail on, say, a source file over 1000 lines:
+//!
+//! ```sh
+//! rswc -l --max-lines=1000 $(git ls-files '*.rs')
+//! ```
+
+use crate::counter::{CountOptions, Counts, FileResult, counted_inputs};
+use std::io::{self, Write};
+
+/// The most a count may be, of every file or of the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    /// The column name of the count, like `lines`.
+    pub column: &'static str,
+    pub max: u64,
+    pub total: bool,
+}
+
+impl Limit {
+    /// Fails if `flags` don't count the column, since it would always be 0.
+    pub fn check(&self, flags: &CountOptions) -> Result<(), String> {
+        let counted = Counts::default()
+            .named_columns(flags)
+            .iter()
+            .any(|(name, _)| *name == self.column);
+        if counted {
+            Ok(())
+        } else {
+            Err(format!(
+                "rswc: cannot limit the {}, which are not counted",
+                self.column
+            ))
+        }
+    }
+}
+
+/// An input, or the total, over a limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub name: String,
+    pub value: u64,
+    pub limit: Limit,
+}
+
+impl Violation {
+    fn of(limit: &Limit, name: &str, counts: &Counts, flags: &CountOptions) -> Option<Self> {
+        let (_, value) = counts
+            .named_columns(flags)
+            .into_iter()
+            .find(|(column, _)| *column == limit.column)?;
+        (value > limit.max).then(|| Violation {
+            name: name.to_string(),
+            value,
+            limit: *limit,
+        })
+    }
+}
+
+/// The inputs of `results` over one of the per-file `limits`, archive
+/// members included, in order.
+pub fn check_files(
+    limits: &[Limit],
+    results: &[FileResult],
+    flags: &CountOptions,
+) -> Vec<Violation> {
+    let inputs = counted_inputs(results);
+    inputs
+        .iter()
+        .flat_map(|(name, counts)| {
+            limits
+                .iter()
+                .filter(|limit| !limit.total)
+                .filter_map(|limit| Violation::of(limit, name, counts, flags))
+        })
+        .collect()
+}
+
+/// The limits of the total that `total` is over.
+pub fn check_total(limits: &[Limit], total: &Counts, flags: &CountOptions) -> Vec<Violation> {
+    limits
+        .iter()
+        .filter(|limit| limit.total)
+        .filter_map(|limit| Violation::of(limit, "total", total, flags))
+        .collect()
+}
+
+/// Prints a line per violation, like `rswc: big.rs: 1234 lines, over the
+/// limit of 1000`.
+pub fn print_violations<W: Write>(writer: &mut W, violations: &[Violation]) -> io::Result<()> {
+    for v in violations {
+        writeln!(
+            writer,
+            "rswc: {}: {} {}, over the limit of {}",
+            v.name, v.value, v.limit.column, v.limit.max
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let flags = CountOptions {
+            lines: true,
+            bytes: true,
+            ..Default::default()
+        };
+        let file = |lines, bytes| Counts {
+            lines,
+            bytes,
+            ..Default::default()
+        };
+        let results = [
+            FileResult::Ok("small.rs".into(), file(10, 300)),
+            FileResult::Ok("big.rs".into(), file(1200, 40000)),
+            FileResult::Archive(
+                "vendor.tar".into(),
+                vec![FileResult::Ok("huge.rs".into(), file(5000, 100))],
+            ),
+        ];
+        let limits = [
+            Limit {
+                column: "lines",
+                max: 1000,
+                total: false,
+            },
+            Limit {
+                column: "bytes",
+                max: 40000,
+                total: true,
+            },
+        ];
+        let files = check_files(&limits, &results, &flags);
+        let total = check_total(&limits, &crate::counter::total(&results), &flags);
+        let mut out = Vec::new();
+        print_violations(&mut out, &[files, total].concat()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "rswc: big.rs: 1200 lines, over the limit of 1000\n\
+             rswc: vendor.tar!huge.rs: 5000 lines, over the limit of 1000\n\
+             rswc: total: 40400 bytes, over the limit of 40000\n"
+        );
+
+        let limit = Limit {
+            column: "words",
+            max: 1,
+            total: false,
+        };
+        assert!(limit.check(&flags).is_err());
+        assert!(limits[0].check(&flags).is_ok());
+    }
+}
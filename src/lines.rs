@@ -0,0 +1,83 @@
+//! Lines of input fed in chunks of any size, for the counters that look at
+//! a line at a time.
+
+/// Splits input fed in chunks of any size into lines, keeping the line cut
+/// off at the end of a chunk until the rest of it comes in. Lines are passed
+/// on without their delimiter.
+pub struct LineSplitter {
+    // The line cut off at the end of the last chunk.
+    pending: Vec<u8>,
+    delimiter: u8,
+}
+
+impl Default for LineSplitter {
+    fn default() -> Self {
+        LineSplitter::new(b'\n')
+    }
+}
+
+impl LineSplitter {
+    /// Splits lines at `delimiter`.
+    pub fn new(delimiter: u8) -> Self {
+        LineSplitter {
+            pending: Vec::new(),
+            delimiter,
+        }
+    }
+
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    /// Passes on every line that `chunk` ends.
+    pub fn feed(&mut self, mut chunk: &[u8], mut line: impl FnMut(&[u8])) {
+        while let Some(end) = chunk.iter().position(|&b| b == self.delimiter) {
+            if self.pending.is_empty() {
+                line(&chunk[..end]);
+            } else {
+                self.pending.extend_from_slice(&chunk[..end]);
+                line(&self.pending);
+                self.pending.clear();
+            }
+            chunk = &chunk[end + 1..];
+        }
+        self.pending.extend_from_slice(chunk);
+    }
+
+    /// Passes on the last line if it had no delimiter.
+    pub fn finish(self, mut line: impl FnMut(&[u8])) {
+        if !self.pending.is_empty() {
+            line(&self.pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(input: &[u8], size: usize, delimiter: u8) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        let mut splitter = LineSplitter::new(delimiter);
+        for chunk in input.chunks(size) {
+            splitter.feed(chunk, |line| lines.push(line.to_vec()));
+        }
+        splitter.finish(|line| lines.push(line.to_vec()));
+        lines
+    }
+
+    #[test]
+    fn test_line_splitter_chunks() {
+        let input = b"one\n\ntwo three\nfour";
+        for size in 1..=input.len() {
+            assert_eq!(
+                split(input, size, b'\n'),
+                [&b"one"[..], b"", b"two three", b"four"],
+                "chunk size {}",
+                size
+            );
+        }
+        assert_eq!(split(b"a\0b\0", 1, 0), [b"a", b"b"]);
+        assert!(split(b"", 1, b'\n').is_empty());
+    }
+}
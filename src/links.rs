@@ -0,0 +1,179 @@
+//! URLs and email addresses for `--links`: they are counted, and stay one
+//! word even where `WordRules` would split them at hyphens or apostrophes.
+
+use crate::scanner::WordRules;
+
+/// Longer tokens are never taken for links, which bounds the memory used.
+const MAX_LINK_LEN: usize = 4096;
+
+/// Finds the links among the whitespace-separated tokens of input fed in
+/// chunks of any size.
+#[derive(Debug, Default)]
+pub struct LinkWords {
+    rules: WordRules,
+    token: Vec<u8>,
+    in_token: bool,
+    // Words of the current token under `rules`.
+    words: usize,
+    in_word: bool,
+    links: u64,
+    joined: u64,
+}
+
+impl LinkWords {
+    pub fn new(rules: WordRules) -> Self {
+        LinkWords {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if b.is_ascii_whitespace() {
+                self.end_token();
+                continue;
+            }
+
+            self.in_token = true;
+            if self.token.len() <= MAX_LINK_LEN {
+                self.token.push(b);
+            }
+            if self.rules.is_separator(b) {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.words += 1;
+                self.in_word = true;
+            }
+        }
+    }
+
+    fn end_token(&mut self) {
+        if self.in_token && self.token.len() <= MAX_LINK_LEN && is_link(&self.token) {
+            self.links += 1;
+            self.joined += self.words.saturating_sub(1) as u64;
+        }
+        self.token.clear();
+        self.in_token = false;
+        self.words = 0;
+        self.in_word = false;
+    }
+
+    /// The number of links, and how many fewer words they make up than
+    /// `rules` split them into.
+    pub fn finish(mut self) -> (u64, u64) {
+        self.end_token();
+        (self.links, self.joined)
+    }
+}
+
+/// Whether `token` is a URL with a scheme (`https://...`), a `www.` host,
+/// or an email address, possibly `mailto:`, once surrounding brackets,
+/// quotes and trailing punctuation are removed.
+pub fn is_link(token: &[u8]) -> bool {
+    let start = token
+        .iter()
+        .position(|b| !b"([<\"'".contains(b))
+        .unwrap_or(token.len());
+    let end = token
+        .iter()
+        .rposition(|b| !b".,;:!?)]>\"'".contains(b))
+        .map_or(start, |i| i + 1);
+    if start >= end {
+        return false;
+    }
+    let token = &token[start..end];
+
+    if let Some(at) = find(token, b"://") {
+        let (scheme, rest) = (&token[..at], &token[at + 3..]);
+        return scheme.first().is_some_and(u8::is_ascii_alphabetic)
+            && scheme
+                .iter()
+                .all(|&b| b.is_ascii_alphanumeric() || b"+.-".contains(&b))
+            && !rest.is_empty();
+    }
+    if token.len() > 4 && token[..4].eq_ignore_ascii_case(b"www.") {
+        return true;
+    }
+    let address = match token.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(b"mailto:") => &token[7..],
+        _ => token,
+    };
+    is_email(address)
+}
+
+fn is_email(address: &[u8]) -> bool {
+    let mut parts = address.split(|&b| b == b'@');
+    let (Some(local), Some(domain), None) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+
+    let local_ok = !local.is_empty()
+        && local
+            .iter()
+            .all(|&b| b.is_ascii_alphanumeric() || b".!#$%&'*+/=?^_`{|}~-".contains(&b));
+    let labels: Vec<&[u8]> = domain.split(|&b| b == b'.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label
+                    .iter()
+                    .all(|&b| b.is_ascii_alphanumeric() || b == b'-')
+        });
+    local_ok && domain_ok
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_link() {
+        let links: [&[u8]; 7] = [
+            b"https://example.com/a-b",
+            b"(http://x.org).",
+            b"ftp://files.example.net",
+            b"www.rust-lang.org,",
+            b"jane.doe@example.co.uk",
+            b"<mailto:ops@example.com>",
+            b"git+ssh://host/repo",
+        ];
+        for token in links {
+            assert!(is_link(token), "{}", String::from_utf8_lossy(token));
+        }
+
+        let words: [&[u8]; 7] = [
+            b"state-of-the-art",
+            b"://nothing",
+            b"a@b",
+            b"@handle",
+            b"x@y..com",
+            b"www.",
+            b"e.g.",
+        ];
+        for token in words {
+            assert!(!is_link(token), "{}", String::from_utf8_lossy(token));
+        }
+    }
+
+    #[test]
+    fn test_link_words_in_any_chunking() {
+        let rules = WordRules {
+            split_hyphens: true,
+            ..WordRules::DEFAULT
+        };
+        let data =
+            b"see https://my-site.example/a-b-c or mail first-last@example.com, not well-known";
+        for size in 1..=data.len() {
+            let mut links = LinkWords::new(rules);
+            for chunk in data.chunks(size) {
+                links.feed(chunk);
+            }
+            assert_eq!(links.finish(), (2, 4), "chunk size {}", size);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Log lines by severity for `--log-levels`, counted in the same pass as
+//! everything else instead of with a `grep -c` per level.
+
+use crate::lines::LineSplitter;
+
+/// The severities lines are counted by, most severe first, as named in
+/// `--log-levels` and in the output.
+pub const LEVELS: [&str; 4] = ["error", "warn", "info", "debug"];
+
+/// The words that mark a line as of each of `LEVELS`. They only match as
+/// whole words, and case-sensitively, since `error` is as likely to be part
+/// of a message as to be a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LevelWords([Vec<String>; 4]);
+
+impl Default for LevelWords {
+    fn default() -> Self {
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect();
+        LevelWords([
+            words(&["ERROR", "ERR", "FATAL", "CRITICAL"]),
+            words(&["WARN", "WARNING"]),
+            words(&["INFO"]),
+            words(&["DEBUG", "TRACE"]),
+        ])
+    }
+}
+
+impl LevelWords {
+    /// Parses a comma-separated list of `LEVEL=WORD|WORD...`, replacing the
+    /// words of the levels given and keeping those of the others.
+    pub fn new(spec: &str) -> Result<Self, String> {
+        let mut words = LevelWords::default();
+        for entry in spec.split(',') {
+            let invalid = || {
+                format!(
+                    "rswc: invalid log level '{}' (expected LEVEL=WORD|WORD..., with LEVEL one of {})",
+                    entry,
+                    LEVELS.join(", ")
+                )
+            };
+            let (level, list) = entry.split_once('=').ok_or_else(invalid)?;
+            let i = LEVELS
+                .iter()
+                .position(|l| *l == level.trim())
+                .ok_or_else(invalid)?;
+            let list: Vec<String> = list
+                .split('|')
+                .map(str::trim)
+                .filter(|w| !w.is_empty())
+                .map(String::from)
+                .collect();
+            if list.is_empty() {
+                return Err(invalid());
+            }
+            words.0[i] = list;
+        }
+        Ok(words)
+    }
+
+    /// The index in `LEVELS` of the level word found first in `line`.
+    fn level_of(&self, line: &[u8]) -> Option<usize> {
+        let is_word = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+        for start in 0..line.len() {
+            if start > 0 && is_word(&line[start - 1]) {
+                continue;
+            }
+            let rest = &line[start..];
+            for (level, words) in self.0.iter().enumerate() {
+                let found = words.iter().any(|w| {
+                    rest.starts_with(w.as_bytes()) && !rest.get(w.len()).is_some_and(is_word)
+                });
+                if found {
+                    return Some(level);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Lines per level, in the order of `LEVELS`. Lines of no level are in none.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogLevels(pub [usize; 4]);
+
+impl LogLevels {
+    pub fn merge(&mut self, other: &LogLevels) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+    }
+}
+
+/// Counts the lines of each level of input fed in chunks of any size.
+pub struct LevelCounter<'a> {
+    words: &'a LevelWords,
+    levels: LogLevels,
+    lines: LineSplitter,
+}
+
+impl<'a> LevelCounter<'a> {
+    pub fn new(words: &'a LevelWords) -> Self {
+        LevelCounter {
+            words,
+            levels: LogLevels::default(),
+            lines: LineSplitter::default(),
+        }
+    }
+
+    /// Splits lines at `delimiter` instead of `\n`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.lines = LineSplitter::new(delimiter);
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut lines = std::mem::take(&mut self.lines);
+        lines.feed(chunk, |line| self.classify(line));
+        self.lines = lines;
+    }
+
+    fn classify(&mut self, line: &[u8]) {
+        if let Some(level) = self.words.level_of(line) {
+            self.levels.0[level] += 1;
+        }
+    }
+
+    /// Counts the last line if it had no newline.
+    pub fn finish(mut self) -> LogLevels {
+        let lines = std::mem::take(&mut self.lines);
+        lines.finish(|line| self.classify(line));
+        self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_in_any_chunking() {
+        let log = b"2024-05-01 12:00:00 INFO started\n\
+            2024-05-01 12:00:01 WARNING disk at 91%\n\
+            [ERROR] request failed after INFO was sent\n\
+            DEBUGGING is not a level, nor is error\n\
+            level=DEBUG msg=\"cache miss\"\n\
+            FATAL: out of memory";
+        let words = LevelWords::default();
+        for size in 1..=log.len() {
+            let mut counter = LevelCounter::new(&words);
+            for chunk in log.chunks(size) {
+                counter.feed(chunk);
+            }
+            assert_eq!(
+                counter.finish(),
+                LogLevels([2, 1, 1, 1]),
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_level_words() {
+        let words = LevelWords::new("error=E|F, warn=W").unwrap();
+        assert_eq!(words.level_of(b"W 12:00 E"), Some(1));
+        assert_eq!(words.level_of(b"ERROR"), None);
+        assert_eq!(words.level_of(b"INFO ok"), Some(2));
+        assert!(LevelWords::new("fatal=F").is_err());
+        assert!(LevelWords::new("error=").is_err());
+    }
+}
@@ -1,67 +1,2522 @@
-mod args;
-mod counter;
-
-use args::ArgSet;
-use counter::{Flags, print_files_results, print_stdin_results, process_files, process_stdin};
+use rswc::anomalies::{find_anomalies, print_anomalies};
+use rswc::args::{ArgSet, Rule, is_url};
+use rswc::baseline::{Baseline, print_deltas};
+use rswc::batch::{self, file_record, report_json, total_record};
+use rswc::buckets::{Bucket, TimestampFormat, print_buckets};
+use rswc::cache::Cache;
+use rswc::case::CaseFold;
+use rswc::charclass::print_char_classes;
+use rswc::config::Config;
+use rswc::configfile::{ConfigFormat, count_config_slice};
+use rswc::counter::{
+    Backend, CountOptions, Counts, Estimates, FileResult, Report, count_directories,
+    count_failures, interrupt, interrupted, is_interrupted, print_errors, print_files_results,
+    print_raw_total, print_stdin_results, process_files, process_files_unordered,
+    process_files_with_progress, process_stdin, process_stdin_tee, stdin_reader, stdin_result,
+    total, total_saturates,
+};
+use rswc::crash;
+use rswc::dedup::Seen;
+use rswc::diff::print_diff;
+use rswc::dirs::dir_subtotals;
+use rswc::duplicates::{find_duplicates, print_duplicates};
+use rswc::eol::print_line_ends;
+use rswc::error::RswcError;
+use rswc::explain::explain;
+use rswc::extensions::extension_totals;
+use rswc::features::{capabilities, print_capabilities};
+use rswc::filelist::{self, Queue};
+use rswc::follow::{FollowMode, Follower, POLL_INTERVAL};
+use rswc::freq::{frequencies_of, print_frequencies, top};
+use rswc::groups::print_groups;
+use rswc::indent::print_indents;
+use rswc::lang::lang_pack;
+use rswc::layout::{ColorChoice, Layout, LayoutOptions, system_group_separator};
+use rswc::limits::{Limit, check_files, check_total, print_violations};
+use rswc::loglevels::LevelWords;
+use rswc::numbers::Numbers;
+use rswc::outfile::{Output, OutputFile};
+use rswc::output::{Template, print_formatted};
+use rswc::pattern::Pattern;
+use rswc::progress::{ProgressFormat, ProgressMeter};
+use rswc::range::LineRange;
+use rswc::readahead::timed_out;
+use rswc::repro;
+use rswc::runlog;
+use rswc::scanner::{ByteSet, InvalidUtf8, WordRules};
+use rswc::segments::{self, count_segments};
+use rswc::serve::{self, Symlinks};
+use rswc::size;
+use rswc::sort::{Collation, SortKey, sort_results, top_results};
+use rswc::stats::print_stats;
+#[cfg(unix)]
+use rswc::status::status;
+use rswc::summary::{print_summary, summarize};
+#[cfg(feature = "tokens")]
+use rswc::tokens::Encoding;
+use rswc::transcode::input_encoding;
+use rswc::unique::print_repeated_lines;
+use rswc::watch::watch;
 use std::convert::TryInto;
-use std::io::stdout;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write, stdout};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const CUSTOM_FLAGS: &[&str] = &[
+    "-c",
+    "--bytes",
+    "-l",
+    "--lines",
+    "-w",
+    "--words",
+    "-m",
+    "--chars",
+    "-z",
+    "--zero-terminated",
+    "--anomalies",
+    "--summary",
+    "--find-duplicates",
+    "--mmap",
+    "--no-parallel",
+    "--watch",
+    "--follow",
+    "--progress",
+    "--archive",
+    "--no-extract",
+    "--epub",
+    "--subtitles",
+    "--ipynb",
+    "--freq",
+    "--ignore-case",
+    "--fold-case",
+    "--annotate",
+    "--skip-binary",
+    "--detect",
+    "--sentences",
+    "--stats",
+    "--eol",
+    "--indent-stats",
+    "--char-classes",
+    "--non-ascii",
+    "--max-word-length",
+    "--links",
+    "--tokens",
+    "--code",
+    "--blank-lines",
+    "--unique-lines",
+    "--unique-words",
+    "--dup-lines",
+    "--fields",
+    "--log-levels",
+    "--reading-time",
+    "--raw-total",
+    "--reverse",
+    "--group-digits",
+    "--color",
+    "--color-largest",
+    "--split-stdin",
+    "--tee",
+    "--header",
+    "--group-by-dir",
+    "--by-extension",
+    "--log-files",
+    "--json",
+    "--jsonl",
+    "--print0",
+    "--no-pad",
+    "--strict",
+    "--unordered",
+    "--dedup",
+    "--unicode-ws",
+    "--posix",
+    "--cache",
+    "--no-cache",
+    "--clear-cache",
+    "--invert-match",
+    "--follow-symlinks",
+    "--no-dereference",
+    "--hidden",
+    "--no-ignore",
+    "--help",
+    "--version",
+];
+const VALUE_FLAGS: &[&str] = &[
+    "--config",
+    "-j",
+    "--threads",
+    "--compat",
+    "--top",
+    "--count-pattern",
+    "--match",
+    "--ignore-regex",
+    "--tokens",
+    "--fold-case",
+    "--reading-time",
+    "--word-rules",
+    "--numbers",
+    "--lang",
+    "--invalid-utf8",
+    "--encoding",
+    "--delimiter",
+    "--word-sep",
+    "--fields",
+    "--files-from",
+    "--files0-from",
+    "--max-args-per-batch",
+    "--queue-size",
+    "--follow",
+    "--progress",
+    "--sort",
+    "--collate",
+    "--config-input",
+    "--log-levels",
+    "--dup-lines",
+    "--extract",
+    "--group-by-capture",
+    "--group-digits",
+    "--color",
+    "--bucket",
+    "--timestamp-format",
+    "--format",
+    "-o",
+    "--output",
+    "--label",
+    "--group-by-dir",
+    "--baseline",
+    "--log",
+    "--max-lines",
+    "--max-words",
+    "--max-bytes",
+    "--max-chars",
+    "--max-total-lines",
+    "--max-total-words",
+    "--max-total-bytes",
+    "--max-total-chars",
+    "--seek",
+    "--take",
+    "--head-bytes",
+    "--head-lines",
+    "--range",
+    "--io-backend",
+    "--directories",
+    "--buffer-size",
+    "--timeout",
+    "--min-width",
+];
+/// The value flags that take a path, kept as given rather than required to
+/// be UTF-8.
+const PATH_FLAGS: &[&str] = &[
+    "--config",
+    "--files-from",
+    "--files0-from",
+    "-o",
+    "--output",
+    "--baseline",
+    "--log",
+];
+/// The short and long names of the same options.
+const SYNONYMS: &[[&str; 2]] = &[
+    ["-c", "--bytes"],
+    ["-l", "--lines"],
+    ["-w", "--words"],
+    ["-m", "--chars"],
+    ["-z", "--zero-terminated"],
+    ["-j", "--threads"],
+    ["-o", "--output"],
+    ["-h", "--help"],
+];
+/// The options that limit a count, with the column they limit and whether
+/// they limit the total rather than every file.
+const LIMIT_FLAGS: &[(&str, &str, bool)] = &[
+    ("--max-lines", "lines", false),
+    ("--max-words", "words", false),
+    ("--max-bytes", "bytes", false),
+    ("--max-chars", "chars", false),
+    ("--max-total-lines", "lines", true),
+    ("--max-total-words", "words", true),
+    ("--max-total-bytes", "bytes", true),
+    ("--max-total-chars", "chars", true),
+];
+/// Lines listed by `--dup-lines` without a number, as many as `head` shows.
+const DEFAULT_DUP_LINES: usize = 10;
+/// Words per minute for `--reading-time`, within the usual 200-250 range
+/// for silent reading of prose.
+const DEFAULT_READING_WPM: usize = 230;
+const RULES: &[Rule] = &[
+    Rule::Conflicts("--raw-total", "--anomalies"),
+    Rule::Conflicts("--raw-total", "--summary"),
+    Rule::Conflicts("--raw-total", "--find-duplicates"),
+    Rule::Conflicts("--raw-total", "--freq"),
+    Rule::Conflicts("--raw-total", "--top"),
+    Rule::Conflicts("--raw-total", "--annotate"),
+    Rule::Conflicts("--raw-total", "--stats"),
+    Rule::Conflicts("--raw-total", "--eol"),
+    Rule::Conflicts("--raw-total", "--indent-stats"),
+    Rule::Conflicts("--raw-total", "--char-classes"),
+    Rule::Conflicts("--raw-total", "--dup-lines"),
+    Rule::Conflicts("--raw-total", "--compat"),
+    Rule::Conflicts("--raw-total", "--sort"),
+    Rule::Conflicts("--raw-total", "--extract"),
+    Rule::Conflicts("--raw-total", "--bucket"),
+    Rule::Conflicts("--compat", "--annotate"),
+    Rule::Conflicts("--compat", "--group-digits"),
+    Rule::Conflicts("--raw-total", "--group-digits"),
+    Rule::Conflicts("--raw-total", "--format"),
+    Rule::Conflicts("--compat", "--format"),
+    Rule::Conflicts("--compat", "--header"),
+    Rule::Conflicts("--raw-total", "--header"),
+    Rule::Conflicts("--group-digits", "--format"),
+    Rule::Conflicts("--raw-total", "--group-by-dir"),
+    Rule::Conflicts("--compat", "--group-by-dir"),
+    Rule::Conflicts("--by-extension", "--group-by-dir"),
+    Rule::Conflicts("--by-extension", "--top"),
+    Rule::Conflicts("--by-extension", "--raw-total"),
+    Rule::Conflicts("--by-extension", "--compat"),
+    Rule::Conflicts("--by-extension", "--json"),
+    Rule::Conflicts("--by-extension", "--jsonl"),
+    Rule::Conflicts("--by-extension", "--unordered"),
+    Rule::Conflicts("--by-extension", "--max-args-per-batch"),
+    Rule::Conflicts("--by-extension", "--follow"),
+    Rule::Conflicts("--by-extension", "--baseline"),
+    Rule::Conflicts("--json", "--format"),
+    Rule::Conflicts("--json", "--compat"),
+    Rule::Conflicts("--json", "--raw-total"),
+    Rule::Conflicts("--json", "--header"),
+    Rule::Conflicts("--json", "--group-digits"),
+    Rule::Conflicts("--compat", "--color"),
+    Rule::Conflicts("--raw-total", "--color"),
+    Rule::Conflicts("--json", "--color"),
+    Rule::Conflicts("--json", "--top"),
+    Rule::Conflicts("--json", "--stats"),
+    Rule::Conflicts("--json", "--eol"),
+    Rule::Conflicts("--json", "--indent-stats"),
+    Rule::Conflicts("--json", "--char-classes"),
+    Rule::Conflicts("--json", "--dup-lines"),
+    Rule::Conflicts("--json", "--freq"),
+    Rule::Conflicts("--json", "--anomalies"),
+    Rule::Conflicts("--json", "--summary"),
+    Rule::Conflicts("--json", "--find-duplicates"),
+    Rule::Conflicts("--json", "--extract"),
+    Rule::Conflicts("--json", "--bucket"),
+    Rule::Conflicts("--json", "--group-by-dir"),
+    Rule::Conflicts("--json", "--baseline"),
+    Rule::Conflicts("--jsonl", "--format"),
+    Rule::Conflicts("--jsonl", "--compat"),
+    Rule::Conflicts("--jsonl", "--raw-total"),
+    Rule::Conflicts("--jsonl", "--header"),
+    Rule::Conflicts("--jsonl", "--group-digits"),
+    Rule::Conflicts("--jsonl", "--color"),
+    Rule::Conflicts("--jsonl", "--top"),
+    Rule::Conflicts("--jsonl", "--stats"),
+    Rule::Conflicts("--jsonl", "--eol"),
+    Rule::Conflicts("--jsonl", "--indent-stats"),
+    Rule::Conflicts("--jsonl", "--char-classes"),
+    Rule::Conflicts("--jsonl", "--dup-lines"),
+    Rule::Conflicts("--jsonl", "--freq"),
+    Rule::Conflicts("--jsonl", "--anomalies"),
+    Rule::Conflicts("--jsonl", "--summary"),
+    Rule::Conflicts("--jsonl", "--find-duplicates"),
+    Rule::Conflicts("--jsonl", "--extract"),
+    Rule::Conflicts("--jsonl", "--bucket"),
+    Rule::Conflicts("--jsonl", "--group-by-dir"),
+    Rule::Conflicts("--jsonl", "--baseline"),
+    Rule::Conflicts("--jsonl", "--json"),
+    Rule::Conflicts("--jsonl", "--unordered"),
+    Rule::Conflicts("--jsonl", "--max-args-per-batch"),
+    Rule::Conflicts("--jsonl", "--watch"),
+    Rule::Conflicts("--jsonl", "--follow"),
+    Rule::Conflicts("--jsonl", "--sort"),
+    Rule::Conflicts("--jsonl", "--progress"),
+    Rule::Conflicts("--jsonl", "--split-stdin"),
+    Rule::Conflicts("--jsonl", "--cache"),
+    Rule::Conflicts("--raw-total", "--baseline"),
+    Rule::Conflicts("--min-width", "--no-pad"),
+    Rule::Conflicts("--min-width", "--compat"),
+    Rule::Conflicts("--min-width", "--format"),
+    Rule::Conflicts("--min-width", "--raw-total"),
+    Rule::Conflicts("--min-width", "--json"),
+    Rule::Conflicts("--min-width", "--jsonl"),
+    Rule::Conflicts("--min-width", "--print0"),
+    Rule::Conflicts("--no-pad", "--compat"),
+    Rule::Conflicts("--no-pad", "--format"),
+    Rule::Conflicts("--no-pad", "--raw-total"),
+    Rule::Conflicts("--no-pad", "--json"),
+    Rule::Conflicts("--no-pad", "--jsonl"),
+    Rule::Conflicts("--no-pad", "--print0"),
+    Rule::Conflicts("--detect", "--compat"),
+    Rule::Conflicts("--detect", "--format"),
+    Rule::Conflicts("--detect", "--raw-total"),
+    Rule::Conflicts("--log", "--unordered"),
+    Rule::Conflicts("--log", "--jsonl"),
+    Rule::Conflicts("--log", "--max-args-per-batch"),
+    Rule::Conflicts("--log", "--follow"),
+    Rule::Conflicts("--log", "--watch"),
+    Rule::Conflicts("--print0", "--format"),
+    Rule::Conflicts("--print0", "--compat"),
+    Rule::Conflicts("--print0", "--raw-total"),
+    Rule::Conflicts("--print0", "--json"),
+    Rule::Conflicts("--print0", "--jsonl"),
+    Rule::Conflicts("--print0", "--color"),
+    Rule::Conflicts("--print0", "--stats"),
+    Rule::Conflicts("--print0", "--eol"),
+    Rule::Conflicts("--print0", "--indent-stats"),
+    Rule::Conflicts("--print0", "--char-classes"),
+    Rule::Conflicts("--print0", "--dup-lines"),
+    Rule::Conflicts("--print0", "--freq"),
+    Rule::Conflicts("--print0", "--anomalies"),
+    Rule::Conflicts("--print0", "--summary"),
+    Rule::Conflicts("--print0", "--find-duplicates"),
+    Rule::Conflicts("--print0", "--extract"),
+    Rule::Conflicts("--print0", "--bucket"),
+    Rule::Conflicts("--print0", "--baseline"),
+    Rule::Conflicts("-z", "--delimiter"),
+    Rule::Conflicts("--zero-terminated", "--delimiter"),
+    Rule::Conflicts("--files-from", "--files0-from"),
+    Rule::Conflicts("--no-parallel", "--threads"),
+    Rule::Conflicts("--no-parallel", "-j"),
+    Rule::Requires("--max-args-per-batch", &["--files-from", "--files0-from"]),
+    Rule::Requires("--queue-size", &["--max-args-per-batch"]),
+    Rule::Conflicts("--max-args-per-batch", "--watch"),
+    Rule::Conflicts("--max-args-per-batch", "--raw-total"),
+    Rule::Conflicts("--max-args-per-batch", "--compat"),
+    Rule::Conflicts("--max-args-per-batch", "--stats"),
+    Rule::Conflicts("--max-args-per-batch", "--eol"),
+    Rule::Conflicts("--max-args-per-batch", "--indent-stats"),
+    Rule::Conflicts("--max-args-per-batch", "--char-classes"),
+    Rule::Conflicts("--max-args-per-batch", "--dup-lines"),
+    Rule::Conflicts("--max-args-per-batch", "--anomalies"),
+    Rule::Conflicts("--max-args-per-batch", "--summary"),
+    Rule::Conflicts("--max-args-per-batch", "--find-duplicates"),
+    Rule::Conflicts("--max-args-per-batch", "--freq"),
+    Rule::Conflicts("--max-args-per-batch", "--top"),
+    Rule::Conflicts("--max-args-per-batch", "--sort"),
+    Rule::Conflicts("--max-args-per-batch", "--extract"),
+    Rule::Conflicts("--max-args-per-batch", "--bucket"),
+    Rule::Conflicts("--max-args-per-batch", "--format"),
+    Rule::Conflicts("--max-args-per-batch", "--group-by-dir"),
+    Rule::Conflicts("--max-args-per-batch", "--json"),
+    Rule::Conflicts("--max-args-per-batch", "--baseline"),
+    Rule::Conflicts("--unordered", "--max-args-per-batch"),
+    Rule::Conflicts("--unordered", "--watch"),
+    Rule::Conflicts("--unordered", "--raw-total"),
+    Rule::Conflicts("--unordered", "--compat"),
+    Rule::Conflicts("--unordered", "--stats"),
+    Rule::Conflicts("--unordered", "--eol"),
+    Rule::Conflicts("--unordered", "--indent-stats"),
+    Rule::Conflicts("--unordered", "--char-classes"),
+    Rule::Conflicts("--unordered", "--dup-lines"),
+    Rule::Conflicts("--unordered", "--anomalies"),
+    Rule::Conflicts("--unordered", "--summary"),
+    Rule::Conflicts("--unordered", "--find-duplicates"),
+    Rule::Conflicts("--unordered", "--freq"),
+    Rule::Conflicts("--unordered", "--top"),
+    Rule::Conflicts("--unordered", "--sort"),
+    Rule::Conflicts("--unordered", "--extract"),
+    Rule::Conflicts("--unordered", "--bucket"),
+    Rule::Conflicts("--unordered", "--format"),
+    Rule::Conflicts("--unordered", "--group-by-dir"),
+    Rule::Conflicts("--unordered", "--json"),
+    Rule::Conflicts("--unordered", "--baseline"),
+    Rule::Conflicts("--unordered", "--progress"),
+    Rule::Conflicts("--unordered", "--split-stdin"),
+    Rule::Conflicts("--cache", "--progress"),
+    Rule::Conflicts("--cache", "--unordered"),
+    Rule::Conflicts("--cache", "--max-args-per-batch"),
+    Rule::Conflicts("--follow", "--watch"),
+    Rule::Conflicts("--follow", "--unordered"),
+    Rule::Conflicts("--follow", "--max-args-per-batch"),
+    Rule::Conflicts("--follow", "--split-stdin"),
+    Rule::Conflicts("--follow", "--tee"),
+    Rule::Conflicts("--follow", "--raw-total"),
+    Rule::Conflicts("--follow", "--json"),
+    Rule::Conflicts("--follow", "--format"),
+    Rule::Conflicts("--follow", "--compat"),
+    Rule::Conflicts("--follow", "--freq"),
+    Rule::Conflicts("--follow", "--top"),
+    Rule::Conflicts("--follow", "--sort"),
+    Rule::Conflicts("--follow", "--stats"),
+    Rule::Conflicts("--follow", "--eol"),
+    Rule::Conflicts("--follow", "--indent-stats"),
+    Rule::Conflicts("--follow", "--char-classes"),
+    Rule::Conflicts("--follow", "--dup-lines"),
+    Rule::Conflicts("--follow", "--anomalies"),
+    Rule::Conflicts("--follow", "--summary"),
+    Rule::Conflicts("--follow", "--find-duplicates"),
+    Rule::Conflicts("--follow", "--group-by-dir"),
+    Rule::Conflicts("--follow", "--baseline"),
+    Rule::Conflicts("--follow", "--progress"),
+    Rule::Conflicts("--follow", "--cache"),
+    Rule::Conflicts("--follow", "-o"),
+    Rule::Conflicts("--follow", "--output"),
+    Rule::Conflicts("--follow-symlinks", "--no-dereference"),
+    Rule::Conflicts("--mmap", "--io-backend"),
+    Rule::Conflicts("--unicode-ws", "--posix"),
+    Rule::Conflicts("--unicode-ws", "--numbers"),
+    Rule::Conflicts("--unicode-ws", "--links"),
+    Rule::Conflicts("--unicode-ws", "--lang"),
+    Rule::Requires("--reverse", &["--sort"]),
+    Rule::Requires("--collate", &["--sort"]),
+    Rule::Requires("--invert-match", &["--match"]),
+    Rule::Requires("--group-by-capture", &["--extract"]),
+    Rule::Requires("--timestamp-format", &["--bucket"]),
+    Rule::Requires("--label", &["--split-stdin"]),
+    Rule::Requires("--log-files", &["--log"]),
+    Rule::Requires("--take", &["--seek"]),
+    Rule::Conflicts("--split-stdin", "--watch"),
+    Rule::Conflicts("-o", "--watch"),
+    Rule::Conflicts("--output", "--watch"),
+    Rule::Conflicts("--split-stdin", "--files-from"),
+    Rule::Conflicts("--split-stdin", "--files0-from"),
+    Rule::Conflicts("--split-stdin", "--config-input"),
+    Rule::Conflicts("--take", "--head-bytes"),
+    Rule::Conflicts("--tee", "--seek"),
+    Rule::Conflicts("--follow", "--seek"),
+    Rule::Conflicts("--tee", "--head-bytes"),
+    Rule::Conflicts("--tee", "--head-lines"),
+    Rule::Conflicts("--follow", "--head-bytes"),
+    Rule::Conflicts("--follow", "--head-lines"),
+    Rule::Conflicts("--tee", "--range"),
+    Rule::Conflicts("--follow", "--range"),
+    Rule::Conflicts("--tee", "--split-stdin"),
+    Rule::Conflicts("--tee", "--watch"),
+    Rule::Conflicts("--tee", "--config-input"),
+    Rule::Conflicts("--tee", "--files-from"),
+    Rule::Conflicts("--tee", "--files0-from"),
+    Rule::Affects("--invalid-utf8", &["--chars", "-m"]),
+    Rule::Affects("--ignore-case", &["--freq", "--top", "--unique-words"]),
+    Rule::Affects("--fold-case", &["--freq", "--top", "--unique-words"]),
+];
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut my_flags = Flags {
-        bytes: false,
-        lines: false,
-        words: false,
-        chars: false,
-    };
-    let custom_flags = [
-        "-c", "--bytes", "-l", "--lines", "-w", "--words", "-m", "--chars", "--help",
-    ];
+    crash::install(
+        std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+    );
+    report_status_on_signal();
+    let command = std::env::args_os().nth(1);
+    let command = command.as_deref().and_then(OsStr::to_str);
+    if command == Some("self-update") {
+        return self_update();
+    }
+    if command == Some("explain") {
+        let paths = args_after(1);
+        let [path] = paths.as_slice() else {
+            eprintln!("rswc: explain needs exactly one file");
+            std::process::exit(1);
+        };
+        match explain(path.as_ref()) {
+            Ok(explanation) => print!("{}", explanation),
+            Err(e) => {
+                eprintln!("rswc: {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if command == Some("batch") {
+        let config = load_config(args_after(1)).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let parse = |words: Vec<String>| {
+            let args_set = ArgSet::try_from((
+                expand_args(&config, words)?,
+                CUSTOM_FLAGS,
+                VALUE_FLAGS,
+                PATH_FLAGS,
+            ))
+            .map_err(|e| e.to_string())?;
+            args_set.validate(RULES).map_err(|e| e.to_string())?;
+            Ok((parse_flags(&args_set, None)?, args_set.file_paths))
+        };
+        batch::run(io::stdin().lock(), &mut stdout().lock(), &parse)?;
+        return Ok(());
+    }
+    if command == Some("split-marker") {
+        let mut out = stdout().lock();
+        out.write_all(segments::MARKER)?;
+        out.write_all(b"\n")?;
+        return Ok(());
+    }
+    if command == Some("diff") {
+        return diff();
+    }
+    if command == Some("repro") {
+        return repro();
+    }
+    if command == Some("serve") {
+        return serve();
+    }
+    if command == Some("tui") {
+        return tui();
+    }
+    if command == Some("features") {
+        print_capabilities(&mut stdout(), &capabilities())?;
+        return Ok(());
+    }
 
     let help_msg = [
-        "Usage: rswc [OPTION]... [FILE]...",
-        "  -c, --bytes    print the byte counts",
-        "  -l, --lines    print the line counts",
-        "  -w, --words    print the word counts",
-        "  -m, --chars    print the character counts",
-        "      --help     display help and exit",
+        "Usage: rswc [OPTION | @ALIAS]... [FILE]...",
+        "  or:  rswc self-update",
+        "  or:  rswc features",
+        "  or:  rswc explain FILE",
+        "  or:  rswc batch < JOBS",
+        "  or:  rswc split-marker",
+        "  or:  rswc diff [OPTION]... A B",
+        "  or:  rswc repro [--anonymize] [--sample=N] [--bundle=F] [OPTION]... FILE",
+        "  or:  rswc serve [--listen=ADDR | --socket=PATH] [OPTION]... --watch DIR",
+        "  or:  rswc tui [OPTION]... [--watch] DIR",
+        "  -c, --bytes             print the byte counts",
+        "  -l, --lines             print the line counts",
+        "  -w, --words             print the word counts",
+        "  -m, --chars             print the character counts",
+        "      --invalid-utf8=POLICY",
+        "                          what -m does with malformed UTF-8: skip it (default),",
+        "                          count it as U+FFFD (lossy), fail (strict) or count its",
+        "                          bytes in a column of their own (count); unchecked",
+        "                          counts bytes that start a character, without checking",
+        "      --non-ascii         print the number of characters outside ASCII",
+        "      --max-word-length   print the length of the longest word, in characters",
+        "  -z, --zero-terminated   end lines at NUL bytes instead of newlines",
+        "      --delimiter=C       end lines at byte C instead of newlines (';', '\\0', '\\x1e')",
+        "      --sentences         print the sentence counts (heuristic)",
+        "      --tokens[=ENC]      print the token counts of encoding ENC (o200k_base)",
+        "      --code              print blank, comment and code line counts",
+        "      --blank-lines       print the number of empty and whitespace-only lines",
+        "      --count-pattern=RE  print the number of matches of regex RE",
+        "      --unique-lines      print the number of distinct lines",
+        "      --unique-words      print the number of distinct words",
+        "      --dup-lines[=N]     list the N most repeated lines of each file (default 10,",
+        "                          0 for all) and how often each occurs",
+        "      --fields[=DELIM]    print the total, min and max fields per record of",
+        "                          DELIM-separated data (',', CSV quoting unless '\\t')",
+        "      --log-levels[=LIST] print the lines of each log level: error, warn, info and",
+        "                          debug, or with other words (error=ERR|FATAL,warn=W)",
+        "      --word-rules=LIST   split words at hyphens or apostrophes (split-hyphens,",
+        "                          join-hyphens, split-apostrophes, keep-apostrophes)",
+        "      --word-sep=CHARS    split words at CHARS instead of whitespace (',\\n')",
+        "      --unicode-ws        split words at whitespace beyond ASCII too, like U+00A0",
+        "                          and U+3000, as wc does in a UTF-8 locale",
+        "      --posix             split words at ASCII whitespace only (the default)",
+        "      --links             print the number of URLs and email addresses, and",
+        "                          count each as one word whatever --word-rules say",
+        "      --numbers=MODE      count numbers as words, ignore them, or separate them",
+        "                          into their own column (count, ignore, separate)",
+        "      --lang=CODE         count sentences and words by the rules of language CODE",
+        "                          (en, de, fr, ja), or auto to detect it per file",
+        "      --encoding=NAME     decode input from NAME (utf-16le, latin1, shift_jis, ...),",
+        "                          or auto for UTF-8 unless it starts with a byte order mark",
+        "      --reading-time[=N]  print the minutes needed to read at N words per minute (230)",
+        "      --match=RE          count only the lines matching regex RE",
+        "      --invert-match      count only the lines not matching --match",
+        "      --ignore-regex=RE   leave out the lines matching regex RE before counting",
+        "                          anything; repeat it to leave out more",
+        "      --extract=RE        list the number of lines per match of regex RE",
+        "      --group-by-capture=N",
+        "                          list them per value of capture group N of --extract",
+        "      --bucket=SIZE       list the number of lines per hour or day of their timestamps",
+        "      --timestamp-format=FMT",
+        "                          find timestamps like FMT (%Y-%m-%d %H, a space also",
+        "                          matching T), with %Y, %m, %b, %d, %H, %M and %S",
+        "      --stats             list the min, mean and max line lengths of each file",
+        "      --eol               list the LF, CRLF and CR line ends of each file",
+        "      --indent-stats      list the lines of each file indented with tabs, spaces",
+        "                          or both, and the usual indent width",
+        "      --char-classes      list the letters, digits, punctuation, whitespace and",
+        "                          other characters of each file",
+        "      --anomalies         report files with an unusual bytes-per-line ratio",
+        "      --summary           print the mean, median and max of each count per file,",
+        "                          and the largest file by it",
+        "      --find-duplicates   group files with identical content and report wasted bytes",
+        "      --seek=OFFSET       count each input from byte OFFSET on, like the part of",
+        "                          a log written since a saved size",
+        "      --take=N            with --seek, count only N bytes from there",
+        "      --head-bytes=N      count only the first N bytes of each input, and stop",
+        "                          reading it there",
+        "      --head-lines=N      count only the first N lines of each input",
+        "      --range=START:END   count only lines START to END of each input (100:200),",
+        "                          from the first line or to the last if one is left out",
+        "      --mmap              memory-map regular files instead of reading them",
+        "      --io-backend=NAME   how files are read: auto (the default), read, mmap, or",
+        "                          uring for io_uring on Linux",
+        "      --buffer-size=SIZE  read files SIZE bytes at a time (64k, 1M; default 512k)",
+        "      --timeout=SECS      stop reading stdin or a pipe once idle for SECS seconds,",
+        "                          print the counts so far and exit with 124",
+        "  -j, --threads=N         count with at most N threads (0: one per core)",
+        "      --no-parallel       count the files one after another in the order given,",
+        "                          on one thread (-j 1)",
+        "      --watch             recount and reprint whenever one of the files changes",
+        "      --follow[=MODE]     keep reading files as they grow, like tail -f, and print",
+        "                          their counts (total) or what was added (delta) each second",
+        "      --follow-symlinks   follow links to directories below the DIR of serve and",
+        "                          tui, except those up the tree",
+        "      --no-dereference    leave out links below the DIR of serve and tui",
+        "      --hidden            count the dotfiles below the DIR of serve and tui too",
+        "      --no-ignore         count the files .gitignore files leave out there too",
+        "      --files-from=F      also count the files named in F, one per line (-: stdin)",
+        "      --files0-from=F     also count the files named in F, separated by NUL bytes",
+        "      --max-args-per-batch=N",
+        "                          read and count the names of F N at a time, printing",
+        "                          each batch while reading the next",
+        "      --queue-size=N      read at most N names of F ahead of the batch being",
+        "                          counted (the batch size)",
+        "      --dedup             count a file given more than once, by any name, once",
+        "      --unordered         print each file as soon as it is counted, in the order",
+        "                          they are done, widening the columns as needed",
+        "      --cache             take the counts of files unchanged since an earlier run",
+        "                          from a cache, and keep the others for the next one",
+        "      --no-cache          read every file, even with --cache (e.g. from an alias)",
+        "      --clear-cache       remove the cache and exit",
+        "      --split-stdin       count each part of stdin between the lines printed by",
+        "                          rswc split-marker as an input of its own",
+        "      --label=NAME        name the next part of --split-stdin NAME (-:N if unnamed)",
+        "      --tee               copy stdin to stdout unchanged while counting it, and",
+        "                          print the counts on stderr",
+        "      --progress[=FMT]    show progress on stderr: per-file bars (default), or",
+        "                          json for one record per line with files, bytes and ETA",
+        "      --archive           count the members of .tar, .tar.gz, .tgz and .zip files",
+        "      --no-extract        count .pdf, .docx and .odt files as bytes, not as text",
+        "      --epub              count each chapter of .epub books, and the text of .html",
+        "                          files without their markup",
+        "      --subtitles         count the captions of .srt and .vtt files, and print their",
+        "                          cues and words per minute",
+        "      --ipynb             count the cells of .ipynb notebooks instead of their JSON,",
+        "                          and print their code lines, markdown words and outputs",
+        "      --sort=KEY          print the files by lines, words, bytes or chars, fewest",
+        "                          first, or by name, instead of in the order given",
+        "      --reverse           print the files in the reverse order of --sort",
+        "      --group-by-dir[=N]  also print a subtotal for each directory of the files,",
+        "                          down to N levels deep",
+        "      --by-extension      print a row for each file extension instead of each",
+        "                          file, with the counts of all files that have it",
+        "      --collate=ORDER     order names for --sort=name by their bytes (bytewise),",
+        "                          with numbers by value (natural: file2 before file10),",
+        "                          or ignoring case and punctuation (locale)",
+        "      --config-input=FMT  read files as yaml or toml, and print their keys, their",
+        "                          deepest nesting and their scalar values",
+        "      --group-digits[=C]  print counts with digits grouped in threes, by C or",
+        "                          the separator of the locale (1,234,567)",
+        "      --color[=WHEN]      color the totals and errors always, never, or auto:",
+        "                          on a terminal, unless NO_COLOR is set (the default)",
+        "      --color-largest     color the largest count of each column as well",
+        "      --format=TEMPLATE   print a line of TEMPLATE per file instead of columns, with",
+        "                          {path} and counts like {lines}, {words}, {bytes} or",
+        "                          {chars} filled in, and \\t or \\n escapes; or aligned-tsv",
+        "                          for the table with a tab after each column",
+        "      --min-width=N       pad every column of counts to at least N characters",
+        "                          (default 7)",
+        "      --no-pad            separate the counts by a single space, unaligned",
+        "      --print0            end each row with a NUL byte and each field with a tab,",
+        "                          unpadded, for names holding newlines or leading spaces",
+        "  -o, --output=FILE       write the report to FILE, replacing it once complete",
+        "      --header            print a row naming the columns above the counts",
+        "      --strict            exit with 1 if a file can't be read, and say how many",
+        "      --directories=ACTION",
+        "                          report a directory given as a file like wc and go on",
+        "                          (report, the default), or also exit with 1 (fail)",
+        "      --max-lines=N       exit with 1 and name the files with more than N lines;",
+        "                          also --max-words, --max-bytes and --max-chars, and",
+        "                          --max-total-lines and so on for the total",
+        "      --json              print the counts as JSON, e.g. to save for --baseline",
+        "      --jsonl             print a JSON object for each file as soon as it is",
+        "                          counted, one per line, and a last one for the total",
+        "      --baseline=F        also print the changes since the counts F saved by --json",
+        "      --log=FILE          also append a line of JSON with the time and the total",
+        "                          to FILE, to follow counts over time, e.g. from cron",
+        "      --log-files         log the counts of every file as well",
+        "      --compat=gnu        format output exactly like GNU wc",
+        "      --raw-total         print only the total of the single selected count",
+        "      --preset=NAME       use the options of preset prose, code or data",
+        "      --config=FILE       read aliases, presets and default options from FILE",
+        "      --annotate          note after each name how it was counted",
+        "      --skip-binary       report files with a NUL byte in their first 8 KiB as",
+        "                          skipped instead of counting them",
+        "      --detect            print the type of each file before its name, from its",
+        "                          first 8 KiB: ascii, utf-8, utf-16le, latin-1, binary...",
+        "      --freq              list words by how often they occur",
+        "      --top=N             only list the N most common words (implies --freq), or",
+        "                          with --sort only the N files with the most of KEY",
+        "      --ignore-case       fold case when listing or counting distinct words",
+        "      --fold-case[=LANG]  like --ignore-case, with the rules of LANG (turkish)",
+        "      --help              display help and exit",
+        "      --version           output version information and exit",
     ];
 
-    let args_set: ArgSet = (std::env::args().skip(1), &custom_flags[..])
+    let words = args_after(0);
+    let args = load_config(&words)
+        .and_then(|config| expand_args(&config, words))
+        .map_err(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })?;
+
+    let args_set: ArgSet = (args, CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS)
         .try_into()
-        .map_err(|e: String| {
+        .map_err(|e: RswcError| {
             eprintln!("{}", e);
             std::process::exit(1);
         })?;
 
-    my_flags.bytes = args_set.has("--bytes") || args_set.has("-c");
-    my_flags.lines = args_set.has("--lines") || args_set.has("-l");
-    my_flags.words = args_set.has("--words") || args_set.has("-w");
-    my_flags.chars = args_set.has("--chars") || args_set.has("-m");
+    let warnings = args_set.validate(RULES).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+
+    // `aligned-tsv` is the table with tabs between its columns rather than
+    // a template.
+    let aligned_tsv = args_set.value("--format") == Some("aligned-tsv");
+    let template = args_set
+        .value("--format")
+        .filter(|_| !aligned_tsv)
+        .map(|spec| {
+            Template::new(spec).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })
+        });
+
+    let my_flags = parse_flags(&args_set, template.as_ref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    if let Some(Err(e)) = template.as_ref().map(|t| t.check(&my_flags)) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if template.is_some() && my_flags.header {
+        eprintln!("rswc: --header cannot be used with a --format template");
+        std::process::exit(1);
+    }
+
+    let limits: Vec<Limit> = LIMIT_FLAGS
+        .iter()
+        .filter_map(|&(option, column, total)| {
+            let max = args_set.get_value(option).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })?;
+            let limit = Limit { column, max, total };
+            if let Err(e) = limit.check(&my_flags) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Some(limit)
+        })
+        .collect();
+
+    let sort = args_set.value("--sort").map(|name| {
+        let key = SortKey::new(name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        if !key.is_counted(&my_flags) {
+            eprintln!("rswc: cannot sort by {}, which are not counted", name);
+            std::process::exit(1);
+        }
+        key
+    });
+    let reverse = args_set.has("--reverse");
+    let collation = match args_set.value("--collate") {
+        Some(_) if sort != Some(SortKey::Name) => {
+            eprintln!("rswc: --collate only applies to --sort=name");
+            std::process::exit(1);
+        }
+        Some(name) => Collation::new(name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => Collation::default(),
+    };
+
+    // With --sort, --top limits the files, and the words only if --freq is
+    // given too.
+    let top_n: Option<usize> = args_set.get_value("--top").unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let top_files = top_n.filter(|_| sort.is_some());
+    let freq = my_flags.freq;
+    let top_words = if freq { top_n.unwrap_or(0) } else { 0 };
+
+    // Bars are only drawn on a terminal; records are for programs.
+    let progress = match args_set.value("--progress") {
+        Some(name) => Some(ProgressFormat::new(name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })),
+        None if args_set.has("--progress") => Some(ProgressFormat::Bars),
+        None => None,
+    }
+    .filter(|&f| f == ProgressFormat::Json || io::stderr().is_terminal());
+
+    let follow = match args_set.value("--follow") {
+        Some(name) => Some(FollowMode::new(name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })),
+        None if args_set.has("--follow") => Some(FollowMode::Totals),
+        None => None,
+    };
+
+    let group_separator = match args_set.value("--group-digits") {
+        Some(arg) => {
+            let mut chars = arg.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => {
+                    eprintln!(
+                        "rswc: invalid argument '{}' for '--group-digits' (expected one character)",
+                        arg
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        None if args_set.has("--group-digits") => Some(system_group_separator()),
+        None => None,
+    };
+    let color = match args_set.value("--color") {
+        Some(name) => ColorChoice::new(name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None if args_set.has("--color") => ColorChoice::Always,
+        None => ColorChoice::Auto,
+    };
+    let output_given = args_set.given("--output") || args_set.given("-o");
+    let color = color.enabled(!output_given && io::stdout().is_terminal());
+    let mut table_layout = LayoutOptions {
+        group_separator,
+        color,
+        color_largest: color && args_set.has("--color-largest"),
+        ..if aligned_tsv {
+            LayoutOptions::aligned_tsv()
+        } else if args_set.has("--print0") {
+            LayoutOptions::print0()
+        } else {
+            LayoutOptions::default()
+        }
+    };
+    let min_width = args_set
+        .get_value::<usize>("--min-width")
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    if let Some(min_width) = min_width {
+        table_layout.min_width = min_width;
+    }
+    if args_set.has("--no-pad") {
+        table_layout.align = false;
+    }
+
+    let group_depth = args_set
+        .get_value::<NonZeroUsize>("--group-by-dir")
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+        .map(NonZeroUsize::get);
+    let group_by_dir = args_set.given("--group-by-dir");
+    let by_extension = args_set.has("--by-extension");
+    let json = args_set.has("--json");
+    let jsonl = args_set.has("--jsonl");
+    let strict = args_set.has("--strict");
+    let log = args_set.path("--log").map(PathBuf::from);
+    let log_files = args_set.has("--log-files");
+    let baseline = args_set.path("--baseline").map(|path| {
+        Baseline::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+
+    let gnu_compat = match args_set.value("--compat") {
+        None => false,
+        Some("gnu") => true,
+        Some(other) => {
+            eprintln!("rswc: invalid argument '{}' for '--compat'", other);
+            std::process::exit(1);
+        }
+    };
+
+    let fail_on_directories = match args_set.value("--directories") {
+        None | Some("report") => false,
+        Some("fail") => true,
+        Some(other) => {
+            eprintln!(
+                "rswc: invalid argument '{}' for '--directories' (expected report or fail)",
+                other
+            );
+            std::process::exit(1);
+        }
+    };
 
     if args_set.has("-h") || args_set.has("--help") {
         print_help(&help_msg);
         std::process::exit(1);
     }
 
-    if !my_flags.bytes && !my_flags.lines && !my_flags.words && !my_flags.chars {
-        my_flags.bytes = true;
-        my_flags.lines = true;
-        my_flags.words = true;
+    if args_set.has("--version") {
+        print_version();
+        return Ok(());
+    }
+
+    let cache_path = Cache::path();
+    if args_set.has("--clear-cache") {
+        if let Some(path) = &cache_path
+            && let Err(e) = Cache::clear(path)
+        {
+            eprintln!("rswc: cannot remove the cache '{}': {}", path.display(), e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut cache = if args_set.has("--cache") && !args_set.has("--no-cache") {
+        let Some(path) = &cache_path else {
+            eprintln!("rswc: --cache needs $XDG_CACHE_HOME or $HOME to be set");
+            std::process::exit(1);
+        };
+        if !Cache::applies(&my_flags) {
+            let option = if my_flags.unique_words {
+                "--unique-words"
+            } else {
+                "--freq"
+            };
+            eprintln!("rswc: warning: {} counts are not cached", option);
+        }
+        Some(Cache::open(path))
+    } else {
+        None
+    };
+
+    let list = match (
+        args_set.path("--files-from"),
+        args_set.path("--files0-from"),
+    ) {
+        (Some(path), _) => Some((path, b'\n')),
+        (_, Some(path)) => Some((path, 0)),
+        _ => None,
+    };
+    let mut list = list.map(|(path, separator)| {
+        filelist::open(path, separator).unwrap_or_else(|e| {
+            eprintln!(
+                "rswc: cannot read file names from '{}': {}",
+                path.display(),
+                e
+            );
+            std::process::exit(1);
+        })
+    });
+
+    if let Some(n) = args_set.value("--max-args-per-batch") {
+        let batch_size: NonZeroUsize = n.parse().unwrap_or_else(|_| {
+            eprintln!("rswc: invalid batch size: '{}'", n);
+            std::process::exit(1);
+        });
+        // By default the next batch is read while one is counted.
+        let queue_size = match args_set.value("--queue-size") {
+            Some(n) => n.parse::<NonZeroUsize>().unwrap_or_else(|_| {
+                eprintln!("rswc: invalid queue size: '{}'", n);
+                std::process::exit(1);
+            }),
+            None => batch_size,
+        };
+        if let Some(list) = list.take() {
+            let mut out = open_output(&args_set);
+            stop_on_interrupt();
+            let mut seen = args_set.has("--dedup").then(Seen::new);
+            let outcome = report_in_batches(
+                &mut out,
+                &args_set.file_paths,
+                &mut Queue::new(list, queue_size.get()),
+                batch_size.get(),
+                &my_flags,
+                &table_layout,
+                &limits,
+                seen.as_mut(),
+            )?;
+            return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+        }
+    }
+
+    let mut files = args_set.file_paths.clone();
+    if let Some(list) = list {
+        for name in list {
+            files.push(name.unwrap_or_else(|e| {
+                eprintln!("rswc: cannot read file names: {}", e);
+                std::process::exit(1);
+            }));
+        }
+    }
+    let files = &files;
+    let single_value = Counts::default().columns(&my_flags).len() == 1;
+    let raw_total = args_set.has("--raw-total");
+    if raw_total && !single_value {
+        eprintln!("rswc: --raw-total needs exactly one count to be selected");
+        std::process::exit(1);
+    }
+
+    // The segments are counted up front, like files would be in the report.
+    let segments = if args_set.has("--split-stdin") {
+        if !files.is_empty() {
+            eprintln!("rswc: --split-stdin counts only stdin, not files");
+            std::process::exit(1);
+        }
+        let mut data = Vec::new();
+        stdin_reader(&my_flags).read_to_end(&mut data)?;
+        let labels: Vec<&str> = args_set.values("--label").collect();
+        Some(count_segments(&data, &labels, &my_flags))
+    } else {
+        None
+    };
+
+    let tee = args_set.has("--tee");
+    if tee && !files.is_empty() {
+        eprintln!("rswc: --tee copies only stdin, not files");
+        std::process::exit(1);
     }
 
-    let files = &args_set.file_paths;
+    let mut out = open_output(&args_set);
+    let from_list = args_set.given("--files-from") || args_set.given("--files0-from");
+    let read_stdin = files.is_empty() && !from_list && segments.is_none();
+    let mut files = exclude_output(files.clone(), &out);
+    if args_set.has("--dedup") {
+        files = dedup_files(files, &mut Seen::new());
+    }
+    let files = &files;
+    if let Some(mode) = follow {
+        if read_stdin {
+            eprintln!("rswc: --follow needs at least one file");
+            std::process::exit(1);
+        }
+        follow_files(&mut out, files, mode, &my_flags, &table_layout)?;
+        return Ok(());
+    }
+    if !read_stdin && !args_set.has("--watch") {
+        stop_on_interrupt();
+    }
+    if jsonl && !read_stdin {
+        let outcome = report_jsonl(&mut out, files, &my_flags, &limits)?;
+        return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+    }
+    if args_set.has("--unordered") && !read_stdin {
+        let outcome = report_unordered(&mut out, files, &my_flags, &table_layout, &limits)?;
+        return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+    }
+    let mut outcome = Outcome::default();
+    if read_stdin {
+        if args_set.has("--watch") {
+            eprintln!("rswc: --watch needs at least one file");
+            std::process::exit(1);
+        }
 
-    if files.is_empty() {
-        let counts = process_stdin(&my_flags)?;
-        print_stdin_results(&mut stdout(), &counts, &my_flags)?;
+        // Configuration files are parsed whole, so keep them.
+        let counted = if let Some(format) = my_flags.config_input {
+            let mut data = Vec::new();
+            stdin_reader(&my_flags)
+                .read_to_end(&mut data)
+                .and_then(|_| count_config_slice(&data, format, &my_flags))
+        } else if tee {
+            process_stdin_tee(&my_flags, &mut io::stdout().lock())
+        } else {
+            process_stdin(&my_flags)
+        };
+        let counts = match stdin_result(counted) {
+            FileResult::Ok(_, counts) => counts,
+            failed => {
+                let results = [failed];
+                if json {
+                    writeln!(out, "{}", report_json(&results, &my_flags))?;
+                } else if jsonl {
+                    writeln!(out, "{}", file_record(&results[0], &my_flags))?;
+                    writeln!(out, "{}", total_record(&total(&results), &my_flags))?;
+                } else {
+                    print_errors(&mut io::stderr(), &results)?;
+                }
+                let outcome = Outcome::of(&results);
+                return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+            }
+        };
+        outcome.over_limit = !limits.is_empty()
+            && over_limits(
+                &limits,
+                &[FileResult::Ok("-".into(), counts.clone())],
+                &my_flags,
+            )?;
+        if let Some(path) = &log {
+            let results = [FileResult::Ok("-".into(), counts.clone())];
+            outcome.unlogged = !log_run(path, &results, &my_flags, log_files);
+        }
+        if raw_total {
+            let results = [FileResult::Ok("-".into(), counts)];
+            print_raw_total(&mut out, &mut io::stderr(), &results, &my_flags)?;
+            return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+        }
+        if json {
+            let results = [FileResult::Ok("-".into(), counts)];
+            writeln!(out, "{}", report_json(&results, &my_flags))?;
+            return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+        }
+        if jsonl {
+            let result = FileResult::Ok("-".into(), counts.clone());
+            writeln!(out, "{}", file_record(&result, &my_flags))?;
+            writeln!(out, "{}", total_record(&counts, &my_flags))?;
+            return finish(out, &outcome, strict, gnu_compat, fail_on_directories);
+        }
+
+        let layout = if gnu_compat {
+            let regular = fs::metadata("/dev/stdin").is_ok_and(|m| m.is_file());
+            LayoutOptions::gnu_compatible(counts.bytes, regular, single_value)
+        } else {
+            table_layout
+        };
+        let label = if gnu_compat { "" } else { "-" };
+        if let Some(template) = &template {
+            writeln!(out, "{}", template.render(label, &counts, &my_flags))?;
+        } else {
+            print_stdin_results(&mut out, &counts, &my_flags, &layout, label)?;
+        }
+        let results = [FileResult::Ok("-".into(), counts)];
+        if my_flags.stats {
+            print_stats(&mut out, &results)?;
+        }
+        if my_flags.eol {
+            print_line_ends(&mut out, &results)?;
+        }
+        if my_flags.indent_stats {
+            print_indents(&mut out, &results)?;
+        }
+        if my_flags.char_classes {
+            print_char_classes(&mut out, &results)?;
+        }
+        if my_flags.dup_lines.is_some() {
+            print_repeated_lines(&mut out, &results)?;
+        }
+        if my_flags.extract.is_some() {
+            print_groups(&mut out, &results, my_flags.capture_group)?;
+        }
+        if let Some(bucket) = my_flags.bucket {
+            print_buckets(&mut out, &results, bucket)?;
+        }
+
+        if freq {
+            let freqs = frequencies_of(&results);
+            print_frequencies(&mut out, &top(&freqs, top_words))?;
+        }
+
+        if let Some(baseline) = &baseline {
+            print_deltas(&mut out, baseline, &results, &my_flags)?;
+        }
     } else {
-        let results = process_files(&files, &my_flags);
-        print_files_results(&mut stdout(), &results, &my_flags)?;
+        let mut report = || -> io::Result<()> {
+            let mut results = if let Some(segments) = &segments {
+                segments.clone()
+            } else if let Some(format) = progress {
+                let meter = ProgressMeter::new(files, format);
+                let results =
+                    process_files_with_progress(files, &my_flags, &|i, n| meter.advance(i, n));
+                meter.finish();
+                results
+            } else if let Some(cache) = &mut cache {
+                let results = cache.process_files(files, &my_flags);
+                if let (Err(e), Some(path)) = (cache.save(), &cache_path) {
+                    eprintln!(
+                        "rswc: warning: cannot write the cache '{}': {}",
+                        path.display(),
+                        e
+                    );
+                }
+                results
+            } else {
+                process_files(files, &my_flags)
+            };
+            let partial = interrupted();
+            results.retain(|r| !is_interrupted(r));
+            if let Some(key) = sort {
+                sort_results(&mut results, key, collation, reverse);
+            }
+            outcome = Outcome {
+                over_limit: over_limits(&limits, &results, &my_flags)?,
+                ..Outcome::of(&results)
+            };
+            if let Some(path) = &log {
+                outcome.unlogged = !log_run(path, &results, &my_flags, log_files);
+            }
+            // The rows of failed files are in the report, which isn't on
+            // the terminal. Raw totals and templates print them on stderr
+            // anyway. Rows by extension leave them out.
+            if (out.is_file() && !raw_total && template.is_none()) || by_extension {
+                print_errors(&mut io::stderr(), &results)?;
+            }
+            if raw_total {
+                return print_raw_total(&mut out, &mut io::stderr(), &results, &my_flags);
+            }
+            if json {
+                return writeln!(out, "{}", report_json(&results, &my_flags));
+            }
+
+            let layout = if gnu_compat {
+                gnu_layout(&results, single_value)
+            } else {
+                table_layout.clone()
+            };
+            // Directory subtotals follow the rows of the files, and are of
+            // all files even when --top shows only some.
+            let subtotals = if group_by_dir {
+                dir_subtotals(&results, group_depth)
+            } else {
+                Vec::new()
+            };
+            let extension_rows = by_extension.then(|| {
+                let mut rows = extension_totals(&results);
+                if let Some(key) = sort {
+                    sort_results(&mut rows, key, collation, reverse);
+                }
+                rows
+            });
+            if let Some(template) = &template {
+                let mut shown = match (&extension_rows, top_files) {
+                    (Some(rows), _) => rows.clone(),
+                    (None, Some(n)) => top_results(&results, n, reverse),
+                    (None, None) => results.clone(),
+                };
+                shown.extend(subtotals);
+                let total =
+                    (top_files.is_some() || group_by_dir || by_extension || results.len() > 1)
+                        .then(|| total(&results));
+                print_formatted(
+                    &mut out,
+                    &mut io::stderr(),
+                    &shown,
+                    total.as_ref(),
+                    &my_flags,
+                    template,
+                )?;
+            } else if top_files.is_some() || group_by_dir || by_extension {
+                let mut shown = match (extension_rows, top_files) {
+                    (Some(rows), _) => rows,
+                    (None, Some(n)) => top_results(&results, n, reverse),
+                    (None, None) => results.clone(),
+                };
+                shown.extend(subtotals);
+                let report = Report::new(&shown, &my_flags)
+                    .with_layout(layout)
+                    .with_total(total(&results));
+                if partial {
+                    write!(out, "{}", report.partial())?;
+                } else {
+                    write!(out, "{}", report)?;
+                }
+            } else if partial {
+                let report = Report::new(&results, &my_flags).with_layout(layout);
+                write!(out, "{}", report.partial())?;
+            } else {
+                print_files_results(&mut out, &results, &my_flags, &layout)?;
+            }
+
+            if my_flags.stats {
+                print_stats(&mut out, &results)?;
+            }
+
+            if my_flags.eol {
+                print_line_ends(&mut out, &results)?;
+            }
+
+            if my_flags.indent_stats {
+                print_indents(&mut out, &results)?;
+            }
+
+            if my_flags.char_classes {
+                print_char_classes(&mut out, &results)?;
+            }
+
+            if my_flags.dup_lines.is_some() {
+                print_repeated_lines(&mut out, &results)?;
+            }
+
+            if my_flags.extract.is_some() {
+                print_groups(&mut out, &results, my_flags.capture_group)?;
+            }
+
+            if let Some(bucket) = my_flags.bucket {
+                print_buckets(&mut out, &results, bucket)?;
+            }
+
+            if args_set.has("--summary") {
+                print_summary(&mut out, summarize(&results, &my_flags).as_ref())?;
+            }
+
+            if args_set.has("--anomalies") {
+                print_anomalies(&mut out, &find_anomalies(&results))?;
+            }
+
+            if args_set.has("--find-duplicates") {
+                print_duplicates(&mut out, &find_duplicates(&results))?;
+            }
+
+            if freq {
+                let freqs = frequencies_of(&results);
+                print_frequencies(&mut out, &top(&freqs, top_words))?;
+            }
+
+            if let Some(baseline) = &baseline {
+                print_deltas(&mut out, baseline, &results, &my_flags)?;
+            }
+
+            Ok(())
+        };
+
+        if args_set.has("--watch") {
+            if files.iter().any(|f| is_url(f)) {
+                eprintln!("rswc: --watch cannot watch URLs");
+                std::process::exit(1);
+            }
+            watch(files, report)?;
+        } else {
+            report()?;
+        }
     }
 
+    finish(out, &outcome, strict, gnu_compat, fail_on_directories)
+}
+
+/// The arguments after the program name and the first `skip` of them.
+/// cmd.exe leaves wildcards like `*.txt` to the program, so on Windows they
+/// are expanded here, as a Unix shell would have.
+fn args_after(skip: usize) -> Vec<OsString> {
+    #[cfg(windows)]
+    let args = wild::args_os();
+    #[cfg(not(windows))]
+    let args = std::env::args_os();
+    args.skip(1 + skip).collect()
+}
+
+/// The configuration file of `--config`, or the default one. Its default
+/// options must be options rswc has.
+fn load_config<I>(args: I) -> Result<Config, String>
+where
+    I: IntoIterator,
+    I::Item: AsRef<OsStr>,
+{
+    let mut path = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_ref().to_str() {
+            Some("--") => break,
+            Some("--config") => path = args.next().map(|p| PathBuf::from(p.as_ref())),
+            Some(arg) => {
+                if let Some(p) = arg.strip_prefix("--config=") {
+                    path = Some(PathBuf::from(p));
+                }
+            }
+            None => {}
+        }
+    }
+    let (mut config, path) = match path {
+        Some(path) => (Config::load_from(&path)?, Some(path)),
+        None => (Config::load()?, Config::path()),
+    };
+    config
+        .check_defaults(CUSTOM_FLAGS, VALUE_FLAGS)
+        .map_err(|e| format!("rswc: {}: {}", path.unwrap_or_default().display(), e))?;
+    let options = env_options(&config)?;
+    config.extend_defaults(options);
+    Ok(config)
+}
+
+/// The options of `$RSWC_OPTIONS`, or else `$RSWC_DEFAULT_FLAGS`, split at
+/// whitespace and parsed like the command line, aliases and presets
+/// included.
+fn env_options(config: &Config) -> Result<Vec<String>, String> {
+    let Some((var, value)) = ["RSWC_OPTIONS", "RSWC_DEFAULT_FLAGS"]
+        .into_iter()
+        .find_map(|var| Some((var, std::env::var(var).ok()?)))
+    else {
+        return Ok(Vec::new());
+    };
+    let words: Vec<String> = value.split_whitespace().map(str::to_string).collect();
+    let args_set = config
+        .expand(words)
+        .and_then(|args| {
+            ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS))
+                .map_err(|e| e.to_string())
+        })
+        .map_err(|e| format!("{} (in {})", e, var))?;
+    if let Some(file) = args_set.file_paths.first() {
+        return Err(format!(
+            "rswc: {} can only hold options, not '{}'",
+            var,
+            file.display()
+        ));
+    }
+    Ok(args_set.options(SYNONYMS))
+}
+
+/// `words` with the aliases and presets of `config` expanded, after the
+/// default options of `config` they don't override.
+fn expand_args<S: Into<OsString>>(config: &Config, words: Vec<S>) -> Result<Vec<OsString>, String> {
+    let args = config.expand(words)?;
+    let given = ArgSet::try_from((args.clone(), CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS))
+        .map_err(|e| e.to_string())?;
+    Ok(config.with_defaults(args, |option| given.overrides(option, RULES, SYNONYMS)))
+}
+
+/// What a run found besides its report, for its exit status.
+#[derive(Debug, Default)]
+struct Outcome {
+    over_limit: bool,
+    inputs: usize,
+    failed: usize,
+    /// The inputs that were directories, among those that failed.
+    directories: usize,
+    /// A count of the total was too large to hold.
+    saturated: bool,
+    /// The run couldn't be written to the `--log`.
+    unlogged: bool,
+}
+
+impl Outcome {
+    fn of(results: &[FileResult]) -> Self {
+        let (inputs, failed) = count_failures(results);
+        Outcome {
+            inputs,
+            failed,
+            directories: count_directories(results),
+            saturated: total_saturates(results),
+            ..Default::default()
+        }
+    }
+}
+
+/// Appends the record of this run to the log at `path`, and tells whether
+/// it could, saying why not on stderr.
+fn log_run(path: &Path, results: &[FileResult], flags: &CountOptions, files: bool) -> bool {
+    let record = runlog::record(SystemTime::now(), results, flags, files);
+    match runlog::append(path, &record) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("rswc: cannot write the log '{}': {}", path.display(), e);
+            false
+        }
+    }
+}
+
+/// Writes out the report, and exits with 1 if a count was over its limit or
+/// the run couldn't be logged, or if an input couldn't be read with `--strict`,
+/// which also says how many, or with `--compat=gnu` like GNU wc, or if an
+/// input was a directory with `--directories=fail`; with 124 if reading stdin
+/// timed out.
+fn finish(
+    out: Output,
+    outcome: &Outcome,
+    strict: bool,
+    gnu_compat: bool,
+    fail_on_directories: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    out.finish()?;
+    if outcome.saturated {
+        eprintln!(
+            "rswc: warning: the total is too large to count, and its largest counts are held at {}",
+            u64::MAX
+        );
+    }
+    if interrupted() {
+        eprintln!(
+            "rswc: interrupted, the total is of the {} files counted",
+            outcome.inputs
+        );
+        std::process::exit(130);
+    }
+    if timed_out() {
+        eprintln!("rswc: timed out waiting for input, the counts are of what was read");
+        std::process::exit(124);
+    }
+    if strict && outcome.failed > 0 {
+        eprintln!(
+            "rswc: {} of {} files could not be read",
+            outcome.failed, outcome.inputs
+        );
+    }
+    if outcome.directories > 0 && !gnu_compat {
+        eprintln!(
+            "rswc: to count the files in a directory, list them: find DIR -type f -print0 | rswc --files0-from=-"
+        );
+    }
+    if outcome.over_limit
+        || outcome.unlogged
+        || (outcome.failed > 0 && (strict || gnu_compat))
+        || (outcome.directories > 0 && fail_on_directories)
+    {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
+/// Makes Ctrl-C stop counting instead of killing rswc, so the files counted
+/// so far are still reported, with a partial total. Files being counted are
+/// finished first; a second Ctrl-C exits at once.
+fn stop_on_interrupt() {
+    let installed = ctrlc::set_handler(|| {
+        if interrupted() {
+            std::process::exit(130);
+        }
+        interrupt();
+    });
+    if let Err(e) = installed {
+        eprintln!("rswc: warning: Ctrl-C will not keep partial results: {}", e);
+    }
+}
+
+/// Prints a status line, like `rswc: 12 files counted, 40960 bytes read,
+/// counting src/main.rs`, on stderr whenever rswc gets SIGUSR1, or SIGINFO
+/// (Ctrl-T) on BSD and macOS, like `dd` does.
+#[cfg(unix)]
+fn report_status_on_signal() {
+    use signal_hook::consts::SIGUSR1;
+    #[cfg(any(
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos"
+    ))]
+    let signals = [SIGUSR1, signal_hook::consts::SIGINFO];
+    #[cfg(not(any(
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos"
+    )))]
+    let signals = [SIGUSR1];
+
+    match signal_hook::iterator::Signals::new(signals) {
+        Ok(mut signals) => {
+            std::thread::spawn(move || {
+                for _ in signals.forever() {
+                    eprintln!("{}", status());
+                }
+            });
+        }
+        Err(e) => eprintln!("rswc: warning: SIGUSR1 will not report the status: {}", e),
+    }
+}
+
+#[cfg(not(unix))]
+fn report_status_on_signal() {}
+
+/// Prints the counts of `results` over their `limits` on stderr, and
+/// whether there were any.
+fn over_limits(limits: &[Limit], results: &[FileResult], flags: &CountOptions) -> io::Result<bool> {
+    let violations = [
+        check_files(limits, results, flags),
+        check_total(limits, &total(results), flags),
+    ]
+    .concat();
+    print_violations(&mut io::stderr(), &violations)?;
+    Ok(!violations.is_empty())
+}
+
+/// The report destination of `-o`/`--output`, or stdout, or stderr with
+/// `--tee`.
+fn open_output(args_set: &ArgSet) -> Output {
+    let path = args_set.path("--output").or(args_set.path("-o"));
+    // stdout carries the input.
+    if path.is_none() && args_set.has("--tee") {
+        return Output::stderr();
+    }
+    Output::new(path).unwrap_or_else(|e| {
+        eprintln!(
+            "rswc: cannot write to '{}': {}",
+            path.unwrap_or(Path::new("")).display(),
+            e
+        );
+        std::process::exit(1);
+    })
+}
+
+/// `files` without the one the report is written to, which would be counted
+/// half written, and grow with every recount of `--watch`.
+fn exclude_output(mut files: Vec<PathBuf>, out: &Output) -> Vec<PathBuf> {
+    files.retain(|file| {
+        let excluded = out.writes_to(file);
+        if excluded {
+            eprintln!(
+                "rswc: warning: not counting '{}', the report is written to it",
+                file.display()
+            );
+        }
+        !excluded
+    });
+    files
+}
+
+/// `files` without those `seen` before, by any name.
+fn dedup_files(mut files: Vec<PathBuf>, seen: &mut Seen) -> Vec<PathBuf> {
+    files.retain(|file| match seen.earlier(file) {
+        Some(first) if first == file => {
+            eprintln!(
+                "rswc: warning: counting '{}' once, it is given more than once",
+                file.display()
+            );
+            false
+        }
+        Some(first) => {
+            eprintln!(
+                "rswc: warning: not counting '{}', the same file as '{}'",
+                file.display(),
+                first.display()
+            );
+            false
+        }
+        None => true,
+    });
+    files
+}
+
+/// Counts `operands`, then the files of `list` `batch_size` at a time,
+/// printing the rows of every batch as it is done, while the queue reads the
+/// next names, and finally the total of all of them. Failed files are
+/// reported on stderr as well when the report goes to a file, and so are
+/// counts over `limits`. With `seen`, files given before are left out.
+#[allow(clippy::too_many_arguments)]
+fn report_in_batches(
+    out: &mut Output,
+    operands: &[PathBuf],
+    list: &mut Queue,
+    batch_size: usize,
+    flags: &CountOptions,
+    layout: &LayoutOptions,
+    limits: &[Limit],
+    mut seen: Option<&mut Seen>,
+) -> io::Result<Outcome> {
+    let mut outcome = Outcome::default();
+    let mut subtotals = Vec::new();
+    let mut counted = 0;
+    let mut files = operands.to_vec();
+    // The header goes above the first batch only.
+    let mut report_flags = flags.clone();
+    loop {
+        files.extend(list.next_batch(batch_size)?);
+        if files.is_empty() {
+            break;
+        }
+        files = exclude_output(files, out);
+        if let Some(seen) = seen.as_deref_mut() {
+            files = dedup_files(files, seen);
+        }
+
+        let mut results = process_files(&files, flags);
+        results.retain(|r| !is_interrupted(r));
+        if out.is_file() {
+            print_errors(&mut io::stderr(), &results)?;
+        }
+        let report = Report::new(&results, &report_flags)
+            .with_layout(layout.clone())
+            .without_total();
+        write!(out, "{}", report)?;
+        let violations = check_files(limits, &results, flags);
+        print_violations(&mut io::stderr(), &violations)?;
+        let (inputs, failed) = count_failures(&results);
+        outcome.over_limit |= !violations.is_empty();
+        outcome.inputs += inputs;
+        outcome.failed += failed;
+        outcome.directories += count_directories(&results);
+        report_flags.header = false;
+        counted += results.len();
+        subtotals.push(FileResult::Ok("total".into(), total(&results)));
+        files.clear();
+        if interrupted() {
+            break;
+        }
+    }
+
+    let all = total(&subtotals);
+    outcome.saturated = all.estimates.contains(Estimates::SATURATED);
+    if counted > 1 || interrupted() {
+        write_total(out, &all, &report_flags, layout.clone())?;
+    }
+    let violations = check_total(limits, &all, flags);
+    print_violations(&mut io::stderr(), &violations)?;
+    outcome.over_limit |= !violations.is_empty();
+    Ok(outcome)
+}
+
+/// Counts `files`, then what is appended to them every `POLL_INTERVAL`, and
+/// prints a row for each file that grew: its counts so far, or those of what
+/// was appended with `FollowMode::Deltas`. Files that are truncated or
+/// replaced are counted from their start again. Only returns on error.
+fn follow_files(
+    out: &mut Output,
+    files: &[PathBuf],
+    mode: FollowMode,
+    flags: &CountOptions,
+    layout: &LayoutOptions,
+) -> io::Result<()> {
+    let mut followers: Vec<Follower> = files.iter().map(|f| Follower::new(f)).collect();
+    // An error is printed once, not at every poll until it goes away.
+    let mut failing = vec![false; followers.len()];
+    let mut layout = layout.clone();
+    // The header goes above the first row only.
+    let mut report_flags = flags.clone();
+    loop {
+        let mut rows = Vec::new();
+        for (follower, failing) in followers.iter_mut().zip(&mut failing) {
+            let path = follower.path().to_path_buf();
+            match follower.poll(flags) {
+                Ok(update) => {
+                    *failing = false;
+                    if let Some(restart) = update.restart {
+                        eprintln!("rswc: {}: {}", path.display(), restart);
+                    }
+                    let Some(appended) = update.appended else {
+                        continue;
+                    };
+                    let counts = match mode {
+                        FollowMode::Totals => follower.counts().clone(),
+                        FollowMode::Deltas => appended,
+                    };
+                    rows.push(FileResult::Ok(path, counts));
+                }
+                Err(e) if !*failing => {
+                    *failing = true;
+                    rows.push(FileResult::Err(path, e.into()));
+                }
+                Err(_) => {}
+            }
+        }
+
+        if !rows.is_empty() {
+            let report = Report::new(&rows, &report_flags)
+                .with_layout(layout.clone())
+                .without_total();
+            write!(out, "{}", report)?;
+            out.flush()?;
+            let mut widest = Layout::new(layout.clone());
+            widest.measure(&total(&rows).columns(flags));
+            layout.min_width = widest.widths().iter().copied().max().unwrap_or(0);
+            report_flags.header = false;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Counts `files`, printing the rows of each as soon as it is done, in the
+/// order they are done, and finally the total. The columns are as wide as
+/// the widest count printed so far, so they only ever widen. Failed files
+/// are reported on stderr as well when the report goes to a file, and so are
+/// counts over `limits`.
+fn report_unordered(
+    out: &mut Output,
+    files: &[PathBuf],
+    flags: &CountOptions,
+    layout: &LayoutOptions,
+    limits: &[Limit],
+) -> io::Result<Outcome> {
+    let mut results = Vec::with_capacity(files.len());
+    let mut layout = layout.clone();
+    // The header goes above the first row only.
+    let mut report_flags = flags.clone();
+    let mut written = Ok(());
+    process_files_unordered(files, flags, &mut |_, result| {
+        if is_interrupted(&result) {
+            return;
+        }
+        let result = [result];
+        if written.is_ok() {
+            written = (|| {
+                if out.is_file() {
+                    print_errors(&mut io::stderr(), &result)?;
+                }
+                let report = Report::new(&result, &report_flags)
+                    .with_layout(layout.clone())
+                    .without_total();
+                write!(out, "{}", report)?;
+                out.flush()?;
+                print_violations(&mut io::stderr(), &check_files(limits, &result, flags))
+            })();
+        }
+        let mut widest = Layout::new(layout.clone());
+        widest.measure(&total(&result).columns(flags));
+        layout.min_width = widest.widths().iter().copied().max().unwrap_or(0);
+        report_flags.header = false;
+        let [result] = result;
+        results.push(result);
+    });
+    written?;
+
+    let mut outcome = Outcome {
+        over_limit: !check_files(limits, &results, flags).is_empty(),
+        ..Outcome::of(&results)
+    };
+    let all = total(&results);
+    if results.len() > 1 || interrupted() {
+        write_total(out, &all, &report_flags, layout)?;
+    }
+    let violations = check_total(limits, &all, flags);
+    print_violations(&mut io::stderr(), &violations)?;
+    outcome.over_limit |= !violations.is_empty();
+    Ok(outcome)
+}
+
+/// Writes a JSON record for each file as soon as it is counted, in the
+/// order they are done, and one for their total at the end.
+fn report_jsonl(
+    out: &mut Output,
+    files: &[PathBuf],
+    flags: &CountOptions,
+    limits: &[Limit],
+) -> io::Result<Outcome> {
+    let mut results = Vec::with_capacity(files.len());
+    let mut written = Ok(());
+    process_files_unordered(files, flags, &mut |_, result| {
+        if is_interrupted(&result) {
+            return;
+        }
+        let result = [result];
+        if written.is_ok() {
+            written = (|| {
+                if out.is_file() {
+                    print_errors(&mut io::stderr(), &result)?;
+                }
+                writeln!(out, "{}", file_record(&result[0], flags))?;
+                out.flush()?;
+                print_violations(&mut io::stderr(), &check_files(limits, &result, flags))
+            })();
+        }
+        let [result] = result;
+        results.push(result);
+    });
+    written?;
+
+    let mut outcome = Outcome {
+        over_limit: !check_files(limits, &results, flags).is_empty(),
+        ..Outcome::of(&results)
+    };
+    let all = total(&results);
+    writeln!(out, "{}", total_record(&all, flags))?;
+    let violations = check_total(limits, &all, flags);
+    print_violations(&mut io::stderr(), &violations)?;
+    outcome.over_limit |= !violations.is_empty();
+    Ok(outcome)
+}
+
+/// Writes the row of the total of a report written row by row, named
+/// `partial total` if counting was interrupted.
+fn write_total(
+    out: &mut Output,
+    all: &Counts,
+    flags: &CountOptions,
+    layout: LayoutOptions,
+) -> io::Result<()> {
+    let name = if interrupted() {
+        "partial total"
+    } else {
+        "total"
+    };
+    let total = [FileResult::Ok(name.into(), all.clone())];
+    write!(out, "{}", Report::new(&total, flags).with_layout(layout))
+}
+
+/// Builds the counting flags from the parsed options and the counts of the
+/// `--format` template, selecting lines, words and bytes when no count was
+/// asked for.
+fn parse_flags(args_set: &ArgSet, template: Option<&Template>) -> Result<CountOptions, String> {
+    let mut flags = CountOptions::new()
+        .bytes(args_set.has("--bytes") || args_set.has("-c"))
+        .lines(args_set.has("--lines") || args_set.has("-l"))
+        .words(args_set.has("--words") || args_set.has("-w"))
+        .chars(args_set.has("--chars") || args_set.has("-m"))
+        .sentences(args_set.has("--sentences"))
+        .stats(args_set.has("--stats"))
+        .eol(args_set.has("--eol"))
+        .indent_stats(args_set.has("--indent-stats"))
+        .char_classes(args_set.has("--char-classes"))
+        .non_ascii(args_set.has("--non-ascii"))
+        .max_word_length(args_set.has("--max-word-length"))
+        .links(args_set.has("--links"))
+        .code(args_set.has("--code"))
+        .blank_lines(args_set.has("--blank-lines"))
+        .unique_lines(args_set.has("--unique-lines"))
+        .unique_words(args_set.has("--unique-words"))
+        // --top lists words, unless it picks the files of --sort.
+        .freq(args_set.has("--freq") || (args_set.given("--top") && !args_set.given("--sort")))
+        .digest(args_set.has("--find-duplicates"))
+        .force_read(args_set.has("--anomalies"))
+        .archives(args_set.has("--archive"))
+        .documents(!args_set.has("--no-extract") && !args_set.given("--compat"))
+        .epub(args_set.has("--epub"))
+        .subtitles(args_set.has("--subtitles"))
+        .ipynb(args_set.has("--ipynb"))
+        .header(args_set.has("--header"))
+        .annotate(args_set.has("--annotate"))
+        .mark_binary(!args_set.given("--compat"))
+        .skip_binary(args_set.has("--skip-binary"))
+        .detect(args_set.has("--detect"))
+        .invert_match(args_set.has("--invert-match"));
+    #[cfg(feature = "tokens")]
+    if args_set.given("--tokens") {
+        flags.tokens = Some(match args_set.value("--tokens") {
+            Some(name) => Encoding::new(name)?,
+            None => Encoding::DEFAULT,
+        });
+    }
+    #[cfg(not(feature = "tokens"))]
+    if args_set.given("--tokens") {
+        return Err("rswc: --tokens is not enabled in this build (feature `tokens`)".to_string());
+    }
+    if let Some(name) = args_set.value("--fold-case") {
+        flags.fold_case = Some(CaseFold::new(name)?);
+    } else if args_set.has("--fold-case") || args_set.has("--ignore-case") {
+        flags.fold_case = Some(CaseFold::Default);
+    }
+    if let Some(list) = args_set.value("--word-rules") {
+        flags.word_rules = word_rules(list)?;
+    }
+    if let Some(separators) = args_set.value("--word-sep") {
+        flags.word_rules.separators = Some(word_separators(separators)?);
+    }
+    flags.word_rules.unicode_whitespace = args_set.has("--unicode-ws");
+    let delimiter = if args_set.has("--zero-terminated") || args_set.has("-z") {
+        Some(0)
+    } else {
+        args_set
+            .value("--delimiter")
+            .map(|arg| one_byte("--delimiter", arg))
+            .transpose()?
+    };
+    if let Some(d) = delimiter {
+        flags.record_delimiter = Some(d);
+        flags.word_rules = flags.word_rules.with_delimiter(d);
+    }
+    if let Some(delimiter) = args_set.value("--fields") {
+        flags.fields = Some(one_byte("--fields", delimiter)?);
+    } else if args_set.has("--fields") {
+        flags.fields = Some(b',');
+    }
+    if let Some(mode) = args_set.value("--numbers") {
+        flags.numbers = Numbers::new(mode)?;
+    }
+    if let Some(policy) = args_set.value("--invalid-utf8") {
+        flags.invalid_utf8 = InvalidUtf8::new(policy)?;
+    }
+    if let Some(name) = args_set.value("--bucket") {
+        let bucket = Bucket::new(name)?;
+        if let Some(spec) = args_set.value("--timestamp-format") {
+            flags.timestamp_format = TimestampFormat::new(spec)?;
+        }
+        if bucket == Bucket::Hour && !flags.timestamp_format.has_hour() {
+            return Err(
+                "rswc: --bucket=hour needs the hour (%H) in --timestamp-format".to_string(),
+            );
+        }
+        flags.bucket = Some(bucket);
+    }
+    if let Some(spec) = args_set.value("--log-levels") {
+        flags.log_levels = Some(LevelWords::new(spec)?);
+    } else if args_set.has("--log-levels") {
+        flags.log_levels = Some(LevelWords::default());
+    }
+    if let Some(n) = args_set
+        .get_value::<usize>("--dup-lines")
+        .map_err(|e| e.to_string())?
+    {
+        flags.dup_lines = Some(n);
+    } else if args_set.has("--dup-lines") {
+        flags.dup_lines = Some(DEFAULT_DUP_LINES);
+    }
+    if let Some(name) = args_set.value("--config-input") {
+        flags.config_input = Some(ConfigFormat::new(name)?);
+    }
+    if let Some(label) = args_set.value("--encoding") {
+        flags.encoding = Some(input_encoding(label)?);
+    }
+    match args_set.value("--lang") {
+        Some("auto") => flags.detect_lang = true,
+        Some(code) => flags.lang = Some(lang_pack(code)?),
+        None => {}
+    }
+    if let Some(wpm) = args_set.value("--reading-time") {
+        flags.reading_wpm = Some(
+            wpm.parse()
+                .map_err(|_| format!("rswc: invalid reading speed: '{}'", wpm))?,
+        );
+    } else if args_set.has("--reading-time") {
+        flags.reading_wpm = NonZeroUsize::new(DEFAULT_READING_WPM);
+    }
+    if let Some(pattern) = args_set.value("--count-pattern") {
+        flags.pattern = Some(Pattern::new(pattern)?);
+    }
+    if let Some(pattern) = args_set.value("--match") {
+        flags.line_filter = Some(Pattern::new(pattern)?);
+    }
+    if args_set.given("--ignore-regex") {
+        flags.ignore_lines = Some(Pattern::any(args_set.values("--ignore-regex"))?);
+    }
+    if let Some(pattern) = args_set.value("--extract") {
+        let pattern = Pattern::new(pattern)?;
+        if let Some(n) = args_set.value("--group-by-capture") {
+            flags.capture_group = n
+                .parse()
+                .ok()
+                .filter(|&group| group < pattern.captures_len())
+                .ok_or_else(|| {
+                    format!(
+                        "rswc: invalid capture group '{}' for '--group-by-capture' (the pattern has {} groups)",
+                        n,
+                        pattern.captures_len() - 1
+                    )
+                })?;
+        }
+        flags.extract = Some(pattern);
+    }
+    if args_set.has("--mmap") {
+        flags.backend = Backend::Mmap;
+    }
+    if let Some(spec) = args_set.value("--buffer-size") {
+        flags.buffer_size = Some(size::byte_size(spec).ok_or_else(|| {
+            format!(
+                "rswc: invalid argument '{}' for '--buffer-size' (expected a size, like 64k or 1M)",
+                spec
+            )
+        })?);
+    }
+    if let Some(spec) = args_set.value("--timeout") {
+        let timeout = spec
+            .parse::<f64>()
+            .ok()
+            .filter(|&secs| secs > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+        flags.timeout = Some(timeout.ok_or_else(|| {
+            format!(
+                "rswc: invalid argument '{}' for '--timeout' (expected a number of seconds, like 5 or 0.5)",
+                spec
+            )
+        })?);
+    }
+    if let Some(name) = args_set.value("--io-backend") {
+        flags.backend = Backend::new(name)?;
+        if flags.backend == Backend::Uring && !cfg!(all(feature = "uring", target_os = "linux")) {
+            return Err(
+                "rswc: --io-backend=uring is not enabled in this build (feature `uring`, Linux only)"
+                    .to_string(),
+            );
+        }
+    }
+    flags.seek = args_set.get_value("--seek").map_err(|e| e.to_string())?;
+    flags.head_bytes = args_set
+        .get_value("--head-bytes")
+        .map_err(|e| e.to_string())?;
+    if let Some(take) = args_set.get_value("--take").map_err(|e| e.to_string())? {
+        flags.head_bytes = Some(take);
+    }
+    flags.head_lines = args_set
+        .get_value("--head-lines")
+        .map_err(|e| e.to_string())?;
+    if let Some(spec) = args_set.value("--range") {
+        flags.range = Some(LineRange::new(spec)?);
+    }
+
+    if let Some(threads) = args_set.value("--threads").or(args_set.value("-j")) {
+        flags.threads = threads
+            .parse()
+            .map_err(|_| format!("rswc: invalid number of threads: '{}'", threads))?;
+    }
+    if args_set.has("--no-parallel") {
+        flags.threads = 1;
+    }
+
+    // A template selects the basic counts it prints; the others need their
+    // own options.
+    for name in template.into_iter().flat_map(Template::counts) {
+        match name {
+            "lines" => flags.lines = true,
+            "words" => flags.words = true,
+            "bytes" => flags.bytes = true,
+            "chars" => flags.chars = true,
+            _ => {}
+        }
+    }
+
+    if Counts::default().columns(&flags).is_empty() {
+        flags.bytes = true;
+        flags.lines = true;
+        flags.words = true;
+    }
+
+    Ok(flags)
+}
+
+/// Parses a comma-separated `--word-rules` list; later rules override
+/// earlier ones.
+fn word_rules(list: &str) -> Result<WordRules, String> {
+    let mut rules = WordRules::default();
+    for rule in list.split(',') {
+        match rule.trim() {
+            "split-hyphens" => rules.split_hyphens = true,
+            "join-hyphens" => rules.split_hyphens = false,
+            "split-apostrophes" => rules.split_apostrophes = true,
+            "keep-apostrophes" => rules.split_apostrophes = false,
+            other => return Err(format!("rswc: invalid word rule '{}'", other)),
+        }
+    }
+    Ok(rules)
+}
+
+/// The bytes written as `arg`: ASCII characters and the escapes `\0`,
+/// `\n`, `\r`, `\t`, `\\` and `\xHH`. None if anything else is in it.
+fn escaped_bytes(arg: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut rest = arg.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let (byte, tail) = match (b, tail) {
+            (b'\\', [b'0', tail @ ..]) => (0, tail),
+            (b'\\', [b'n', tail @ ..]) => (b'\n', tail),
+            (b'\\', [b'r', tail @ ..]) => (b'\r', tail),
+            (b'\\', [b't', tail @ ..]) => (b'\t', tail),
+            (b'\\', [b'\\', tail @ ..]) => (b'\\', tail),
+            (b'\\', [b'x', hi, lo, tail @ ..])
+                if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() =>
+            {
+                let hex = [*hi, *lo];
+                (
+                    u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?,
+                    tail,
+                )
+            }
+            (b'\\', _) => return None,
+            (b, tail) if b.is_ascii() => (b, tail),
+            _ => return None,
+        };
+        bytes.push(byte);
+        rest = tail;
+    }
+    Some(bytes)
+}
+
+/// The byte given to `option`, like `--delimiter`.
+fn one_byte(option: &str, arg: &str) -> Result<u8, String> {
+    match escaped_bytes(arg).as_deref() {
+        Some(&[b]) => Ok(b),
+        _ => Err(format!(
+            "rswc: invalid argument '{}' for '{}' (expected one byte, like ';', '\\t' or '\\x1e')",
+            arg, option
+        )),
+    }
+}
+
+/// The bytes given to `--word-sep`.
+fn word_separators(arg: &str) -> Result<ByteSet, String> {
+    match escaped_bytes(arg) {
+        Some(bytes) if !bytes.is_empty() => Ok(ByteSet::from_bytes(&bytes)),
+        _ => Err(format!(
+            "rswc: invalid word separators '{}' (expected ASCII characters, like ',\\n')",
+            arg
+        )),
+    }
+}
+
+fn gnu_layout(results: &[FileResult], single_value: bool) -> LayoutOptions {
+    let mut total_bytes = 0;
+    let mut all_regular = true;
+    for r in results {
+        if let FileResult::Ok(path, c) = r {
+            total_bytes += c.bytes;
+            all_regular &= fs::metadata(path).is_ok_and(|m| m.is_file());
+        }
+    }
+    LayoutOptions::gnu_compatible(total_bytes, all_regular, single_value && results.len() == 1)
+}
+
+#[cfg(feature = "self-update")]
+fn self_update() -> Result<(), Box<dyn std::error::Error>> {
+    rswc::update::self_update().map_err(|e| {
+        eprintln!("rswc: self-update failed: {}", e);
+        std::process::exit(1);
+    })
+}
+
+#[cfg(not(feature = "self-update"))]
+fn self_update() -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("rswc: self-update is not enabled in this build (feature `self-update`)");
+    std::process::exit(1);
+}
+
+/// `rswc diff`: counts two files like `rswc` would and compares them. Like
+/// diff(1), exits with 1 if any count differs, and with 2 on errors.
+fn diff() -> Result<(), Box<dyn std::error::Error>> {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    };
+    let words = args_after(1);
+    let config = load_config(&words).unwrap_or_else(|e| fail(e));
+    let args = expand_args(&config, words).unwrap_or_else(|e| fail(e));
+    let args_set = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS))
+        .unwrap_or_else(|e| fail(e.to_string()));
+    args_set
+        .validate(RULES)
+        .unwrap_or_else(|e| fail(e.to_string()));
+    let flags = parse_flags(&args_set, None).unwrap_or_else(|e| fail(e));
+    if args_set.file_paths.len() != 2 {
+        fail("rswc: diff needs exactly two files".to_string());
+    }
+
+    // An archive is compared by the total of its members.
+    let results = process_files(&args_set.file_paths, &flags);
+    let counts: Vec<(String, Counts)> = results
+        .iter()
+        .map(|r| match r {
+            FileResult::Err(path, msg) => fail(format!("rswc: {}: {}", path.display(), msg)),
+            FileResult::Ok(path, _) | FileResult::Archive(path, _) => {
+                (path.display().to_string(), total(std::slice::from_ref(r)))
+            }
+        })
+        .collect();
+    let [(name_a, a), (name_b, b)] = counts.as_slice() else {
+        unreachable!()
+    };
+    let mut out = stdout().lock();
+    let differ = print_diff(&mut out, (name_a, a), (name_b, b), &flags)?;
+    out.flush()?;
+    if differ {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `rswc serve`: keeps the counts of a directory tree up to date and
+/// answers queries for them over HTTP or a Unix socket.
+fn serve() -> Result<(), Box<dyn std::error::Error>> {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    };
+    let mut listen = serve::DEFAULT_ADDR.to_string();
+    let mut socket = None;
+    let mut words = Vec::new();
+    let mut args = args_after(1).into_iter();
+    while let Some(arg) = args.next() {
+        let option = arg.to_str().unwrap_or_default();
+        if option == "--" {
+            words.push(arg);
+            words.extend(args);
+            break;
+        } else if let Some(addr) = option.strip_prefix("--listen=") {
+            listen = addr.to_string();
+        } else if let Some(path) = option.strip_prefix("--socket=") {
+            socket = Some(PathBuf::from(path));
+        } else {
+            words.push(arg);
+        }
+    }
+
+    let (args_set, flags) = tree_args(words).unwrap_or_else(|e| fail(e));
+    let (true, [dir]) = (args_set.has("--watch"), args_set.file_paths.as_slice()) else {
+        fail("rswc: serve needs --watch and exactly one directory".to_string());
+    };
+    if !dir.is_dir() {
+        fail(format!("rswc: {}: not a directory", dir.display()));
+    }
+
+    let listener = match socket {
+        #[cfg(unix)]
+        Some(path) => {
+            use std::os::unix::net::{UnixListener, UnixStream};
+            // A socket nobody answers on is left over from an earlier run.
+            if path.exists() && UnixStream::connect(&path).is_err() {
+                fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)
+                .unwrap_or_else(|e| fail(format!("rswc: {}: {}", path.display(), e)));
+            eprintln!("rswc: serving {} on {}", dir.display(), path.display());
+            serve::Listener::Unix(listener)
+        }
+        #[cfg(not(unix))]
+        Some(_) => fail("rswc: --socket needs a Unix system".to_string()),
+        None => {
+            let listener = std::net::TcpListener::bind(&listen)
+                .unwrap_or_else(|e| fail(format!("rswc: {}: {}", listen, e)));
+            eprintln!(
+                "rswc: serving {} on http://{}",
+                dir.display(),
+                listener.local_addr()?
+            );
+            serve::Listener::Http(listener)
+        }
+    };
+    let tree = open_tree(dir, &flags, &args_set)?;
+    serve::serve(tree, listener)?;
+    Ok(())
+}
+
+/// The options of a subcommand counting a tree, like `serve`, after those of
+/// the configuration, and the count options they select.
+fn tree_args(words: Vec<OsString>) -> Result<(ArgSet, CountOptions), String> {
+    let config = load_config(words.iter())?;
+    let args = expand_args(&config, words)?;
+    let args_set = ArgSet::try_from((args, CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS))
+        .map_err(|e| e.to_string())?;
+    args_set.validate(RULES).map_err(|e| e.to_string())?;
+    let flags = parse_flags(&args_set, None)?;
+    Ok((args_set, flags))
+}
+
+/// The tree of `serve` and `tui` at `dir`, with the symbolic links, hidden
+/// files and ignored files the options ask for. Files named on the command
+/// line of a plain `rswc` are always counted.
+fn open_tree(dir: &Path, flags: &CountOptions, args_set: &ArgSet) -> io::Result<serve::Tree> {
+    let symlinks = if args_set.has("--follow-symlinks") {
+        Symlinks::Follow
+    } else if args_set.has("--no-dereference") {
+        Symlinks::Skip
+    } else {
+        Symlinks::Files
+    };
+    Ok(serve::Tree::new(dir, flags)?
+        .with_symlinks(symlinks)
+        .with_hidden(args_set.has("--hidden"))
+        .with_ignore_files(!args_set.has("--no-ignore")))
+}
+
+/// `rswc tui`: a table of the counts of a directory tree in the terminal,
+/// kept up to date with `--watch`.
+#[cfg(feature = "tui")]
+fn tui() -> Result<(), Box<dyn std::error::Error>> {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    };
+    let (args_set, flags) = tree_args(args_after(1)).unwrap_or_else(|e| fail(e));
+    let [dir] = args_set.file_paths.as_slice() else {
+        fail("rswc: tui needs exactly one directory".to_string());
+    };
+    if !dir.is_dir() {
+        fail(format!("rswc: {}: not a directory", dir.display()));
+    }
+    if !io::stdout().is_terminal() {
+        fail("rswc: tui needs a terminal".to_string());
+    }
+    let tree = open_tree(dir, &flags, &args_set)?;
+    rswc::tui::run(tree, args_set.has("--watch"))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "tui"))]
+fn tui() -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("rswc: tui is not enabled in this build (feature `tui`)");
+    std::process::exit(1);
+}
+
+/// `rswc repro`: counts one file like `rswc` would and bundles a sample of
+/// it with the options and the counts, for a bug report.
+fn repro() -> Result<(), Box<dyn std::error::Error>> {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    };
+    let mut anonymize = false;
+    let mut sample_size = repro::DEFAULT_SAMPLE;
+    let mut bundle = PathBuf::from("rswc-repro.tar.gz");
+    let mut words = Vec::new();
+    let mut args = args_after(1).into_iter();
+    while let Some(arg) = args.next() {
+        let option = arg.to_str().unwrap_or_default();
+        if option == "--" {
+            words.push(arg);
+            words.extend(args);
+            break;
+        } else if option == "--anonymize" {
+            anonymize = true;
+        } else if let Some(n) = option.strip_prefix("--sample=") {
+            sample_size = n
+                .parse::<NonZeroUsize>()
+                .map(NonZeroUsize::get)
+                .unwrap_or_else(|_| fail(format!("rswc: invalid argument '{}' for '--sample'", n)));
+        } else if let Some(path) = option.strip_prefix("--bundle=") {
+            bundle = path.into();
+        } else {
+            words.push(arg);
+        }
+    }
+
+    let config = load_config(words.iter()).unwrap_or_else(|e| fail(e));
+    let args = expand_args(&config, words).unwrap_or_else(|e| fail(e));
+    let args_set = ArgSet::try_from((args.clone(), CUSTOM_FLAGS, VALUE_FLAGS, PATH_FLAGS))
+        .unwrap_or_else(|e| fail(e.to_string()));
+    args_set
+        .validate(RULES)
+        .unwrap_or_else(|e| fail(e.to_string()));
+    let flags = parse_flags(&args_set, None).unwrap_or_else(|e| fail(e));
+    let [path] = args_set.file_paths.as_slice() else {
+        fail("rswc: repro needs exactly one file".to_string());
+    };
+
+    let mut sample = repro::Sample::read(path, sample_size)
+        .unwrap_or_else(|e| fail(format!("rswc: {}: {}", path.display(), e)));
+    // The name can give away as much as the text; its extension decides
+    // how the file is read.
+    let name = if anonymize {
+        sample.anonymize();
+        match path.extension() {
+            Some(ext) => format!("input.{}", ext.to_string_lossy()),
+            None => "input".to_string(),
+        }
+    } else {
+        path.display().to_string()
+    };
+    let mut results = process_files(std::slice::from_ref(path), &flags);
+    for r in &mut results {
+        let (FileResult::Ok(counted, _)
+        | FileResult::Err(counted, _)
+        | FileResult::Archive(counted, _)) = r;
+        *counted = name.clone().into();
+    }
+    let counts = Report::new(&results, &flags).to_string();
+    let args: Vec<String> = args
+        .into_iter()
+        .filter(|arg| Path::new(arg) != path.as_path())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    let mut out = OutputFile::create(&bundle).unwrap_or_else(|e| {
+        fail(format!(
+            "rswc: cannot write to '{}': {}",
+            bundle.display(),
+            e
+        ))
+    });
+    repro::write_bundle(&mut out, &name, &sample, &args, &counts, anonymize)?;
+    out.commit()?;
+    eprintln!(
+        "rswc: wrote {}, attach it to an issue at https://github.com/nobletk/rswc/issues",
+        bundle.display()
+    );
+    Ok(())
+}
+
+fn print_version() {
+    println!("rswc {}", env!("CARGO_PKG_VERSION"));
+    println!("built with {}", env!("RSWC_RUSTC_VERSION"));
+    println!(
+        "target: {} ({} linking)",
+        env!("RSWC_TARGET"),
+        env!("RSWC_LINKAGE")
+    );
+    println!(
+        "minimum supported Rust version: {}",
+        env!("CARGO_PKG_RUST_VERSION")
+    );
+}
+
 fn print_help(messages: &[&str]) {
     for msg in messages {
         println!("{}", msg);
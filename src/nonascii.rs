@@ -0,0 +1,58 @@
+//! Characters outside ASCII for `--non-ascii`, to find stray smart quotes,
+//! non-breaking spaces and invisible characters in text meant to be ASCII.
+
+/// Counts the non-ASCII characters of input fed in chunks of any size.
+/// Every byte that is not part of a well-formed UTF-8 character counts as
+/// a character of its own, so the count is that of bytes for input in other
+/// encodings.
+#[derive(Debug, Default)]
+pub struct NonAscii {
+    count: u64,
+    // Continuation bytes still expected of the current character.
+    pending: u8,
+}
+
+impl NonAscii {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.pending > 0 && b & 0xC0 == 0x80 {
+                self.pending -= 1;
+                continue;
+            }
+            self.pending = 0;
+            if b >= 0x80 {
+                self.count += 1;
+                self.pending = match b {
+                    0xC2..=0xDF => 1,
+                    0xE0..=0xEF => 2,
+                    0xF0..=0xF4 => 3,
+                    _ => 0,
+                };
+            }
+        }
+    }
+
+    pub fn finish(self) -> u64 {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_ascii_in_any_chunking() {
+        // Two smart quotes, a zero-width space, an emoji, a Latin-1 byte
+        // and a stray continuation byte.
+        let data = "“quoted”\u{200b} 🦀 ".as_bytes();
+        let data = [data, b"caf\xe9 \x80"].concat();
+        for size in 1..=data.len() {
+            let mut counter = NonAscii::default();
+            for chunk in data.chunks(size) {
+                counter.feed(chunk);
+            }
+            assert_eq!(counter.finish(), 6, "chunk size {}", size);
+        }
+    }
+}
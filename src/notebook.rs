@@ -0,0 +1,127 @@
+//! Jupyter notebooks for `--ipynb`: the sources of their cells rather than
+//! the JSON around them, with code, markdown and outputs counted apart.
+
+#[cfg(feature = "notebook")]
+use crate::counter::{Annotations, count_slice};
+use crate::counter::{CountOptions, Counts};
+use std::io;
+use std::path::Path;
+
+/// What the cells of a notebook hold.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cells {
+    /// Lines of the sources of code cells.
+    pub code_lines: usize,
+    /// Words of the sources of markdown cells.
+    pub markdown_words: usize,
+    /// Outputs of code cells, like printed text, tables and plots.
+    pub outputs: usize,
+}
+
+impl Cells {
+    pub fn merge(&mut self, other: &Cells) {
+        self.code_lines += other.code_lines;
+        self.markdown_words += other.markdown_words;
+        self.outputs += other.outputs;
+    }
+}
+
+/// Whether `path` names a Jupyter notebook.
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("ipynb"))
+}
+
+/// Counts the cell sources of the notebook at `path`.
+pub fn count_notebook(path: &Path, flags: &CountOptions) -> io::Result<Counts> {
+    #[cfg(feature = "notebook")]
+    {
+        let (text, cells) = cell_sources(&std::fs::read(path)?, flags)?;
+        let mut counts = count_slice(text.as_bytes(), flags);
+        counts.cells = Some(Box::new(cells));
+        counts.annotations.insert(Annotations::EXTRACTED);
+        Ok(counts)
+    }
+    #[cfg(not(feature = "notebook"))]
+    {
+        let _ = (path, flags);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Jupyter notebook support is not enabled in this build (feature `notebook`)",
+        ))
+    }
+}
+
+/// The sources of all cells of a notebook, each ending in a newline, and
+/// what its cells hold.
+#[cfg(feature = "notebook")]
+pub fn cell_sources(json: &[u8], flags: &CountOptions) -> io::Result<(String, Cells)> {
+    use serde_json::Value;
+
+    let notebook: Value = serde_json::from_slice(json)?;
+    let cells = notebook["cells"]
+        .as_array()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a notebook: no cells"))?;
+
+    let mut text = String::new();
+    let mut counts = Cells::default();
+    for cell in cells {
+        // Sources are a string or a list of lines.
+        let source = match &cell["source"] {
+            Value::String(s) => s.clone(),
+            Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+            _ => String::new(),
+        };
+
+        match cell["cell_type"].as_str() {
+            Some("code") => {
+                counts.code_lines += source.lines().count();
+                counts.outputs += cell["outputs"].as_array().map_or(0, Vec::len);
+            }
+            Some("markdown") => {
+                counts.markdown_words += flags.word_rules.words(source.as_bytes()).count();
+            }
+            _ => {}
+        }
+
+        text.push_str(&source);
+        if !source.is_empty() && !source.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+
+    Ok((text, counts))
+}
+
+#[cfg(all(test, feature = "notebook"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_sources() {
+        let json = br##"{
+ "cells": [
+  {"cell_type": "markdown", "metadata": {}, "source": ["# Title\n", "Some *notes* here"]},
+  {"cell_type": "code", "execution_count": 1, "metadata": {},
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": ["hi\n"]},
+               {"output_type": "execute_result", "data": {"text/plain": ["2"]}}],
+   "source": "print('hi')\n1 + 1"},
+  {"cell_type": "raw", "metadata": {}, "source": []}
+ ],
+ "metadata": {}, "nbformat": 4, "nbformat_minor": 5
+}"##;
+        let (text, cells) = cell_sources(json, &CountOptions::default()).unwrap();
+        assert_eq!(text, "# Title\nSome *notes* here\nprint('hi')\n1 + 1\n");
+        assert_eq!(
+            cells,
+            Cells {
+                code_lines: 2,
+                markdown_words: 5,
+                outputs: 2
+            }
+        );
+        assert!(cell_sources(b"{\"nbformat\": 4}", &CountOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Numeric words for `--numbers`: words made of ASCII digits, optionally
+//! with a sign and `.` or `,` separators, like `42`, `-3.5` or `1,024`.
+
+use crate::scanner::WordRules;
+
+/// What the word count does with numeric words.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Numbers {
+    /// Numbers are words like any other.
+    #[default]
+    Count,
+    /// Numbers aren't counted as words.
+    Ignore,
+    /// Numbers aren't counted as words but in a column of their own.
+    Separate,
+}
+
+impl Numbers {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "count" => Ok(Numbers::Count),
+            "ignore" => Ok(Numbers::Ignore),
+            "separate" => Ok(Numbers::Separate),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--numbers' (expected count, ignore or separate)",
+                name
+            )),
+        }
+    }
+}
+
+/// Counts the numeric words of input fed in chunks of any size, splitting
+/// words like the word counter.
+#[derive(Debug, Default)]
+pub struct NumberWords {
+    rules: WordRules,
+    count: u64,
+    in_word: bool,
+    // Whether the current word is numeric so far, and has a digit yet.
+    numeric: bool,
+    has_digit: bool,
+}
+
+impl NumberWords {
+    pub fn new(rules: WordRules) -> Self {
+        NumberWords {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.rules.is_separator(b) {
+                self.end_word();
+                continue;
+            }
+
+            let first = !self.in_word;
+            self.in_word = true;
+            if first {
+                self.numeric = true;
+            }
+            match b {
+                b'0'..=b'9' => self.has_digit = true,
+                b'+' | b'-' if first => {}
+                b'.' | b',' if !first => {}
+                _ => self.numeric = false,
+            }
+        }
+    }
+
+    fn end_word(&mut self) {
+        if self.in_word && self.numeric && self.has_digit {
+            self.count += 1;
+        }
+        self.in_word = false;
+        self.has_digit = false;
+    }
+
+    pub fn finish(mut self) -> u64 {
+        self.end_word();
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_words_in_any_chunking() {
+        let data = b"42 apples, -3.5 1,024 3rd - v1.2 +7 .5 2024.";
+        for size in 1..=data.len() {
+            let mut numbers = NumberWords::default();
+            for chunk in data.chunks(size) {
+                numbers.feed(chunk);
+            }
+            assert_eq!(numbers.finish(), 5, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_numbers_names() {
+        assert_eq!(Numbers::new("separate"), Ok(Numbers::Separate));
+        assert!(Numbers::new("skip").is_err());
+    }
+}
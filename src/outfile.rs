@@ -0,0 +1,249 @@
+//! The report file of `-o`/`--output`, written next to its destination and
+//! renamed over it once complete, so readers never see half a report and a
+//! failed run leaves the previous one in place.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Where the report goes: stdout, stderr when stdout carries the input of
+/// `--tee`, or a file written by `OutputFile`.
+pub enum Output {
+    Stdout(io::StdoutLock<'static>),
+    Stderr(io::StderrLock<'static>),
+    File(OutputFile),
+}
+
+impl Output {
+    /// Writes to `path`, or to stdout without one.
+    pub fn new(path: Option<&Path>) -> io::Result<Self> {
+        Ok(match path {
+            Some(path) => Output::File(OutputFile::create(path)?),
+            None => Output::Stdout(io::stdout().lock()),
+        })
+    }
+
+    /// Writes to stderr.
+    pub fn stderr() -> Self {
+        Output::Stderr(io::stderr().lock())
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self, Output::File(_))
+    }
+
+    /// Whether the report goes to the file at `path`: the destination of
+    /// `-o`, or a file stdout is redirected to. A `path` that doesn't exist
+    /// yet is compared by name with the destination of `-o`.
+    pub fn writes_to(&self, path: &Path) -> bool {
+        let Ok(input) = fs::metadata(path) else {
+            return matches!(self, Output::File(file) if same_path(&file.path, path));
+        };
+        match self {
+            Output::File(file) => fs::metadata(&file.path).is_ok_and(|m| same_file(&m, &input)),
+            Output::Stdout(_) => stdout_metadata().is_some_and(|m| same_file(&m, &input)),
+            Output::Stderr(_) => false,
+        }
+    }
+
+    /// Flushes stdout or stderr, or commits the file.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Stdout(mut out) => out.flush(),
+            Output::Stderr(mut out) => out.flush(),
+            Output::File(file) => file.commit(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::Stderr(out) => out.write(buf),
+            Output::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::Stderr(out) => out.flush(),
+            Output::File(file) => file.flush(),
+        }
+    }
+}
+
+/// Whether `a` and `b` name the same file once made absolute, with `.` and
+/// `..` resolved without following links.
+fn same_path(a: &Path, b: &Path) -> bool {
+    let normalized = |path: &Path| {
+        let mut normal = PathBuf::new();
+        for part in std::path::absolute(path).ok()?.components() {
+            match part {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normal.pop();
+                }
+                part => normal.push(part),
+            }
+        }
+        Some(normal)
+    };
+    normalized(a).is_some_and(|a| Some(a) == normalized(b))
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(unix)]
+fn stdout_metadata() -> Option<fs::Metadata> {
+    use std::os::fd::AsFd;
+    let fd = io::stdout().as_fd().try_clone_to_owned().ok()?;
+    File::from(fd).metadata().ok().filter(|m| m.is_file())
+}
+
+// std has no portable identity of files elsewhere, so nothing is excluded.
+#[cfg(not(unix))]
+fn same_file(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn stdout_metadata() -> Option<fs::Metadata> {
+    None
+}
+
+/// A file that replaces its destination only once committed.
+pub struct OutputFile {
+    path: PathBuf,
+    temp: PathBuf,
+    writer: Option<BufWriter<File>>,
+}
+
+impl OutputFile {
+    /// Starts a file that replaces `path` on `commit`, keeping the
+    /// permissions of the file it replaces.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?;
+        let temp = path.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+        let file = File::create(&temp)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            temp,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Replaces the destination with everything written so far.
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+        }
+        fs::rename(&self.temp, &self.path)
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        // Only `commit` takes the writer, and it consumes `self`.
+        self.writer.as_mut().unwrap_or_else(|| unreachable!())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+/// Leaves the destination alone unless committed.
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_replaces_and_drop_keeps_the_old_file() {
+        let dir = std::env::temp_dir().join(format!("rswc-outfile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut file = OutputFile::create(&path).unwrap();
+        write!(file, "half").unwrap();
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+
+        let mut file = OutputFile::create(&path).unwrap();
+        writeln!(file, "      3 a.txt").unwrap();
+        file.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "      3 a.txt\n");
+
+        let left: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(left.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_writes_to() {
+        let dir = std::env::temp_dir().join(format!("rswc-writes-to-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.txt");
+        fs::write(&path, "old\n").unwrap();
+        fs::write(dir.join("other.txt"), "old\n").unwrap();
+
+        let output = Output::new(Some(&path)).unwrap();
+        let writes_to = |name: &str| output.writes_to(&dir.join(name));
+        let found = (
+            writes_to("report.txt"),
+            writes_to("./report.txt"),
+            writes_to("other.txt"),
+            writes_to("missing.txt"),
+        );
+        drop(output);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, (true, true, false, false));
+    }
+
+    #[test]
+    fn test_writes_to_a_new_file() {
+        let dir = std::env::temp_dir().join(format!("rswc-writes-new-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.txt");
+
+        let output = Output::new(Some(&path)).unwrap();
+        let writes_to = |name: &str| output.writes_to(&dir.join(name));
+        let found = (
+            writes_to("report.txt"),
+            writes_to("./sub/../report.txt"),
+            writes_to("other.txt"),
+        );
+        drop(output);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, (true, true, false));
+    }
+}
@@ -0,0 +1,244 @@
+//! Rows laid out by a `--format` template instead of aligned columns, for
+//! scripts that expect an exact layout, like `{path}\t{lines}`.
+
+use crate::counter::{CountOptions, Counts, FileResult};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Path,
+    /// A count, by its column name like `lines` or `unique_words`.
+    Count(String),
+}
+
+/// A `--format` template: text with `{path}` and count placeholders like
+/// `{lines}`, `{{` and `}}` for braces, and the escapes `\t`, `\n`, `\r`,
+/// `\0` and `\\`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Piece>);
+
+impl Template {
+    pub fn new(spec: &str) -> Result<Self, String> {
+        let invalid = |what: &str| format!("rswc: invalid format '{}' ({})", spec, what);
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = spec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => text.push(match chars.next() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    _ => return Err(invalid("expected \\t, \\n, \\r, \\0 or \\\\ after \\")),
+                }),
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| invalid("unclosed {"))?;
+                    if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+                    {
+                        return Err(invalid(&format!("invalid placeholder {{{}}}", name)));
+                    }
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(match name {
+                        "path" => Piece::Path,
+                        _ => Piece::Count(name.to_string()),
+                    });
+                    chars = rest.chars();
+                }
+                '}' => return Err(invalid("unmatched }, write }} for a brace")),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Template(pieces))
+    }
+
+    /// The names of the counts the template prints.
+    pub fn counts(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().filter_map(|piece| match piece {
+            Piece::Count(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Fails on a count that `flags` don't count, since it would always be 0.
+    pub fn check(&self, flags: &CountOptions) -> Result<(), String> {
+        let columns = Counts::default().named_columns(flags);
+        match self
+            .counts()
+            .find(|name| !columns.iter().any(|(column, _)| column == name))
+        {
+            Some(name) => Err(format!(
+                "rswc: {{{}}} in --format is not a count of this run (counted: {})",
+                name,
+                columns
+                    .iter()
+                    .map(|(column, _)| *column)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The row of the input `name`, without a newline.
+    pub fn render(&self, name: &str, counts: &Counts, flags: &CountOptions) -> String {
+        let columns = counts.named_columns(flags);
+        let mut out = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Path => out.push_str(name),
+                Piece::Count(name) => {
+                    let value = columns.iter().find(|(column, _)| column == name);
+                    out.push_str(&value.map_or(0, |(_, v)| *v).to_string());
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Prints a line by `template` for each of `results`, archive members and
+/// archive subtotals included, and one for `total` named `total` if given.
+/// Errors go to `errors` instead, so the output has only the lines asked for.
+pub fn print_formatted<W: Write, E: Write>(
+    writer: &mut W,
+    errors: &mut E,
+    results: &[FileResult],
+    total: Option<&Counts>,
+    flags: &CountOptions,
+    template: &Template,
+) -> io::Result<()> {
+    for r in results {
+        match r {
+            FileResult::Ok(path, c) => {
+                writeln!(
+                    writer,
+                    "{}",
+                    template.render(&path.display().to_string(), c, flags)
+                )?;
+            }
+            FileResult::Err(path, msg) => writeln!(errors, "rswc: {}: {}", path.display(), msg)?,
+            FileResult::Archive(path, members) => {
+                for m in members {
+                    match m {
+                        FileResult::Ok(member, c) => {
+                            let name = format!("{}!{}", path.display(), member.display());
+                            writeln!(writer, "{}", template.render(&name, c, flags))?;
+                        }
+                        FileResult::Err(member, msg) => writeln!(
+                            errors,
+                            "rswc: {}!{}: {}",
+                            path.display(),
+                            member.display(),
+                            msg
+                        )?,
+                        FileResult::Archive(..) => {}
+                    }
+                }
+                let subtotal = crate::counter::total(members);
+                writeln!(
+                    writer,
+                    "{}",
+                    template.render(&path.display().to_string(), &subtotal, flags)
+                )?;
+            }
+        }
+    }
+    if let Some(total) = total {
+        writeln!(writer, "{}", template.render("total", total, flags))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template() {
+        let template = Template::new(r"{path}\t{lines}:{{{words}}}\\").unwrap();
+        assert_eq!(template.counts().collect::<Vec<_>>(), ["lines", "words"]);
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            ..Default::default()
+        };
+        let counts = Counts {
+            lines: 3,
+            words: 12,
+            ..Default::default()
+        };
+        assert_eq!(template.render("a.txt", &counts, &flags), "a.txt\t3:{12}\\");
+
+        assert!(template.check(&flags).is_ok());
+        assert!(Template::new("{chars}").unwrap().check(&flags).is_err());
+        assert!(Template::new("{lines").is_err());
+        assert!(Template::new("lines}").is_err());
+        assert!(Template::new("{Lines}").is_err());
+        assert!(Template::new(r"\q").is_err());
+    }
+
+    #[test]
+    fn test_print_formatted() {
+        let flags = CountOptions {
+            bytes: true,
+            ..Default::default()
+        };
+        let file = |bytes| Counts {
+            bytes,
+            ..Default::default()
+        };
+        let results = [
+            FileResult::Ok("a".into(), file(5)),
+            FileResult::Err(
+                "gone".into(),
+                io::Error::other("No such file or directory").into(),
+            ),
+            FileResult::Archive(
+                "b.zip".into(),
+                vec![
+                    FileResult::Ok("x".into(), file(1)),
+                    FileResult::Ok("y".into(), file(2)),
+                ],
+            ),
+        ];
+        let template = Template::new("{bytes} {path}").unwrap();
+        let (mut out, mut errors) = (Vec::new(), Vec::new());
+        print_formatted(
+            &mut out,
+            &mut errors,
+            &results,
+            Some(&file(8)),
+            &flags,
+            &template,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "5 a\n1 b.zip!x\n2 b.zip!y\n3 b.zip\n8 total\n"
+        );
+        assert_eq!(
+            String::from_utf8(errors).unwrap(),
+            "rswc: gone: No such file or directory\n"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Regular expression matching for `--count-pattern`, `--match`,
+//! `--ignore-regex` and `--extract`.
+
+use crate::groups::Groups;
+use crate::lines::LineSplitter;
+use regex::bytes::Regex;
+use std::fmt;
+
+/// A regular expression from the command line, compared by its source.
+#[derive(Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Self, String> {
+        Regex::new(source).map(Pattern).map_err(|e| {
+            // Syntax errors come with a drawing of the pattern; only keep
+            // the description from its last line.
+            let message = e.to_string();
+            let reason = message.lines().last().unwrap_or_default();
+            let reason = reason.strip_prefix("error: ").unwrap_or(reason);
+            format!("rswc: invalid pattern '{}': {}", source, reason)
+        })
+    }
+
+    /// A pattern matching wherever any of `sources` does.
+    pub fn any<'s>(sources: impl IntoIterator<Item = &'s str>) -> Result<Self, String> {
+        let alternatives: Vec<String> = sources
+            .into_iter()
+            .map(|source| Pattern::new(source).map(|_| format!("(?:{})", source)))
+            .collect::<Result<_, _>>()?;
+        Pattern::new(&alternatives.join("|"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, data: &[u8]) -> bool {
+        self.0.is_match(data)
+    }
+
+    /// The number of capture groups, the whole match included.
+    pub fn captures_len(&self) -> usize {
+        self.0.captures_len()
+    }
+
+    /// What capture group `group` matched in the first match in `data`.
+    fn capture<'d>(&self, data: &'d [u8], group: usize) -> Option<&'d [u8]> {
+        Some(self.0.captures(data)?.get(group)?.as_bytes())
+    }
+
+    fn count_in_lines(&self, data: &[u8], delimiter: u8) -> usize {
+        data.split(|&b| b == delimiter)
+            .map(|line| self.0.find_iter(line).filter(|m| !m.is_empty()).count())
+            .sum()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pattern({:?})", self.as_str())
+    }
+}
+
+/// Serialized as its source.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Pattern::new(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Counts the matches of a pattern in input fed in chunks of any size. The
+/// input is searched line by line like `grep -o`: matches never span lines,
+/// `^` and `$` anchor at line ends, and empty matches aren't counted.
+pub struct MatchCounter<'a> {
+    pattern: &'a Pattern,
+    // The line cut off at the end of the last chunk.
+    pending: Vec<u8>,
+    count: u64,
+    delimiter: u8,
+}
+
+impl<'a> MatchCounter<'a> {
+    pub fn new(pattern: &'a Pattern) -> Self {
+        MatchCounter {
+            pattern,
+            pending: Vec::new(),
+            count: 0,
+            delimiter: b'\n',
+        }
+    }
+
+    /// Splits lines at `delimiter` instead of `\n`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let Some(end) = chunk.iter().rposition(|&b| b == self.delimiter) else {
+            self.pending.extend_from_slice(chunk);
+            return;
+        };
+
+        if self.pending.is_empty() {
+            self.count += self.pattern.count_in_lines(&chunk[..end], self.delimiter) as u64;
+        } else {
+            self.pending.extend_from_slice(&chunk[..end]);
+            self.count += self.pattern.count_in_lines(&self.pending, self.delimiter) as u64;
+            self.pending.clear();
+        }
+        self.pending.extend_from_slice(&chunk[end + 1..]);
+    }
+
+    pub fn finish(self) -> u64 {
+        self.count + self.pattern.count_in_lines(&self.pending, self.delimiter) as u64
+    }
+}
+
+/// Passes on only the lines that match a pattern, or with `invert` only
+/// those that don't. Lines keep their newline; a pattern is matched against
+/// the line without it.
+pub struct LineFilter<'a> {
+    pattern: &'a Pattern,
+    invert: bool,
+    lines: LineSplitter,
+}
+
+impl<'a> LineFilter<'a> {
+    pub fn new(pattern: &'a Pattern, invert: bool) -> Self {
+        LineFilter {
+            pattern,
+            invert,
+            lines: LineSplitter::default(),
+        }
+    }
+
+    /// Splits lines at `delimiter` instead of `\n`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.lines = LineSplitter::new(delimiter);
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8], mut emit: impl FnMut(&[u8])) {
+        let delimiter = [self.lines.delimiter()];
+        let (pattern, invert) = (self.pattern, self.invert);
+        self.lines.feed(chunk, |line| {
+            if pattern.is_match(line) != invert {
+                emit(line);
+                emit(&delimiter);
+            }
+        });
+    }
+
+    /// Passes on the last line if it had no newline.
+    pub fn finish(self, mut emit: impl FnMut(&[u8])) {
+        self.lines.finish(|line| {
+            if self.pattern.is_match(line) != self.invert {
+                emit(line);
+            }
+        });
+    }
+}
+
+/// Counts the lines of each value that a capture group of a pattern takes
+/// in input fed in chunks of any size. Only the first match of a line
+/// counts, and lines where the group doesn't match count for no value.
+pub struct GroupCounter<'a> {
+    pattern: &'a Pattern,
+    group: usize,
+    groups: Groups,
+    lines: LineSplitter,
+}
+
+impl<'a> GroupCounter<'a> {
+    pub fn new(pattern: &'a Pattern, group: usize) -> Self {
+        GroupCounter {
+            pattern,
+            group,
+            groups: Groups::default(),
+            lines: LineSplitter::default(),
+        }
+    }
+
+    /// Splits lines at `delimiter` instead of `\n`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.lines = LineSplitter::new(delimiter);
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut lines = std::mem::take(&mut self.lines);
+        lines.feed(chunk, |line| self.add(line));
+        self.lines = lines;
+    }
+
+    fn add(&mut self, line: &[u8]) {
+        if let Some(value) = self.pattern.capture(line, self.group) {
+            self.groups.add(value);
+        }
+    }
+
+    /// Counts the last line if it had no newline.
+    pub fn finish(mut self) -> Groups {
+        let lines = std::mem::take(&mut self.lines);
+        lines.finish(|line| self.add(line));
+        self.groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_counter_in_any_chunking() {
+        let pattern = Pattern::new(r"^ERROR|\d+").unwrap();
+        let data = b"ERROR 42 failed\ninfo ERROR 7\n\nERROR\nno digits";
+        for size in 1..=data.len() {
+            let mut counter = MatchCounter::new(&pattern);
+            for chunk in data.chunks(size) {
+                counter.feed(chunk);
+            }
+            assert_eq!(counter.finish(), 4, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_match_counter_skips_empty_matches() {
+        let pattern = Pattern::new("x*").unwrap();
+        let mut counter = MatchCounter::new(&pattern);
+        counter.feed(b"axxb\nx\n");
+        assert_eq!(counter.finish(), 2);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert_eq!(
+            Pattern::new("(").unwrap_err(),
+            "rswc: invalid pattern '(': unclosed group"
+        );
+    }
+
+    #[test]
+    fn test_any_pattern() {
+        let pattern = Pattern::any(["^a", "b$"]).unwrap();
+        assert!(pattern.is_match(b"ax") && pattern.is_match(b"xb"));
+        assert!(!pattern.is_match(b"xa"));
+        assert_eq!(
+            Pattern::any(["ok", "(unclosed"]).unwrap_err(),
+            "rswc: invalid pattern '(unclosed': unclosed group"
+        );
+    }
+
+    fn filtered(data: &[u8], size: usize, invert: bool) -> Vec<u8> {
+        let pattern = Pattern::new("^ERROR").unwrap();
+        let mut filter = LineFilter::new(&pattern, invert);
+        let mut out = Vec::new();
+        for chunk in data.chunks(size) {
+            filter.feed(chunk, |line| out.extend_from_slice(line));
+        }
+        filter.finish(|line| out.extend_from_slice(line));
+        out
+    }
+
+    #[test]
+    fn test_line_filter_in_any_chunking() {
+        let data = b"ERROR one\ninfo two\nERROR three";
+        for size in 1..=data.len() {
+            assert_eq!(
+                filtered(data, size, false),
+                b"ERROR one\nERROR three",
+                "chunk size {}",
+                size
+            );
+            assert_eq!(
+                filtered(data, size, true),
+                b"info two\n",
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_group_counter_in_any_chunking() {
+        let pattern = Pattern::new(r#"" (\d{3}) (\d+)?"#).unwrap();
+        let log =
+            b"GET / HTTP/1.1\" 200 512\nGET /x HTTP/1.1\" 404 -\nbad line\nGET / HTTP/1.1\" 200 90";
+        for size in 1..=log.len() {
+            let groups = |group| {
+                let mut counter = GroupCounter::new(&pattern, group);
+                for chunk in log.chunks(size) {
+                    counter.feed(chunk);
+                }
+                counter.finish()
+            };
+            let statuses = groups(1);
+            assert_eq!(
+                statuses.sorted(),
+                [(&b"200"[..], 2), (&b"404"[..], 1)],
+                "chunk size {}",
+                size
+            );
+            let sizes = groups(2);
+            assert_eq!(
+                sizes.sorted(),
+                [(&b"512"[..], 1), (&b"90"[..], 1)],
+                "chunk size {}",
+                size
+            );
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Progress of long runs on stderr, as bars for people or as NDJSON
+//! records for programs.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+/// Records are written less often than bars are redrawn, as every one of
+/// them stays in the output.
+const RECORD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How progress is shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// A bar per file being counted, redrawn in place.
+    #[default]
+    Bars,
+    /// One JSON object per line with the files and bytes done so far, the
+    /// totals and an estimate of the time left.
+    Json,
+}
+
+impl ProgressFormat {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "bars" => Ok(ProgressFormat::Bars),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--progress' (expected bars or json)",
+                name
+            )),
+        }
+    }
+
+    fn interval(self) -> Duration {
+        match self {
+            ProgressFormat::Bars => REDRAW_INTERVAL,
+            ProgressFormat::Json => RECORD_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FileProgress {
+    path: PathBuf,
+    /// Size from metadata, 0 when unknown (pipes, devices).
+    size: u64,
+    done: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    format: ProgressFormat,
+    files: Vec<FileProgress>,
+    started: Instant,
+    drawn_lines: usize,
+    last_draw: Option<Instant>,
+}
+
+/// Progress of the files being counted, shown on stderr.
+#[derive(Debug)]
+pub struct ProgressMeter {
+    state: Mutex<State>,
+}
+
+impl ProgressMeter {
+    pub fn new(files: &[PathBuf], format: ProgressFormat) -> Self {
+        let files = files
+            .iter()
+            .map(|path| FileProgress {
+                path: path.clone(),
+                size: fs::metadata(path)
+                    .ok()
+                    .filter(|m| m.is_file())
+                    .map_or(0, |m| m.len()),
+                done: 0,
+            })
+            .collect();
+
+        ProgressMeter {
+            state: Mutex::new(State {
+                format,
+                files,
+                started: Instant::now(),
+                drawn_lines: 0,
+                last_draw: None,
+            }),
+        }
+    }
+
+    /// Records `bytes` more counted for file `index`, showing the progress
+    /// if it was last shown long enough ago.
+    pub fn advance(&self, index: usize, bytes: usize) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(file) = state.files.get_mut(index) {
+            file.done += bytes as u64;
+        }
+
+        if state
+            .last_draw
+            .is_some_and(|t| t.elapsed() < state.format.interval())
+        {
+            return;
+        }
+        state.last_draw = Some(Instant::now());
+
+        let mut err = io::stderr().lock();
+        let _ = state.draw(&mut err);
+    }
+
+    /// Erases the bars so the report can be printed on a clean terminal, or
+    /// writes a last record with every file done.
+    pub fn finish(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            let mut err = io::stderr().lock();
+            let _ = match state.format {
+                ProgressFormat::Bars => state.clear(&mut err),
+                ProgressFormat::Json => state.write_record(&mut err, true),
+            };
+        }
+    }
+}
+
+impl State {
+    fn clear<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.drawn_lines > 0 {
+            write!(writer, "\x1b[{}A\x1b[J", self.drawn_lines)?;
+            self.drawn_lines = 0;
+        }
+        writer.flush()
+    }
+
+    fn draw<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.format == ProgressFormat::Json {
+            return self.write_record(writer, false);
+        }
+        self.clear(writer)?;
+
+        for file in &self.files {
+            let in_progress = file.done > 0 && (file.size == 0 || file.done < file.size);
+            if in_progress {
+                writeln!(
+                    writer,
+                    "{}",
+                    render_bar(file.done, file.size, file.path.display())
+                )?;
+                self.drawn_lines += 1;
+            }
+        }
+
+        // The overall bar, with the time left at the rate so far.
+        if self.drawn_lines > 0 {
+            let (done, total) = self.bytes();
+            let name = match eta(self.started.elapsed(), done, total) {
+                Some(left) => format!("total, {} left", format_duration(left)),
+                None => "total".to_string(),
+            };
+            writeln!(writer, "{}", render_bar(done, total, name))?;
+            self.drawn_lines += 1;
+        }
+
+        writer.flush()
+    }
+
+    /// Bytes counted so far, and the size of all files. Files of unknown
+    /// size count as large as what was read of them.
+    fn bytes(&self) -> (u64, u64) {
+        let done = self.files.iter().map(|f| f.done).sum();
+        let total = self.files.iter().map(|f| f.size.max(f.done)).sum();
+        (done, total)
+    }
+
+    /// Writes the progress as one line of JSON. Files of unknown size are
+    /// only done once `finished`.
+    fn write_record<W: Write>(&self, writer: &mut W, finished: bool) -> io::Result<()> {
+        let files_done = self
+            .files
+            .iter()
+            .filter(|f| finished || (f.size > 0 && f.done >= f.size))
+            .count();
+        let (bytes_done, bytes_total) = self.bytes();
+        let elapsed = self.started.elapsed();
+        let eta = if finished {
+            Some(Duration::ZERO)
+        } else {
+            eta(elapsed, bytes_done, bytes_total)
+        };
+
+        writeln!(
+            writer,
+            "{{\"files_done\":{},\"files_total\":{},\"bytes_done\":{},\"bytes_total\":{},\"elapsed_ms\":{},\"eta_ms\":{}}}",
+            files_done,
+            self.files.len(),
+            bytes_done,
+            bytes_total,
+            elapsed.as_millis(),
+            eta.map_or("null".to_string(), |d| d.as_millis().to_string())
+        )?;
+        writer.flush()
+    }
+}
+
+/// The time left to count `total` bytes at the rate `done` bytes were
+/// counted in `elapsed`, unknown until something was counted.
+pub fn eta(elapsed: Duration, done: u64, total: u64) -> Option<Duration> {
+    if done == 0 {
+        return None;
+    }
+    let left = total.saturating_sub(done);
+    Some(elapsed.mul_f64(left as f64 / done as f64))
+}
+
+/// `h:mm:ss`, or `m:ss` under an hour, rounded to the second.
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs_f64().round() as u64;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+fn render_bar(done: u64, size: u64, name: impl fmt::Display) -> String {
+    if size == 0 {
+        return format!("[{}] {:>10} bytes {}", "?".repeat(BAR_WIDTH), done, name);
+    }
+
+    let ratio = (done as f64 / size as f64).min(1.0);
+    let filled = (ratio * BAR_WIDTH as f64) as usize;
+    format!(
+        "[{}{}] {:>3}% {}",
+        "#".repeat(filled),
+        ".".repeat(BAR_WIDTH - filled),
+        (ratio * 100.0) as u32,
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta() {
+        let second = Duration::from_secs(1);
+        assert_eq!(eta(second, 25, 100), Some(3 * second));
+        assert_eq!(eta(second, 100, 100), Some(Duration::ZERO));
+        assert_eq!(eta(second, 0, 100), None);
+    }
+
+    #[test]
+    fn test_write_record() {
+        let state = State {
+            format: ProgressFormat::Json,
+            started: Instant::now(),
+            files: vec![
+                FileProgress {
+                    path: PathBuf::from("a"),
+                    size: 10,
+                    done: 10,
+                },
+                FileProgress {
+                    path: PathBuf::from("-"),
+                    size: 0,
+                    done: 6,
+                },
+            ],
+            drawn_lines: 0,
+            last_draw: None,
+        };
+
+        let mut out = Vec::new();
+        state.write_record(&mut out, false).unwrap();
+        let record = String::from_utf8(out).unwrap();
+        assert!(
+            record.starts_with(
+                r#"{"files_done":1,"files_total":2,"bytes_done":16,"bytes_total":16,"elapsed_ms":"#
+            ),
+            "{}",
+            record
+        );
+        assert!(record.ends_with("\"eta_ms\":0}\n"), "{}", record);
+    }
+
+    #[test]
+    fn test_render_bar() {
+        assert_eq!(
+            render_bar(50, 200, "big.log"),
+            "[#######.......................]  25% big.log"
+        );
+        assert_eq!(
+            render_bar(1024, 0, "-"),
+            "[??????????????????????????????]       1024 bytes -"
+        );
+    }
+
+    #[test]
+    fn test_draw_only_shows_unfinished_files() {
+        let mut state = State {
+            format: ProgressFormat::Bars,
+            started: Instant::now(),
+            files: vec![
+                FileProgress {
+                    path: PathBuf::from("a"),
+                    size: 10,
+                    done: 10,
+                },
+                FileProgress {
+                    path: PathBuf::from("b"),
+                    size: 10,
+                    done: 5,
+                },
+                FileProgress {
+                    path: PathBuf::from("c"),
+                    size: 10,
+                    done: 0,
+                },
+            ],
+            drawn_lines: 0,
+            last_draw: None,
+        };
+
+        let mut out = Vec::new();
+        state.draw(&mut out).unwrap();
+        assert_eq!(state.drawn_lines, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[###############...............]  50% b\n\
+             [###############...............]  50% total, 0:00 left\n"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(65_400)), "1:05");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 3600 + 7)),
+            "3:00:07"
+        );
+    }
+}
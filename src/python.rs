@@ -0,0 +1,155 @@
+//! The `rswc` Python module: counts of bytes and files for Python code,
+//! counted with the GIL released so other threads keep running.
+
+use crate::counter::{CountOptions, Counts, FileResult, count_slice, process_files};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+/// The counts of an input. Those not asked for are 0.
+#[pyclass(name = "Counts", module = "rswc", frozen, get_all, eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyCounts {
+    pub lines: u64,
+    pub words: u64,
+    pub chars: u64,
+    pub bytes: u64,
+    /// Bytes that are not valid UTF-8, counted along with `chars`.
+    pub invalid_bytes: u64,
+}
+
+#[pymethods]
+impl PyCounts {
+    fn __repr__(&self) -> String {
+        format!(
+            "Counts(lines={}, words={}, chars={}, bytes={}, invalid_bytes={})",
+            self.lines, self.words, self.chars, self.bytes, self.invalid_bytes
+        )
+    }
+}
+
+impl PyCounts {
+    /// The counts `flags` asked for; the others may have been counted on
+    /// the way.
+    fn new(counts: &Counts, flags: &CountOptions) -> Self {
+        let asked = |asked: bool, n: u64| if asked { n } else { 0 };
+        PyCounts {
+            lines: asked(flags.lines, counts.lines),
+            words: asked(flags.words, counts.words),
+            chars: asked(flags.chars, counts.chars),
+            bytes: asked(flags.bytes, counts.bytes),
+            invalid_bytes: asked(flags.chars, counts.invalid_bytes),
+        }
+    }
+}
+
+/// The options for `flags` given like the short options of wc, `"lw"` for
+/// lines and words; everything for none.
+fn options(flags: Option<&str>) -> PyResult<CountOptions> {
+    let Some(flags) = flags else {
+        return Ok(CountOptions::new()
+            .lines(true)
+            .words(true)
+            .chars(true)
+            .bytes(true));
+    };
+    flags
+        .chars()
+        .try_fold(CountOptions::new(), |options, flag| {
+            Ok(match flag {
+                'l' => options.lines(true),
+                'w' => options.words(true),
+                'm' => options.chars(true),
+                'c' => options.bytes(true),
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "invalid flag '{}' (expected some of 'lwmc')",
+                        flag
+                    )));
+                }
+            })
+        })
+}
+
+/// The counts of `results` in order, or an `OSError` for the first file
+/// that couldn't be counted.
+fn counts_of(results: Vec<FileResult>, flags: &CountOptions) -> PyResult<Vec<PyCounts>> {
+    results
+        .into_iter()
+        .map(|result| match result {
+            FileResult::Ok(_, counts) => Ok(PyCounts::new(&counts, flags)),
+            FileResult::Err(path, e) => {
+                Err(PyOSError::new_err(format!("{}: {}", path.display(), e)))
+            }
+            FileResult::Archive(path, _) => Err(PyOSError::new_err(format!(
+                "{}: archives are not counted",
+                path.display()
+            ))),
+        })
+        .collect()
+}
+
+/// Counts lines, words, characters and bytes of `data`.
+#[pyfunction]
+fn count_bytes(py: Python<'_>, data: &[u8]) -> PyCounts {
+    let flags = options(None).unwrap_or_default();
+    py.detach(|| PyCounts::new(&count_slice(data, &flags), &flags))
+}
+
+/// Counts the file at `path`, what `flags` asks for or everything.
+#[pyfunction]
+#[pyo3(signature = (path, flags=None))]
+fn count_file(py: Python<'_>, path: PathBuf, flags: Option<&str>) -> PyResult<PyCounts> {
+    let flags = options(flags)?;
+    let results = py.detach(|| process_files(&[path], &flags));
+    Ok(counts_of(results, &flags)?.remove(0))
+}
+
+/// Counts the files at `paths` in parallel, what `flags` asks for or
+/// everything.
+#[pyfunction]
+#[pyo3(signature = (paths, flags=None))]
+fn count_files(
+    py: Python<'_>,
+    paths: Vec<PathBuf>,
+    flags: Option<&str>,
+) -> PyResult<Vec<PyCounts>> {
+    let flags = options(flags)?;
+    counts_of(py.detach(|| process_files(&paths, &flags)), &flags)
+}
+
+#[pymodule]
+fn rswc(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCounts>()?;
+    module.add_function(wrap_pyfunction!(count_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(count_file, module)?)?;
+    module.add_function(wrap_pyfunction!(count_files, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_asked_counts() {
+        let flags = options(Some("lc")).unwrap();
+        let counts = PyCounts::new(&count_slice(b"one two\n", &flags), &flags);
+        let expected = PyCounts {
+            lines: 1,
+            words: 0,
+            chars: 0,
+            bytes: 8,
+            invalid_bytes: 0,
+        };
+        assert_eq!(counts, expected);
+
+        let flags = options(None).unwrap();
+        let counts = PyCounts::new(&count_slice(b"\xffx y", &flags), &flags);
+        assert_eq!(
+            (counts.words, counts.chars, counts.invalid_bytes),
+            (2, 3, 1)
+        );
+        assert!(options(Some("lx")).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Line ranges for `--range`, like `100:200`, so a section of every input
+//! can be counted without cutting it out first.
+
+/// Lines `start` to `end` of an input, counting from 1, both included. No
+/// `end` is to the end of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl LineRange {
+    /// Parses `START:END`, where `START` defaults to 1 and `END` to the end
+    /// of the input, like `100:200`, `100:` or `:200`.
+    pub fn new(spec: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "rswc: invalid argument '{}' for '--range' (expected START:END, like 100:200)",
+                spec
+            )
+        };
+        let (start, end) = spec.split_once(':').ok_or_else(invalid)?;
+        let start = match start {
+            "" => 1,
+            n => n.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+        };
+        let end = match end {
+            "" => None,
+            n => Some(n.parse().ok().filter(|&n| n >= start).ok_or_else(invalid)?),
+        };
+        Ok(LineRange { start, end })
+    }
+
+    /// The lines before the range.
+    pub fn skipped(&self) -> usize {
+        self.start - 1
+    }
+
+    /// The lines in the range, if it ends.
+    pub fn lines(&self) -> Option<usize> {
+        self.end.map(|end| end - self.skipped())
+    }
+}
+
+/// Counts `left` down at every `delimiter` of `chunk`, and gives the end of
+/// the line it reaches 0 at, if that is in `chunk`.
+pub(crate) fn end_of_lines(chunk: &[u8], delimiter: u8, left: &mut usize) -> Option<usize> {
+    if *left == 0 {
+        return Some(0);
+    }
+    for (i, &b) in chunk.iter().enumerate() {
+        if b == delimiter {
+            *left -= 1;
+            if *left == 0 {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_range() {
+        let range = LineRange::new("100:200").unwrap();
+        assert_eq!((range.skipped(), range.lines()), (99, Some(101)));
+        assert_eq!(LineRange::new("5:").unwrap().lines(), None);
+        assert_eq!(LineRange::new(":3").unwrap().skipped(), 0);
+        assert_eq!(LineRange::new("7:7").unwrap().lines(), Some(1));
+        for spec in ["", "7", "0:3", "3:2", "a:b"] {
+            assert!(LineRange::new(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_end_of_lines() {
+        let mut left = 2;
+        assert_eq!(end_of_lines(b"a\nb", b'\n', &mut left), None);
+        assert_eq!(end_of_lines(b"\nc\n", b'\n', &mut left), Some(1));
+        assert_eq!(end_of_lines(b"d\n", b'\n', &mut left), Some(0));
+    }
+}
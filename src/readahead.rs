@@ -0,0 +1,200 @@
+//! Reading a stream on a thread of its own, a few buffers ahead of the
+//! thread counting it, so that reading from a fast producer like `pv` or a
+//! decompressor and counting overlap instead of taking turns. With
+//! `--timeout`, the stream also ends when nothing arrives for a while, so
+//! a run that was given no input doesn't wait forever.
+
+use std::io::{self, Read};
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Buffers of a `ReadAhead`: one being counted while the others are read
+/// into.
+const BUFFERS: usize = 3;
+
+/// Set when a `ReadAhead` gave up waiting.
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+
+/// Whether reading gave up on input that stopped arriving.
+pub fn timed_out() -> bool {
+    TIMED_OUT.load(Ordering::Relaxed)
+}
+
+/// A stream read ahead by a thread of its own, handed over a chunk at a
+/// time. Once the `ReadAhead` is dropped, the thread stops after the read
+/// it is waiting on, if any.
+pub struct ReadAhead {
+    filled: Receiver<io::Result<Chunk>>,
+    empty: Sender<Vec<u8>>,
+    timeout: Option<Duration>,
+    /// The chunk being read through `Read`, and how much of it was.
+    chunk: Chunk,
+    pos: usize,
+}
+
+/// Bytes read at once, at the start of a buffer that is kept whole to be
+/// read into again.
+pub struct Chunk {
+    buf: Vec<u8>,
+    len: usize,
+}
+
+impl Deref for Chunk {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl ReadAhead {
+    /// Reads `reader` `size` bytes at a time at most. Every chunk is handed
+    /// over as soon as it is read, without waiting for the buffer to fill.
+    pub fn spawn<R: Read + Send + 'static>(mut reader: R, size: usize) -> Self {
+        let (filled_sender, filled) = mpsc::sync_channel(BUFFERS);
+        let (empty, empty_receiver) = mpsc::channel::<Vec<u8>>();
+        thread::spawn(move || {
+            let mut unallocated = BUFFERS;
+            loop {
+                let mut buf = if unallocated > 0 {
+                    unallocated -= 1;
+                    vec![0; size]
+                } else {
+                    match empty_receiver.recv() {
+                        Ok(buf) => buf,
+                        Err(_) => break,
+                    }
+                };
+                let read = loop {
+                    match reader.read(&mut buf) {
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                        read => break read,
+                    }
+                };
+                let sent = match read {
+                    Ok(0) => break,
+                    Ok(len) => filled_sender.send(Ok(Chunk { buf, len })),
+                    Err(e) => {
+                        let _ = filled_sender.send(Err(e));
+                        break;
+                    }
+                };
+                if sent.is_err() {
+                    break;
+                }
+            }
+        });
+        ReadAhead {
+            filled,
+            empty,
+            timeout: None,
+            chunk: Chunk {
+                buf: Vec::new(),
+                len: 0,
+            },
+            pos: 0,
+        }
+    }
+
+    /// Ends the stream once nothing has arrived for `timeout`, noting that
+    /// for `timed_out`.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The next chunk read, or `None` at the end of the stream.
+    pub fn next_chunk(&mut self) -> io::Result<Option<Chunk>> {
+        let next = match self.timeout {
+            Some(timeout) => match self.filled.recv_timeout(timeout) {
+                Ok(chunk) => Some(chunk),
+                Err(RecvTimeoutError::Timeout) => {
+                    TIMED_OUT.store(true, Ordering::Relaxed);
+                    None
+                }
+                Err(RecvTimeoutError::Disconnected) => None,
+            },
+            None => self.filled.recv().ok(),
+        };
+        next.transpose()
+    }
+
+    /// Gives back a chunk from `next_chunk`, to be read into again.
+    pub fn recycle(&self, chunk: Chunk) {
+        // The first chunk of `Read` has no buffer.
+        if !chunk.buf.is_empty() {
+            let _ = self.empty.send(chunk.buf);
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len {
+            let Some(chunk) = self.next_chunk()? else {
+                return Ok(0);
+            };
+            let used = mem::replace(&mut self.chunk, chunk);
+            self.recycle(used);
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.chunk.len - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gives its bytes, then waits as if for input that never comes.
+    struct Stalled(&'static [u8]);
+
+    impl Read for Stalled {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                thread::sleep(Duration::from_secs(60));
+            }
+            let n = buf.len().min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_ahead() {
+        let data: Vec<u8> = (0..10_000u32).flat_map(u32::to_le_bytes).collect();
+        let mut chunks = ReadAhead::spawn(io::Cursor::new(data.clone()), 1000);
+        let mut read = Vec::new();
+        while let Some(chunk) = chunks.next_chunk().unwrap() {
+            assert!(chunk.len() <= 1000);
+            read.extend_from_slice(&chunk);
+            chunks.recycle(chunk);
+        }
+        assert_eq!(read, data);
+
+        let mut read = Vec::new();
+        ReadAhead::spawn(io::Cursor::new(data.clone()), 7)
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn test_read_ahead_timeout() {
+        let mut data = Vec::new();
+        ReadAhead::spawn(Stalled(b"one\n"), 3)
+            .with_timeout(Some(Duration::from_millis(50)))
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"one\n");
+        assert!(timed_out());
+    }
+}
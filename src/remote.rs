@@ -0,0 +1,71 @@
+use crate::counter::{CountOptions, Counts, Progress, count_reader_with_progress};
+use std::io;
+
+/// Streams the body of `url` through the counter without buffering it, so
+/// responses of any size can be counted. Connection failures and non-2xx
+/// responses are returned as errors and end up as error rows in the report.
+pub fn count_url(url: &str, flags: &CountOptions, progress: &Progress<'_>) -> io::Result<Counts> {
+    let response = ureq::get(url)
+        .header("User-Agent", concat!("rswc/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(io::Error::other)?;
+    count_reader_with_progress(response.into_body().into_reader(), flags, progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers a single request with `response` and returns the server's URL.
+    fn serve_once(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        format!("http://{}/data.txt", addr)
+    }
+
+    fn flags() -> CountOptions {
+        CountOptions {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_count_url() {
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\nhello world\nbye\n",
+        );
+        let counts = count_url(&url, &flags(), &|_| {}).unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 2,
+                words: 3,
+                bytes: 16,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_url_http_error() {
+        let url =
+            serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        let err = count_url(&url, &flags(), &|_| {}).unwrap_err();
+        assert_eq!(err.to_string(), "http status: 404");
+    }
+}
@@ -0,0 +1,230 @@
+//! Bundles for bug reports from `rswc repro`: the start and end of a file,
+//! its text optionally scrambled, the options it was counted with and the
+//! counts rswc printed, in a `.tar.gz` to attach to an issue.
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// How many bytes of the start and of the end of a file a bundle keeps.
+pub const DEFAULT_SAMPLE: usize = 4096;
+
+/// The start and end of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    /// The size of the whole file.
+    pub len: u64,
+    pub head: Vec<u8>,
+    /// Empty when `head` is the whole file.
+    pub tail: Vec<u8>,
+}
+
+impl Sample {
+    /// The first and last `n` bytes of the file at `path`, or all of it if
+    /// it has no more than `2 * n`.
+    pub fn read(path: &Path, n: usize) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut head = Vec::new();
+        let mut tail = Vec::new();
+        if len <= 2 * n as u64 {
+            file.read_to_end(&mut head)?;
+        } else {
+            (&mut file).take(n as u64).read_to_end(&mut head)?;
+            file.seek(SeekFrom::End(-(n as i64)))?;
+            file.read_to_end(&mut tail)?;
+        }
+        Ok(Sample { len, head, tail })
+    }
+
+    /// Scrambles the text of the sample, keeping what it counts as:
+    /// letters stay letters of the same case, digits stay digits, a
+    /// character keeps its length in UTF-8, and whitespace, punctuation and
+    /// invalid UTF-8 are kept as they are.
+    pub fn anonymize(&mut self) {
+        let mut scrambler = Scrambler(0x9e37_79b9_7f4a_7c15);
+        self.head = scrambler.scramble(&self.head);
+        self.tail = scrambler.scramble(&self.tail);
+    }
+}
+
+/// Replaces letters and digits by pseudo-random ones of the same kind. The
+/// replacements don't depend on the text, so they tell nothing about it.
+struct Scrambler(u64);
+
+impl Scrambler {
+    // xorshift64
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 32) as u32
+    }
+
+    fn scramble(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len());
+        for chunk in data.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                let c = self.replace(c);
+                out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            out.extend_from_slice(chunk.invalid());
+        }
+        out
+    }
+
+    fn replace(&mut self, c: char) -> char {
+        let from = |first: u8, n: u32, r: u32| char::from(first + (r % n) as u8);
+        match c {
+            'a'..='z' => from(b'a', 26, self.next()),
+            'A'..='Z' => from(b'A', 26, self.next()),
+            '0'..='9' => from(b'0', 10, self.next()),
+            c if c.is_ascii() || c.is_whitespace() => c,
+            c if !c.is_alphanumeric() => c,
+            c => {
+                // Another character of the same kind from the same block of
+                // 128, so the script and the length stay the same.
+                let kind = |c: char| {
+                    (
+                        c.is_uppercase(),
+                        c.is_lowercase(),
+                        c.is_alphabetic(),
+                        c.is_numeric(),
+                        c.len_utf8(),
+                    )
+                };
+                let block = u32::from(c) & !0x7f;
+                let start = self.next();
+                (0..128)
+                    .filter_map(|i| char::from_u32(block + (start + i) % 128))
+                    .find(|&other| kind(other) == kind(c))
+                    .unwrap_or(c)
+            }
+        }
+    }
+}
+
+/// Writes a `.tar.gz` bundle of `sample` of the file `name`, the `args` it
+/// was counted with and the `counts` rswc printed for the whole file.
+pub fn write_bundle<W: Write>(
+    writer: W,
+    name: &str,
+    sample: &Sample,
+    args: &[String],
+    counts: &str,
+    anonymized: bool,
+) -> io::Result<()> {
+    let mut about = format!(
+        "rswc {} ({})\nfile: {} ({} bytes)\n",
+        env!("CARGO_PKG_VERSION"),
+        env!("RSWC_TARGET"),
+        name,
+        sample.len
+    );
+    if sample.tail.is_empty() {
+        about.push_str("sample.bin: the whole file\n");
+    } else {
+        about.push_str(&format!(
+            "head.bin, tail.bin: the first {} and last {} bytes\n",
+            sample.head.len(),
+            sample.tail.len()
+        ));
+    }
+    if anonymized {
+        about.push_str("letters and digits are scrambled\n");
+    }
+    let mut args_file: String = args.iter().map(|arg| format!("{}\n", arg)).collect();
+    if args.is_empty() {
+        args_file.push_str("(none)\n");
+    }
+
+    let mut tar = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+    let mut add = |path: &str, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, format!("rswc-repro/{}", path), data)
+    };
+    add("README.txt", about.as_bytes())?;
+    add("args.txt", args_file.as_bytes())?;
+    add("counts.txt", counts.as_bytes())?;
+    if sample.tail.is_empty() {
+        add("sample.bin", &sample.head)?;
+    } else {
+        add("head.bin", &sample.head)?;
+        add("tail.bin", &sample.tail)?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, count_slice};
+    use flate2::read::GzDecoder;
+
+    #[test]
+    fn test_anonymize_keeps_the_counts() {
+        let text = "Hello, Wörld 42!\n\tÉtude 日本語 — ok\u{a0}x\n".as_bytes();
+        let text = [text, b"\xffend"].concat();
+        let mut sample = Sample {
+            len: text.len() as u64,
+            head: text.clone(),
+            tail: Vec::new(),
+        };
+        sample.anonymize();
+        assert_ne!(sample.head, text);
+        assert_eq!(sample.head.len(), text.len());
+
+        let flags = CountOptions {
+            lines: true,
+            words: true,
+            chars: true,
+            bytes: true,
+            ..Default::default()
+        };
+        let (before, after) = (
+            count_slice(&text, &flags),
+            count_slice(&sample.head, &flags),
+        );
+        assert_eq!(after.columns(&flags), before.columns(&flags));
+        let after = String::from_utf8_lossy(&sample.head);
+        assert!(after.contains(", "));
+        assert!(after.contains(" — "));
+        assert!(!after.contains("Hello"));
+    }
+
+    #[test]
+    fn test_bundle() {
+        let sample = Sample {
+            len: 10,
+            head: b"abc".to_vec(),
+            tail: b"xyz".to_vec(),
+        };
+        let mut bundle = Vec::new();
+        let args = ["-l".to_string()];
+        write_bundle(&mut bundle, "a.txt", &sample, &args, "1 a.txt\n", false).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(&bundle[..]));
+        let mut files = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().display().to_string();
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            files.push((path, data));
+        }
+        assert_eq!(files[1], ("rswc-repro/args.txt".into(), "-l\n".into()));
+        assert_eq!(
+            files[2],
+            ("rswc-repro/counts.txt".into(), "1 a.txt\n".into())
+        );
+        assert_eq!(files[3], ("rswc-repro/head.bin".into(), "abc".into()));
+        assert_eq!(files[4], ("rswc-repro/tail.bin".into(), "xyz".into()));
+        assert!(files[0].1.contains("file: a.txt (10 bytes)\n"));
+    }
+}
@@ -0,0 +1,114 @@
+//! `--log`: a record of the counts of every run appended to a file, to
+//! follow how documents grow over weeks, e.g. from cron.
+
+use crate::batch::{counts_json, result_json};
+use crate::counter::{CountOptions, FileResult, total};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The record of a run at `time`: a line of JSON with the time in UTC and
+/// the total, and with `files`, the counts or error of every file too, like
+/// `{"time":"2026-10-17T06:00:00Z","total":{"lines":120}}`.
+pub fn record(
+    time: SystemTime,
+    results: &[FileResult],
+    flags: &CountOptions,
+    files: bool,
+) -> String {
+    let files = if files {
+        let files: Vec<String> = results.iter().map(|r| result_json(r, flags)).collect();
+        format!(",\"files\":[{}]", files.join(","))
+    } else {
+        String::new()
+    };
+    format!(
+        "{{\"time\":\"{}\",\"total\":{}{}}}",
+        utc_timestamp(time),
+        counts_json(&total(results), flags, None),
+        files
+    )
+}
+
+/// Appends `record` and a newline to the log at `path`, creating it if
+/// needed. The log is locked while writing, so the records of runs at the
+/// same time follow each other whole.
+pub fn append(path: &Path, record: &str) -> io::Result<()> {
+    let mut log = File::options().append(true).create(true).open(path)?;
+    log.lock()?;
+    log.write_all(format!("{}\n", record).as_bytes())?;
+    log.flush()
+}
+
+/// `time` as an RFC 3339 timestamp in UTC, to the second.
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_date(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// The year, month and day `days` after 1970-01-01, by Howard Hinnant's
+/// `civil_from_days`.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+    use std::time::Duration;
+    use std::{env, fs};
+
+    #[test]
+    fn test_utc_timestamp() {
+        let at = |secs| utc_timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_792_195_200 + 3_723), "2026-10-17T01:02:03Z");
+    }
+
+    #[test]
+    fn test_append_records() {
+        let flags = CountOptions::new().lines(true);
+        let results = [
+            FileResult::Ok(
+                "a.txt".into(),
+                Counts {
+                    lines: 2,
+                    ..Default::default()
+                },
+            ),
+            FileResult::Err("b.txt".into(), io::Error::other("gone").into()),
+        ];
+        let path = env::temp_dir().join(format!("rswc-log-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        append(&path, &record(UNIX_EPOCH, &results, &flags, false)).unwrap();
+        append(&path, &record(UNIX_EPOCH, &results, &flags, true)).unwrap();
+
+        let expected = r#"{"time":"1970-01-01T00:00:00Z","total":{"lines":2}}
+{"time":"1970-01-01T00:00:00Z","total":{"lines":2},"files":[{"file":"a.txt","lines":2},{"file":"b.txt","error":"gone"}]}
+"#;
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+}
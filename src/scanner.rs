@@ -0,0 +1,652 @@
+//! The byte-level counting state machine. It only depends on `core`, never
+//! allocates, and can be fed input of any chunking, which makes it usable
+//! without the standard library, e.g. on serial streams in firmware.
+
+/// Line, word, byte and character counts of everything fed so far.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Scanner {
+    pub lines: u64,
+    pub words: u64,
+    pub bytes: u64,
+    pub chars: u64,
+    /// Bytes that are not valid UTF-8, when counting characters.
+    pub invalid: u64,
+    /// Offset of the first of the `invalid` bytes.
+    pub first_invalid: Option<u64>,
+    /// Characters of the longest word, when measuring words.
+    pub longest_word: u64,
+    count_chars: bool,
+    measure_words: bool,
+    // Characters of the word being read.
+    word_chars: u64,
+    invalid_utf8: InvalidUtf8,
+    // The bytes that split words under the word rules.
+    separators: ByteSet,
+    delimiter: u8,
+    in_word: bool,
+    // Whether whitespace beyond ASCII splits words too, and the start of
+    // such a character cut off at the end of the last chunk.
+    unicode_whitespace: bool,
+    space: [u8; 3],
+    space_len: usize,
+    // Leading bytes of a UTF-8 sequence cut off at the end of the last chunk.
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl Scanner {
+    /// Character counting validates UTF-8 and is skipped unless asked for.
+    pub const fn new(count_chars: bool) -> Self {
+        Scanner {
+            lines: 0,
+            words: 0,
+            bytes: 0,
+            chars: 0,
+            invalid: 0,
+            first_invalid: None,
+            longest_word: 0,
+            count_chars,
+            measure_words: false,
+            word_chars: 0,
+            invalid_utf8: InvalidUtf8::Skip,
+            separators: WordRules::DEFAULT.table(),
+            delimiter: b'\n',
+            in_word: false,
+            unicode_whitespace: false,
+            space: [0; 3],
+            space_len: 0,
+            partial: [0; 4],
+            partial_len: 0,
+        }
+    }
+
+    /// Splits words by `rules` instead of at whitespace only.
+    pub const fn with_word_rules(mut self, rules: WordRules) -> Self {
+        self.separators = rules.table();
+        self.unicode_whitespace = rules.unicode_whitespace;
+        self
+    }
+
+    /// Counts the characters of malformed UTF-8 by `policy`.
+    pub const fn with_invalid_utf8(mut self, policy: InvalidUtf8) -> Self {
+        self.invalid_utf8 = policy;
+        self
+    }
+
+    /// Measures the longest word in characters, a little slower.
+    pub const fn with_longest_word(mut self, measure: bool) -> Self {
+        self.measure_words = measure;
+        self
+    }
+
+    /// Counts records ending in `delimiter` as lines instead of `\n`.
+    pub const fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Carries on after input that ended in `last` without counting it,
+    /// like input resumed at an offset: a word `last` is part of isn't
+    /// counted again.
+    pub fn continue_after(&mut self, last: u8) {
+        self.in_word = !self.separators.contains(last);
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.bytes += chunk.len() as u64;
+
+        if self.unicode_whitespace {
+            for &b in chunk {
+                if b == self.delimiter {
+                    self.lines += 1;
+                }
+                self.feed_unicode_word(b);
+            }
+        } else if self.measure_words {
+            for &b in chunk {
+                if b == self.delimiter {
+                    self.lines += 1;
+                }
+
+                if self.separators.contains(b) {
+                    self.in_word = false;
+                } else {
+                    self.word_byte(b);
+                }
+            }
+        } else {
+            for &b in chunk {
+                if b == self.delimiter {
+                    self.lines += 1;
+                }
+
+                if self.separators.contains(b) {
+                    self.in_word = false;
+                } else if !self.in_word {
+                    self.words += 1;
+                    self.in_word = true;
+                }
+            }
+        }
+
+        if self.count_chars {
+            self.feed_chars(chunk);
+        }
+    }
+
+    /// Words split at Unicode whitespace too. The first bytes of what may be
+    /// a space beyond ASCII are held back until the character is complete.
+    fn feed_unicode_word(&mut self, b: u8) {
+        if self.space_len > 0 {
+            self.space[self.space_len] = b;
+            let sequence = &self.space[..self.space_len + 1];
+            if UNICODE_SPACES.contains(&sequence) {
+                self.in_word = false;
+                self.space_len = 0;
+                return;
+            }
+            if UNICODE_SPACES
+                .iter()
+                .any(|space| space.starts_with(sequence))
+            {
+                self.space_len += 1;
+                return;
+            }
+            // Not a space after all: the held back bytes, one character
+            // cut short, are part of a word, and `b` is looked at afresh.
+            self.space_len = 0;
+            self.word_byte(self.space[0]);
+        }
+
+        if self.separators.contains(b) {
+            self.in_word = false;
+        } else if UNICODE_SPACES.iter().any(|space| space[0] == b) {
+            self.space[0] = b;
+            self.space_len = 1;
+        } else {
+            self.word_byte(b);
+        }
+    }
+
+    fn word_byte(&mut self, b: u8) {
+        if !self.in_word {
+            self.words += 1;
+            self.in_word = true;
+            self.word_chars = 0;
+        }
+        // Continuation bytes are part of the character before.
+        if self.measure_words && (b as i8) >= -0x40 {
+            self.word_chars += 1;
+            self.longest_word = self.longest_word.max(self.word_chars);
+        }
+    }
+
+    fn feed_chars(&mut self, mut chunk: &[u8]) {
+        if self.invalid_utf8 == InvalidUtf8::Unchecked {
+            self.chars += char_starts(chunk) as u64;
+            return;
+        }
+        let mut offset = self.bytes - chunk.len() as u64;
+
+        // Complete the character cut off at the end of the last chunk, a
+        // byte at a time, since the chunk may end before it does.
+        while self.partial_len > 0 && !chunk.is_empty() {
+            let len = self.partial_len + 1;
+            self.partial[self.partial_len] = chunk[0];
+            match core::str::from_utf8(&self.partial[..len]) {
+                Ok(_) => {
+                    self.chars += 1;
+                    self.partial_len = 0;
+                }
+                Err(e) if e.error_len().is_none() => self.partial_len = len,
+                // The byte doesn't continue the sequence: what came before
+                // it is malformed, and the byte is looked at afresh.
+                Err(_) => {
+                    self.invalid_sequence(offset - self.partial_len as u64, self.partial_len);
+                    self.partial_len = 0;
+                    continue;
+                }
+            }
+            chunk = &chunk[1..];
+            offset += 1;
+        }
+
+        while !chunk.is_empty() {
+            match core::str::from_utf8(chunk) {
+                Ok(_) => {
+                    self.chars += char_starts(chunk) as u64;
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = chunk.split_at(e.valid_up_to());
+                    self.chars += char_starts(valid) as u64;
+                    match e.error_len() {
+                        Some(len) => {
+                            self.invalid_sequence(offset + valid.len() as u64, len);
+                            chunk = &rest[len..];
+                            offset += (valid.len() + len) as u64;
+                        }
+                        // The chunk ends in the middle of a character: keep
+                        // the tail for the next chunk.
+                        None => {
+                            self.partial[..rest.len()].copy_from_slice(rest);
+                            self.partial_len = rest.len();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn invalid_sequence(&mut self, offset: u64, len: usize) {
+        self.invalid += len as u64;
+        self.first_invalid.get_or_insert(offset);
+        if self.invalid_utf8 == InvalidUtf8::Lossy {
+            self.chars += 1;
+        }
+    }
+
+    /// Ends the input: a character still cut off is malformed, and part of
+    /// a word.
+    pub fn flush(&mut self) {
+        if self.space_len > 0 {
+            self.space_len = 0;
+            self.word_byte(self.space[0]);
+        }
+        if self.partial_len > 0 {
+            self.invalid_sequence(self.bytes - self.partial_len as u64, self.partial_len);
+            self.partial_len = 0;
+        }
+    }
+}
+
+/// The bytes of `bytes` that start a character rather than continue one,
+/// which are its characters if it is valid UTF-8.
+#[cfg(feature = "simd")]
+fn char_starts(bytes: &[u8]) -> usize {
+    bytecount::num_chars(bytes)
+}
+
+#[cfg(not(feature = "simd"))]
+fn char_starts(bytes: &[u8]) -> usize {
+    // Continuation bytes are 0b10xxxxxx, -64 and less as `i8`. Written as a
+    // plain filter so the compiler vectorizes it.
+    bytes.iter().filter(|&&b| (b as i8) >= -0x40).count()
+}
+
+/// What counting characters does with bytes that are not valid UTF-8. A
+/// malformed sequence is as long as `String::from_utf8_lossy` takes it to be.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum InvalidUtf8 {
+    /// Each malformed sequence is one character, as if replaced by U+FFFD.
+    Lossy,
+    /// Malformed input is an error for the whole input.
+    Strict,
+    /// Malformed sequences are no characters, as in GNU wc.
+    #[default]
+    Skip,
+    /// Like `Skip`, with the invalid bytes counted on their own.
+    Count,
+    /// Every byte that doesn't continue a character is one, without
+    /// validating the input. The same as `Skip` on valid UTF-8, and the
+    /// fastest.
+    Unchecked,
+}
+
+#[cfg(feature = "std")]
+impl InvalidUtf8 {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "lossy" => Ok(InvalidUtf8::Lossy),
+            "strict" => Ok(InvalidUtf8::Strict),
+            "skip" => Ok(InvalidUtf8::Skip),
+            "count" => Ok(InvalidUtf8::Count),
+            "unchecked" => Ok(InvalidUtf8::Unchecked),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--invalid-utf8' (expected lossy, strict, skip, count or unchecked)",
+                name
+            )),
+        }
+    }
+}
+
+/// A set of bytes as a 256-bit lookup table.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    pub const EMPTY: ByteSet = ByteSet([0; 4]);
+
+    pub const fn from_bytes(bytes: &[u8]) -> Self {
+        let mut set = ByteSet::EMPTY;
+        let mut i = 0;
+        while i < bytes.len() {
+            set = set.with(bytes[i]);
+            i += 1;
+        }
+        set
+    }
+
+    pub const fn with(mut self, b: u8) -> Self {
+        self.0[(b >> 6) as usize] |= 1 << (b & 63);
+        self
+    }
+
+    pub const fn contains(self, b: u8) -> bool {
+        self.0[(b >> 6) as usize] & (1 << (b & 63)) != 0
+    }
+}
+
+/// The UTF-8 of the whitespace characters beyond ASCII, those
+/// `char::is_whitespace` is true of, no-break spaces included.
+const UNICODE_SPACES: [&[u8]; 19] = [
+    "\u{85}".as_bytes(),
+    "\u{a0}".as_bytes(),
+    "\u{1680}".as_bytes(),
+    "\u{2000}".as_bytes(),
+    "\u{2001}".as_bytes(),
+    "\u{2002}".as_bytes(),
+    "\u{2003}".as_bytes(),
+    "\u{2004}".as_bytes(),
+    "\u{2005}".as_bytes(),
+    "\u{2006}".as_bytes(),
+    "\u{2007}".as_bytes(),
+    "\u{2008}".as_bytes(),
+    "\u{2009}".as_bytes(),
+    "\u{200a}".as_bytes(),
+    "\u{2028}".as_bytes(),
+    "\u{2029}".as_bytes(),
+    "\u{202f}".as_bytes(),
+    "\u{205f}".as_bytes(),
+    "\u{3000}".as_bytes(),
+];
+
+/// What splits words: ASCII whitespace unless `separators` says otherwise,
+/// and optionally punctuation and the whitespace beyond ASCII. By default no
+/// punctuation does, like in wc: `state-of-the-art` and `don't` are one
+/// word. Only the ASCII `-` and `'` are considered, and the record
+/// delimiter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WordRules {
+    /// The bytes that split words instead of ASCII whitespace, like `,`
+    /// for comma-separated tokens.
+    pub separators: Option<ByteSet>,
+    /// `state-of-the-art` is four words.
+    pub split_hyphens: bool,
+    /// `don't` is two words.
+    pub split_apostrophes: bool,
+    /// A record delimiter that isn't whitespace, like `;`, which words
+    /// don't span either.
+    pub delimiter: Option<u8>,
+    /// Whitespace beyond ASCII, like U+00A0 and U+3000, splits words too,
+    /// as in wc in a UTF-8 locale.
+    pub unicode_whitespace: bool,
+}
+
+impl WordRules {
+    pub const DEFAULT: WordRules = WordRules {
+        separators: None,
+        split_hyphens: false,
+        split_apostrophes: false,
+        delimiter: None,
+        unicode_whitespace: false,
+    };
+
+    /// These rules with words also split at `delimiter`.
+    pub const fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Whether `b` separates words. Everything that tokenizes input uses
+    /// this, so word counts and word lists always agree on what a word is.
+    /// Whitespace beyond ASCII is more than a byte: see `separator_len`.
+    pub const fn is_separator(self, b: u8) -> bool {
+        let base = match self.separators {
+            Some(set) => set.contains(b),
+            None => b.is_ascii_whitespace(),
+        };
+        base || (self.split_hyphens && b == b'-')
+            || (self.split_apostrophes && b == b'\'')
+            || matches!(self.delimiter, Some(d) if d == b)
+    }
+
+    /// The separators as a lookup table, for tight loops.
+    pub const fn table(self) -> ByteSet {
+        let mut set = ByteSet::EMPTY;
+        let mut b = 0;
+        while b < 256 {
+            if self.is_separator(b as u8) {
+                set = set.with(b as u8);
+            }
+            b += 1;
+        }
+        set
+    }
+
+    /// The length of the separator `data` starts with, or 0 if it doesn't
+    /// start with one.
+    pub fn separator_len(self, data: &[u8]) -> usize {
+        match data.first() {
+            Some(&b) if self.is_separator(b) => 1,
+            Some(_) if self.unicode_whitespace => UNICODE_SPACES
+                .iter()
+                .find(|space| data.starts_with(space))
+                .map_or(0, |space| space.len()),
+            _ => 0,
+        }
+    }
+
+    /// The length of the separator `data` ends with, or 0 if it doesn't
+    /// end with one.
+    pub fn separator_len_before(self, data: &[u8]) -> usize {
+        match data.last() {
+            Some(&b) if self.is_separator(b) => 1,
+            Some(_) if self.unicode_whitespace => UNICODE_SPACES
+                .iter()
+                .find(|space| data.ends_with(space))
+                .map_or(0, |space| space.len()),
+            _ => 0,
+        }
+    }
+
+    /// The words of `data`, split exactly as `Scanner` counts them.
+    pub fn words(self, data: &[u8]) -> impl Iterator<Item = &[u8]> {
+        let mut rest = data;
+        core::iter::from_fn(move || {
+            loop {
+                let n = self.separator_len(rest);
+                if n == 0 {
+                    break;
+                }
+                rest = &rest[n..];
+            }
+            if rest.is_empty() {
+                return None;
+            }
+            let end = (1..rest.len())
+                .find(|&i| self.separator_len(&rest[i..]) > 0)
+                .unwrap_or(rest.len());
+            let (word, tail) = rest.split_at(end);
+            rest = tail;
+            Some(word)
+        })
+    }
+}
+
+/// Whether `b` separates words under the default rules, i.e. is whitespace.
+pub const fn is_separator(b: u8) -> bool {
+    WordRules::DEFAULT.is_separator(b)
+}
+
+/// The words of `data` under the default rules.
+pub fn words(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    WordRules::DEFAULT.words(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_in_any_chunking() {
+        let data = "äö ü€\n𝄞 x\n".as_bytes();
+
+        let counts = |s: Scanner| (s.lines, s.words, s.bytes, s.chars);
+
+        for size in 1..=data.len() {
+            let mut scanner = Scanner::new(true);
+            for chunk in data.chunks(size) {
+                scanner.feed(chunk);
+            }
+            assert_eq!(
+                counts(scanner),
+                (2, 4, data.len() as u64, 10),
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8_in_any_chunking() {
+        // A lone continuation byte, a truncated 3-byte sequence followed by
+        // ASCII, an overlong encoding and a sequence cut off at the end.
+        let data = b"a\x80b\xe2\x82c\xc0\xafd\xf0\x9f";
+        for size in 1..=data.len() {
+            let count = |policy| {
+                let mut scanner = Scanner::new(true).with_invalid_utf8(policy);
+                for chunk in data.chunks(size) {
+                    scanner.feed(chunk);
+                }
+                scanner.flush();
+                (scanner.chars, scanner.invalid, scanner.first_invalid)
+            };
+            assert_eq!(
+                count(InvalidUtf8::Skip),
+                (4, 7, Some(1)),
+                "chunk size {}",
+                size
+            );
+            assert_eq!(
+                count(InvalidUtf8::Lossy),
+                (9, 7, Some(1)),
+                "chunk size {}",
+                size
+            );
+            assert_eq!(
+                count(InvalidUtf8::Unchecked),
+                (7, 0, None),
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_words_match_word_count() {
+        let data = b"  one\ttwo\n\nthree  four";
+        let mut scanner = Scanner::new(false);
+        scanner.feed(data);
+
+        let words: [&[u8]; 4] = [b"one", b"two", b"three", b"four"];
+        assert!(super::words(data).eq(words));
+        assert_eq!(scanner.words, words.len() as u64);
+    }
+
+    #[test]
+    fn test_longest_word_in_any_chunking() {
+        // “naïve’s” is 7 characters, with a no-break space before "ok".
+        let data = "a naïve’s word\n\u{a0}ok".as_bytes();
+        for unicode_whitespace in [false, true] {
+            let rules = WordRules {
+                unicode_whitespace,
+                ..WordRules::DEFAULT
+            };
+            for size in 1..=data.len() {
+                let mut scanner = Scanner::new(false)
+                    .with_word_rules(rules)
+                    .with_longest_word(true);
+                for chunk in data.chunks(size) {
+                    scanner.feed(chunk);
+                }
+                scanner.flush();
+                assert_eq!(scanner.longest_word, 7, "chunk size {}", size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_word_rules() {
+        let data = b"state-of-the-art isn't - new";
+        let count = |rules: WordRules| {
+            let mut scanner = Scanner::new(false).with_word_rules(rules);
+            scanner.feed(data);
+            assert_eq!(scanner.words, rules.words(data).count() as u64);
+            scanner.words
+        };
+
+        assert_eq!(count(WordRules::DEFAULT), 4);
+        let hyphens = WordRules {
+            split_hyphens: true,
+            ..WordRules::DEFAULT
+        };
+        assert_eq!(count(hyphens), 6);
+        let both = WordRules {
+            split_apostrophes: true,
+            ..hyphens
+        };
+        assert_eq!(count(both), 7);
+        let commas = WordRules {
+            separators: Some(ByteSet::from_bytes(b",\n")),
+            ..WordRules::DEFAULT
+        };
+        assert_eq!(count(commas), 1);
+        assert_eq!(
+            WordRules::DEFAULT.table(),
+            ByteSet::from_bytes(b" \t\n\x0c\r")
+        );
+    }
+
+    #[test]
+    fn test_unicode_whitespace_in_any_chunking() {
+        let data = "a\u{a0}b\u{3000}c\u{2009}d \u{e9}\u{2028}\u{2003}\u{e2}\u{80}".as_bytes();
+        let rules = WordRules {
+            unicode_whitespace: true,
+            ..WordRules::DEFAULT
+        };
+        for size in 1..=data.len() {
+            let mut scanner = Scanner::new(false).with_word_rules(rules);
+            for chunk in data.chunks(size) {
+                scanner.feed(chunk);
+            }
+            scanner.flush();
+            assert_eq!(scanner.words, 6, "chunk size {}", size);
+        }
+        let words: [&[u8]; 6] = [
+            b"a",
+            b"b",
+            b"c",
+            b"d",
+            "\u{e9}".as_bytes(),
+            "\u{e2}\u{80}".as_bytes(),
+        ];
+        assert!(rules.words(data).eq(words));
+
+        let mut scanner = Scanner::new(false);
+        scanner.feed(data);
+        assert_eq!(scanner.words, 2);
+
+        let spaces = ('\u{80}'..=char::MAX).filter(|c| c.is_whitespace());
+        for c in spaces.clone() {
+            let mut utf8 = [0; 4];
+            assert!(UNICODE_SPACES.contains(&c.encode_utf8(&mut utf8).as_bytes()));
+        }
+        assert_eq!(spaces.count(), UNICODE_SPACES.len());
+    }
+}
@@ -0,0 +1,100 @@
+//! Stdin split into segments for `--split-stdin`, so the output of several
+//! commands piped into one `rswc` is counted per command:
+//!
+//! ```sh
+//! { make; rswc split-marker; make test; } | rswc --split-stdin --label build --label test
+//! ```
+
+use crate::counter::{CountOptions, FileResult, count_slice};
+
+/// The line `rswc split-marker` prints between the segments of stdin. The
+/// record separator bytes keep it from turning up in ordinary text.
+pub const MARKER: &[u8] = b"\x1erswc-split\x1e";
+
+/// The segments of `data` between lines that are exactly `MARKER`, the
+/// markers left out. A last segment that is empty, after a trailing
+/// marker, is left out too.
+pub fn split_segments(data: &[u8]) -> Vec<&[u8]> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut line_start = 0;
+    while line_start < data.len() {
+        let end = data[line_start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |i| line_start + i);
+        let next = (end + 1).min(data.len());
+        if &data[line_start..end] == MARKER {
+            segments.push(&data[start..line_start]);
+            start = next;
+        }
+        line_start = next;
+    }
+    if start < data.len() || segments.is_empty() {
+        segments.push(&data[start..]);
+    }
+    segments
+}
+
+/// Counts each segment of `data` as an input of its own, named by
+/// `labels` in order, and `-:N` for the N-th segment past the labels.
+pub fn count_segments(data: &[u8], labels: &[&str], flags: &CountOptions) -> Vec<FileResult> {
+    split_segments(data)
+        .into_iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let name = labels
+                .get(i)
+                .map_or_else(|| format!("-:{}", i + 1), |label| label.to_string());
+            FileResult::Ok(name.into(), count_slice(segment, flags))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_segments() {
+        let marker = [MARKER, b"\n"].concat();
+        let data = [b"a b\nc\n", &marker[..], b"d\n", &marker[..], b"e"].concat();
+        assert_eq!(
+            split_segments(&data),
+            [&b"a b\nc\n"[..], &b"d\n"[..], &b"e"[..]]
+        );
+
+        let trailing = [b"a\n", &marker[..]].concat();
+        assert_eq!(split_segments(&trailing), [&b"a\n"[..]]);
+        assert_eq!(split_segments(b""), [&b""[..]]);
+        assert_eq!(split_segments(MARKER), [&b""[..]]);
+        // Only whole lines are markers.
+        let inline = [b"x", MARKER, b"\n"].concat();
+        assert_eq!(split_segments(&inline), [&inline[..]]);
+    }
+
+    #[test]
+    fn test_count_segments_names_them_by_label() {
+        let flags = CountOptions {
+            lines: true,
+            ..Default::default()
+        };
+        let data = [b"a\nb\n", MARKER, b"\nc\n", MARKER, b"\nd\n"].concat();
+        let results = count_segments(&data, &["build"], &flags);
+        let names: Vec<_> = results
+            .iter()
+            .map(|r| match r {
+                FileResult::Ok(name, c) => (name.display().to_string(), c.lines),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("build".to_string(), 2),
+                ("-:2".to_string(), 1),
+                ("-:3".to_string(), 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! A heuristic sentence counter: a sentence ends with `.`, `!` or `?`,
+//! optionally followed by closing quotes or brackets, and then whitespace
+//! or the end of the input. Periods after common abbreviations and single
+//! letter initials don't end a sentence. The abbreviations are those of a
+//! language pack, English by default; ideographic languages also end
+//! sentences with `。`, `！` and `？`, which need no whitespace after them.
+
+use crate::lang::{ENGLISH, LangPack};
+use crate::scanner::is_separator;
+
+/// Longest token prefix remembered for the abbreviation check.
+const TOKEN_CAP: usize = 8;
+
+/// Full-width `。`, `！` and `？` in UTF-8.
+const WIDE_TERMINATORS: [[u8; 3]; 3] = [[0xE3, 0x80, 0x82], [0xEF, 0xBC, 0x81], [0xEF, 0xBC, 0x9F]];
+
+#[derive(Debug)]
+pub struct Sentences {
+    pack: &'static LangPack,
+    count: u64,
+    // Lowercased start of the current token, up to its trailing terminators.
+    token: [u8; TOKEN_CAP],
+    token_len: usize,
+    // Length of the token before its trailing terminators, capped.
+    stem_len: usize,
+    ends_with: Option<u8>,
+    // Bytes held back as the possible start of a full-width terminator.
+    wide: [u8; 3],
+    wide_len: usize,
+}
+
+impl Default for Sentences {
+    fn default() -> Self {
+        Sentences::new(&ENGLISH)
+    }
+}
+
+impl Sentences {
+    pub fn new(pack: &'static LangPack) -> Self {
+        Sentences {
+            pack,
+            count: 0,
+            token: [0; TOKEN_CAP],
+            token_len: 0,
+            stem_len: 0,
+            ends_with: None,
+            wide: [0; 3],
+            wide_len: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.pack.ideographic {
+                self.feed_ideographic(b);
+            } else {
+                self.feed_byte(b);
+            }
+        }
+    }
+
+    fn feed_byte(&mut self, b: u8) {
+        if is_separator(b) {
+            self.end_token();
+            return;
+        }
+
+        match b {
+            b'.' | b'!' | b'?' => self.ends_with = Some(b),
+            b'"' | b'\'' | b')' | b']' if self.ends_with.is_some() => {}
+            _ => {
+                // A terminator inside a token, as in `e.g` or `3.5`.
+                if let Some(t) = self.ends_with.take() {
+                    self.push(t);
+                }
+                self.push(b.to_ascii_lowercase());
+                self.stem_len = self.token_len;
+            }
+        }
+    }
+
+    /// Holds back bytes that may start a full-width terminator, which ends
+    /// a sentence right away; the other bytes go on to `feed_byte`.
+    fn feed_ideographic(&mut self, b: u8) {
+        self.wide[self.wide_len] = b;
+        self.wide_len += 1;
+        let pending = &self.wide[..self.wide_len];
+        if let Some(t) = WIDE_TERMINATORS.iter().find(|t| t.starts_with(pending)) {
+            if t.len() == self.wide_len {
+                self.wide_len = 0;
+                // A run of terminators ends one sentence.
+                if self.stem_len > 0 {
+                    self.count += 1;
+                }
+                self.token_len = 0;
+                self.stem_len = 0;
+                self.ends_with = None;
+            }
+            return;
+        }
+
+        let held = self.wide;
+        let len = self.wide_len;
+        self.wide_len = 0;
+        for &h in &held[..len - 1] {
+            self.feed_byte(h);
+        }
+        if WIDE_TERMINATORS.iter().any(|t| t[0] == b) {
+            self.wide[0] = b;
+            self.wide_len = 1;
+        } else {
+            self.feed_byte(b);
+        }
+    }
+
+    fn push(&mut self, b: u8) {
+        if self.token_len < TOKEN_CAP {
+            self.token[self.token_len] = b;
+        }
+        self.token_len += 1;
+    }
+
+    fn end_token(&mut self) {
+        let stem = &self.token[..self.stem_len.min(TOKEN_CAP)];
+        let abbreviation = self.stem_len <= TOKEN_CAP
+            && (self.pack.abbreviations.contains(&stem)
+                || (self.stem_len == 1 && stem[0].is_ascii_alphabetic()));
+
+        match self.ends_with {
+            Some(b'.') if abbreviation => {}
+            Some(_) if self.stem_len > 0 => self.count += 1,
+            _ => {}
+        }
+
+        self.token_len = 0;
+        self.stem_len = 0;
+        self.ends_with = None;
+    }
+
+    /// The number of sentences, counting one still open at the end.
+    pub fn finish(mut self) -> u64 {
+        let held = self.wide;
+        for &h in &held[..self.wide_len] {
+            self.feed_byte(h);
+        }
+        self.end_token();
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences(text: &str) -> u64 {
+        let mut s = Sentences::default();
+        s.feed(text.as_bytes());
+        s.finish()
+    }
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(sentences("One. Two! Three?"), 3);
+        assert_eq!(sentences("Really?!\nYes...  Fine."), 3);
+        assert_eq!(sentences("He said \"stop.\" Then left."), 2);
+        assert_eq!(sentences("Version 3.5 is out. It is fast"), 1);
+        assert_eq!(sentences("no terminator at all"), 0);
+        assert_eq!(sentences("... ?!"), 0);
+    }
+
+    #[test]
+    fn test_sentences_skip_abbreviations() {
+        assert_eq!(
+            sentences("Dr. Smith met Mr. J. Doe, e.g. at St. Mary's etc. They talked."),
+            1
+        );
+        assert_eq!(sentences("See fig. 3 vs. fig. 4. Done."), 2);
+    }
+
+    #[test]
+    fn test_sentences_in_any_chunking() {
+        let data = b"Dr. Who arrived. Then? He left!\n\"Bye.\" ok";
+        for size in 1..=data.len() {
+            let mut s = Sentences::default();
+            for chunk in data.chunks(size) {
+                s.feed(chunk);
+            }
+            assert_eq!(s.finish(), 4, "chunk size {}", size);
+        }
+    }
+
+    #[cfg(feature = "lang-ja")]
+    #[test]
+    fn test_sentences_ideographic() {
+        let data = "これはペンです。本当？！はい 「そうです。」終わり".as_bytes();
+        for size in 1..=data.len() {
+            let mut s = Sentences::new(&crate::lang::JAPANESE);
+            for chunk in data.chunks(size) {
+                s.feed(chunk);
+            }
+            assert_eq!(s.finish(), 3, "chunk size {}", size);
+        }
+    }
+}
@@ -0,0 +1,518 @@
+//! `rswc serve --watch DIR`: the counts of a tree of files, kept up to date
+//! as files change and answered in JSON, so editors and status bars can ask
+//! for them without counting the tree again every time.
+//!
+//! A query is the path of a file or directory relative to the tree, or
+//! nothing for all of it, and is answered like `--json` with the files below
+//! it and their total. Over HTTP it is the path of a `GET` request:
+//!
+//! ```sh
+//! curl http://127.0.0.1:7373/src
+//! ```
+//!
+//! Over a Unix socket it is a line, answered by a line, as many times as the
+//! client likes.
+
+use crate::batch::report_json;
+use crate::counter::{CountOptions, FileResult, process_files};
+use crate::watch::watch_tree;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+/// Where `serve` listens when no other address is given.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7373";
+
+/// Which symbolic links below the directory of a tree are followed. The
+/// directory itself is, even if it is a link.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Links to files, but not to directories.
+    #[default]
+    Files,
+    /// Links to files and directories, but not those to a directory the
+    /// link is in, which would loop.
+    Follow,
+    /// None: links are left out.
+    Skip,
+}
+
+/// The results of every file of a tree, named relative to it.
+#[derive(Debug)]
+pub struct Tree {
+    root: PathBuf,
+    flags: CountOptions,
+    symlinks: Symlinks,
+    /// Whether files and directories whose name starts with a dot are in it.
+    hidden: bool,
+    /// Whether the files matched by `.gitignore` files are left out.
+    ignore_files: bool,
+    results: BTreeMap<PathBuf, FileResult>,
+}
+
+impl Tree {
+    /// An empty tree at `root`, filled in by `update`.
+    pub fn new(root: &Path, flags: &CountOptions) -> io::Result<Self> {
+        Ok(Tree {
+            root: std::path::absolute(root)?,
+            flags: flags.clone(),
+            symlinks: Symlinks::default(),
+            hidden: false,
+            ignore_files: true,
+            results: BTreeMap::new(),
+        })
+    }
+
+    /// Follows `symlinks` below the root.
+    pub fn with_symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Counts the files and directories whose name starts with a dot too,
+    /// if `hidden`.
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Leaves out the files matched by the `.gitignore` files of the tree,
+    /// if `ignore_files`, as by default.
+    pub fn with_ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    /// Counts the files at or below the `changed` paths again, and forgets
+    /// those that are gone. Paths outside the tree are ignored.
+    pub fn update(&mut self, changed: &BTreeSet<PathBuf>) {
+        let mut files = BTreeSet::new();
+        for path in changed {
+            // What a changed ignore file leaves out changes in all of its
+            // directory.
+            let path = match path.parent() {
+                Some(dir) if self.ignore_files && path.ends_with(".gitignore") => dir,
+                _ => path,
+            };
+            let Ok(name) = path.strip_prefix(&self.root) else {
+                continue;
+            };
+            self.results.retain(|file, _| !file.starts_with(name));
+            let given = *path == self.root;
+            if let Some(mut ignores) = self.ignores_above(path) {
+                files.extend(self.files_below(path, given, &mut Vec::new(), &mut ignores));
+            }
+        }
+        let files: Vec<PathBuf> = files.into_iter().collect();
+        for result in process_files(&files, &self.flags) {
+            let result = match result {
+                FileResult::Ok(path, counts) => FileResult::Ok(self.name(&path), counts),
+                FileResult::Err(path, e) => FileResult::Err(self.name(&path), e),
+                FileResult::Archive(path, members) => {
+                    FileResult::Archive(self.name(&path), members)
+                }
+            };
+            let (FileResult::Ok(name, _) | FileResult::Err(name, _) | FileResult::Archive(name, _)) =
+                &result;
+            self.results.insert(name.clone(), result);
+        }
+    }
+
+    /// The directory of the tree, made absolute.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn flags(&self) -> &CountOptions {
+        &self.flags
+    }
+
+    /// The results of every file, named relative to the root.
+    pub fn results(&self) -> &BTreeMap<PathBuf, FileResult> {
+        &self.results
+    }
+
+    /// The regular files at `path` or in the directories below it, in
+    /// order, following the links of `symlinks`, and `path` itself if it is
+    /// `given`. `ancestors` are the directories `path` is in, by their real
+    /// path, so a link back up the tree isn't followed, and `ignores` the
+    /// ignore files that apply to it.
+    fn files_below(
+        &self,
+        path: &Path,
+        given: bool,
+        ancestors: &mut Vec<PathBuf>,
+        ignores: &mut Vec<Gitignore>,
+    ) -> Vec<PathBuf> {
+        let is_link = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        let follow = match self.symlinks {
+            _ if !is_link || given => true,
+            Symlinks::Files => fs::metadata(path).is_ok_and(|m| m.is_file()),
+            Symlinks::Follow => true,
+            Symlinks::Skip => false,
+        };
+        let metadata = match fs::metadata(path) {
+            Ok(m) if follow => m,
+            _ => return Vec::new(),
+        };
+        if !given && self.left_out(path, metadata.is_dir(), ignores) {
+            return Vec::new();
+        }
+        if metadata.is_file() {
+            return vec![path.to_path_buf()];
+        }
+        let Some(real) = fs::canonicalize(path)
+            .ok()
+            .filter(|real| metadata.is_dir() && !ancestors.contains(real))
+        else {
+            return Vec::new();
+        };
+
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+        ancestors.push(real);
+        let ignored = self.push_ignore_file(path, ignores);
+        let files = entries
+            .iter()
+            .flat_map(|entry| self.files_below(entry, false, ancestors, ignores))
+            .collect();
+        if ignored {
+            ignores.pop();
+        }
+        ancestors.pop();
+        files
+    }
+
+    /// Whether `path` is left out for being hidden, or by the last of
+    /// `ignores` that has a say on it.
+    fn left_out(&self, path: &Path, is_dir: bool, ignores: &[Gitignore]) -> bool {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+        if hidden && !self.hidden {
+            return true;
+        }
+        ignores
+            .iter()
+            .rev()
+            .map(|ignore| ignore.matched(path, is_dir))
+            .find(|m| !m.is_none())
+            .is_some_and(|m| m.is_ignore())
+    }
+
+    /// Adds the `.gitignore` file of `dir` to `ignores`, and whether it has
+    /// one, unless ignore files are disregarded.
+    fn push_ignore_file(&self, dir: &Path, ignores: &mut Vec<Gitignore>) -> bool {
+        let file = dir.join(".gitignore");
+        if !self.ignore_files || !file.is_file() {
+            return false;
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        // Lines that don't parse are skipped, like git does.
+        let _ = builder.add(&file);
+        match builder.build() {
+            Ok(ignore) => {
+                ignores.push(ignore);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// The ignore files that apply to `path`, of the directories from the
+    /// root down to the one it is in, or `None` if one of those directories
+    /// is left out.
+    fn ignores_above(&self, path: &Path) -> Option<Vec<Gitignore>> {
+        let mut ignores = Vec::new();
+        if *path == self.root {
+            return Some(ignores);
+        }
+        let name = path.strip_prefix(&self.root).ok()?;
+        let mut dir = self.root.clone();
+        self.push_ignore_file(&dir, &mut ignores);
+        for component in name.parent().into_iter().flat_map(Path::components) {
+            dir.push(component);
+            if self.left_out(&dir, true, &ignores) {
+                return None;
+            }
+            self.push_ignore_file(&dir, &mut ignores);
+        }
+        Some(ignores)
+    }
+
+    fn name(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_path_buf()
+    }
+
+    /// The results of the files at or below `path` as JSON, or `None` if
+    /// there are none. An empty path is the whole tree.
+    pub fn query(&self, path: &str) -> Option<String> {
+        let prefix = Path::new(path.trim_matches('/'));
+        let results: Vec<FileResult> = self
+            .results
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(_, result)| result.clone())
+            .collect();
+        (!results.is_empty() || prefix.as_os_str().is_empty())
+            .then(|| report_json(&results, &self.flags))
+    }
+}
+
+/// Where to answer queries.
+pub enum Listener {
+    Http(TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+/// Counts the tree, then answers queries on `listener` while a thread keeps
+/// the counts up to date. Only returns on error.
+pub fn serve(tree: Tree, listener: Listener) -> io::Result<()> {
+    let root = tree.root.clone();
+    let tree = Arc::new(Mutex::new(tree));
+    let (ready, counted) = mpsc::channel();
+    let watcher = {
+        let tree = Arc::clone(&tree);
+        thread::spawn(move || {
+            watch_tree(&root, |changed| {
+                tree.lock().unwrap().update(changed);
+                let _ = ready.send(());
+                Ok(())
+            })
+        })
+    };
+    // Queries wait for the first count of the tree.
+    if counted.recv().is_err() {
+        return watcher
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the watcher stopped")));
+    }
+
+    match listener {
+        Listener::Http(listener) => {
+            for stream in listener.incoming() {
+                let stream = stream?;
+                let tree = Arc::clone(&tree);
+                thread::spawn(move || answer_http(&stream, &tree));
+            }
+        }
+        #[cfg(unix)]
+        Listener::Unix(listener) => {
+            for stream in listener.incoming() {
+                let stream = stream?;
+                let tree = Arc::clone(&tree);
+                thread::spawn(move || answer_lines(&stream, &tree));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Answers one HTTP request, whose path is the query.
+fn answer_http<S>(stream: S, tree: &Mutex<Tree>) -> io::Result<()>
+where
+    S: io::Read + Write,
+{
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers say nothing a query needs.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = match request.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", target, _] => {
+            let path = percent_decode(target.split('?').next().unwrap_or_default());
+            match tree.lock().unwrap().query(&path) {
+                Some(json) => ("200 OK", json),
+                None => ("404 Not Found", error_json("no such file or directory")),
+            }
+        }
+        [_, _, _] => ("405 Method Not Allowed", error_json("only GET is allowed")),
+        _ => ("400 Bad Request", error_json("not an HTTP request")),
+    };
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+        status,
+        body.len() + 1,
+        body
+    )?;
+    stream.flush()
+}
+
+/// Answers every line of `stream` with a line of JSON.
+#[cfg(unix)]
+fn answer_lines(stream: &std::os::unix::net::UnixStream, tree: &Mutex<Tree>) -> io::Result<()> {
+    let mut writer = stream;
+    for line in BufReader::new(stream).lines() {
+        let json = tree
+            .lock()
+            .unwrap()
+            .query(line?.trim())
+            .unwrap_or_else(|| error_json("no such file or directory"));
+        writeln!(writer, "{}", json)?;
+    }
+    Ok(())
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", message)
+}
+
+/// `%XX` escapes of a URL path replaced by their bytes.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        let dir = std::env::temp_dir().join(format!("rswc-serve-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("README"), "a b\n").unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        let flags = CountOptions::new().lines(true);
+        let mut tree = Tree::new(&dir, &flags).unwrap();
+        tree.update(&BTreeSet::from([dir.clone()]));
+        assert_eq!(
+            tree.query("").unwrap(),
+            r#"{"files":[{"file":"README","lines":1},{"file":"src/main.rs","lines":1}],"total":{"lines":2}}"#
+        );
+        assert_eq!(
+            tree.query("/src/").unwrap(),
+            r#"{"files":[{"file":"src/main.rs","lines":1}],"total":{"lines":1}}"#
+        );
+        assert_eq!(tree.query("docs"), None);
+
+        fs::write(dir.join("src/main.rs"), "fn main() {\n}\n").unwrap();
+        fs::remove_file(dir.join("README")).unwrap();
+        tree.update(&BTreeSet::from([
+            dir.join("src/main.rs"),
+            dir.join("README"),
+        ]));
+        assert_eq!(
+            tree.query("").unwrap(),
+            r#"{"files":[{"file":"src/main.rs","lines":2}],"total":{"lines":2}}"#
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tree_symlinks() {
+        use std::os::unix::fs::symlink;
+        let dir = std::env::temp_dir().join(format!("rswc-links-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        symlink(dir.join("src/main.rs"), dir.join("main.rs")).unwrap();
+        symlink(dir.join("src"), dir.join("lib")).unwrap();
+        symlink(&dir, dir.join("src/up")).unwrap();
+        let flags = CountOptions::new().lines(true);
+        let names = |symlinks| {
+            let mut tree = Tree::new(&dir, &flags).unwrap().with_symlinks(symlinks);
+            tree.update(&BTreeSet::from([tree.root().to_path_buf()]));
+            tree.results().keys().cloned().collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(Symlinks::Files),
+            [PathBuf::from("main.rs"), PathBuf::from("src/main.rs")]
+        );
+        assert_eq!(names(Symlinks::Skip), [PathBuf::from("src/main.rs")]);
+        // Links up the tree would loop.
+        assert_eq!(
+            names(Symlinks::Follow),
+            [
+                PathBuf::from("lib/main.rs"),
+                PathBuf::from("main.rs"),
+                PathBuf::from("src/main.rs"),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tree_hidden_and_ignored() {
+        let dir = std::env::temp_dir().join(format!("rswc-hidden-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::create_dir_all(dir.join("target/debug")).unwrap();
+        fs::write(dir.join(".git/HEAD"), "ref\n").unwrap();
+        fs::write(dir.join(".gitignore"), "/target\n*.log\n!keep.log\n").unwrap();
+        fs::write(dir.join("target/debug/out"), "x\n").unwrap();
+        fs::write(dir.join("a.log"), "x\n").unwrap();
+        fs::write(dir.join("keep.log"), "x\n").unwrap();
+        fs::write(dir.join("main.rs"), "x\n").unwrap();
+        let flags = CountOptions::new().lines(true);
+        let names = |hidden, ignore_files| {
+            let mut tree = Tree::new(&dir, &flags)
+                .unwrap()
+                .with_hidden(hidden)
+                .with_ignore_files(ignore_files);
+            tree.update(&BTreeSet::from([tree.root().to_path_buf()]));
+            tree.update(&BTreeSet::from([tree.root().join("target/debug/out")]));
+            tree.results().keys().cloned().collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(false, true),
+            [PathBuf::from("keep.log"), PathBuf::from("main.rs")]
+        );
+        assert_eq!(names(true, true).len(), 4);
+        assert_eq!(names(false, false).len(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_answer_http() {
+        let tree = Mutex::new(Tree {
+            root: PathBuf::from("/srv"),
+            flags: CountOptions::new().words(true),
+            symlinks: Symlinks::default(),
+            hidden: false,
+            ignore_files: true,
+            results: BTreeMap::new(),
+        });
+        let mut stream = io::Cursor::new(b"GET /a%20b.txt HTTP/1.1\r\nHost: x\r\n\r\n".to_vec());
+        answer_http(&mut stream, &tree).unwrap();
+        let response = String::from_utf8(stream.into_inner()).unwrap();
+        assert!(response.contains("HTTP/1.1 404 Not Found\r\n"));
+        assert_eq!(percent_decode("/a%20b%zz"), "/a b%zz");
+    }
+}
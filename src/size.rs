@@ -0,0 +1,40 @@
+//! Sizes given on the command line, like `64k` or `1M`.
+
+/// Parses a number of bytes with an optional binary suffix, `k`, `M` or
+/// `G` in either case, optionally followed by `iB` or `B`: `64k`, `1MiB`
+/// and `4096` are all sizes. Zero is not.
+pub fn byte_size(spec: &str) -> Option<usize> {
+    let digits = spec
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(spec.len());
+    let (number, suffix) = spec.split_at(digits);
+    let unit: usize = match suffix.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return None,
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .filter(|&n| n > 0)
+        .and_then(|n| n.checked_mul(unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_size() {
+        assert_eq!(byte_size("4096"), Some(4096));
+        assert_eq!(byte_size("64k"), Some(64 * 1024));
+        assert_eq!(byte_size("1M"), Some(1024 * 1024));
+        assert_eq!(byte_size("2MiB"), Some(2 * 1024 * 1024));
+        assert_eq!(byte_size("1g"), Some(1 << 30));
+        for spec in ["", "0", "k", "1x", "-1k", "1.5M"] {
+            assert_eq!(byte_size(spec), None, "{}", spec);
+        }
+    }
+}
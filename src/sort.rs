@@ -0,0 +1,317 @@
+//! Row order for `--sort`: the results of all files by one of their counts
+//! or by name instead of in the order they were given.
+
+use crate::counter::{CountOptions, FileResult, total};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// What `--sort` orders rows by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Lines,
+    Words,
+    Bytes,
+    Chars,
+    Name,
+}
+
+impl SortKey {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "lines" => Ok(SortKey::Lines),
+            "words" => Ok(SortKey::Words),
+            "bytes" => Ok(SortKey::Bytes),
+            "chars" => Ok(SortKey::Chars),
+            "name" => Ok(SortKey::Name),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--sort' (expected lines, words, bytes, chars or name)",
+                name
+            )),
+        }
+    }
+
+    /// Whether `flags` count what this key sorts by; counts that are not
+    /// selected may be left at zero.
+    pub fn is_counted(self, flags: &CountOptions) -> bool {
+        match self {
+            SortKey::Lines => flags.lines,
+            SortKey::Words => flags.words,
+            SortKey::Bytes => flags.bytes,
+            SortKey::Chars => flags.chars,
+            SortKey::Name => true,
+        }
+    }
+
+    fn of(self, result: &FileResult) -> Option<Key> {
+        let subtotal;
+        let (path, counts) = match result {
+            FileResult::Ok(path, c) => (path, c),
+            FileResult::Archive(path, members) => {
+                subtotal = total(members);
+                (path, &subtotal)
+            }
+            FileResult::Err(..) => return None,
+        };
+        Some(match self {
+            SortKey::Lines => Key::Count(counts.lines),
+            SortKey::Words => Key::Count(counts.words),
+            SortKey::Bytes => Key::Count(counts.bytes),
+            SortKey::Chars => Key::Count(counts.chars),
+            SortKey::Name => Key::Name(path.clone()),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Count(u64),
+    Name(PathBuf),
+}
+
+impl Key {
+    fn cmp(&self, other: &Key, collation: Collation) -> Ordering {
+        match (self, other) {
+            (Key::Count(a), Key::Count(b)) => a.cmp(b),
+            (Key::Name(a), Key::Name(b)) => collation.compare(a, b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// How `--sort=name` orders names.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Collation {
+    /// By the bytes of the names, the same on every machine.
+    #[default]
+    Bytewise,
+    /// With runs of digits compared as numbers, so `file2` comes before
+    /// `file10`.
+    Natural,
+    /// Like dictionaries: letters and digits only, ignoring case, unless
+    /// the locale is `C` or `POSIX`.
+    Locale,
+}
+
+impl Collation {
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "bytewise" => Ok(Collation::Bytewise),
+            "natural" => Ok(Collation::Natural),
+            "locale" => Ok(Collation::Locale),
+            _ => Err(format!(
+                "rswc: invalid argument '{}' for '--collate' (expected bytewise, natural or locale)",
+                name
+            )),
+        }
+    }
+
+    /// Orders `a` and `b`; names that only collate equal, like `a` and `A`,
+    /// are ordered bytewise.
+    pub fn compare(self, a: &Path, b: &Path) -> Ordering {
+        let (a, b) = (
+            a.as_os_str().as_encoded_bytes(),
+            b.as_os_str().as_encoded_bytes(),
+        );
+        let order = match self {
+            Collation::Bytewise => Ordering::Equal,
+            Collation::Natural => natural(a, b),
+            Collation::Locale if is_c_locale() => Ordering::Equal,
+            Collation::Locale => {
+                let folded = |name: &[u8]| {
+                    String::from_utf8_lossy(name)
+                        .chars()
+                        .filter(|c| c.is_alphanumeric())
+                        .flat_map(char::to_lowercase)
+                        .collect::<String>()
+                };
+                folded(a).cmp(&folded(b))
+            }
+        };
+        order.then_with(|| a.cmp(b))
+    }
+}
+
+/// Compares `a` and `b` bytewise, except for runs of digits, which compare
+/// by their value.
+fn natural(mut a: &[u8], mut b: &[u8]) -> Ordering {
+    let digits = |s: &[u8]| s.iter().take_while(|b| b.is_ascii_digit()).count();
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (m, n) = (digits(a), digits(b));
+                let trim = |run: &[u8]| {
+                    let zeros = run.iter().take_while(|&&b| b == b'0').count();
+                    run[zeros..].to_vec()
+                };
+                let (x, y) = (trim(&a[..m]), trim(&b[..n]));
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a = &a[m..];
+                b = &b[n..];
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+/// Whether the collation locale, from `LC_ALL`, `LC_COLLATE` or `LANG`,
+/// is `C` or `POSIX`, or not set at all.
+fn is_c_locale() -> bool {
+    let locale = ["LC_ALL", "LC_COLLATE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    matches!(
+        locale.split('.').next().unwrap_or_default(),
+        "" | "C" | "POSIX"
+    )
+}
+
+/// Orders `results` by `key`, smallest first unless `reverse`, and the
+/// members of archives the same way; an archive sorts by its subtotal.
+/// Names are ordered by `collation`. Ties and errors keep the order they
+/// were given in, errors after all rows.
+pub fn sort_results(
+    results: &mut Vec<FileResult>,
+    key: SortKey,
+    collation: Collation,
+    reverse: bool,
+) {
+    let mut keyed: Vec<(Option<Key>, FileResult)> = results
+        .drain(..)
+        .map(|mut r| {
+            if let FileResult::Archive(_, members) = &mut r {
+                sort_results(members, key, collation, reverse);
+            }
+            (key.of(&r), r)
+        })
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) if reverse => b.cmp(a, collation),
+        (Some(a), Some(b)) => a.cmp(b, collation),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    results.extend(keyed.into_iter().map(|(_, r)| r));
+}
+
+/// The `n` rows of `results` ordered by `sort_results` that sort last, or
+/// first if `reverse`, i.e. those with the largest counts, in their order.
+/// Errors are all kept, so failures still show.
+pub fn top_results(results: &[FileResult], n: usize, reverse: bool) -> Vec<FileResult> {
+    let rows = results
+        .iter()
+        .filter(|r| !matches!(r, FileResult::Err(..)))
+        .count();
+    let skip = if reverse {
+        n..rows
+    } else {
+        0..rows.saturating_sub(n)
+    };
+    let mut row = 0;
+    results
+        .iter()
+        .filter(|r| {
+            if matches!(r, FileResult::Err(..)) {
+                return true;
+            }
+            row += 1;
+            !skip.contains(&(row - 1))
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::Counts;
+    use std::io;
+
+    fn names(results: &[FileResult]) -> Vec<String> {
+        results
+            .iter()
+            .map(|r| match r {
+                FileResult::Ok(path, _) | FileResult::Err(path, _) => path.display().to_string(),
+                FileResult::Archive(path, members) => {
+                    format!("{}[{}]", path.display(), names(members).join(" "))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_results() {
+        let file = |name: &str, lines| {
+            FileResult::Ok(
+                name.into(),
+                Counts {
+                    lines,
+                    ..Default::default()
+                },
+            )
+        };
+        let results = || {
+            vec![
+                file("b", 3),
+                FileResult::Err(
+                    "gone".into(),
+                    io::Error::other("No such file or directory").into(),
+                ),
+                file("c", 1),
+                FileResult::Archive("a.zip".into(), vec![file("y", 1), file("x", 1)]),
+                file("a", 3),
+            ]
+        };
+
+        let mut sorted = results();
+        sort_results(&mut sorted, SortKey::Lines, Collation::Bytewise, false);
+        assert_eq!(names(&sorted), ["c", "a.zip[y x]", "b", "a", "gone"]);
+
+        sort_results(&mut sorted, SortKey::Lines, Collation::Bytewise, true);
+        assert_eq!(names(&sorted), ["b", "a", "a.zip[y x]", "c", "gone"]);
+
+        let mut sorted = results();
+        sort_results(&mut sorted, SortKey::Name, Collation::Bytewise, false);
+        assert_eq!(names(&sorted), ["a", "a.zip[x y]", "b", "c", "gone"]);
+
+        sort_results(&mut sorted, SortKey::Lines, Collation::Bytewise, false);
+        assert_eq!(names(&top_results(&sorted, 2, false)), ["a", "b", "gone"]);
+        sort_results(&mut sorted, SortKey::Lines, Collation::Bytewise, true);
+        assert_eq!(names(&top_results(&sorted, 2, true)), ["a", "b", "gone"]);
+        assert_eq!(top_results(&sorted, 9, true).len(), 5);
+    }
+
+    #[test]
+    fn test_collations() {
+        let sorted = |collation: Collation| {
+            let mut names = ["file10", "File3", "file2", "file02", "_notes", "file1a"];
+            names.sort_by(|a, b| collation.compare(Path::new(a), Path::new(b)));
+            names
+        };
+        assert_eq!(
+            sorted(Collation::Bytewise),
+            ["File3", "_notes", "file02", "file10", "file1a", "file2"]
+        );
+        assert_eq!(
+            sorted(Collation::Natural),
+            ["File3", "_notes", "file1a", "file02", "file2", "file10"]
+        );
+        assert_eq!(natural(b"a10b2", b"a10b10"), Ordering::Less);
+        assert_eq!(natural(b"a", b"a0"), Ordering::Less);
+    }
+}
@@ -0,0 +1,231 @@
+//! Line length statistics for `--stats`.
+
+use crate::counter::{Counts, FileResult, counted_inputs, total};
+use std::io::{self, Write};
+
+/// Shortest, longest and average length of a set of lines, not counting
+/// their line ends.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineLengths {
+    pub lines: usize,
+    pub min: usize,
+    pub max: usize,
+    pub total: usize,
+}
+
+impl LineLengths {
+    pub fn add(&mut self, len: usize) {
+        if self.lines == 0 {
+            self.min = len;
+        }
+        self.lines += 1;
+        self.min = self.min.min(len);
+        self.max = self.max.max(len);
+        self.total += len;
+    }
+
+    pub fn merge(&mut self, other: &LineLengths) {
+        if other.lines == 0 {
+            return;
+        }
+        if self.lines == 0 {
+            *self = *other;
+            return;
+        }
+        self.lines += other.lines;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.total += other.total;
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.total as f64 / self.lines.max(1) as f64
+    }
+}
+
+/// Line lengths in bytes and, if they were counted, in characters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineStats {
+    pub bytes: LineLengths,
+    pub chars: Option<LineLengths>,
+}
+
+impl LineStats {
+    pub fn merge(&mut self, other: &LineStats) {
+        self.bytes.merge(&other.bytes);
+        if let Some(chars) = &other.chars {
+            self.chars.get_or_insert_default().merge(chars);
+        }
+    }
+}
+
+/// Measures the lines of input fed in chunks of any size, without their
+/// `\n` or `\r\n` line ends. A last line without a line end counts too. Characters are the bytes that don't
+/// continue a UTF-8 sequence, so an invalid sequence counts as one
+/// character per byte.
+#[derive(Debug, Default)]
+pub struct LineMeter {
+    stats: LineStats,
+    current_bytes: usize,
+    current_chars: usize,
+    // Whether the current line so far ends in `\r`.
+    ends_in_cr: bool,
+    delimiter: u8,
+}
+
+impl LineMeter {
+    pub fn new(count_chars: bool) -> Self {
+        LineMeter {
+            stats: LineStats {
+                chars: count_chars.then(LineLengths::default),
+                ..Default::default()
+            },
+            delimiter: b'\n',
+            ..Default::default()
+        }
+    }
+
+    /// Splits lines at `delimiter` instead of `\n`. A `\r` before it is
+    /// then part of the line.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let delimiter = self.delimiter;
+        for piece in chunk.split_inclusive(|&b| b == delimiter) {
+            let (line, ended) = match piece.split_last() {
+                Some((&last, line)) if last == delimiter => (line, true),
+                _ => (piece, false),
+            };
+            self.current_bytes += line.len();
+            if self.stats.chars.is_some() {
+                self.current_chars += line.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+            }
+            if let Some(&last) = line.last() {
+                self.ends_in_cr = last == b'\r';
+            }
+            if ended {
+                if self.ends_in_cr && delimiter == b'\n' {
+                    self.current_bytes -= 1;
+                    self.current_chars = self.current_chars.saturating_sub(1);
+                }
+                self.end_line();
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.stats.bytes.add(self.current_bytes);
+        if let Some(chars) = self.stats.chars.as_mut() {
+            chars.add(self.current_chars);
+        }
+        self.current_bytes = 0;
+        self.current_chars = 0;
+        self.ends_in_cr = false;
+    }
+
+    pub fn finish(mut self) -> LineStats {
+        if self.current_bytes > 0 {
+            self.end_line();
+        }
+        self.stats
+    }
+}
+
+/// Prints the line lengths of every counted input, archive members
+/// included, followed by their total when there is more than one.
+pub fn print_stats<W: Write>(writer: &mut W, results: &[FileResult]) -> io::Result<()> {
+    let stats_of = |c: &Counts| c.line_stats.as_deref().copied().unwrap_or_default();
+    let rows: Vec<(String, LineStats)> = counted_inputs(results)
+        .into_iter()
+        .map(|(name, c)| (name, stats_of(c)))
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let total = stats_of(&total(results));
+    for (unit, in_chars) in [("bytes", false), ("chars", true)] {
+        let lengths = |s: &LineStats| if in_chars { s.chars } else { Some(s.bytes) };
+        if rows.iter().all(|(_, s)| lengths(s).is_none()) {
+            continue;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "line lengths in {} (min, mean, max):", unit)?;
+        for (name, s) in &rows {
+            write_lengths(writer, &lengths(s).unwrap_or_default(), name)?;
+        }
+        if rows.len() > 1 {
+            write_lengths(writer, &lengths(&total).unwrap_or_default(), "total")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_lengths<W: Write>(writer: &mut W, l: &LineLengths, name: &str) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{:>7} {:>9.1} {:>7} {}",
+        l.min,
+        l.mean(),
+        l.max,
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, count_slice};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_line_stats_in_any_chunking() {
+        let data = "héllo\r\n\nworld!!\nlast".as_bytes();
+        for size in 1..=data.len() {
+            let mut meter = LineMeter::new(true);
+            for chunk in data.chunks(size) {
+                meter.feed(chunk);
+            }
+            let LineStats { bytes, chars } = meter.finish();
+            let expected = |min, max, total| LineLengths {
+                lines: 4,
+                min,
+                max,
+                total,
+            };
+            assert_eq!(bytes, expected(0, 7, 17), "chunk size {}", size);
+            assert_eq!(chars, Some(expected(0, 7, 16)), "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_print_stats() {
+        let flags = CountOptions {
+            lines: true,
+            stats: true,
+            ..Default::default()
+        };
+        let results = vec![
+            FileResult::Ok(PathBuf::from("a"), count_slice(b"ab\nabcd\n", &flags)),
+            FileResult::Err(PathBuf::from("b"), io::Error::other("gone").into()),
+            FileResult::Ok(PathBuf::from("c"), count_slice(b"", &flags)),
+        ];
+
+        let mut output = Vec::new();
+        print_stats(&mut output, &results).unwrap();
+        let expected = "
+line lengths in bytes (min, mean, max):
+      2       3.0       4 a
+      0       0.0       0 c
+      2       3.0       4 total
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -0,0 +1,144 @@
+//! Subtitles for `--subtitles`: the captions of SRT and WebVTT files
+//! without their indices, timestamps and markup, and the timeline they span.
+
+use crate::counter::{Annotations, CountOptions, Counts, count_slice};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The cues of subtitles and how long they span, from the start of the
+/// first to the end of the last.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timeline {
+    pub cues: usize,
+    pub duration_ms: u64,
+}
+
+impl Timeline {
+    pub fn merge(&mut self, other: &Timeline) {
+        self.cues += other.cues;
+        self.duration_ms += other.duration_ms;
+    }
+
+    /// Words per minute of the timeline, rounded down.
+    pub fn words_per_minute(&self, words: u64) -> u64 {
+        match self.duration_ms {
+            0 => 0,
+            ms => words * 60_000 / ms,
+        }
+    }
+}
+
+/// Whether `path` names an SRT or WebVTT file.
+pub fn is_subtitles(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("srt") || e.eq_ignore_ascii_case("vtt"))
+}
+
+/// Counts the caption text of the subtitles at `path`.
+pub fn count_subtitles(path: &Path, flags: &CountOptions) -> io::Result<Counts> {
+    let data = fs::read(path)?;
+    let (text, timeline) = captions(&String::from_utf8_lossy(&data));
+    let mut counts = count_slice(text.as_bytes(), flags);
+    counts.timeline = Some(Box::new(timeline));
+    counts.annotations.insert(Annotations::EXTRACTED);
+    Ok(counts)
+}
+
+/// The caption text of SRT or WebVTT subtitles, a line per caption line,
+/// and their timeline. Blocks without a timestamp line, like the WebVTT
+/// header and its notes and styles, are left out.
+pub fn captions(subtitles: &str) -> (String, Timeline) {
+    let subtitles = subtitles.trim_start_matches('\u{feff}');
+    let mut text = String::new();
+    let mut timeline = Timeline::default();
+    let mut span: Option<(u64, u64)> = None;
+
+    let mut lines = subtitles.lines().map(str::trim_end);
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = timing(line) else {
+            continue;
+        };
+        timeline.cues += 1;
+        span = Some(match span {
+            Some((first, last)) => (first.min(start), last.max(end)),
+            None => (start, end),
+        });
+
+        for caption in lines.by_ref().take_while(|l| !l.is_empty()) {
+            text.push_str(&without_tags(caption));
+            text.push('\n');
+        }
+    }
+
+    if let Some((first, last)) = span {
+        timeline.duration_ms = last.saturating_sub(first);
+    }
+    (text, timeline)
+}
+
+/// The start and end of a timestamp line like `00:01:02,500 --> 00:01:04,000`,
+/// where WebVTT may leave out the hours and add cue settings.
+fn timing(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((timestamp(start.trim())?, timestamp(end)?))
+}
+
+/// Milliseconds of `hh:mm:ss,mmm`, `hh:mm:ss.mmm` or `mm:ss.mmm`.
+fn timestamp(s: &str) -> Option<u64> {
+    let (clock, millis) = s.split_once([',', '.'])?;
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(seconds * 1000 + millis.parse::<u64>().ok()?)
+}
+
+/// `line` without HTML-like tags such as `<i>` and `<v Speaker>`, and
+/// without `{\an8}` style overrides.
+fn without_tags(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => out.push(c),
+            (Some(end), c) if c == end => closing = None,
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srt_captions() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:04,000\r\n<i>Hello</i> there,\r\nfriend.\r\n\r\n2\r\n00:00:05,500 --> 00:00:07,000\r\n{\\an8}Bye!\r\n";
+        let (text, timeline) = captions(srt);
+        assert_eq!(text, "Hello there,\nfriend.\nBye!\n");
+        assert_eq!(
+            timeline,
+            Timeline {
+                cues: 2,
+                duration_ms: 6000
+            }
+        );
+        assert_eq!(timeline.words_per_minute(4), 40);
+    }
+
+    #[test]
+    fn test_vtt_captions() {
+        let vtt = "WEBVTT - a header\n\nNOTE written by hand\n\nSTYLE\n::cue { color: red }\n\nintro\n00:01.000 --> 00:03.500 align:start\n<v Roger>One two</v>\n\n01:00:00.000 --> 01:00:01.000\nthree\n";
+        let (text, timeline) = captions(vtt);
+        assert_eq!(text, "One two\nthree\n");
+        assert_eq!(timeline.cues, 2);
+        assert_eq!(timeline.duration_ms, 3_600_000);
+    }
+}
@@ -0,0 +1,130 @@
+//! `--summary`: what a typical file of the set looks like, for each count
+//! asked for, and which file is the largest by it.
+
+use crate::counter::{CountOptions, FileResult, counted_inputs};
+use std::io::{self, Write};
+
+/// One count across the files of a summary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSummary {
+    /// The column, named as in `Counts::named_columns`.
+    pub name: &'static str,
+    pub mean: f64,
+    pub median: f64,
+    pub max: u64,
+    /// The first file with the `max`.
+    pub largest: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The inputs counted, archive members included.
+    pub files: usize,
+    pub columns: Vec<ColumnSummary>,
+}
+
+/// The summary of the counts of `flags` over every counted input of
+/// `results`, or `None` when none was counted.
+pub fn summarize(results: &[FileResult], flags: &CountOptions) -> Option<Summary> {
+    let inputs: Vec<(String, Vec<(&'static str, u64)>)> = counted_inputs(results)
+        .into_iter()
+        .map(|(name, c)| (name, c.named_columns(flags)))
+        .collect();
+    let (_, first) = inputs.first()?;
+
+    let columns = first
+        .iter()
+        .enumerate()
+        .map(|(i, &(name, _))| {
+            // The first of the largest, as `max_by_key` gives the last.
+            let (largest, _) = inputs.iter().rev().max_by_key(|(_, c)| c[i].1)?;
+            let mut values: Vec<u64> = inputs.iter().map(|(_, c)| c[i].1).collect();
+            let mean = values.iter().map(|&n| n as f64).sum::<f64>() / values.len() as f64;
+            values.sort_unstable();
+            let max = values[values.len() - 1];
+            let mid = values.len() / 2;
+            let median = if values.len().is_multiple_of(2) {
+                (values[mid - 1] as f64 + values[mid] as f64) / 2.0
+            } else {
+                values[mid] as f64
+            };
+            Some(ColumnSummary {
+                name,
+                mean,
+                median,
+                max,
+                largest: largest.clone(),
+            })
+        })
+        .collect::<Option<_>>()?;
+
+    Some(Summary {
+        files: inputs.len(),
+        columns,
+    })
+}
+
+pub fn print_summary<W: Write>(writer: &mut W, summary: Option<&Summary>) -> io::Result<()> {
+    let Some(summary) = summary else {
+        return Ok(());
+    };
+
+    writeln!(writer)?;
+    match summary.files {
+        1 => writeln!(writer, "summary of 1 file (mean, median, max, largest):")?,
+        n => writeln!(
+            writer,
+            "summary of {} files (mean, median, max, largest):",
+            n
+        )?,
+    }
+    for column in &summary.columns {
+        writeln!(
+            writer,
+            "{:>9.1} {:>9.1} {:>7} {} in {}",
+            column.mean, column.median, column.max, column.name, column.largest
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::{CountOptions, count_slice};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_summary() {
+        let flags = CountOptions::new().lines(true).words(true);
+        let file = |name: &str, data: &[u8]| {
+            FileResult::Ok(PathBuf::from(name), count_slice(data, &flags))
+        };
+        let results = [
+            file("a", b"one\ntwo\n"),
+            file("b", b"one two three four\n"),
+            FileResult::Err(PathBuf::from("gone"), io::Error::other("gone").into()),
+            file("c", b"x\ny\nz\n"),
+            file("d", b"x y\nz\n"),
+        ];
+
+        let summary = summarize(&results, &flags).unwrap();
+        assert_eq!(summary.files, 4);
+        let lines = &summary.columns[0];
+        assert_eq!((lines.name, lines.mean, lines.median), ("lines", 2.0, 2.0));
+        assert_eq!((lines.max, lines.largest.as_str()), (3, "c"));
+        let words = &summary.columns[1];
+        assert_eq!((words.mean, words.median), (3.0, 3.0));
+        assert_eq!((words.max, words.largest.as_str()), (4, "b"));
+
+        let mut out = Vec::new();
+        print_summary(&mut out, Some(&summary)).unwrap();
+        let expected = "
+summary of 4 files (mean, median, max, largest):
+      2.0       2.0       3 lines in c
+      3.0       3.0       4 words in b
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+        assert_eq!(summarize(&results[2..3], &flags), None);
+    }
+}
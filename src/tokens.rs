@@ -0,0 +1,187 @@
+//! Token counts of OpenAI's BPE encodings for `--tokens`.
+
+use std::borrow::Cow;
+use tiktoken_rs::CoreBPE;
+
+/// A tiktoken encoding, named like in tiktoken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Encoding {
+    /// GPT-4o and later.
+    O200kBase,
+    /// GPT-4 and GPT-3.5.
+    Cl100kBase,
+    /// Codex and `text-davinci-002`/`-003`.
+    P50kBase,
+    /// GPT-3.
+    R50kBase,
+}
+
+impl Encoding {
+    pub const DEFAULT: Encoding = Encoding::O200kBase;
+
+    const ALL: [Encoding; 4] = [
+        Encoding::O200kBase,
+        Encoding::Cl100kBase,
+        Encoding::P50kBase,
+        Encoding::R50kBase,
+    ];
+
+    pub fn new(name: &str) -> Result<Self, String> {
+        Encoding::ALL
+            .into_iter()
+            .find(|e| e.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Encoding::ALL.iter().map(|e| e.name()).collect();
+                format!(
+                    "rswc: unknown token encoding '{}' (expected {})",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::O200kBase => "o200k_base",
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::P50kBase => "p50k_base",
+            Encoding::R50kBase => "r50k_base",
+        }
+    }
+
+    /// The tokenizer, loaded on first use and shared afterwards.
+    fn bpe(self) -> &'static CoreBPE {
+        match self {
+            Encoding::O200kBase => tiktoken_rs::o200k_base_singleton(),
+            Encoding::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+            Encoding::P50kBase => tiktoken_rs::p50k_base_singleton(),
+            Encoding::R50kBase => tiktoken_rs::r50k_base_singleton(),
+        }
+    }
+}
+
+/// Pending input beyond this size is split inside a line.
+const MAX_PENDING: usize = 1024 * 1024;
+
+/// Counts the tokens of input fed in chunks of any size. Special tokens like
+/// `<|endoftext|>` are counted as ordinary text, and invalid UTF-8 as the
+/// replacement character.
+pub struct TokenCounter {
+    bpe: &'static CoreBPE,
+    // Input after the last point it could be split at.
+    pending: Vec<u8>,
+    count: u64,
+    exact: bool,
+}
+
+impl TokenCounter {
+    pub fn new(encoding: Encoding) -> Self {
+        TokenCounter {
+            bpe: encoding.bpe(),
+            pending: Vec::new(),
+            count: 0,
+            exact: true,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        if let Some(at) = split_point(&self.pending) {
+            let (count, valid) = self.tokenize(&self.pending[..at]);
+            self.count += count as u64;
+            self.exact &= valid && self.pending[at - 1] == b'\n';
+            self.pending.drain(..at);
+        }
+    }
+
+    /// The number of tokens, and whether it is exact: it isn't if the input
+    /// wasn't valid UTF-8 or a line had to be split between words.
+    pub fn finish(self) -> (u64, bool) {
+        let (count, valid) = self.tokenize(&self.pending);
+        (self.count + count as u64, self.exact && valid)
+    }
+
+    /// The tokens of `data`, and whether it was valid UTF-8.
+    fn tokenize(&self, data: &[u8]) -> (usize, bool) {
+        if data.is_empty() {
+            return (0, true);
+        }
+        let text = String::from_utf8_lossy(data);
+        let valid = matches!(text, Cow::Borrowed(_));
+        (self.bpe.encode_ordinary(&text).len(), valid)
+    }
+}
+
+/// The last offset `data` can be cut at without changing how the encodings
+/// split it into words: after a line break that is followed by something
+/// other than whitespace. Long lines are cut before a single space between
+/// two words instead, which may merge differently where words are joined by
+/// punctuation. Cuts are always next to ASCII bytes, so UTF-8 stays intact.
+fn split_point(data: &[u8]) -> Option<usize> {
+    let after_line_break =
+        (1..data.len()).rfind(|&i| data[i - 1] == b'\n' && !data[i].is_ascii_whitespace());
+    if after_line_break.is_some() || data.len() < MAX_PENDING {
+        return after_line_break;
+    }
+    (1..data.len() - 1).rfind(|&i| {
+        data[i] == b' ' && !data[i - 1].is_ascii_whitespace() && !data[i + 1].is_ascii_whitespace()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(encoding: Encoding, chunks: &[&[u8]]) -> u64 {
+        let mut counter = TokenCounter::new(encoding);
+        for chunk in chunks {
+            counter.feed(chunk);
+        }
+        counter.finish().0
+    }
+
+    #[test]
+    fn test_chunks_count_like_the_whole_input() {
+        let text = std::fs::read("testdata/test.txt").unwrap();
+        for encoding in [Encoding::O200kBase, Encoding::Cl100kBase] {
+            let whole = encoding
+                .bpe()
+                .encode_ordinary(&String::from_utf8_lossy(&text))
+                .len();
+            let chunks: Vec<&[u8]> = text.chunks(4093).collect();
+            assert_eq!(count(encoding, &chunks), whole as u64);
+        }
+    }
+
+    #[test]
+    fn test_token_count() {
+        assert_eq!(count(Encoding::Cl100kBase, &[b"hello world"]), 2);
+        assert_eq!(count(Encoding::O200kBase, &[b"hel", b"lo wor", b"ld\n"]), 3);
+        assert_eq!(count(Encoding::O200kBase, &[]), 0);
+    }
+
+    #[test]
+    fn test_token_count_exactness() {
+        let exact = |chunks: &[&[u8]]| {
+            let mut counter = TokenCounter::new(Encoding::O200kBase);
+            for chunk in chunks {
+                counter.feed(chunk);
+            }
+            counter.finish().1
+        };
+        assert!(exact(&[b"one\n", b"two\n"]));
+        assert!(!exact(&[b"one\n\xff", b"two\n"]));
+        assert!(!exact(&[b"\xff\n", b"two\n"]));
+    }
+
+    #[test]
+    fn test_encoding_names() {
+        assert_eq!(Encoding::new("cl100k_base"), Ok(Encoding::Cl100kBase));
+        assert_eq!(
+            Encoding::new("gpt2"),
+            Err("rswc: unknown token encoding 'gpt2' (expected o200k_base, cl100k_base, p50k_base, r50k_base)".to_string())
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Decoding of input in legacy and UTF-16 encodings for `--encoding`, so
+//! that characters and words are counted from the text rather than its bytes.
+
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_8};
+
+/// UTF-8 is written out of the decoder in pieces of at most this size.
+const OUT_SIZE: usize = 64 * 1024;
+
+/// The encoding named by `label`, as in the `charset` of HTML: `utf-16le`,
+/// `latin1` and `sjis` are all understood. `auto` is UTF-8 unless the input
+/// starts with a byte order mark.
+pub fn input_encoding(label: &str) -> Result<&'static Encoding, String> {
+    if label == "auto" {
+        return Ok(UTF_8);
+    }
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+        format!(
+            "rswc: invalid argument '{}' for '--encoding' (expected auto or a label like utf-8, utf-16le, latin1 or shift_jis)",
+            label
+        )
+    })
+}
+
+/// `CountOptions::encoding` as the name of the encoding, for serde.
+#[cfg(feature = "serde")]
+pub(crate) mod by_name {
+    use super::input_encoding;
+    use encoding_rs::Encoding;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        encoding: &Option<&'static Encoding>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        encoding.map(Encoding::name).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<&'static Encoding>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| input_encoding(&name).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Turns input fed in chunks of any size into UTF-8. A byte order mark at
+/// the start overrides the encoding, and malformed sequences become U+FFFD.
+pub struct Transcoder {
+    decoder: Decoder,
+    out: Vec<u8>,
+}
+
+impl Transcoder {
+    pub fn new(encoding: &'static Encoding) -> Self {
+        Transcoder {
+            decoder: encoding.new_decoder(),
+            out: vec![0; OUT_SIZE],
+        }
+    }
+
+    /// Decodes `chunk`, passing the text to `sink` in one or more pieces.
+    pub fn feed(&mut self, chunk: &[u8], mut sink: impl FnMut(&[u8])) {
+        self.decode(chunk, false, &mut sink);
+    }
+
+    /// Passes what is left of an incomplete sequence at the end to `sink`.
+    pub fn finish(mut self, mut sink: impl FnMut(&[u8])) {
+        self.decode(&[], true, &mut sink);
+    }
+
+    fn decode(&mut self, mut src: &[u8], last: bool, sink: &mut impl FnMut(&[u8])) {
+        loop {
+            let (result, read, written, _) = self.decoder.decode_to_utf8(src, &mut self.out, last);
+            if written > 0 {
+                sink(&self.out[..written]);
+            }
+            src = &src[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, UTF_16LE};
+
+    fn transcode(data: &[u8], encoding: &'static Encoding, size: usize) -> String {
+        let mut text = Vec::new();
+        let mut transcoder = Transcoder::new(encoding);
+        for chunk in data.chunks(size) {
+            transcoder.feed(chunk, |t| text.extend_from_slice(t));
+        }
+        transcoder.finish(|t| text.extend_from_slice(t));
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn test_transcode_in_any_chunking() {
+        let utf16: Vec<u8> = "añb €\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let sjis = b"\x82\xa0\x82\xa2 x";
+        for size in 1..=utf16.len() {
+            assert_eq!(
+                transcode(&utf16, UTF_16LE, size),
+                "añb €\n",
+                "chunk size {}",
+                size
+            );
+            assert_eq!(
+                transcode(sjis, SHIFT_JIS, size),
+                "あい x",
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_input_encoding() {
+        assert_eq!(input_encoding("latin1").unwrap().name(), "windows-1252");
+        assert_eq!(input_encoding("UTF-16BE").unwrap().name(), "UTF-16BE");
+        assert_eq!(input_encoding("auto").unwrap(), UTF_8);
+        assert!(input_encoding("klingon").is_err());
+
+        // A byte order mark wins over `auto`.
+        assert_eq!(transcode(b"\xff\xfeh\0i\0", UTF_8, 3), "hi");
+        assert_eq!(transcode(b"a\xffb", UTF_8, 1), "a\u{fffd}b");
+    }
+}
@@ -0,0 +1,440 @@
+//! `rswc tui DIR`: the counts of a tree in a table of the terminal, one
+//! directory at a time, to find where the lines of a project are. Rows can
+//! be sorted by any column, filtered by name and opened when they are
+//! directories; with `--watch` the counts follow the files as they change.
+
+use crate::counter::{CountOptions, Counts, FileResult, total};
+use crate::serve::Tree;
+use crate::watch::watch_tree;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Cell, Paragraph, Row, Table, TableState};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+/// A row of the directory shown: a file, or a directory with the total of
+/// the files below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub dir: bool,
+    /// `None` for a file that couldn't be counted.
+    pub counts: Option<Counts>,
+}
+
+/// What the rows are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    /// A count, by its index in the columns.
+    Column(usize),
+}
+
+/// The state of the table: the counts, the directory shown and how.
+#[derive(Debug)]
+pub struct App {
+    root: PathBuf,
+    flags: CountOptions,
+    columns: Vec<&'static str>,
+    hidden: Vec<bool>,
+    results: BTreeMap<PathBuf, FileResult>,
+    counted: bool,
+    /// The directory shown, relative to the root.
+    dir: PathBuf,
+    sort: SortKey,
+    reverse: bool,
+    filter: String,
+    filtering: bool,
+    state: TableState,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(root: &Path, flags: &CountOptions) -> Self {
+        let columns = Counts::column_names(flags);
+        App {
+            root: root.to_path_buf(),
+            flags: flags.clone(),
+            hidden: vec![false; columns.len()],
+            columns,
+            results: BTreeMap::new(),
+            counted: false,
+            dir: PathBuf::new(),
+            sort: SortKey::Name,
+            reverse: false,
+            filter: String::new(),
+            filtering: false,
+            state: TableState::default().with_selected(0),
+            quit: false,
+        }
+    }
+
+    /// Shows `results`, the files of the tree named relative to it.
+    pub fn update(&mut self, results: BTreeMap<PathBuf, FileResult>) {
+        self.results = results;
+        self.counted = true;
+    }
+
+    /// The rows of the directory shown that match the filter, sorted.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut children: BTreeMap<String, (bool, Vec<&FileResult>)> = BTreeMap::new();
+        for (path, result) in &self.results {
+            let Ok(rest) = path.strip_prefix(&self.dir) else {
+                continue;
+            };
+            let mut components = rest.components();
+            let Some(first) = components.next() else {
+                continue;
+            };
+            let name = first.as_os_str().to_string_lossy().into_owned();
+            let child = children.entry(name).or_default();
+            child.0 |= components.next().is_some();
+            child.1.push(result);
+        }
+
+        let filter = self.filter.to_lowercase();
+        let mut entries: Vec<Entry> = children
+            .into_iter()
+            .filter(|(name, _)| name.to_lowercase().contains(&filter))
+            .map(|(name, (dir, results))| {
+                let counts = match results[..] {
+                    [FileResult::Err(..)] if !dir => None,
+                    _ => Some(total(results)),
+                };
+                Entry { name, dir, counts }
+            })
+            .collect();
+        if let SortKey::Column(i) = self.sort {
+            entries.sort_by_key(|entry| {
+                entry
+                    .counts
+                    .as_ref()
+                    .map(|counts| counts.columns(&self.flags)[i])
+            });
+        }
+        if self.reverse {
+            entries.reverse();
+        }
+        entries
+    }
+
+    /// Acts on a key press: moving, opening directories, sorting, toggling
+    /// columns, or typing the filter after `/`.
+    pub fn key(&mut self, code: KeyCode) {
+        if self.filtering {
+            match code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.filtering = false;
+                }
+                _ => return,
+            }
+            self.state.select(Some(0));
+            return;
+        }
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                let entries = self.entries();
+                if let Some(entry) = self.state.selected().and_then(|i| entries.get(i))
+                    && entry.dir
+                {
+                    self.dir.push(&entry.name);
+                    self.filter.clear();
+                    self.state.select(Some(0));
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                let Some(name) = self
+                    .dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                else {
+                    return;
+                };
+                self.dir.pop();
+                self.filter.clear();
+                // The directory left is selected, to go on from there.
+                let selected = self.entries().iter().position(|entry| entry.name == name);
+                self.state.select(Some(selected.unwrap_or(0)));
+            }
+            KeyCode::Char('s') => {
+                self.sort = match self.sort {
+                    SortKey::Name if !self.columns.is_empty() => SortKey::Column(0),
+                    SortKey::Column(i) if i + 1 < self.columns.len() => SortKey::Column(i + 1),
+                    _ => SortKey::Name,
+                };
+            }
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Char(c @ '1'..='9') => {
+                let i = c as usize - '1' as usize;
+                if let Some(hidden) = self.hidden.get_mut(i) {
+                    *hidden = !*hidden;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Draws the table with a line about the directory shown above it and
+    /// one with its total and the keys below it.
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let entries = self.entries();
+        let [top, middle, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        let sort = match self.sort {
+            SortKey::Name => "name",
+            SortKey::Column(i) => self.columns[i],
+        };
+        let mut title = format!(
+            "{}  sorted by {}{}",
+            self.root.join(&self.dir).display(),
+            sort,
+            if self.reverse { ", reversed" } else { "" }
+        );
+        if !self.counted {
+            title.push_str("  counting...");
+        }
+        if self.filtering || !self.filter.is_empty() {
+            title.push_str(&format!("  filter: {}", self.filter));
+        }
+        frame.render_widget(
+            Paragraph::new(title).style(Style::new().add_modifier(Modifier::BOLD)),
+            top,
+        );
+
+        let shown: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| !self.hidden[i])
+            .collect();
+        let values = |counts: &Counts| counts.columns(&self.flags);
+        let mut widths: Vec<usize> = shown.iter().map(|&i| self.columns[i].len()).collect();
+        for counts in entries.iter().filter_map(|entry| entry.counts.as_ref()) {
+            let values = values(counts);
+            for (width, &i) in widths.iter_mut().zip(&shown) {
+                *width = (*width).max(values[i].to_string().len());
+            }
+        }
+
+        let header = Row::new(
+            std::iter::once(Cell::from("name")).chain(
+                shown
+                    .iter()
+                    .map(|&i| Cell::from(Line::from(self.columns[i]).right_aligned())),
+            ),
+        )
+        .style(Style::new().add_modifier(Modifier::UNDERLINED));
+        let rows = entries.iter().map(|entry| {
+            let name = if entry.dir {
+                Cell::from(format!("{}/", entry.name))
+                    .style(Style::new().add_modifier(Modifier::BOLD))
+            } else {
+                Cell::from(entry.name.as_str())
+            };
+            let cells: Vec<Cell> = match &entry.counts {
+                Some(counts) => {
+                    let values = values(counts);
+                    shown
+                        .iter()
+                        .map(|&i| Cell::from(Line::from(values[i].to_string()).right_aligned()))
+                        .collect()
+                }
+                None => vec![Cell::from("unreadable").style(Style::new().fg(Color::Red))],
+            };
+            Row::new(std::iter::once(name).chain(cells))
+        });
+        let constraints = std::iter::once(Constraint::Min(16))
+            .chain(widths.iter().map(|&width| Constraint::Length(width as u16)));
+        let table = Table::new(rows, constraints)
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, middle, &mut self.state);
+
+        let counts: Vec<&FileResult> = self
+            .results
+            .iter()
+            .filter(|(path, _)| path.starts_with(&self.dir))
+            .map(|(_, result)| result)
+            .collect();
+        let total = values(&total(counts.iter().copied()));
+        let total: Vec<String> = shown
+            .iter()
+            .map(|&i| format!("{} {}", total[i], self.columns[i]))
+            .collect();
+        let help = if self.filtering {
+            "type to filter, enter keep, esc clear"
+        } else {
+            "q quit  j/k move  enter open  backspace up  s sort  r reverse  / filter  1-9 columns"
+        };
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(format!("{} files, {}", counts.len(), total.join(", "))),
+                Line::from(help).style(Style::new().add_modifier(Modifier::DIM)),
+            ]),
+            bottom,
+        );
+    }
+}
+
+/// Counts the tree and shows it until `q` is pressed. With `watch`, a
+/// thread counts changed files again as they change.
+pub fn run(mut tree: Tree, watch: bool) -> io::Result<()> {
+    let mut app = App::new(tree.root(), tree.flags());
+    let (changed_tx, changed) = mpsc::channel();
+    let tree = if watch {
+        Arc::new(Mutex::new(tree))
+    } else {
+        tree.update(&BTreeSet::from([tree.root().to_path_buf()]));
+        app.update(tree.results().clone());
+        Arc::new(Mutex::new(tree))
+    };
+    let watcher = watch.then(|| {
+        let tree = Arc::clone(&tree);
+        let root = tree.lock().unwrap().root().to_path_buf();
+        thread::spawn(move || {
+            watch_tree(&root, |paths| {
+                tree.lock().unwrap().update(paths);
+                let _ = changed_tx.send(());
+                Ok(())
+            })
+        })
+    });
+
+    let mut terminal = ratatui::init();
+    let result = (|| loop {
+        if changed.try_iter().count() > 0 {
+            app.update(tree.lock().unwrap().results().clone());
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        if event::poll(Duration::from_millis(250))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.key(key.code);
+        }
+        if app.quit {
+            return Ok(());
+        }
+        if let Some(watcher) = &watcher
+            && watcher.is_finished()
+        {
+            return Err(io::Error::other("the watcher stopped"));
+        }
+    })();
+    ratatui::restore();
+    match (result, watcher) {
+        // The watcher's own error says more.
+        (Err(_), Some(watcher)) if watcher.is_finished() => watcher
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the watcher stopped"))),
+        (result, _) => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(lines: u64) -> Counts {
+        Counts {
+            lines,
+            ..Default::default()
+        }
+    }
+
+    fn app() -> App {
+        let flags = CountOptions::new().lines(true);
+        let mut app = App::new(Path::new("/srv"), &flags);
+        let file = |name: &str, lines| {
+            (
+                PathBuf::from(name),
+                FileResult::Ok(name.into(), counts(lines)),
+            )
+        };
+        app.update(BTreeMap::from([
+            file("README", 5),
+            file("src/main.rs", 40),
+            file("src/lib.rs", 2),
+            file("src/bin/x.rs", 1),
+            (
+                PathBuf::from("secret"),
+                FileResult::Err("secret".into(), io::Error::other("denied").into()),
+            ),
+        ]));
+        app
+    }
+
+    #[test]
+    fn test_entries() {
+        let mut app = app();
+        let names = |app: &App| {
+            app.entries()
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            app.entries(),
+            [
+                Entry {
+                    name: "README".into(),
+                    dir: false,
+                    counts: Some(counts(5))
+                },
+                Entry {
+                    name: "secret".into(),
+                    dir: false,
+                    counts: None
+                },
+                Entry {
+                    name: "src".into(),
+                    dir: true,
+                    counts: Some(counts(43))
+                },
+            ]
+        );
+
+        app.key(KeyCode::Char('s'));
+        app.key(KeyCode::Char('r'));
+        assert_eq!(names(&app), ["src", "README", "secret"]);
+
+        app.key(KeyCode::Enter);
+        assert_eq!(names(&app), ["main.rs", "lib.rs", "bin"]);
+        app.key(KeyCode::Char('/'));
+        app.key(KeyCode::Char('B'));
+        app.key(KeyCode::Char('I'));
+        assert_eq!(names(&app), ["bin"]);
+        app.key(KeyCode::Enter);
+        app.key(KeyCode::Enter);
+        assert_eq!(names(&app), ["x.rs"]);
+
+        app.key(KeyCode::Backspace);
+        assert_eq!(app.state.selected(), Some(2));
+        app.key(KeyCode::Backspace);
+        app.key(KeyCode::Backspace);
+        assert_eq!(app.dir, PathBuf::new());
+        app.key(KeyCode::Char('1'));
+        assert_eq!(app.hidden, [true]);
+        app.key(KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+}
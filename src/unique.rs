@@ -0,0 +1,408 @@
+//! Distinct line and word counting. Lines and words are not stored, only a
+//! 128-bit hash of each distinct one, so memory grows with the number of
+//! distinct values rather than with their length. `--dup-lines` tells lines
+//! apart the same way, but keeps one copy of each to show the most repeated.
+
+use crate::case::CaseFold;
+use crate::counter::{FileResult, counted_inputs};
+use crate::scanner::WordRules;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hasher, RandomState};
+use std::io::{self, Write};
+
+/// Set of values that already are uniformly distributed hashes.
+type HashSet128 = HashSet<u128, BuildHasherDefault<Prehashed>>;
+/// Map keyed by values that already are uniformly distributed hashes.
+type HashMap128<V> = HashMap<u128, V, BuildHasherDefault<Prehashed>>;
+
+#[derive(Default)]
+struct Prehashed(u64);
+
+impl Hasher for Prehashed {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.rotate_left(8) ^ b as u64;
+        }
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.0 = n as u64;
+    }
+}
+
+/// Streaming distinct line counter. Lines may be split across any number
+/// of `feed` calls; a final line without a newline counts as a line, and
+/// `a` and `a\n` are the same line, as with `sort -u`.
+pub struct UniqueLines {
+    seen: HashSet128,
+    keys: (RandomState, RandomState),
+    current: (DefaultHasher, DefaultHasher),
+    in_line: bool,
+    delimiter: u8,
+}
+
+impl Default for UniqueLines {
+    fn default() -> Self {
+        let keys = (RandomState::new(), RandomState::new());
+        let current = (keys.0.build_hasher(), keys.1.build_hasher());
+        UniqueLines {
+            seen: HashSet128::default(),
+            keys,
+            current,
+            in_line: false,
+            delimiter: b'\n',
+        }
+    }
+}
+
+impl UniqueLines {
+    /// Splits lines at `delimiter` instead of `\n`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        while let Some(end) = chunk.iter().position(|&b| b == self.delimiter) {
+            self.current.0.write(&chunk[..end]);
+            self.current.1.write(&chunk[..end]);
+            self.end_line();
+            chunk = &chunk[end + 1..];
+        }
+
+        if !chunk.is_empty() {
+            self.current.0.write(chunk);
+            self.current.1.write(chunk);
+            self.in_line = true;
+        }
+    }
+
+    fn end_line(&mut self) {
+        let next = (self.keys.0.build_hasher(), self.keys.1.build_hasher());
+        let (a, b) = std::mem::replace(&mut self.current, next);
+        self.seen
+            .insert((a.finish() as u128) << 64 | b.finish() as u128);
+        self.in_line = false;
+    }
+
+    /// The number of distinct lines fed so far, including an unterminated
+    /// last line.
+    pub fn finish(mut self) -> u64 {
+        if self.in_line {
+            self.end_line();
+        }
+        self.seen.len() as u64
+    }
+}
+
+/// The distinct words of one or more inputs. Word hashes don't depend on
+/// the process or the input, so sets can be merged into totals.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct WordSet(HashSet128);
+
+impl WordSet {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn merge(&mut self, other: &WordSet) {
+        self.0.extend(&other.0);
+    }
+}
+
+impl fmt::Debug for WordSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WordSet({} words)", self.len())
+    }
+}
+
+fn hash128(data: &[u8]) -> u128 {
+    let mut a = DefaultHasher::new();
+    let mut b = DefaultHasher::new();
+    b.write_u8(0xff);
+    a.write(data);
+    b.write(data);
+    (a.finish() as u128) << 64 | b.finish() as u128
+}
+
+/// Streaming distinct word collector, splitting words like the word counter.
+/// With `fold_case` words are compared case-folded.
+pub struct UniqueWords {
+    set: WordSet,
+    // The word cut off at the end of the last chunk.
+    current: Vec<u8>,
+    rules: WordRules,
+    fold_case: Option<CaseFold>,
+}
+
+impl UniqueWords {
+    pub fn new(rules: WordRules, fold_case: Option<CaseFold>) -> Self {
+        UniqueWords {
+            set: WordSet::default(),
+            current: Vec::new(),
+            rules,
+            fold_case,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let rules = self.rules;
+        if rules.unicode_whitespace {
+            // A space beyond ASCII is told once its last byte is in.
+            for &b in chunk {
+                self.current.push(b);
+                let n = rules.separator_len_before(&self.current);
+                if n > 0 {
+                    self.current.truncate(self.current.len() - n);
+                    self.end_word();
+                }
+            }
+            return;
+        }
+        for piece in chunk.split_inclusive(|&b| rules.is_separator(b)) {
+            match piece.split_last() {
+                Some((&last, word)) if rules.is_separator(last) => {
+                    self.current.extend_from_slice(word);
+                    self.end_word();
+                }
+                _ => self.current.extend_from_slice(piece),
+            }
+        }
+    }
+
+    fn end_word(&mut self) {
+        if self.current.is_empty() {
+            return;
+        }
+
+        let hash = match self.fold_case {
+            Some(fold) => hash128(&fold.fold(&self.current)),
+            None => hash128(&self.current),
+        };
+        self.set.0.insert(hash);
+        self.current.clear();
+    }
+
+    pub fn finish(mut self) -> WordSet {
+        self.end_word();
+        self.set
+    }
+}
+
+/// Streaming counter of how often each line occurs. Lines are split like
+/// those of `UniqueLines`, and the first copy of each distinct line is kept.
+pub struct DupLines {
+    lines: HashMap128<(usize, Vec<u8>)>,
+    // The line cut off at the end of the last chunk.
+    current: Vec<u8>,
+    delimiter: u8,
+    top: usize,
+}
+
+impl DupLines {
+    /// Keeps the `top` lines that occur most often, of those that occur
+    /// more than once, or all of them for 0; lines end at `delimiter`.
+    pub fn new(top: usize, delimiter: u8) -> Self {
+        DupLines {
+            lines: HashMap128::default(),
+            current: Vec::new(),
+            delimiter,
+            top,
+        }
+    }
+
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        while let Some(end) = chunk.iter().position(|&b| b == self.delimiter) {
+            self.current.extend_from_slice(&chunk[..end]);
+            self.end_line();
+            chunk = &chunk[end + 1..];
+        }
+        self.current.extend_from_slice(chunk);
+    }
+
+    fn end_line(&mut self) {
+        match self.lines.entry(hash128(&self.current)) {
+            Entry::Occupied(mut line) => {
+                line.get_mut().0 += 1;
+                self.current.clear();
+            }
+            Entry::Vacant(line) => {
+                line.insert((1, std::mem::take(&mut self.current)));
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> RepeatedLines {
+        if !self.current.is_empty() {
+            self.end_line();
+        }
+        // The least repeated line kept is on top, and the last in byte
+        // order of those as repeated, to make way for a more repeated one.
+        let mut top = BinaryHeap::new();
+        for (count, line) in self.lines.into_values() {
+            if count < 2 {
+                continue;
+            }
+            top.push(Reverse((count, Reverse(line))));
+            if self.top > 0 && top.len() > self.top {
+                top.pop();
+            }
+        }
+        RepeatedLines(
+            top.into_sorted_vec()
+                .into_iter()
+                .map(|Reverse((count, Reverse(line)))| (line, count))
+                .collect(),
+        )
+    }
+}
+
+/// The most repeated lines of an input and how often each occurs, most
+/// first and ties in byte order.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepeatedLines(Vec<(Vec<u8>, usize)>);
+
+impl RepeatedLines {
+    pub fn lines(&self) -> &[(Vec<u8>, usize)] {
+        &self.0
+    }
+}
+
+/// Prints the most repeated lines of every counted input that has any,
+/// archive members included.
+pub fn print_repeated_lines<W: Write>(writer: &mut W, results: &[FileResult]) -> io::Result<()> {
+    for (name, counts) in counted_inputs(results) {
+        let Some(repeated) = counts.repeated_lines.as_deref() else {
+            continue;
+        };
+        if repeated.lines().is_empty() {
+            continue;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "repeated lines of {}:", name)?;
+        for (line, count) in repeated.lines() {
+            writeln!(writer, "{:>7} {}", count, String::from_utf8_lossy(line))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_lines_in_any_chunking() {
+        let data = b"b\na\n\nb\nlonger line\n\na\nlonger line";
+        for size in 1..=data.len() {
+            let mut unique = UniqueLines::default();
+            for chunk in data.chunks(size) {
+                unique.feed(chunk);
+            }
+            assert_eq!(unique.finish(), 4, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_unique_lines_empty_input() {
+        assert_eq!(UniqueLines::default().finish(), 0);
+
+        let mut unique = UniqueLines::default();
+        unique.feed(b"\n\n");
+        assert_eq!(unique.finish(), 1);
+    }
+
+    #[test]
+    fn test_unique_words_in_any_chunking() {
+        let data = "the Cat and the cat\n\tÄpfel äpfel  ".as_bytes();
+        for size in 1..=data.len() {
+            let mut exact = UniqueWords::new(WordRules::DEFAULT, None);
+            let mut folded = UniqueWords::new(WordRules::DEFAULT, Some(CaseFold::Default));
+            for chunk in data.chunks(size) {
+                exact.feed(chunk);
+                folded.feed(chunk);
+            }
+            assert_eq!(exact.finish().len(), 6, "chunk size {}", size);
+            assert_eq!(folded.finish().len(), 4, "chunk size {}", size);
+        }
+
+        let data = "the\u{a0}cat\u{3000}the cat\u{3000}".as_bytes();
+        let rules = WordRules {
+            unicode_whitespace: true,
+            ..WordRules::DEFAULT
+        };
+        for size in 1..=data.len() {
+            let mut words = UniqueWords::new(rules, None);
+            for chunk in data.chunks(size) {
+                words.feed(chunk);
+            }
+            assert_eq!(words.finish().len(), 2, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_dup_lines_in_any_chunking() {
+        let data = b"b\na\nc\nb\n\nd\na\nb\nd\n\nc\nb";
+        for size in 1..=data.len() {
+            let mut dup = DupLines::new(3, b'\n');
+            for chunk in data.chunks(size) {
+                dup.feed(chunk);
+            }
+            let expected: Vec<(Vec<u8>, usize)> =
+                vec![(b"b".to_vec(), 4), (b"".to_vec(), 2), (b"a".to_vec(), 2)];
+            assert_eq!(dup.finish().lines(), expected, "chunk size {}", size);
+        }
+
+        let mut dup = DupLines::new(0, b'\n');
+        dup.feed(b"x\ny\nz\nx");
+        let repeated = dup.finish();
+        assert_eq!(repeated.lines(), [(b"x".to_vec(), 2)]);
+    }
+
+    #[test]
+    fn test_print_repeated_lines() {
+        let mut dup = DupLines::new(10, b'\n');
+        dup.feed(b"GET /\nGET /a\nGET /\n");
+        let results = [
+            FileResult::Ok(
+                "access.log".into(),
+                crate::counter::Counts {
+                    repeated_lines: Some(Box::new(dup.finish())),
+                    ..Default::default()
+                },
+            ),
+            FileResult::Ok("empty.log".into(), Default::default()),
+        ];
+
+        let mut out = Vec::new();
+        print_repeated_lines(&mut out, &results).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\nrepeated lines of access.log:\n      2 GET /\n"
+        );
+    }
+
+    #[test]
+    fn test_word_sets_merge() {
+        let mut a = UniqueWords::new(WordRules::DEFAULT, None);
+        a.feed(b"one two three");
+        let mut b = UniqueWords::new(WordRules::DEFAULT, None);
+        b.feed(b"three four");
+
+        let mut total = a.finish();
+        total.merge(&b.finish());
+        assert_eq!(total.len(), 4);
+    }
+}
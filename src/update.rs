@@ -0,0 +1,177 @@
+//! `rswc self-update`: replaces the running binary with the latest GitHub
+//! release for the same target, after checking its published SHA-256 sum.
+//!
+//! Every release is expected to carry a `rswc-<target>` asset (with `.exe`
+//! on Windows) and a `rswc-<target>.sha256` file in `sha256sum` format.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/nobletk/rswc/releases/latest";
+const MAX_DOWNLOAD: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Release {
+    version: String,
+    binary_url: String,
+    checksum_url: String,
+}
+
+pub fn self_update() -> io::Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let json: Value = serde_json::from_slice(&download(RELEASES_URL)?).map_err(io::Error::other)?;
+    let release = parse_release(&json, &asset_name()).map_err(io::Error::other)?;
+
+    if !is_newer(&release.version, current) {
+        println!("rswc {} is up to date", current);
+        return Ok(());
+    }
+
+    println!("updating rswc {} to {}", current, release.version);
+    let binary = download(&release.binary_url)?;
+    let checksum = String::from_utf8_lossy(&download(&release.checksum_url)?).into_owned();
+    let expected = parse_checksum(&checksum)
+        .ok_or_else(|| io::Error::other("malformed checksum file in release"))?;
+
+    let actual: [u8; 32] = Sha256::digest(&binary).into();
+    if actual != expected {
+        return Err(io::Error::other(
+            "checksum mismatch, the downloaded binary was not installed",
+        ));
+    }
+
+    replace_exe(&env::current_exe()?, &binary)?;
+    println!("rswc updated to {}", release.version);
+    Ok(())
+}
+
+fn asset_name() -> String {
+    format!("rswc-{}{}", env!("RSWC_TARGET"), env::consts::EXE_SUFFIX)
+}
+
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let mut response = ureq::get(url)
+        .header("User-Agent", concat!("rswc/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(io::Error::other)?;
+    response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD)
+        .read_to_vec()
+        .map_err(io::Error::other)
+}
+
+fn parse_release(json: &Value, asset: &str) -> Result<Release, String> {
+    let tag = json["tag_name"].as_str().ok_or("release has no tag")?;
+    let checksum_asset = format!("{}.sha256", asset);
+
+    let url_of = |name: &str| {
+        json["assets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|a| a["name"] == name)
+            .and_then(|a| a["browser_download_url"].as_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("release {} has no {} asset", tag, name))
+    };
+
+    Ok(Release {
+        version: tag.trim_start_matches('v').to_string(),
+        binary_url: url_of(asset)?,
+        checksum_url: url_of(&checksum_asset)?,
+    })
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse =
+        |v: &str| -> Vec<u64> { v.split(['.', '-']).map_while(|p| p.parse().ok()).collect() };
+    parse(candidate) > parse(current)
+}
+
+/// Reads the digest from `sha256sum` output: `<64 hex digits>  <name>`.
+fn parse_checksum(text: &str) -> Option<[u8; 32]> {
+    let hex = text.split_whitespace().next()?;
+    if hex.len() != 64 {
+        return None;
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// Writes the new binary next to the old one and renames it into place, so
+/// an interrupted update never leaves a truncated executable behind.
+fn replace_exe(exe: &Path, binary: &[u8]) -> io::Result<()> {
+    let staged = exe.with_extension("new");
+    fs::write(&staged, binary)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // A running executable can't be overwritten on Windows, but it can be
+    // moved out of the way.
+    #[cfg(windows)]
+    fs::rename(exe, exe.with_extension("old"))?;
+
+    fs::rename(&staged, exe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_release() {
+        let json: Value = serde_json::from_str(
+            r#"{
+                "tag_name": "v0.2.0",
+                "assets": [
+                    {"name": "rswc-x86_64-unknown-linux-musl", "browser_download_url": "https://example.com/bin"},
+                    {"name": "rswc-x86_64-unknown-linux-musl.sha256", "browser_download_url": "https://example.com/sum"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            parse_release(&json, "rswc-x86_64-unknown-linux-musl"),
+            Ok(Release {
+                version: "0.2.0".to_string(),
+                binary_url: "https://example.com/bin".to_string(),
+                checksum_url: "https://example.com/sum".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_release(&json, "rswc-aarch64-apple-darwin"),
+            Err("release v0.2.0 has no rswc-aarch64-apple-darwin asset".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let sum = format!("{}  rswc-x86_64-unknown-linux-musl\n", "ab".repeat(32));
+        assert_eq!(parse_checksum(&sum), Some([0xab; 32]));
+        assert_eq!(parse_checksum("abcd  rswc"), None);
+        assert_eq!(parse_checksum(&"zz".repeat(32)), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! Reads through io_uring for `--io-backend=uring`, on Linux. Several reads
+//! of a file are in flight at once on a ring kept by each thread, so the
+//! device is kept busy while the chunk read before is counted, and the many
+//! small files of a tree cost no ring setup each.
+
+use crate::counter::{Buffered, ReadBackend};
+use io_uring::{IoUring, opcode, types};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Seek};
+use std::os::fd::AsRawFd;
+
+/// Reads of a file submitted ahead of the one being counted.
+const IN_FLIGHT: usize = 4;
+
+thread_local! {
+    /// The ring of this thread, or `None` where the kernel has no io_uring or
+    /// a sandbox forbids it.
+    static RING: RefCell<Option<Ring>> = RefCell::new(Ring::new().ok());
+}
+
+/// Reads files through the ring of the calling thread, falling back to
+/// buffered reads when there is none.
+pub(crate) struct Uring;
+
+impl ReadBackend for Uring {
+    fn read(
+        &self,
+        file: &File,
+        size: usize,
+        chunk: &mut dyn FnMut(&[u8]) -> bool,
+    ) -> io::Result<()> {
+        RING.with(|ring| match ring.borrow_mut().as_mut() {
+            Some(ring) => ring.read(file, size, chunk),
+            None => Buffered.read(file, size, chunk),
+        })
+    }
+}
+
+struct Ring {
+    ring: IoUring,
+    /// A buffer for each read in flight, indexed by the user data of the
+    /// read, allocated for the size of the first read.
+    buffers: Vec<Vec<u8>>,
+    /// The results of the reads completed but not counted yet.
+    results: [Option<i32>; IN_FLIGHT],
+}
+
+impl Ring {
+    fn new() -> io::Result<Self> {
+        Ok(Ring {
+            ring: IoUring::new(IN_FLIGHT as u32)?,
+            buffers: vec![Vec::new(); IN_FLIGHT],
+            results: [None; IN_FLIGHT],
+        })
+    }
+
+    /// Hands `file` to `chunk` in order, `size` bytes at a time, reading up
+    /// to `IN_FLIGHT` chunks ahead of it.
+    fn read(
+        &mut self,
+        file: &File,
+        size: usize,
+        chunk: &mut dyn FnMut(&[u8]) -> bool,
+    ) -> io::Result<()> {
+        if self.buffers[0].len() != size {
+            self.buffers = vec![vec![0; size]; IN_FLIGHT];
+        }
+        let fd = types::Fd(file.as_raw_fd());
+        // The buffer and file offset of each read in flight, in file order.
+        let mut pending: VecDeque<(usize, u64)> = VecDeque::new();
+        let mut free: Vec<usize> = (0..IN_FLIGHT).collect();
+        let mut next = (&*file).stream_position()?;
+
+        loop {
+            while let Some(slot) = free.pop() {
+                if let Err(e) = self.submit(fd, slot, next) {
+                    free.push(slot);
+                    self.drain(&mut pending)?;
+                    return Err(e);
+                }
+                pending.push_back((slot, next));
+                next += size as u64;
+            }
+
+            let Some(&(slot, offset)) = pending.front() else {
+                return Ok(());
+            };
+            if let Err(e) = self.wait_for(slot) {
+                self.drain(&mut pending)?;
+                return Err(e);
+            }
+            pending.pop_front();
+            free.push(slot);
+
+            let result = self.results[slot].take().unwrap_or_default();
+            let read = match usize::try_from(result) {
+                Ok(read) => read,
+                Err(_) => {
+                    self.drain(&mut pending)?;
+                    return Err(io::Error::from_raw_os_error(-result));
+                }
+            };
+            if read == 0 || !chunk(&self.buffers[slot][..read]) {
+                return self.drain(&mut pending);
+            }
+            if read < size {
+                // The reads after this one started past what it got, at the
+                // end of the file or after a short read: read on from here.
+                self.drain(&mut pending)?;
+                free = (0..IN_FLIGHT).collect();
+                next = offset + read as u64;
+            }
+        }
+    }
+
+    /// Queues a read of the buffer `slot` from `offset` of `fd`.
+    fn submit(&mut self, fd: types::Fd, slot: usize, offset: u64) -> io::Result<()> {
+        let buffer = &mut self.buffers[slot];
+        let len = buffer.len().min(u32::MAX as usize) as u32;
+        let entry = opcode::Read::new(fd, buffer.as_mut_ptr(), len)
+            .offset(offset)
+            .build()
+            .user_data(slot as u64);
+        // SAFETY: the buffer is owned by the ring and neither moved nor
+        // touched until the read completes: every read queued is waited for,
+        // by `wait_for` or `drain`, before `read` returns.
+        unsafe { self.ring.submission().push(&entry) }
+            .map_err(|_| io::Error::other("io_uring submission queue full"))
+    }
+
+    /// Submits the queued reads and waits until the read of `slot` is done.
+    fn wait_for(&mut self, slot: usize) -> io::Result<()> {
+        while self.results[slot].is_none() {
+            match self.ring.submit_and_wait(1) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            for entry in self.ring.completion() {
+                self.results[entry.user_data() as usize] = Some(entry.result());
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the reads in flight and throws their data away, so the
+    /// buffers are free for the next file.
+    fn drain(&mut self, pending: &mut VecDeque<(usize, u64)>) -> io::Result<()> {
+        while let Some((slot, _)) = pending.pop_front() {
+            self.wait_for(slot)?;
+            self.results[slot] = None;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::BUF_SIZE;
+
+    #[test]
+    fn test_uring_reads_in_order() {
+        let path = std::env::temp_dir().join(format!("rswc-uring-{}", std::process::id()));
+        let data: Vec<u8> = (0..BUF_SIZE * 5 + 123).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let mut read = Vec::new();
+        Uring
+            .read(&file, BUF_SIZE, &mut |chunk| {
+                read.extend_from_slice(chunk);
+                true
+            })
+            .unwrap();
+        assert_eq!(read, data);
+
+        // Stopping early leaves the ring ready for the next file.
+        let mut chunks = 0;
+        Uring
+            .read(&file, BUF_SIZE, &mut |_| {
+                chunks += 1;
+                false
+            })
+            .unwrap();
+        assert_eq!(chunks, 1);
+        let mut read = Vec::new();
+        Uring
+            .read(&file, BUF_SIZE, &mut |chunk| {
+                read.extend_from_slice(chunk);
+                true
+            })
+            .unwrap();
+        assert_eq!(read.len(), data.len());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Editors tend to save in bursts of events (write, rename, chmod), so wait
+/// for things to settle before recounting.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Calls `render` once, then again every time one of `paths` changes. Only
+/// returns on error or when the watcher goes away.
+pub fn watch<F>(paths: &[PathBuf], mut render: F) -> io::Result<()>
+where
+    F: FnMut() -> io::Result<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+
+    // Watch the parent of every file rather than the file itself so that
+    // atomic saves, which replace the file, keep being noticed.
+    let mut watched = HashSet::new();
+    let mut targets = HashSet::new();
+    for path in paths.iter().filter(|p| p.as_path() != Path::new("-")) {
+        let absolute = std::path::absolute(path)?;
+        let dir = if absolute.is_dir() {
+            absolute.clone()
+        } else {
+            absolute
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| absolute.clone())
+        };
+        if watched.insert(dir.clone()) {
+            watcher
+                .watch(&dir, RecursiveMode::NonRecursive)
+                .map_err(io::Error::other)?;
+        }
+        targets.insert(absolute);
+    }
+
+    render()?;
+
+    while let Ok(event) = rx.recv() {
+        if !burst(&rx, event)
+            .iter()
+            .any(|event| is_relevant(event, &targets))
+        {
+            continue;
+        }
+
+        let mut out = io::stdout();
+        if out.is_terminal() {
+            write!(out, "\x1b[2J\x1b[H")?;
+        } else {
+            writeln!(out)?;
+        }
+        out.flush()?;
+        render()?;
+    }
+
+    Ok(())
+}
+
+/// Calls `update` with `dir` once the tree below it is watched, then with
+/// the paths in it that were changed, created or removed, every time some
+/// are. Only returns on error or when the watcher goes away.
+pub fn watch_tree<F>(dir: &Path, mut update: F) -> io::Result<()>
+where
+    F: FnMut(&BTreeSet<PathBuf>) -> io::Result<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+    watcher
+        .watch(dir, RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+
+    update(&BTreeSet::from([dir.to_path_buf()]))?;
+
+    while let Ok(event) = rx.recv() {
+        let changed: BTreeSet<PathBuf> = burst(&rx, event)
+            .into_iter()
+            .flatten()
+            .filter(|event| !event.kind.is_access())
+            .flat_map(|event| event.paths)
+            .collect();
+        if !changed.is_empty() {
+            update(&changed)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `first` and the events that follow it until none come for `DEBOUNCE`.
+fn burst(
+    rx: &mpsc::Receiver<notify::Result<Event>>,
+    first: notify::Result<Event>,
+) -> Vec<notify::Result<Event>> {
+    let mut events = vec![first];
+    while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+        events.push(event);
+    }
+    events
+}
+
+fn is_relevant(event: &notify::Result<Event>, targets: &HashSet<PathBuf>) -> bool {
+    match event {
+        Ok(event) if event.kind.is_access() => false,
+        Ok(event) => event
+            .paths
+            .iter()
+            .any(|p| targets.contains(p) || p.parent().is_some_and(|dir| targets.contains(dir))),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::EventKind;
+    use notify::event::{AccessKind, ModifyKind};
+
+    #[test]
+    fn test_is_relevant_filters_by_target() {
+        let targets: HashSet<PathBuf> = [PathBuf::from("/tmp/a.txt"), PathBuf::from("/srv/docs")]
+            .into_iter()
+            .collect();
+        let event = |kind, path: &str| Ok(Event::new(kind).add_path(PathBuf::from(path)));
+        let modify = EventKind::Modify(ModifyKind::Any);
+
+        assert!(is_relevant(&event(modify, "/tmp/a.txt"), &targets));
+        assert!(is_relevant(&event(modify, "/srv/docs/new.md"), &targets));
+        assert!(!is_relevant(&event(modify, "/tmp/b.txt"), &targets));
+        assert!(!is_relevant(
+            &event(EventKind::Access(AccessKind::Any), "/tmp/a.txt"),
+            &targets
+        ));
+    }
+}